@@ -0,0 +1,33 @@
+// Darkness everywhere except around the fog lights
+
+let MAX_LIGHTS: u32 = 32u;
+
+struct FogMaterial {
+    color: vec4<f32>;
+    // Each light is (x, y, radius, intensity) in world space
+    lights: array<vec4<f32>, 32>;
+    light_count: u32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> material: FogMaterial;
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    var visibility: f32 = 0.0;
+    for (var i: u32 = 0u; i < min(material.light_count, MAX_LIGHTS); i = i + 1u) {
+        let light = material.lights[i];
+        let distance = length(in.world_position.xy - light.xy);
+        // Fully lit in the inner part of the radius, fading out towards the edge
+        let lit = 1.0 - smoothStep(light.z * 0.6, light.z, distance);
+        visibility = max(visibility, lit * light.w);
+    }
+    return vec4<f32>(material.color.rgb, material.color.a * (1.0 - visibility));
+}
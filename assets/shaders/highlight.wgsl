@@ -0,0 +1,53 @@
+// Flash and outline drawn over one frame of a texture atlas
+
+struct HighlightMaterial {
+    color: vec4<f32>;
+    // Min and max corners of the frame in the atlas, in pixels
+    frame: vec4<f32>;
+    atlas_size: vec2<f32>;
+    flash: f32;
+    outline: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> material: HighlightMaterial;
+[[group(1), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(1), binding(2)]]
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+// Alpha of the frame at a pixel position relative to its corner, zero outside it
+fn alpha_at(pixel: vec2<f32>) -> f32 {
+    let size = material.frame.zw - material.frame.xy;
+    let inside = all(pixel >= vec2<f32>(0.0, 0.0)) && all(pixel < size);
+    let clamped = clamp(pixel, vec2<f32>(0.0, 0.0), size - vec2<f32>(1.0, 1.0));
+    let uv = (material.frame.xy + clamped + vec2<f32>(0.5, 0.5)) / material.atlas_size;
+    let alpha = textureSampleLevel(texture, texture_sampler, uv, 0.0).a;
+    return select(0.0, alpha, inside);
+}
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    // The quad is larger than the frame by the outline width on every side
+    let size = material.frame.zw - material.frame.xy;
+    let pixel = in.uv * (size + 2.0 * material.outline) - material.outline;
+
+    let alpha = alpha_at(pixel);
+    let offset = material.outline;
+    let neighbours = max(
+        max(alpha_at(pixel + vec2<f32>(offset, 0.0)), alpha_at(pixel - vec2<f32>(offset, 0.0))),
+        max(alpha_at(pixel + vec2<f32>(0.0, offset)), alpha_at(pixel - vec2<f32>(0.0, offset))),
+    );
+
+    // Tint the sprite itself by the flash amount, and its surroundings by the outline
+    let flash = alpha * material.flash;
+    let outline = neighbours * (1.0 - alpha) * material.color.a;
+    return vec4<f32>(material.color.rgb, max(flash, outline));
+}
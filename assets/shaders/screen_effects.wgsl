@@ -0,0 +1,53 @@
+// Full screen overlay for vignette, aberration and desaturation effects
+
+struct ScreenEffects {
+    vignette: f32;
+    aberration: f32;
+    desaturation: f32;
+    time: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> effects: ScreenEffects;
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+// Composite a straight alpha color over another
+fn over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    let alpha = top.a + bottom.a * (1.0 - top.a);
+    if (alpha <= 0.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+    let rgb = (top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / alpha;
+    return vec4<f32>(rgb, alpha);
+}
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    // 0 in the middle of the screen, 1 at the edges
+    let centered = (in.uv - vec2<f32>(0.5, 0.5)) * 2.0;
+    let edge = length(centered);
+
+    // Washing the screen out with grey drains its colour
+    var color = vec4<f32>(0.45, 0.45, 0.45, effects.desaturation * 0.65);
+
+    // Red and cyan fringes on opposite sides, like split colour channels
+    var fringe_color = vec3<f32>(0.0, 1.0, 1.0);
+    if (centered.x < 0.0) {
+        fringe_color = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let fringe = smoothStep(0.7, 1.0, abs(centered.x)) * effects.aberration * 0.5;
+    color = over(vec4<f32>(fringe_color, fringe), color);
+
+    // Pulsing dark red around the edges
+    let pulse = 0.85 + 0.15 * sin(effects.time * 6.0);
+    let vignette = smoothStep(0.5, 1.4, edge) * effects.vignette * pulse;
+    color = over(vec4<f32>(0.25, 0.0, 0.0, vignette), color);
+
+    return color;
+}
@@ -0,0 +1,24 @@
+// Fading strips behind projectiles. Along each strip uv.x runs from 0 at the tail to 1 at
+// the projectile, and uv.y from one edge to the other.
+
+struct TrailMaterial {
+    color: vec4<f32>;
+};
+
+[[group(1), binding(0)]]
+var<uniform> material: TrailMaterial;
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    // Soft at the edges, fading out towards the tail
+    let edge = 1.0 - abs(in.uv.y * 2.0 - 1.0);
+    let alpha = material.color.a * in.uv.x * smoothStep(0.0, 0.6, edge);
+    return vec4<f32>(material.color.rgb, alpha);
+}
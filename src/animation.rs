@@ -0,0 +1,455 @@
+use crate::{clock::GameClock, util::AnimationTiming, AppState, GameState};
+use bevy::{prelude::*, reflect::TypeUuid};
+use std::{collections::HashMap, time::Duration};
+
+// Plays sprite sheet animations. Sprites in a level go by the game clock, so they freeze
+// when the run is paused and hold still through hit stops, while menus animate in real time.
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SpriteSheetAnimation>().add_system(animate);
+    }
+}
+
+// Switches sprites between animations for what they're doing, picked from how fast they
+// move unless an event is playing over it
+pub struct AnimationStatePlugin;
+
+impl Plugin for AnimationStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnimationEvent>()
+            .add_system(update_animation_states);
+    }
+}
+
+// Below this many pixels per second sprites hover in place
+const IDLE_SPEED: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Motion {
+    Idle,
+    Flying,
+    FastFlying,
+    // Played once over the movement animations
+    Hit,
+    // Played once, then held on the last frame for good
+    Death,
+}
+
+impl Motion {
+    // Motion for moving at `speed`, with sprites flying fast from `fast_speed`
+    fn from_speed(speed: f32, fast_speed: f32) -> Self {
+        if speed < IDLE_SPEED {
+            Self::Idle
+        } else if speed < fast_speed {
+            Self::Flying
+        } else {
+            Self::FastFlying
+        }
+    }
+
+    // Motion to fall back on when a sprite has no animation for this one
+    fn fallback(self) -> Option<Self> {
+        match self {
+            Self::FastFlying => Some(Self::Flying),
+            Self::Flying => Some(Self::Idle),
+            Self::Idle | Self::Hit | Self::Death => None,
+        }
+    }
+}
+
+// Sent to play the Hit or Death animation of an entity
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub motion: Motion,
+}
+
+struct Clip {
+    timing: AnimationTiming,
+    animation: Handle<SpriteSheetAnimation>,
+    // Sprite sheet the clip is drawn from, if it isn't the entity's own
+    atlas: Option<Handle<TextureAtlas>>,
+}
+
+#[derive(Component)]
+pub struct AnimationState {
+    clips: HashMap<Motion, Clip>,
+    current: Motion,
+    // Hit or Death, playing over the movement animations
+    one_shot: Option<Motion>,
+    finished: bool,
+    fast_speed: f32,
+    last_position: Option<Vec2>,
+    // The entity's own sprite sheet, to return to from clips with their own
+    base_atlas: Option<Handle<TextureAtlas>>,
+}
+
+impl AnimationState {
+    // Starts out on the Flying clip, which should be what the entity was spawned playing
+    pub fn new(fast_speed: f32) -> Self {
+        Self {
+            clips: HashMap::new(),
+            current: Motion::Flying,
+            one_shot: None,
+            finished: false,
+            fast_speed,
+            last_position: None,
+            base_atlas: None,
+        }
+    }
+
+    pub fn with_clip(
+        mut self,
+        animations: &mut Assets<SpriteSheetAnimation>,
+        motion: Motion,
+        timing: AnimationTiming,
+        atlas: Option<Handle<TextureAtlas>>,
+    ) -> Self {
+        let clip = Clip {
+            timing,
+            animation: animations.add(timing.animation(1)),
+            atlas,
+        };
+        self.clips.insert(motion, clip);
+        self
+    }
+
+    // The animation playing
+    pub fn motion(&self) -> Motion {
+        self.current
+    }
+
+    // Whether the death animation has played through
+    pub fn is_dead(&self) -> bool {
+        self.current == Motion::Death && self.finished
+    }
+
+    // Each clip's animation along with its timing, so it can be remade at another speed
+    pub fn clips(
+        &self,
+    ) -> impl Iterator<Item = (&Handle<SpriteSheetAnimation>, &AnimationTiming)> + '_ {
+        self.clips
+            .values()
+            .map(|clip| (&clip.animation, &clip.timing))
+    }
+
+    // The motion to play, given how fast the entity is moving
+    fn next(&self, speed: f32) -> Motion {
+        if let Some(motion) = self.one_shot {
+            return motion;
+        }
+        let mut motion = Motion::from_speed(speed, self.fast_speed);
+        while !self.clips.contains_key(&motion) {
+            match motion.fallback() {
+                Some(fallback) => motion = fallback,
+                None => return self.current,
+            }
+        }
+        motion
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationMode {
+    // Played through, then Play is taken off, leaving the last frame showing
+    Once,
+    Repeat,
+    // Forwards then backwards, over and over
+    PingPong,
+}
+
+// Frames of a sprite sheet to show in order, each for the same time
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "4e8a1c07-3b6d-4f92-a5e1-7d0c9b2f6a38"]
+pub struct SpriteSheetAnimation {
+    frames: Vec<usize>,
+    frame_duration: Duration,
+    mode: AnimationMode,
+}
+
+impl SpriteSheetAnimation {
+    pub fn new(
+        frames: impl IntoIterator<Item = usize>,
+        frame_duration: Duration,
+        mode: AnimationMode,
+    ) -> Self {
+        Self {
+            frames: frames.into_iter().collect(),
+            frame_duration,
+            mode,
+        }
+    }
+}
+
+// Animations only advance on entities with this
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Play;
+
+// How fast an entity animates next to the clock it goes by, for things slowed down on
+// their own, like enemies in slow motion
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AnimationSpeed(pub f32);
+
+// How far through its animation an entity is
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AnimationProgress {
+    frame: usize,
+    elapsed_in_frame: Duration,
+    going_backward: bool,
+}
+
+impl AnimationProgress {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Move on by `delta`, returning the sprite sheet index to show, or None once an
+    // animation that plays once is over
+    fn advance(&mut self, animation: &SpriteSheetAnimation, delta: Duration) -> Option<usize> {
+        let last = animation.frames.len().checked_sub(1)?;
+        self.elapsed_in_frame += delta;
+        // A zero duration would never stop moving on
+        while self.elapsed_in_frame >= animation.frame_duration.max(Duration::from_nanos(1)) {
+            self.elapsed_in_frame -= animation.frame_duration;
+            match animation.mode {
+                AnimationMode::Once if self.frame >= last => {
+                    self.reset();
+                    return None;
+                }
+                AnimationMode::Once => self.frame += 1,
+                AnimationMode::Repeat if self.frame >= last => self.frame = 0,
+                AnimationMode::Repeat => self.frame += 1,
+                AnimationMode::PingPong if last == 0 => {}
+                AnimationMode::PingPong => {
+                    if self.frame == 0 {
+                        self.going_backward = false;
+                    } else if self.frame >= last {
+                        self.going_backward = true;
+                    }
+                    if self.going_backward {
+                        self.frame -= 1;
+                    } else {
+                        self.frame += 1;
+                    }
+                }
+            }
+        }
+        Some(animation.frames[self.frame.min(last)])
+    }
+}
+
+// Time animations move on by this frame: the game clock while a level is playing or the bee
+// is falling, nothing while the level is paused any other way, and real time outside levels
+fn animation_delta(
+    time: &Time,
+    clock: &GameClock,
+    app_state: &State<AppState>,
+    game_state: &State<GameState>,
+) -> Duration {
+    if *app_state.current() != AppState::Game {
+        time.delta()
+    } else if matches!(game_state.current(), GameState::Playing | GameState::Dying) {
+        clock.delta()
+    } else {
+        Duration::ZERO
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn animate(
+    mut commands: Commands,
+    time: Res<Time>,
+    clock: Res<GameClock>,
+    app_state: Res<State<AppState>>,
+    game_state: Res<State<GameState>>,
+    animations: Res<Assets<SpriteSheetAnimation>>,
+    mut sprites: Query<
+        (
+            Entity,
+            &Handle<SpriteSheetAnimation>,
+            &mut TextureAtlasSprite,
+            &mut AnimationProgress,
+            Option<&AnimationSpeed>,
+        ),
+        With<Play>,
+    >,
+) {
+    let delta = animation_delta(&time, &clock, &app_state, &game_state);
+    for (entity, animation, mut sprite, mut progress, speed) in sprites.iter_mut() {
+        let animation = match animations.get(animation) {
+            Some(animation) => animation,
+            None => continue,
+        };
+        let delta = delta.mul_f32(speed.map_or(1.0, |speed| speed.0.max(0.0)));
+        match progress.advance(animation, delta) {
+            Some(index) if sprite.index != index => sprite.index = index,
+            Some(_) => {}
+            None => {
+                commands.entity(entity).remove::<Play>();
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_animation_states(
+    mut commands: Commands,
+    time: Res<Time>,
+    clock: Res<GameClock>,
+    app_state: Res<State<AppState>>,
+    game_state: Res<State<GameState>>,
+    mut events: EventReader<AnimationEvent>,
+    mut sprites: Query<(
+        Entity,
+        &Transform,
+        &mut AnimationState,
+        &mut Handle<SpriteSheetAnimation>,
+        &mut Handle<TextureAtlas>,
+        &mut TextureAtlasSprite,
+        Option<&mut AnimationProgress>,
+        Option<&Play>,
+    )>,
+) {
+    // Speed is measured in the same time the sprite animates by, so a paused level doesn't
+    // look like everything stopped moving
+    let delta = animation_delta(&time, &clock, &app_state, &game_state).as_secs_f32();
+
+    let started: HashMap<Entity, Motion> = events
+        .iter()
+        .map(|event| (event.entity, event.motion))
+        .collect();
+
+    for (entity, transform, mut state, mut animation, mut atlas, mut sprite, progress, play) in
+        sprites.iter_mut()
+    {
+        let restart = started.get(&entity).copied();
+        if delta == 0.0 && restart.is_none() {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let speed = match state.last_position {
+            Some(last) if delta > 0.0 => last.distance(position) / delta,
+            _ => 0.0,
+        };
+        state.last_position = Some(position);
+        if state.base_atlas.is_none() {
+            state.base_atlas = Some(atlas.clone());
+        }
+
+        // Nothing plays over death, and sprites without the animation ignore the event
+        let restart = restart.filter(|motion| {
+            state.one_shot != Some(Motion::Death) && state.clips.contains_key(motion)
+        });
+        if let Some(motion) = restart {
+            state.one_shot = Some(motion);
+            state.finished = false;
+        } else if let Some(motion) = state.one_shot {
+            // Play is taken off once an animation that plays once is over
+            if play.is_none() && state.current == motion {
+                state.finished = true;
+                if motion == Motion::Death {
+                    continue;
+                }
+                state.one_shot = None;
+            }
+        }
+
+        let next = state.next(speed);
+        if next == state.current && restart.is_none() {
+            continue;
+        }
+        state.current = next;
+        let clip = &state.clips[&next];
+        *animation = clip.animation.clone();
+        if let Some(clip_atlas) = clip.atlas.as_ref().or(state.base_atlas.as_ref()) {
+            *atlas = clip_atlas.clone();
+        }
+        sprite.index = 0;
+        if let Some(mut progress) = progress {
+            progress.reset();
+        }
+        commands.entity(entity).insert(Play);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::AnimatedSpriteData;
+
+    #[test]
+    fn speed_picks_the_motion() {
+        assert_eq!(Motion::from_speed(0.0, 600.0), Motion::Idle);
+        assert_eq!(Motion::from_speed(500.0, 600.0), Motion::Flying);
+        assert_eq!(Motion::from_speed(750.0, 600.0), Motion::FastFlying);
+    }
+
+    #[test]
+    fn missing_clips_fall_back() {
+        let timing = AnimatedSpriteData {
+            frames: 6,
+            ..AnimatedSpriteData::default()
+        }
+        .timing();
+        let mut state = AnimationState::new(600.0);
+        for motion in [Motion::Idle, Motion::Flying] {
+            let clip = Clip {
+                timing,
+                animation: Handle::default(),
+                atlas: None,
+            };
+            state.clips.insert(motion, clip);
+        }
+        assert_eq!(state.next(750.0), Motion::Flying);
+        assert_eq!(state.next(0.0), Motion::Idle);
+    }
+
+    #[test]
+    fn animations_advance_by_mode() {
+        let frame = Duration::from_millis(100);
+        let shown = |mode, steps: usize| {
+            let animation = SpriteSheetAnimation::new([4, 5, 6], frame, mode);
+            let mut progress = AnimationProgress::default();
+            (0..steps)
+                .map(|_| progress.advance(&animation, frame))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            shown(AnimationMode::Repeat, 4),
+            [Some(5), Some(6), Some(4), Some(5)]
+        );
+        assert_eq!(
+            shown(AnimationMode::PingPong, 5),
+            [Some(5), Some(6), Some(5), Some(4), Some(5)]
+        );
+        assert_eq!(shown(AnimationMode::Once, 3), [Some(5), Some(6), None]);
+
+        // Long frames skip ahead, and frozen ones stay put
+        let animation = SpriteSheetAnimation::new([4, 5, 6], frame, AnimationMode::Repeat);
+        let mut progress = AnimationProgress::default();
+        assert_eq!(progress.advance(&animation, frame * 2), Some(6));
+        assert_eq!(progress.advance(&animation, Duration::ZERO), Some(6));
+    }
+
+    #[test]
+    fn levels_animate_by_the_game_clock() {
+        let time = Time::default();
+        let mut clock = GameClock::default();
+        clock.set_delta(Duration::from_millis(5));
+        let delta = |app_state, game_state| {
+            animation_delta(
+                &time,
+                &clock,
+                &State::new(app_state),
+                &State::new(game_state),
+            )
+        };
+        assert_eq!(
+            delta(AppState::Game, GameState::Playing),
+            Duration::from_millis(5)
+        );
+        assert_eq!(delta(AppState::Game, GameState::Paused), Duration::ZERO);
+        assert_eq!(delta(AppState::Menu, GameState::Paused), time.delta());
+    }
+}
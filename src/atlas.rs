@@ -0,0 +1,154 @@
+use crate::{save::SaveData, world::Wall};
+use bevy::{asset::FileAssetIo, prelude::*};
+use std::{collections::HashMap, path::PathBuf};
+
+// Wall tiles make up most of every level, so they're packed into one texture at startup
+// and drawn with a single texture bind instead of one per wall shape
+const WALL_SHAPES: [&str; 16] = [
+    "eeee", "eeew", "eewe", "eeww", "ewee", "ewew", "ewwe", "ewww", "weee", "weew", "wewe", "weww",
+    "wwee", "wwew", "wwwe", "wwww",
+];
+// Window scale factor from which @2x sprites are used when they exist
+const HIGH_DPI_SCALE_FACTOR: f64 = 1.5;
+
+pub struct AtlasPlugin;
+
+impl Plugin for AtlasPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_wall_sprites)
+            .add_system(pack_wall_sprites)
+            .add_system(use_wall_atlas);
+    }
+}
+
+// The packed wall sprites, found by the handle of the sprite they replace
+struct WallAtlas {
+    atlas: Handle<TextureAtlas>,
+    indices: HashMap<Handle<Image>, usize>,
+}
+
+// Wall sprites still loading, as (sprite used by the level, image to pack in its place)
+struct PendingWallSprites(Vec<(Handle<Image>, Handle<Image>)>);
+
+// The @2x variant of a sprite's path, "walls/eeee.png" to "walls/eeee@2x.png"
+fn high_dpi_path(path: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, extension)) => format!("{stem}@2x.{extension}"),
+        None => format!("{path}@2x"),
+    }
+}
+
+// Where an asset is on disk, for the few read without the AssetServer
+pub fn asset_path(path: &str) -> PathBuf {
+    FileAssetIo::get_root_path().join("assets").join(path)
+}
+
+pub fn asset_exists(path: &str) -> bool {
+    asset_path(path).exists()
+}
+
+fn load_wall_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+) {
+    let high_dpi = windows
+        .get_primary()
+        .is_some_and(|window| window.scale_factor() >= HIGH_DPI_SCALE_FACTOR);
+
+    let sprites = WALL_SHAPES
+        .iter()
+        .map(|shape| {
+            let path = format!("walls/{shape}.png");
+            let packed = match high_dpi_path(&path) {
+                hd if high_dpi && asset_exists(&hd) => hd,
+                _ => path.clone(),
+            };
+            (
+                asset_server.load(PathBuf::from(path)),
+                asset_server.load(PathBuf::from(packed)),
+            )
+        })
+        .collect();
+    commands.insert_resource(PendingWallSprites(sprites));
+}
+
+fn pack_wall_sprites(
+    mut commands: Commands,
+    pending: Option<Res<PendingWallSprites>>,
+    mut images: ResMut<Assets<Image>>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    if !pending.0.iter().all(|(_, packed)| images.contains(packed)) {
+        return;
+    }
+    commands.remove_resource::<PendingWallSprites>();
+
+    let mut builder = TextureAtlasBuilder::default();
+    for (_, packed) in &pending.0 {
+        if let Some(image) = images.get(packed) {
+            builder.add_texture(packed.clone(), image);
+        }
+    }
+    let atlas = match builder.finish(&mut images) {
+        Ok(atlas) => atlas,
+        Err(err) => {
+            warn!("Failed to pack the wall sprites, drawing them separately: {err}");
+            return;
+        }
+    };
+    let indices = pending
+        .0
+        .iter()
+        .filter_map(|(sprite, packed)| Some((sprite.clone(), atlas.get_texture_index(packed)?)))
+        .collect();
+    commands.insert_resource(WallAtlas {
+        atlas: atlases.add(atlas),
+        indices,
+    });
+}
+
+// Swap new walls over to the atlas. Walls a level pack replaces aren't in it and stay
+// as they are, as do the untextured walls of low-spec mode.
+fn use_wall_atlas(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    atlas: Option<Res<WallAtlas>>,
+    walls: Query<(Entity, &Handle<Image>, &Sprite), Added<Wall>>,
+) {
+    let atlas = match atlas {
+        Some(atlas) if !save.settings.low_spec => atlas,
+        _ => return,
+    };
+    for (entity, texture, sprite) in walls.iter() {
+        if let Some(&index) = atlas.indices.get(texture) {
+            commands
+                .entity(entity)
+                .remove::<Sprite>()
+                .remove::<Handle<Image>>()
+                .insert(TextureAtlasSprite {
+                    index,
+                    color: sprite.color,
+                    flip_x: sprite.flip_x,
+                    flip_y: sprite.flip_y,
+                    custom_size: sprite.custom_size,
+                })
+                .insert(atlas.atlas.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn high_dpi_sprites_sit_next_to_the_originals() {
+        assert_eq!(high_dpi_path("walls/eeee.png"), "walls/eeee@2x.png");
+        assert_eq!(high_dpi_path("cloud"), "cloud@2x");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    animation::SpriteSheetAnimation,
+    ghost::{self, Ghost},
+    menu::{MenuBackground, MenuCamera},
+    player::Player,
+    render_layer::RenderLayer,
+    util::{AnimatedSprite, AnimatedSpriteData},
+    world::{spawn_tiles, GameWorld, WorldTile, WorldType, LEVELS},
+    AppState,
+};
+use bevy::{input::mouse::MouseMotion, prelude::*};
+use std::f32::consts::PI;
+
+// Seconds the menu has to sit idle before the demo starts
+const IDLE_SECONDS: f32 = 10.0;
+const DEMO_LEVEL: usize = 0;
+const DEMO_CAMERA_SCALE: f32 = 0.5;
+const DEMO_CAMERA_INTERPOLATION: f32 = 0.1;
+
+// Plays a recorded run behind the main menu when nobody is using it
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Menu).with_system(reset_attract_mode))
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu)
+                    .with_system(stop_demo_on_input)
+                    .with_system(start_demo)
+                    .with_system(play_demo),
+            );
+    }
+}
+
+struct AttractMode {
+    idle: Timer,
+    demo: Option<Ghost>,
+}
+
+#[derive(Component)]
+struct DemoBee {
+    elapsed: f32,
+    // Where the demo level's layout starts, which the ghost is recorded relative to
+    origin: Vec2,
+}
+
+fn reset_attract_mode(mut commands: Commands) {
+    commands.insert_resource(AttractMode {
+        idle: Timer::from_seconds(IDLE_SECONDS, false),
+        demo: None,
+    });
+}
+
+fn stop_demo_on_input(
+    mut commands: Commands,
+    mut attract: ResMut<AttractMode>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    demo_entities: Query<Entity, Or<(With<WorldTile>, With<DemoBee>)>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MenuCamera>>,
+    mut background: Query<&mut Visibility, With<MenuBackground>>,
+) {
+    let input = mouse_motion.iter().count() > 0
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || keys.get_just_pressed().next().is_some();
+    if !input {
+        return;
+    }
+
+    attract.idle.reset();
+    if attract.demo.take().is_some() {
+        for entity in demo_entities.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        let (mut transform, mut projection) = match camera.get_single_mut() {
+            Ok(camera) => camera,
+            Err(_) => return,
+        };
+        transform.translation = Vec2::ZERO.extend(transform.translation.z);
+        projection.scale = 1.0;
+        for mut visibility in background.iter_mut() {
+            visibility.is_visible = true;
+        }
+    }
+}
+
+fn start_demo(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut attract: ResMut<AttractMode>,
+    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
+    mut textures: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+    mut camera: Query<&mut OrthographicProjection, With<MenuCamera>>,
+    mut background: Query<&mut Visibility, With<MenuBackground>>,
+) {
+    if !attract.idle.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let ghost = ghost::dev_ghost(DEMO_LEVEL).expect("demo level should have a dev ghost");
+    let world = GameWorld::parse(LEVELS[DEMO_LEVEL].2, WorldType::Level { index: DEMO_LEVEL })
+        .expect("demo level should be valid");
+
+    spawn_tiles(
+        &mut commands,
+        &world,
+        &mut animations,
+        &mut textures,
+        &asset_server,
+    );
+    let origin = world.origin().0;
+    commands
+        .spawn_bundle(AnimatedSprite::new(
+            &mut animations,
+            &mut textures,
+            &asset_server,
+            AnimatedSpriteData {
+                path: "bee.png".into(),
+                frames: 6,
+                size: Vec2::splat(Player::SIZE),
+                transform: Transform::from_translation(
+                    (origin + ghost.position(0.0).unwrap_or(Vec2::ZERO)).extend(0.0),
+                ),
+                ..AnimatedSpriteData::default()
+            },
+        ))
+        .insert(DemoBee {
+            elapsed: 0.0,
+            origin,
+        })
+        .insert(RenderLayer::Player);
+
+    if let Ok(mut camera) = camera.get_single_mut() {
+        camera.scale = DEMO_CAMERA_SCALE;
+    }
+    for mut visibility in background.iter_mut() {
+        visibility.is_visible = false;
+    }
+    attract.demo = Some(ghost);
+}
+
+fn play_demo(
+    time: Res<Time>,
+    attract: Res<AttractMode>,
+    mut bee: Query<(&mut Transform, &mut DemoBee), Without<MenuCamera>>,
+    mut camera: Query<&mut Transform, With<MenuCamera>>,
+) {
+    let ghost = match &attract.demo {
+        Some(ghost) => ghost,
+        None => return,
+    };
+
+    for (mut transform, mut bee) in bee.iter_mut() {
+        bee.elapsed += time.delta_seconds();
+        // Loop the demo once the ghost reaches the goal
+        if bee.elapsed > ghost.duration() {
+            bee.elapsed = 0.0;
+        }
+
+        if let Some(position) = ghost.position(bee.elapsed) {
+            let position = bee.origin + position;
+            let velocity = position - transform.translation.truncate();
+            if velocity != Vec2::ZERO {
+                transform.rotation = Quat::from_rotation_z(velocity.y.atan2(velocity.x) - PI / 2.0);
+            }
+            transform.translation = position.extend(transform.translation.z);
+        }
+
+        let mut camera = match camera.get_single_mut() {
+            Ok(camera) => camera,
+            Err(_) => return,
+        };
+        camera.translation = camera
+            .translation
+            .truncate()
+            .lerp(transform.translation.truncate(), DEMO_CAMERA_INTERPOLATION)
+            .extend(camera.translation.z);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{
+    change_state,
+    clock::GameClock,
+    controls::{Controls, ReadControls},
+    enemy::Enemy,
+    game_overlay::GameTimer,
+    pack::LevelRegistry,
+    player::Player,
+    playing,
+    profile::Profile,
+    save::SaveData,
+    upgrades::UpgradeTracker,
+    world::{GameWorld, Tile, Variant, WorldOrigin},
+    AppState,
+};
+use bevy::prelude::*;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    panic,
+    sync::{Arc, Mutex},
+};
+
+// Kept apart from real players' progress
+const SOAK_PROFILE: &str = "Soak test";
+// Soak test results are appended here, one line per attempt
+const REPORT_FILE: &str = "soak-report.txt";
+// Attempts at each level before moving on to the next
+const ATTEMPTS_PER_LEVEL: u32 = 3;
+// Going this long without reaching the next tile on the path counts as stuck
+const SOFT_LOCK_SECONDS: f32 = 10.0;
+// Projectiles closer than this are steered away from
+const DODGE_RADIUS: f32 = Tile::SIZE * 3.0;
+// How strongly dodging outweighs following the path
+const DODGE_WEIGHT: f32 = 2.0;
+
+// Description of the attempt in progress, for reporting crashes
+static CURRENT_ATTEMPT: Mutex<String> = Mutex::new(String::new());
+
+// Plays every level over and over, writing what happened to REPORT_FILE. Runs the
+// regular game, so it can be left going in a minimized window for as long as needed.
+pub struct AutoPilotPlugin;
+
+impl Plugin for AutoPilotPlugin {
+    fn build(&self, app: &mut App) {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let attempt = CURRENT_ATTEMPT
+                .lock()
+                .map(|attempt| attempt.clone())
+                .unwrap_or_default();
+            report(&format!("{attempt}: crashed: {info}"));
+            default_hook(info);
+        }));
+
+        app.init_resource::<Soak>()
+            .add_startup_system(use_soak_profile)
+            .add_system_set(SystemSet::on_update(AppState::Menu).with_system(start_attempt))
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(plan_route))
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(steer.after(ReadControls)),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Retry).with_system(report_death))
+            .add_system_set(SystemSet::on_update(AppState::Victory).with_system(report_victory));
+    }
+}
+
+fn use_soak_profile(mut commands: Commands) {
+    match Profile::open(SOAK_PROFILE) {
+        Ok(profile) => {
+            let mut save = SaveData::load(&profile);
+            save.player_name = Some(SOAK_PROFILE.to_string());
+            save.onboarded = true;
+            // Soak tests run unattended, often in a window in the background
+            save.settings.idle_pause_seconds = 0;
+            save.settings.pause_when_unfocused = false;
+            commands.insert_resource(save);
+            commands.insert_resource(profile);
+        }
+        Err(err) => warn!("Failed to open the soak test profile: {err}"),
+    }
+}
+
+#[derive(Default)]
+struct Soak {
+    level: usize,
+    attempt: u32,
+    // Name of the level being played, for the report
+    name: String,
+}
+
+impl Soak {
+    fn describe(&self) -> String {
+        format!("{} attempt {}", self.name, self.attempt + 1)
+    }
+
+    fn next_level(&mut self, levels: &LevelRegistry) {
+        self.level = (self.level + 1) % levels.levels.len();
+        self.attempt = 0;
+    }
+}
+
+// Route through the current level and how far along it the bee is
+struct AutoPilot {
+    path: Vec<(usize, usize)>,
+    next: usize,
+    // Seconds since the last tile on the path was reached
+    stalled: f32,
+    position: Vec2,
+}
+
+fn report(line: &str) {
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(REPORT_FILE)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(err) = result {
+        warn!("Failed to write to {REPORT_FILE}: {err}");
+    }
+}
+
+fn start_attempt(
+    mut commands: Commands,
+    mut soak: ResMut<Soak>,
+    mut levels: ResMut<LevelRegistry>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    mut state: ResMut<State<AppState>>,
+) {
+    soak.name = levels.levels[soak.level].display_name();
+    match levels.load_cached(soak.level, Variant::Original, false) {
+        Ok(world) => {
+            *CURRENT_ATTEMPT.lock().unwrap() = soak.describe();
+            commands.insert_resource(world);
+            *upgrades = UpgradeTracker::default();
+            change_state(&mut state, AppState::Game);
+        }
+        Err(err) => {
+            report(&format!("{}: failed to load: {err}", soak.name));
+            soak.next_level(&levels);
+        }
+    }
+}
+
+fn plan_route(
+    mut commands: Commands,
+    world: Res<Arc<GameWorld>>,
+    mut soak: ResMut<Soak>,
+    levels: Res<LevelRegistry>,
+    mut state: ResMut<State<AppState>>,
+) {
+    match world.path_to_goal() {
+        Some(path) => commands.insert_resource(AutoPilot {
+            path,
+            next: 0,
+            stalled: 0.0,
+            position: Vec2::ZERO,
+        }),
+        None => {
+            report(&format!("{}: goal is unreachable", soak.describe()));
+            soak.next_level(&levels);
+            change_state(&mut state, AppState::Menu);
+        }
+    }
+}
+
+// Direction to fly in to follow the path, turned away from nearby projectiles
+fn steering(position: Vec2, waypoint: Vec2, projectiles: impl Iterator<Item = Vec2>) -> Vec2 {
+    let dodge: Vec2 = projectiles
+        .map(|projectile| position - projectile)
+        .filter(|away| away.length() < DODGE_RADIUS)
+        .fold(Vec2::ZERO, |dodge, away| {
+            dodge + away.normalize_or_zero() * (1.0 - away.length() / DODGE_RADIUS)
+        });
+    (waypoint - position).normalize_or_zero() + dodge * DODGE_WEIGHT
+}
+
+fn steer(
+    clock: Res<GameClock>,
+    autopilot: Option<ResMut<AutoPilot>>,
+    mut soak: ResMut<Soak>,
+    levels: Res<LevelRegistry>,
+    mut state: ResMut<State<AppState>>,
+    mut controls: ResMut<Controls>,
+    origin: Res<WorldOrigin>,
+    player: Query<&Transform, With<Player>>,
+    enemies: Query<&Transform, With<Enemy>>,
+) {
+    let (mut autopilot, transform) = match (autopilot, player.get_single()) {
+        (Some(autopilot), Ok(transform)) => (autopilot, transform),
+        _ => return,
+    };
+    let position = transform.translation.truncate();
+    autopilot.position = position;
+
+    // Skip ahead to wherever the bee has got to on the path
+    if let Some(tile) = origin.world_to_tile(position) {
+        if let Some(reached) = autopilot.path[autopilot.next..]
+            .iter()
+            .position(|waypoint| *waypoint == tile)
+        {
+            autopilot.next = (autopilot.next + reached + 1).min(autopilot.path.len() - 1);
+            autopilot.stalled = 0.0;
+        }
+    }
+    autopilot.stalled += clock.delta_seconds();
+    if autopilot.stalled > SOFT_LOCK_SECONDS {
+        report(&format!(
+            "{}: stuck at tile {:?}",
+            soak.describe(),
+            origin.world_to_tile(position)
+        ));
+        soak.next_level(&levels);
+        change_state(&mut state, AppState::Menu);
+        return;
+    }
+
+    let waypoint = origin.tile_to_world(autopilot.path[autopilot.next]);
+    let direction = steering(
+        position,
+        waypoint,
+        enemies
+            .iter()
+            .map(|transform| transform.translation.truncate()),
+    );
+    *controls = Controls {
+        aim: Some(direction.y.atan2(direction.x)),
+        throttle: direction.length().min(1.0),
+        ..Controls::default()
+    };
+}
+
+fn report_death(
+    mut soak: ResMut<Soak>,
+    levels: Res<LevelRegistry>,
+    autopilot: Option<Res<AutoPilot>>,
+    origin: Res<WorldOrigin>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let position = autopilot.map(|autopilot| autopilot.position);
+    report(&format!(
+        "{}: died at tile {:?}",
+        soak.describe(),
+        position.and_then(|position| origin.world_to_tile(position))
+    ));
+    soak.attempt += 1;
+    if soak.attempt < ATTEMPTS_PER_LEVEL {
+        *CURRENT_ATTEMPT.lock().unwrap() = soak.describe();
+        change_state(&mut state, AppState::Game);
+    } else {
+        soak.next_level(&levels);
+        change_state(&mut state, AppState::Menu);
+    }
+}
+
+fn report_victory(
+    mut soak: ResMut<Soak>,
+    levels: Res<LevelRegistry>,
+    timer: Option<Res<GameTimer>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let time = timer.map_or(0.0, |timer| timer.elapsed());
+    report(&format!("{}: won in {time:.3}s", soak.describe()));
+    soak.next_level(&levels);
+    change_state(&mut state, AppState::Menu);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projectiles_push_the_bee_aside() {
+        let waypoint = Vec2::new(100.0, 0.0);
+        // Nothing nearby, so straight at the waypoint
+        let direction = steering(Vec2::ZERO, waypoint, [Vec2::new(0.0, 500.0)].into_iter());
+        assert_eq!(direction, Vec2::X);
+
+        // A projectile just above pushes the bee downwards
+        let direction = steering(Vec2::ZERO, waypoint, [Vec2::new(0.0, 10.0)].into_iter());
+        assert!(direction.x > 0.0 && direction.y < -1.0);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    clock::GameClock,
+    game_overlay::GameTimer,
+    player::Player,
+    playing,
+    ui::GameFont,
+    world::{GameWorld, OutOfBounds, WorldType},
+    AppState, GameState,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+
+pub struct BoundsPlugin;
+
+impl Plugin for BoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Game)
+                .with_system(spawn_bounds_warning)
+                .with_system(reset_out_of_bounds_timer),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(playing)
+                .with_system(keep_player_in_bounds)
+                .with_system(check_out_of_bounds),
+        );
+    }
+}
+
+// Fraction of the distance back into the play area covered per second in endless mode
+const RETURN_RATE: f32 = 3.0;
+// How far outside the level the player can go before the warning starts
+const OUT_OF_BOUNDS_MARGIN: f32 = 120.0;
+// Seconds the player has to come back before being respawned or killed
+const RETURN_SECONDS: f32 = 3.0;
+
+// Counts down while the player is too far outside the level
+struct OutOfBoundsTimer(Timer);
+
+fn reset_out_of_bounds_timer(mut commands: Commands) {
+    commands.insert_resource(OutOfBoundsTimer(Timer::from_seconds(RETURN_SECONDS, false)));
+}
+
+#[derive(Component)]
+struct BoundsWarning;
+
+fn spawn_bounds_warning(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(25.0),
+                    left: Val::Percent(35.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "Return to the play area!",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 45.0,
+                    color: Color::rgb(1.0, 0.4, 0.3),
+                },
+                TextAlignment::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..TextBundle::default()
+        })
+        .insert(BoundsWarning);
+}
+
+// Where the player would be if pushed fully back inside the level
+fn clamp_to_bounds(position: Vec2, half_size: Vec2, (min, max): (Vec2, Vec2)) -> Vec2 {
+    // Levels smaller than the player keep it centered
+    let (min, max) = (
+        (min + half_size).min((min + max) / 2.0),
+        (max - half_size).max((min + max) / 2.0),
+    );
+    position.clamp(min, max)
+}
+
+// Levels with a boundary stop the player at the edge. Endless mode pulls them back
+// gently instead, with a warning while they're outside.
+fn keep_player_in_bounds(
+    clock: Res<GameClock>,
+    world: Res<Arc<GameWorld>>,
+    mut player: Query<&mut Transform, With<Player>>,
+    mut warning: Query<&mut Visibility, With<BoundsWarning>>,
+) {
+    let endless = matches!(world.world_type, WorldType::Endless);
+    if !endless && !world.metadata.boundary {
+        return;
+    }
+    let mut transform = match player.get_single_mut() {
+        Ok(transform) => transform,
+        Err(_) => return,
+    };
+
+    let position = transform.translation.truncate();
+    let half_size = Vec2::splat(Player::SIZE / 2.0) * transform.scale.truncate();
+    let inside = clamp_to_bounds(position, half_size, world.bounds());
+    let target = if endless {
+        position.lerp(inside, (RETURN_RATE * clock.delta_seconds()).min(1.0))
+    } else {
+        inside
+    };
+    transform.translation.x = target.x;
+    transform.translation.y = target.y;
+
+    if let (true, Ok(mut visibility)) = (endless, warning.get_single_mut()) {
+        visibility.is_visible = inside != position;
+    }
+}
+
+// Levels can choose what happens to a player who strays too far, after a warning
+fn check_out_of_bounds(
+    clock: Res<GameClock>,
+    world: Res<Arc<GameWorld>>,
+    game_timer: Option<Res<GameTimer>>,
+    mut timer: ResMut<OutOfBoundsTimer>,
+    mut state: ResMut<State<GameState>>,
+    mut player: Query<&mut Transform, With<Player>>,
+    mut warning: Query<(&mut Text, &mut Visibility), With<BoundsWarning>>,
+) {
+    let behavior = match world.metadata.out_of_bounds {
+        Some(behavior) if !matches!(world.world_type, WorldType::Endless) => behavior,
+        _ => return,
+    };
+    let (mut transform, (mut text, mut visibility)) =
+        match (player.get_single_mut(), warning.get_single_mut()) {
+            (Ok(transform), Ok(warning)) => (transform, warning),
+            _ => return,
+        };
+
+    let position = transform.translation.truncate();
+    let (min, max) = world.bounds();
+    let outside = position.distance(position.clamp(min, max)) > OUT_OF_BOUNDS_MARGIN;
+    visibility.is_visible = outside;
+    if !outside {
+        timer.0.reset();
+        return;
+    }
+
+    let remaining = RETURN_SECONDS - timer.0.tick(clock.delta()).elapsed_secs();
+    text.sections[0].value = format!("Return! {remaining:.1}");
+    if timer.0.finished() {
+        timer.0.reset();
+        match behavior {
+            // Back at the last checkpoint reached, or the start if there wasn't one
+            OutOfBounds::Respawn => {
+                let checkpoint = game_timer
+                    .and_then(|timer| timer.splits().last().copied())
+                    .and_then(|(checkpoint, _)| world.checkpoint_coordinates(checkpoint));
+                let tile = checkpoint.unwrap_or_else(|| world.safe_start());
+                let start = world.origin().tile_to_world(tile);
+                transform.translation.x = start.x;
+                transform.translation.y = start.y;
+            }
+            // Overwrite in case a collision already queued a death this frame
+            OutOfBounds::Death => {
+                if let Err(err) = state.overwrite_set(GameState::Dying) {
+                    warn!("Couldn't change state to {:?}: {err:?}", GameState::Dying);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_are_clamped_inside_the_level() {
+        let bounds = (Vec2::new(-12.0, -60.0), Vec2::new(60.0, 12.0));
+        let half_size = Vec2::splat(6.0);
+        assert_eq!(
+            clamp_to_bounds(Vec2::new(0.0, 0.0), half_size, bounds),
+            Vec2::new(0.0, 0.0)
+        );
+        assert_eq!(
+            clamp_to_bounds(Vec2::new(-100.0, 100.0), half_size, bounds),
+            Vec2::new(-6.0, 6.0)
+        );
+        // Too big to fit, so kept in the middle
+        assert_eq!(
+            clamp_to_bounds(Vec2::new(100.0, 0.0), Vec2::splat(100.0), bounds),
+            Vec2::new(24.0, -24.0)
+        );
+    }
+}
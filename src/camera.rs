@@ -1,15 +1,37 @@
 use crate::{
+    change_state,
     player::Player,
-    upgrades::{Upgrade, UpgradeTracker},
-    AppState,
+    playing,
+    powerups::{self, ActivePowerUps},
+    upgrades::UpgradeTracker,
+    GameState,
+};
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
 };
-use bevy::prelude::*;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(follow_player));
+        app.init_resource::<CameraZoom>()
+            .add_system(fit_view)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(follow_player)
+                    .with_system(zoom_camera)
+                    .with_system(enter_photo_mode),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::PhotoMode).with_system(hide_hud))
+            .add_system_set(
+                SystemSet::on_update(GameState::PhotoMode)
+                    .with_system(pan_photo_camera)
+                    .with_system(zoom_camera)
+                    .with_system(exit_photo_mode),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::PhotoMode).with_system(show_hud));
     }
 }
 
@@ -20,6 +42,56 @@ impl MainCamera {
     const INTERPOLATION: f32 = 0.1;
 }
 
+// World units shown at the default zoom whatever the window size, 26.7 by 15 tiles.
+// Windows with a different aspect ratio see extra along one axis only.
+pub const VIEW_WIDTH: f32 = 640.0;
+pub const VIEW_HEIGHT: f32 = 360.0;
+// Limits of the player's zoom, zooming out as far as 40 by 22.5 tiles
+const MIN_ZOOM: f32 = 2.0 / 3.0;
+const MAX_ZOOM: f32 = 1.5;
+// Zoom multiplier for each notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.1;
+// Pixels of touchpad scrolling that count as one notch
+const PIXELS_PER_NOTCH: f32 = 50.0;
+
+// Magnification of the game view chosen with the mouse wheel, kept between runs
+pub struct CameraZoom(pub f32);
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+// Largest area the player can see, which spawners treat as on screen
+pub fn widest_view() -> Vec2 {
+    Vec2::new(VIEW_WIDTH, VIEW_HEIGHT) / MIN_ZOOM
+}
+
+// Projection scale that fits the logical view inside a window of the given size
+fn projection_scale(window: Vec2, zoom: f32) -> f32 {
+    (Vec2::new(VIEW_WIDTH, VIEW_HEIGHT) / window).max_element() / zoom
+}
+
+// World position under a cursor, in window pixels from the bottom left, wherever the camera
+// is pointed and however far it's zoomed
+pub fn cursor_to_world(
+    cursor: Vec2,
+    window_size: Vec2,
+    camera: &Camera,
+    transform: &GlobalTransform,
+) -> Vec2 {
+    let ndc = (cursor / window_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = transform.compute_matrix() * camera.projection_matrix.inverse();
+    ndc_to_world.project_point3(ndc.extend(-1.0)).truncate()
+}
+
+// Temporarily points the camera somewhere other than the player
+pub struct CameraOverride {
+    pub position: Vec2,
+    pub timer: Timer,
+}
+
 pub fn spawn_camera(commands: &mut Commands, position: Vec2) {
     let mut orthographic_camera_bundle = OrthographicCameraBundle::new_2d();
     orthographic_camera_bundle.orthographic_projection.scale = 0.5;
@@ -32,25 +104,170 @@ pub fn spawn_camera(commands: &mut Commands, position: Vec2) {
         .insert(MainCamera);
 }
 
+fn fit_view(
+    windows: Res<Windows>,
+    zoom: Res<CameraZoom>,
+    mut camera: Query<&mut OrthographicProjection, With<MainCamera>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) if window.width() > 0.0 && window.height() > 0.0 => window,
+        // Minimized
+        _ => return,
+    };
+    if let Ok(mut projection) = camera.get_single_mut() {
+        let scale = projection_scale(Vec2::new(window.width(), window.height()), zoom.0);
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+fn zoom_camera(mut wheel: EventReader<MouseWheel>, mut zoom: ResMut<CameraZoom>) {
+    let notches: f32 = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_NOTCH,
+        })
+        .sum();
+    if notches != 0.0 {
+        zoom.0 = (zoom.0 * ZOOM_STEP.powf(notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
 fn follow_player(
+    mut commands: Commands,
+    time: Res<Time>,
     mut camera_transform: Query<&mut Transform, With<MainCamera>>,
     player_transform: Query<&Transform, (With<Player>, Without<MainCamera>)>,
     upgrades: Res<UpgradeTracker>,
+    power_ups: Res<ActivePowerUps>,
+    camera_override: Option<ResMut<CameraOverride>>,
 ) {
-    let mut camera_transform = camera_transform.single_mut();
-    let player_transform = player_transform.single();
+    let mut camera_transform = match camera_transform.get_single_mut() {
+        Ok(camera_transform) => camera_transform,
+        Err(_) => return,
+    };
+    let mut target = match player_transform.get_single() {
+        Ok(player_transform) => player_transform.translation.truncate(),
+        Err(_) => return,
+    };
 
-    let interpolation = MainCamera::INTERPOLATION
-        // Double interpolation if double speed is active
-        * if upgrades.has_upgrade(Upgrade::DoubleSpeed) {
-            2.0
+    if let Some(mut camera_override) = camera_override {
+        if camera_override.timer.tick(time.delta()).finished() {
+            commands.remove_resource::<CameraOverride>();
         } else {
-            1.0
-        };
+            target = camera_override.position;
+        }
+    }
+
+    // Keep up with the player when they're sped up
+    let interpolation = MainCamera::INTERPOLATION * powerups::player_speed(&upgrades, &power_ups);
 
     camera_transform.translation = camera_transform
         .translation
         .truncate()
-        .lerp(player_transform.translation.truncate(), interpolation)
+        .lerp(target, interpolation)
         .extend(camera_transform.translation.z);
 }
+
+// Pixels per second the camera moves in photo mode
+const PHOTO_PAN_SPEED: f32 = 400.0;
+
+// UI hidden for photo mode, so only it is shown again afterwards
+#[derive(Component)]
+struct HiddenForPhoto;
+
+fn enter_photo_mode(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keys.clear_just_pressed(KeyCode::P) {
+        change_state(&mut state, GameState::PhotoMode);
+    }
+}
+
+fn exit_photo_mode(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keys.clear_just_pressed(KeyCode::P) || keys.clear_just_pressed(KeyCode::Escape) {
+        change_state(&mut state, GameState::Playing);
+    }
+}
+
+fn pan_photo_camera(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    let mut direction = Vec2::ZERO;
+    for (key, offset) in [
+        (KeyCode::W, Vec2::Y),
+        (KeyCode::A, -Vec2::X),
+        (KeyCode::S, -Vec2::Y),
+        (KeyCode::D, Vec2::X),
+    ] {
+        if keys.pressed(key) {
+            direction += offset;
+        }
+    }
+    let mut transform = match camera.get_single_mut() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    transform.translation += (direction * PHOTO_PAN_SPEED * time.delta_seconds()).extend(0.0);
+}
+
+fn hide_hud(mut commands: Commands, mut nodes: Query<(Entity, &mut Visibility), With<Node>>) {
+    for (entity, mut visibility) in nodes.iter_mut() {
+        if visibility.is_visible {
+            visibility.is_visible = false;
+            commands.entity(entity).insert(HiddenForPhoto);
+        }
+    }
+}
+
+fn show_hud(
+    mut commands: Commands,
+    mut nodes: Query<(Entity, &mut Visibility), With<HiddenForPhoto>>,
+) {
+    for (entity, mut visibility) in nodes.iter_mut() {
+        visibility.is_visible = true;
+        commands.entity(entity).remove::<HiddenForPhoto>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::camera::CameraProjection;
+
+    #[test]
+    fn the_view_fits_any_window() {
+        // The default window shows exactly the logical view
+        assert_eq!(projection_scale(Vec2::new(1280.0, 720.0), 1.0), 0.5);
+        assert_eq!(projection_scale(Vec2::new(2560.0, 1440.0), 1.0), 0.25);
+        // Wider and taller windows keep the whole view on screen
+        let ultrawide = Vec2::new(3440.0, 1440.0);
+        assert_eq!(ultrawide.y * projection_scale(ultrawide, 1.0), VIEW_HEIGHT);
+        let portrait = Vec2::new(720.0, 1280.0);
+        assert_eq!(portrait.x * projection_scale(portrait, 1.0), VIEW_WIDTH);
+        // Zooming out shows more
+        assert_eq!(projection_scale(Vec2::new(1280.0, 720.0), 0.5), 1.0);
+    }
+
+    #[test]
+    fn cursors_follow_the_camera() {
+        let window = Vec2::new(800.0, 600.0);
+        let mut projection = OrthographicProjection {
+            scale: 0.5,
+            ..OrthographicProjection::default()
+        };
+        projection.update(window.x, window.y);
+        let camera = Camera {
+            projection_matrix: projection.get_projection_matrix(),
+            ..Camera::default()
+        };
+        let transform = GlobalTransform::from_xyz(100.0, 50.0, 999.0);
+
+        let to_world = |cursor| cursor_to_world(cursor, window, &camera, &transform);
+        assert!(to_world(window / 2.0).abs_diff_eq(Vec2::new(100.0, 50.0), 1e-3));
+        assert!(to_world(Vec2::ZERO).abs_diff_eq(Vec2::new(-100.0, -100.0), 1e-3));
+        assert!(to_world(window).abs_diff_eq(Vec2::new(300.0, 200.0), 1e-3));
+    }
+}
@@ -0,0 +1,375 @@
+use crate::{
+    challenges::{ActiveChallenges, Challenge},
+    change_state,
+    pack::LevelRegistry,
+    replay::RunSeed,
+    ui::GameFont,
+    upgrades::UpgradeTracker,
+    world::{GameWorld, Variant, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+
+// Short codes for sharing a run's level, variant, challenges and seed, shown after winning.
+// Typing one in on level select plays the same challenge, and best times from it are kept
+// under the code.
+pub struct ChallengeCodePlugin;
+
+impl Plugin for ChallengeCodePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChallengeRun>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelSelect).with_system(create_code_entry),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::LevelSelect)
+                    .with_system(type_code)
+                    .with_system(play_code),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Victory).with_system(create_challenge_code_text),
+            );
+    }
+}
+
+// Crockford's base 32, which leaves out letters easily mistaken for digits
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_BYTES: usize = 9;
+// Characters in a code, not counting the dashes between groups
+const CODE_LENGTH: usize = (CODE_BYTES * 8).div_ceil(5);
+const GROUP_LENGTH: usize = 5;
+const WARNING_COLOR: Color = Color::rgb(1.0, 0.4, 0.3);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChallengeCode {
+    pub level: usize,
+    pub variant: Variant,
+    // ActiveChallenges::bits
+    pub challenges: u8,
+    pub seed: u32,
+    // Lowest byte of the level's layout hash, so a code for an edited level isn't mistaken
+    // for one for the version here
+    pub level_hash: u8,
+}
+
+impl ChallengeCode {
+    // The code for the run on `world`, if it's on a level
+    pub fn for_run(world: &GameWorld, challenges: &ActiveChallenges, seed: u64) -> Option<Self> {
+        match world.world_type {
+            WorldType::Level { index } => Some(Self {
+                level: index,
+                variant: world.variant,
+                challenges: challenges.bits(),
+                seed: seed as u32,
+                level_hash: world.hash() as u8,
+            }),
+            WorldType::Endless => None,
+        }
+    }
+
+    // Packed as: level, variant, challenges, seed (u32 little endian), level hash and a
+    // checksum of the rest, written out in base 32 in dashed groups
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![
+            self.level as u8,
+            Variant::iter().position(|v| v == self.variant).unwrap() as u8,
+            self.challenges,
+        ];
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.level_hash);
+        bytes.push(checksum(&bytes));
+
+        let bits = bytes
+            .iter()
+            .fold(0u128, |bits, &byte| bits << 8 | byte as u128);
+        let characters: Vec<char> = (0..CODE_LENGTH)
+            .rev()
+            .map(|index| ALPHABET[(bits >> (index * 5) & 31) as usize] as char)
+            .collect();
+        characters
+            .chunks(GROUP_LENGTH)
+            .map(|group| group.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    // Read a typed code, which can be in either case with or without the dashes
+    pub fn decode(code: &str) -> Result<Self, String> {
+        let mut bits = 0u128;
+        let mut length = 0;
+        for typed in code.chars().filter(|&c| c != '-' && !c.is_whitespace()) {
+            // Letters that look like digits are read as those digits
+            let typed = match typed.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                typed => typed,
+            };
+            let value = ALPHABET
+                .iter()
+                .position(|&c| c as char == typed)
+                .ok_or_else(|| format!("'{typed}' isn't in challenge codes"))?;
+            bits = bits << 5 | value as u128;
+            length += 1;
+        }
+        if length != CODE_LENGTH {
+            return Err(format!("Challenge codes are {CODE_LENGTH} characters long"));
+        }
+        let bytes: Vec<u8> = (0..CODE_BYTES)
+            .rev()
+            .map(|index| (bits >> (index * 8)) as u8)
+            .collect();
+        let invalid = || "That challenge code isn't right, check for typos".to_string();
+        if bits >> (CODE_BYTES * 8) != 0 || checksum(&bytes[..8]) != bytes[8] {
+            return Err(invalid());
+        }
+        let variant = Variant::iter().nth(bytes[1] as usize).ok_or_else(invalid)?;
+        ActiveChallenges::from_bits(bytes[2]).ok_or_else(invalid)?;
+        Ok(Self {
+            level: bytes[0] as usize,
+            variant,
+            challenges: bytes[2],
+            seed: u32::from_le_bytes([bytes[3], bytes[4], bytes[5], bytes[6]]),
+            level_hash: bytes[7],
+        })
+    }
+}
+
+// Catches most typos, including swapped characters
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &byte| {
+        sum.rotate_left(3).wrapping_add(byte) ^ 0x5a
+    })
+}
+
+// The code the current run was started from, if it was typed in
+#[derive(Debug, Default)]
+pub struct ChallengeRun(pub Option<ChallengeCode>);
+
+#[derive(Component)]
+struct CodeText;
+
+#[derive(Component)]
+struct CodeMessage;
+
+// What's been typed so far, cleared each time level select opens
+#[derive(Default)]
+struct TypedCode(String);
+
+fn create_code_entry(mut commands: Commands, font: Res<GameFont>) {
+    commands.insert_resource(TypedCode::default());
+    // Picking a level afterwards is a normal run
+    commands.insert_resource(ChallengeRun::default());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(6.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        code_prompt(""),
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(CodeText);
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 24.0,
+                            color: WARNING_COLOR,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(CodeMessage);
+        });
+}
+
+fn code_prompt(typed: &str) -> String {
+    format!("Challenge code: {typed}_ (Enter to play)")
+}
+
+fn type_code(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut typed: ResMut<TypedCode>,
+    mut text: Query<&mut Text, With<CodeText>>,
+) {
+    let before = typed.0.clone();
+    for character in characters.iter() {
+        match character.char {
+            '\u{8}' | '\u{7f}' => {
+                typed.0.pop();
+            }
+            // Room for the dashes, but no more
+            _ if typed.0.len() >= CODE_LENGTH + CODE_LENGTH / GROUP_LENGTH => {}
+            c if c.is_ascii_alphanumeric() || c == '-' => typed.0.push(c.to_ascii_uppercase()),
+            _ => {}
+        }
+    }
+    if typed.0 != before {
+        if let Ok(mut text) = text.get_single_mut() {
+            text.sections[0].value = code_prompt(&typed.0);
+        }
+    }
+}
+
+// Load the level the typed code is for, with its challenges, and start it the way level
+// select does
+#[allow(clippy::too_many_arguments)]
+fn play_code(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut levels: ResMut<LevelRegistry>,
+    mut challenges: ResMut<ActiveChallenges>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    typed: Res<TypedCode>,
+    mut message: Query<&mut Text, With<CodeMessage>>,
+) {
+    if typed.0.is_empty() || !keys.clear_just_pressed(KeyCode::Return) {
+        return;
+    }
+    let result = ChallengeCode::decode(&typed.0).and_then(|code| {
+        let wanted = ActiveChallenges::from_bits(code.challenges).unwrap_or_default();
+        if code.level >= levels.levels.len() {
+            return Err("That challenge is for a level that isn't here".to_string());
+        }
+        let world = levels
+            .load_cached(
+                code.level,
+                code.variant,
+                wanted.is_active(Challenge::Mirrored),
+            )
+            .map_err(|err| format!("Couldn't load the level: {err}"))?;
+        if world.hash() as u8 != code.level_hash {
+            return Err("That challenge is for a different version of the level".to_string());
+        }
+        Ok((code, wanted, world))
+    });
+    let (code, wanted, world) = match result {
+        Ok(run) => run,
+        Err(reason) => {
+            if let Ok(mut text) = message.get_single_mut() {
+                text.sections[0].value = reason;
+            }
+            return;
+        }
+    };
+
+    commands.insert_resource(world);
+    commands.insert_resource(ChallengeRun(Some(code)));
+    *challenges = wanted;
+    if challenges.is_active(Challenge::NoUpgrades) {
+        *upgrades = UpgradeTracker::default();
+        change_state(&mut state, AppState::Game);
+    } else {
+        change_state(&mut state, AppState::UpgradeSelect);
+    }
+}
+
+fn create_challenge_code_text(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    world: Res<Arc<GameWorld>>,
+    challenges: Res<ActiveChallenges>,
+    seed: Res<RunSeed>,
+) {
+    let code = match ChallengeCode::for_run(&world, &challenges, seed.0) {
+        Some(code) => code,
+        None => return,
+    };
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                bottom: Val::Percent(5.0),
+                left: Val::Percent(2.0),
+                ..Rect::default()
+            },
+            ..Style::default()
+        },
+        text: Text::with_section(
+            format!("Challenge code: {}", code.encode()),
+            TextStyle {
+                font: font.get_handle(),
+                font_size: 30.0,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code() -> ChallengeCode {
+        ChallengeCode {
+            level: 3,
+            variant: Variant::Rotated180,
+            challenges: 0b100010,
+            seed: 0xdead_beef,
+            level_hash: 0x42,
+        }
+    }
+
+    #[test]
+    fn codes_round_trip() {
+        let encoded = code().encode();
+        assert_eq!(encoded.len(), CODE_LENGTH + 2);
+        assert_eq!(ChallengeCode::decode(&encoded), Ok(code()));
+        // Typed without dashes, in lower case, with look-alike letters
+        let typed = encoded.replace('-', "").to_lowercase().replace('0', "o");
+        assert_eq!(ChallengeCode::decode(&typed), Ok(code()));
+    }
+
+    #[test]
+    fn typos_are_caught() {
+        let encoded = code().encode();
+        assert!(ChallengeCode::decode(&encoded[1..]).is_err());
+        assert!(ChallengeCode::decode(&format!("{encoded}0")).is_err());
+        assert!(ChallengeCode::decode(&encoded.replacen('-', "U", 1)).is_err());
+
+        let mut swapped: Vec<char> = encoded.chars().collect();
+        let last = swapped.len() - 1;
+        for index in 0..last {
+            let (a, b) = (swapped[index], swapped[index + 1]);
+            if a != b && a != '-' && b != '-' {
+                let mut typo = swapped.clone();
+                typo.swap(index, index + 1);
+                let typo: String = typo.into_iter().collect();
+                assert!(ChallengeCode::decode(&typo).is_err(), "{typo} was accepted");
+            }
+        }
+        swapped[0] = if swapped[0] == '1' { '2' } else { '1' };
+        let typo: String = swapped.into_iter().collect();
+        assert!(ChallengeCode::decode(&typo).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{
+    ui::{GameFont, ACTIVE_BUTTON_COLOR, NORMAL_BUTTON_COLOR},
+    util::ReflectResource,
+    AppState,
+};
+use bevy::prelude::*;
+use std::collections::BTreeSet;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+pub struct ChallengesPlugin;
+
+impl Plugin for ChallengesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveChallenges>()
+            .register_type::<ActiveChallenges>()
+            .add_system_set(
+                SystemSet::on_update(AppState::LevelSelect)
+                    .with_system(manage_challenge_buttons)
+                    .with_system(update_reward_text),
+            );
+    }
+}
+
+// Optional handicaps for a level attempt, chosen in level select
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Challenge {
+    NoUpgrades,
+    Mirrored,
+    FastProjectiles,
+    Fog,
+    // Flying flat out uses up stamina
+    RealisticFlight,
+    // The bee falls and the cursor is thrust, like a lunar lander
+    Gravity,
+}
+
+impl Challenge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Challenge::NoUpgrades => "No upgrades",
+            Challenge::Mirrored => "Mirrored",
+            Challenge::FastProjectiles => "2x projectiles",
+            Challenge::Fog => "Fog of war",
+            Challenge::RealisticFlight => "Realistic flight",
+            Challenge::Gravity => "Gravity",
+        }
+    }
+
+    fn reward_multiplier(self) -> f32 {
+        match self {
+            Challenge::NoUpgrades => 1.5,
+            Challenge::Mirrored => 1.25,
+            Challenge::FastProjectiles => 1.5,
+            Challenge::Fog => 1.5,
+            Challenge::RealisticFlight => 1.25,
+            Challenge::Gravity => 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Reflect)]
+#[reflect_value(Resource)]
+pub struct ActiveChallenges(BTreeSet<Challenge>);
+
+impl ActiveChallenges {
+    pub fn toggle(&mut self, challenge: Challenge) {
+        if !self.0.remove(&challenge) {
+            self.0.insert(challenge);
+        }
+    }
+
+    pub fn is_active(&self, challenge: Challenge) -> bool {
+        self.0.contains(&challenge)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // One bit per challenge, in the order they're listed
+    pub fn bits(&self) -> u8 {
+        Challenge::iter()
+            .enumerate()
+            .filter(|(_, challenge)| self.is_active(*challenge))
+            .fold(0, |bits, (index, _)| bits | 1 << index)
+    }
+
+    // The challenges from `bits`, or None if any don't stand for a challenge
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        let challenges: BTreeSet<_> = Challenge::iter()
+            .enumerate()
+            .filter(|(index, _)| bits & 1 << index != 0)
+            .map(|(_, challenge)| challenge)
+            .collect();
+        let all = (1u16 << Challenge::iter().count()) - 1;
+        (bits as u16 & !all == 0).then_some(Self(challenges))
+    }
+
+    pub fn reward_multiplier(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|challenge| challenge.reward_multiplier())
+            .product()
+    }
+
+    pub fn projectile_speed(&self) -> f32 {
+        if self.is_active(Challenge::FastProjectiles) {
+            2.0
+        } else {
+            1.0
+        }
+    }
+
+    // Name of a run of the given level, tagged with the active challenges so
+    // modified runs are recorded separately
+    pub fn run_name(&self, level: &str) -> String {
+        if self.is_empty() {
+            return level.to_string();
+        }
+        let names: Vec<&str> = self.0.iter().map(|challenge| challenge.name()).collect();
+        format!("{level} [{}]", names.join(", "))
+    }
+}
+
+#[derive(Component)]
+pub struct ChallengeButton(Challenge);
+
+#[derive(Component)]
+struct RewardText;
+
+// Spawn the challenge toggles for the level select screen
+pub fn spawn_challenge_panel(parent: &mut ChildBuilder, font: &GameFont, height: f32) {
+    let text_style = |size| TextStyle {
+        font: font.get_handle(),
+        font_size: size,
+        color: Color::BLACK,
+    };
+
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(35.0), Val::Percent(height)),
+                margin: Rect {
+                    left: Val::Auto,
+                    right: Val::Percent(5.0),
+                    top: Val::Auto,
+                    bottom: Val::Auto,
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceAround,
+                ..Style::default()
+            },
+            color: Color::GRAY.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("Challenges", text_style(40.0), TextAlignment::default()),
+                ..TextBundle::default()
+            });
+
+            for challenge in Challenge::iter() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(90.0), Val::Percent(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        ..ButtonBundle::default()
+                    })
+                    .insert(ChallengeButton(challenge))
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                format!(
+                                    "{} (x{})",
+                                    challenge.name(),
+                                    challenge.reward_multiplier()
+                                ),
+                                text_style(26.0),
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        });
+                    });
+            }
+
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("", text_style(30.0), TextAlignment::default()),
+                    ..TextBundle::default()
+                })
+                .insert(RewardText);
+        });
+}
+
+fn manage_challenge_buttons(
+    mut challenges: ResMut<ActiveChallenges>,
+    mut interaction: Query<
+        (&Interaction, &mut UiColor, &ChallengeButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button) in interaction.iter_mut() {
+        if matches!(interaction, Interaction::Clicked) {
+            challenges.toggle(button.0);
+        }
+
+        // Toggled challenges stay highlighted
+        *color = if challenges.is_active(button.0) {
+            ACTIVE_BUTTON_COLOR
+        } else {
+            NORMAL_BUTTON_COLOR
+        }
+        .into();
+    }
+}
+
+fn update_reward_text(
+    challenges: Res<ActiveChallenges>,
+    mut text: Query<&mut Text, With<RewardText>>,
+) {
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = format!("Reward x{:.2}", challenges.reward_multiplier());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modified_runs_are_named_apart() {
+        let mut challenges = ActiveChallenges::default();
+        assert_eq!(challenges.run_name("Level 0"), "Level 0");
+        assert_eq!(challenges.reward_multiplier(), 1.0);
+
+        challenges.toggle(Challenge::Fog);
+        challenges.toggle(Challenge::NoUpgrades);
+        assert_eq!(
+            challenges.run_name("Level 0"),
+            "Level 0 [No upgrades, Fog of war]"
+        );
+        assert_eq!(challenges.reward_multiplier(), 2.25);
+
+        challenges.toggle(Challenge::Fog);
+        assert_eq!(challenges.run_name("Level 0"), "Level 0 [No upgrades]");
+    }
+
+    #[test]
+    fn challenges_are_packed_into_bits() {
+        let mut challenges = ActiveChallenges::default();
+        challenges.toggle(Challenge::Mirrored);
+        challenges.toggle(Challenge::Gravity);
+        assert_eq!(challenges.bits(), 0b100010);
+        let unpacked = ActiveChallenges::from_bits(0b100010).unwrap();
+        assert_eq!(unpacked.run_name("Level 0"), "Level 0 [Mirrored, Gravity]");
+        assert!(ActiveChallenges::from_bits(0b1000000).is_none());
+    }
+}
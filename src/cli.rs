@@ -0,0 +1,137 @@
+use crate::world::{Diagnostic, GameWorld, WorldType};
+use image::Rgb;
+use std::{fs, path::Path};
+
+const USAGE: &str =
+    "Usage: beeline [--validate <level.tsv>... | --thumbnail <level.tsv> <out.png> \
+    | --coverage <level.tsv> <out.png> | --edit <level.tsv>]";
+
+// Size in pixels of each tile in rendered thumbnails
+const THUMBNAIL_TILE_PIXELS: u32 = 8;
+// Open tiles are shaded towards this the more dangerous they are
+const DANGER_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+
+// Handle command line tools, returning the exit code if the game shouldn't be launched
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.split_first() {
+        None => None,
+        // Launches the game, driven by the autopilot
+        Some((command, [])) if cfg!(feature = "autopilot") && command == "--autopilot" => None,
+        // Launches the game into the level editor
+        Some((command, [_])) if command == "--edit" => None,
+        Some((command, paths)) if command == "--validate" && !paths.is_empty() => {
+            Some(validate(paths))
+        }
+        Some((command, [level, output])) if command == "--thumbnail" => {
+            Some(thumbnail(level, output))
+        }
+        Some((command, [level, output])) if command == "--coverage" => {
+            Some(coverage(level, output))
+        }
+        Some(_) => {
+            eprintln!("{USAGE}");
+            Some(2)
+        }
+    }
+}
+
+// Validate each level file, printing diagnostics in a `path: level: message` format
+fn validate(paths: &[String]) -> i32 {
+    let mut failed = false;
+    for path in paths {
+        let source = match fs::read_to_string(Path::new(path)) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{path}: error: {err}");
+                failed = true;
+                continue;
+            }
+        };
+
+        let diagnostics = GameWorld::validate(&source);
+        for diagnostic in &diagnostics {
+            match diagnostic {
+                Diagnostic::Warning(message) => eprintln!("{path}: warning: {message}"),
+                Diagnostic::Error(message) => {
+                    eprintln!("{path}: error: {message}");
+                    failed = true;
+                }
+            }
+        }
+        if diagnostics.is_empty() {
+            println!("{path}: ok");
+        }
+    }
+
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+fn read_level(level: &str) -> Option<GameWorld> {
+    match fs::read_to_string(Path::new(level))
+        .and_then(|source| GameWorld::parse(&source, WorldType::Endless))
+    {
+        Ok(world) => Some(world),
+        Err(err) => {
+            eprintln!("{level}: error: {err}");
+            None
+        }
+    }
+}
+
+fn save_image(image: image::RgbImage, output: &str) -> i32 {
+    match image.save(output) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{output}: error: {err}");
+            1
+        }
+    }
+}
+
+// Render a level's layout to a PNG
+fn thumbnail(level: &str, output: &str) -> i32 {
+    match read_level(level) {
+        Some(world) => save_image(world.thumbnail(THUMBNAIL_TILE_PIXELS), output),
+        None => 1,
+    }
+}
+
+// Render a level's layout with its open tiles shaded by how much spawners cover them, to
+// find dead zones and chokepoints before playtesting
+fn coverage(level: &str, output: &str) -> i32 {
+    let world = match read_level(level) {
+        Some(world) => world,
+        None => return 1,
+    };
+    let mut image = world.thumbnail(THUMBNAIL_TILE_PIXELS);
+    for (y, row) in world.danger_map().iter().enumerate() {
+        for (x, danger) in row.iter().enumerate() {
+            if *danger == 0.0 {
+                continue;
+            }
+            for pixel_y in 0..THUMBNAIL_TILE_PIXELS {
+                for pixel_x in 0..THUMBNAIL_TILE_PIXELS {
+                    let pixel = image.get_pixel_mut(
+                        x as u32 * THUMBNAIL_TILE_PIXELS + pixel_x,
+                        y as u32 * THUMBNAIL_TILE_PIXELS + pixel_y,
+                    );
+                    *pixel = shade(*pixel, *danger);
+                }
+            }
+        }
+    }
+    save_image(image, output)
+}
+
+// Blend a pixel towards DANGER_COLOR, all the way for a danger of 1
+fn shade(pixel: Rgb<u8>, danger: f32) -> Rgb<u8> {
+    let mut shaded = pixel;
+    for (channel, target) in shaded.0.iter_mut().zip(DANGER_COLOR.0) {
+        *channel = (*channel as f32 + (target as f32 - *channel as f32) * danger).round() as u8;
+    }
+    shaded
+}
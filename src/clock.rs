@@ -0,0 +1,84 @@
+use crate::save::SaveData;
+use bevy::prelude::*;
+use std::time::Duration;
+
+// Gameplay time. Movement, spawners and timers in the run go by this clock, so stopping it
+// freezes them all together, while Time keeps the camera, animations and UI going. It runs
+// slower than real time at a reduced game speed setting.
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .add_system_to_stage(CoreStage::PreUpdate, tick_game_clock.label(TickGameClock));
+    }
+}
+
+// Systems that replace the frame's gameplay time, like replays being stepped through, run
+// after this
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TickGameClock;
+
+#[derive(Debug, Default)]
+pub struct GameClock {
+    delta: Duration,
+    // How much longer gameplay stays frozen for
+    stopped_for: Duration,
+}
+
+impl GameClock {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta_seconds_f64(&self) -> f64 {
+        self.delta.as_secs_f64()
+    }
+
+    // Use `delta` for this frame's gameplay time instead of the time that really passed
+    pub fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+
+    // Freeze gameplay for a moment to sell an impact. Overlapping stops don't add up.
+    pub fn hit_stop(&mut self, duration: Duration) {
+        self.stopped_for = self.stopped_for.max(duration);
+    }
+
+    fn tick(&mut self, real_delta: Duration) {
+        let stopped = real_delta.min(self.stopped_for);
+        self.stopped_for -= stopped;
+        self.delta = real_delta - stopped;
+    }
+}
+
+fn tick_game_clock(time: Res<Time>, save: Res<SaveData>, mut clock: ResMut<GameClock>) {
+    clock.tick(time.delta().mul_f32(save.settings.game_speed_scale()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_stop_swallows_frame_time() {
+        let frame = Duration::from_millis(50);
+        let mut clock = GameClock::default();
+        clock.tick(frame);
+        assert_eq!(clock.delta(), frame);
+
+        clock.hit_stop(Duration::from_millis(80));
+        clock.hit_stop(Duration::from_millis(30));
+        clock.tick(frame);
+        assert_eq!(clock.delta(), Duration::ZERO);
+        // The stop ends partway through the next frame
+        clock.tick(frame);
+        assert_eq!(clock.delta(), Duration::from_millis(20));
+        clock.tick(frame);
+        assert_eq!(clock.delta(), frame);
+    }
+}
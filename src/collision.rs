@@ -1,6 +1,5 @@
 use crate::AppState;
 use bevy::prelude::*;
-use impacted::CollisionShape;
 
 pub struct CollisionPlugin;
 
@@ -12,6 +11,214 @@ impl Plugin for CollisionPlugin {
     }
 }
 
+// A shape in the plane, positioned by the entity's GlobalTransform including its scale
+#[derive(Component, Clone, Debug)]
+pub struct CollisionShape {
+    shape: Shape,
+    transform: GlobalTransform,
+}
+
+// Only rectangles are put in the game for now, circles and segments are covered by the tests
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(test), allow(dead_code))]
+enum Shape {
+    Circle { radius: f32 },
+    // Axis aligned until rotated by the transform, so this covers both AABBs and OBBs
+    Rectangle { half_size: Vec2 },
+    Segment { start: Vec2, end: Vec2 },
+}
+
+// How two overlapping shapes touch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    // Direction from the first shape towards the second
+    pub normal: Vec2,
+    // How far the second shape has to move along the normal to separate them
+    pub depth: f32,
+}
+
+// A shape with its transform applied
+enum Placed {
+    Circle { center: Vec2, radius: f32 },
+    // Corners of a rectangle, or the two ends of a segment
+    Polygon { points: [Vec2; 4], len: usize },
+}
+
+impl CollisionShape {
+    fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            transform: GlobalTransform::default(),
+        }
+    }
+
+    pub fn new_rectangle(width: f32, height: f32) -> Self {
+        Self::new(Shape::Rectangle {
+            half_size: Vec2::new(width, height) / 2.0,
+        })
+    }
+
+    #[cfg(test)]
+    fn new_circle(radius: f32) -> Self {
+        Self::new(Shape::Circle { radius })
+    }
+
+    #[cfg(test)]
+    fn new_segment(start: Vec2, end: Vec2) -> Self {
+        Self::new(Shape::Segment { start, end })
+    }
+
+    pub fn with_transform(mut self, transform: GlobalTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn set_transform(&mut self, transform: GlobalTransform) {
+        self.transform = transform;
+    }
+
+    pub fn is_collided_with(&self, other: &CollisionShape) -> bool {
+        self.contact(other).is_some()
+    }
+
+    // Touching edges don't count as a contact
+    pub fn contact(&self, other: &CollisionShape) -> Option<Contact> {
+        match (self.place(), other.place()) {
+            (
+                Placed::Circle {
+                    center: a,
+                    radius: a_radius,
+                },
+                Placed::Circle {
+                    center: b,
+                    radius: b_radius,
+                },
+            ) => {
+                let offset = b - a;
+                let depth = a_radius + b_radius - offset.length();
+                let normal = offset.try_normalize().unwrap_or(Vec2::X);
+                (depth > 0.0).then_some(Contact { normal, depth })
+            }
+            (Placed::Polygon { points, len }, Placed::Circle { center, radius }) => {
+                polygon_circle(&points[..len], center, radius)
+            }
+            (Placed::Circle { center, radius }, Placed::Polygon { points, len }) => {
+                polygon_circle(&points[..len], center, radius).map(|contact| Contact {
+                    normal: -contact.normal,
+                    ..contact
+                })
+            }
+            (
+                Placed::Polygon {
+                    points: a,
+                    len: a_len,
+                },
+                Placed::Polygon {
+                    points: b,
+                    len: b_len,
+                },
+            ) => {
+                let (a, b) = (&a[..a_len], &b[..b_len]);
+                separate(
+                    axes(a).chain(axes(b)),
+                    |axis| project(a, axis),
+                    |axis| project(b, axis),
+                    center(b) - center(a),
+                )
+            }
+        }
+    }
+
+    fn place(&self) -> Placed {
+        let point = |local: Vec2| self.transform.mul_vec3(local.extend(0.0)).truncate();
+        match self.shape {
+            Shape::Circle { radius } => Placed::Circle {
+                center: point(Vec2::ZERO),
+                radius: radius * self.transform.scale.truncate().abs().max_element(),
+            },
+            Shape::Rectangle { half_size } => Placed::Polygon {
+                points: [
+                    point(-half_size),
+                    point(Vec2::new(half_size.x, -half_size.y)),
+                    point(half_size),
+                    point(Vec2::new(-half_size.x, half_size.y)),
+                ],
+                len: 4,
+            },
+            Shape::Segment { start, end } => Placed::Polygon {
+                points: [point(start), point(end), Vec2::ZERO, Vec2::ZERO],
+                len: 2,
+            },
+        }
+    }
+}
+
+fn center(points: &[Vec2]) -> Vec2 {
+    points.iter().fold(Vec2::ZERO, |sum, point| sum + *point) / points.len() as f32
+}
+
+// Lowest and highest point of a polygon along an axis
+fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+    points
+        .iter()
+        .map(|point| point.dot(axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        })
+}
+
+// Candidate separating axes of a polygon: its edge normals, and for a segment its direction too
+fn axes(points: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    let edges = points.iter().zip(points.iter().cycle().skip(1));
+    let normals = edges
+        .take(if points.len() == 2 { 1 } else { points.len() })
+        .map(|(a, b)| (*b - *a).perp());
+    let direction = (points.len() == 2).then(|| points[1] - points[0]);
+    normals
+        .chain(direction)
+        .filter_map(|axis| axis.try_normalize())
+}
+
+// Separating axis test, giving the axis of least overlap as the contact
+fn separate(
+    axes: impl Iterator<Item = Vec2>,
+    project_a: impl Fn(Vec2) -> (f32, f32),
+    project_b: impl Fn(Vec2) -> (f32, f32),
+    a_to_b: Vec2,
+) -> Option<Contact> {
+    let mut contact: Option<Contact> = None;
+    for axis in axes {
+        let ((a_min, a_max), (b_min, b_max)) = (project_a(axis), project_b(axis));
+        let depth = (a_max - b_min).min(b_max - a_min);
+        if depth <= 0.0 {
+            return None;
+        }
+        if contact.is_none_or(|contact| depth < contact.depth) {
+            // Point the normal from a to b
+            let normal = if axis.dot(a_to_b) < 0.0 { -axis } else { axis };
+            contact = Some(Contact { normal, depth });
+        }
+    }
+    contact
+}
+
+fn polygon_circle(points: &[Vec2], circle: Vec2, radius: f32) -> Option<Contact> {
+    // The circle can also be separated along the line to the polygon's nearest corner
+    let nearest = points.iter().copied().min_by(|a, b| {
+        a.distance_squared(circle)
+            .total_cmp(&b.distance_squared(circle))
+    })?;
+    separate(
+        axes(points).chain((circle - nearest).try_normalize()),
+        |axis| project(points, axis),
+        |axis| {
+            let center = circle.dot(axis);
+            (center - radius, center + radius)
+        },
+        circle - center(points),
+    )
+}
+
 fn update_collision_transforms(
     mut shapes: Query<(&mut CollisionShape, &GlobalTransform), Changed<GlobalTransform>>,
 ) {
@@ -20,3 +227,141 @@ fn update_collision_transforms(
         shape.set_transform(*transform);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::f32::consts::TAU;
+
+    const CASES: u32 = 1000;
+
+    // Position and size of a rectangle near the origin
+    fn rectangle() -> impl Strategy<Value = (Vec2, Vec2)> {
+        (
+            -100.0f32..100.0,
+            -100.0f32..100.0,
+            1.0f32..48.0,
+            1.0f32..48.0,
+        )
+            .prop_map(|(x, y, width, height)| (Vec2::new(x, y), Vec2::new(width, height)))
+    }
+
+    fn place((position, size): (Vec2, Vec2)) -> CollisionShape {
+        CollisionShape::new_rectangle(size.x, size.y)
+            .with_transform(GlobalTransform::from_translation(position.extend(0.0)))
+    }
+
+    fn aabb_overlap(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> f32 {
+        // Smallest penetration along either axis, negative if separated
+        let gap = (a.1 + b.1) / 2.0 - (a.0 - b.0).abs();
+        gap.x.min(gap.y)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+        #[test]
+        fn collision_is_symmetric(a in rectangle(), b in rectangle(), angle in 0.0f32..TAU) {
+            let a = place(a);
+            let mut b = place(b);
+            b.set_transform(GlobalTransform {
+                rotation: Quat::from_rotation_z(angle),
+                ..b.transform
+            });
+            prop_assert_eq!(a.is_collided_with(&b), b.is_collided_with(&a));
+        }
+
+        #[test]
+        fn axis_aligned_collision_matches_overlap(a in rectangle(), b in rectangle()) {
+            let overlap = aabb_overlap(a, b);
+            // Skip grazing contacts, where either answer is acceptable
+            prop_assume!(overlap.abs() > 0.01);
+            prop_assert_eq!(place(a).is_collided_with(&place(b)), overlap > 0.0);
+        }
+
+        #[test]
+        fn transforms_are_synced_to_shapes(
+            positions in prop::collection::vec((-50.0f32..50.0, -50.0f32..50.0), 1..20),
+        ) {
+            let mut world = World::new();
+            let mut stage = SystemStage::single(update_collision_transforms);
+            let target = CollisionShape::new_rectangle(24.0, 24.0);
+            let entity = world
+                .spawn()
+                .insert(CollisionShape::new_rectangle(24.0, 24.0))
+                .insert(GlobalTransform::default())
+                .id();
+
+            for (x, y) in positions {
+                let position = Vec2::new(x, y);
+                *world.get_mut::<GlobalTransform>(entity).unwrap() =
+                    GlobalTransform::from_translation(position.extend(0.0));
+                stage.run(&mut world);
+
+                let shape = world.get::<CollisionShape>(entity).unwrap();
+                let overlap = aabb_overlap(
+                    (position, Vec2::splat(24.0)),
+                    (Vec2::ZERO, Vec2::splat(24.0)),
+                );
+                if overlap.abs() > 0.01 {
+                    prop_assert_eq!(shape.is_collided_with(&target), overlap > 0.0);
+                }
+            }
+        }
+
+        // A shape crossing the target, moving less than its own size per step
+        #[test]
+        fn small_steps_never_tunnel(
+            (size, distance) in (4.0f32..24.0).prop_flat_map(|size| (Just(size), 0.1f32..size)),
+            angle in 0.0f32..TAU,
+        ) {
+            let target = CollisionShape::new_rectangle(24.0, 24.0);
+            let step = crate::util::polar_to_cartesian(angle, distance);
+            let mut position = -step.normalize() * 100.0;
+            let mut collided = false;
+            while position.length() <= 100.0 + step.length() {
+                let shape = CollisionShape::new_rectangle(size, size)
+                    .with_transform(GlobalTransform::from_translation(position.extend(0.0)));
+                collided |= shape.is_collided_with(&target);
+                position += step;
+            }
+            prop_assert!(collided, "shape of size {} tunneled with step {}", size, step);
+        }
+    }
+
+    #[test]
+    fn contacts_point_from_the_first_shape() {
+        let a = CollisionShape::new_rectangle(20.0, 20.0);
+        let b = CollisionShape::new_rectangle(20.0, 20.0)
+            .with_transform(GlobalTransform::from_xyz(15.0, 2.0, 0.0));
+        let contact = a.contact(&b).unwrap();
+        assert_eq!(contact.normal, Vec2::X);
+        assert!((contact.depth - 5.0).abs() < 1e-4);
+        assert_eq!(b.contact(&a).unwrap().normal, -Vec2::X);
+    }
+
+    #[test]
+    fn circles_and_segments_collide() {
+        let circle = CollisionShape::new_circle(10.0);
+        let nearby = |x: f32, y: f32| GlobalTransform::from_xyz(x, y, 0.0);
+
+        let other = CollisionShape::new_circle(5.0).with_transform(nearby(0.0, 12.0));
+        let contact = circle.contact(&other).unwrap();
+        assert_eq!(contact.normal, Vec2::Y);
+        assert!((contact.depth - 3.0).abs() < 1e-4);
+
+        // Just past the rounded corner of a box, where a box would still touch
+        let corner = CollisionShape::new_rectangle(10.0, 10.0).with_transform(nearby(13.0, 13.0));
+        assert!(!circle.is_collided_with(&corner));
+        assert!(CollisionShape::new_rectangle(20.0, 20.0).is_collided_with(&corner));
+
+        let segment = CollisionShape::new_segment(Vec2::new(-20.0, 8.0), Vec2::new(20.0, 8.0));
+        assert!(circle.is_collided_with(&segment));
+        assert!(segment.is_collided_with(&circle));
+        let crossing = CollisionShape::new_segment(Vec2::new(0.0, -20.0), Vec2::new(0.0, 20.0));
+        assert!(segment.is_collided_with(&crossing));
+        let parallel = CollisionShape::new_segment(Vec2::new(-20.0, 9.0), Vec2::new(20.0, 9.0));
+        assert!(!segment.is_collided_with(&parallel));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    camera::{self, MainCamera},
+    input_map::Action,
+    save::SaveData,
+    settings::ControlScheme,
+};
+use bevy::{input::InputSystem, math::const_vec2, prelude::*};
+
+// Reads the mouse and keyboard into Controls once per frame, so gameplay doesn't depend on
+// where its input comes from
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Controls>().add_system_to_stage(
+            CoreStage::PreUpdate,
+            read_controls.label(ReadControls).after(InputSystem),
+        );
+    }
+}
+
+// Systems that replace the controls, like replays, run after this
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ReadControls;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ButtonState {
+    pub pressed: bool,
+    pub just_pressed: bool,
+}
+
+impl ButtonState {
+    fn read<T: Copy + Eq + std::hash::Hash>(input: &Input<T>, button: T) -> Self {
+        Self {
+            pressed: input.pressed(button),
+            just_pressed: input.just_pressed(button),
+        }
+    }
+}
+
+// What the player is asking for this frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Controls {
+    // Direction to fly in, in radians, if the cursor is in the window or a steering key is held
+    pub aim: Option<f32>,
+    // Fraction of full speed, from 0 to 1
+    pub throttle: f32,
+    // World position under the cursor
+    pub cursor: Option<Vec2>,
+    // Upgrade slots, on the left and right mouse buttons, or their keys when steering by
+    // keyboard
+    pub primary: ButtonState,
+    pub secondary: ButtonState,
+    pub slow_motion: ButtonState,
+}
+
+// Logical pixels between the cursor and the center of the window for full speed at 1x
+// sensitivity, so handling is the same however big the window or monitor is
+const FULL_THROTTLE_DISTANCE: f32 = 180.0;
+
+// Fraction of full speed for a cursor `offset` from the center of the window
+fn mouse_throttle(offset: Vec2, sensitivity: f32) -> f32 {
+    (offset.length() * sensitivity / FULL_THROTTLE_DISTANCE).min(1.0)
+}
+
+// Keys that steer with the keyboard scheme, and the direction each pushes towards
+const STEERING_KEYS: [(KeyCode, Vec2); 8] = [
+    (KeyCode::W, Vec2::Y),
+    (KeyCode::Up, Vec2::Y),
+    (KeyCode::S, const_vec2!([0.0, -1.0])),
+    (KeyCode::Down, const_vec2!([0.0, -1.0])),
+    (KeyCode::A, const_vec2!([-1.0, 0.0])),
+    (KeyCode::Left, const_vec2!([-1.0, 0.0])),
+    (KeyCode::D, Vec2::X),
+    (KeyCode::Right, Vec2::X),
+];
+
+fn read_controls(
+    windows: Res<Windows>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    save: Res<SaveData>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut controls: ResMut<Controls>,
+) {
+    let scheme = save.settings.control_scheme;
+    let input_map = &save.settings.input_map;
+    *controls = match scheme {
+        ControlScheme::Mouse => Controls {
+            primary: ButtonState::read(&mouse_buttons, MouseButton::Left),
+            secondary: ButtonState::read(&mouse_buttons, MouseButton::Right),
+            ..Controls::default()
+        },
+        ControlScheme::Keyboard => {
+            let direction: Vec2 = STEERING_KEYS
+                .iter()
+                .filter(|(key, _)| keys.pressed(*key))
+                .map(|(_, direction)| direction)
+                .sum();
+            Controls {
+                aim: (direction != Vec2::ZERO).then(|| direction.y.atan2(direction.x)),
+                throttle: if direction == Vec2::ZERO { 0.0 } else { 1.0 },
+                primary: ButtonState::read(&keys, input_map.key(Action::Primary)),
+                secondary: ButtonState::read(&keys, input_map.key(Action::Secondary)),
+                ..Controls::default()
+            }
+        }
+    };
+    controls.slow_motion = ButtonState::read(&keys, input_map.key(Action::SlowMotion));
+
+    let (camera, camera_transform) = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let window = match windows.get(camera.window) {
+        Some(window) => window,
+        None => return,
+    };
+    // Some(_) if the cursor is in the window
+    if let Some(cursor_pos) = window.cursor_position() {
+        let window_size = Vec2::new(window.width(), window.height());
+        if scheme == ControlScheme::Mouse {
+            let relative_pos = cursor_pos - window_size / 2.0;
+            controls.aim = Some(relative_pos.y.atan2(relative_pos.x));
+            controls.throttle = mouse_throttle(relative_pos, save.settings.mouse_sensitivity);
+        }
+
+        controls.cursor = Some(camera::cursor_to_world(
+            cursor_pos,
+            window_size,
+            camera,
+            camera_transform,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_scales_with_sensitivity() {
+        let offset = Vec2::new(0.0, FULL_THROTTLE_DISTANCE / 2.0);
+        assert_eq!(mouse_throttle(Vec2::ZERO, 1.0), 0.0);
+        assert_eq!(mouse_throttle(offset, 1.0), 0.5);
+        assert_eq!(mouse_throttle(offset, 2.0), 1.0);
+        assert_eq!(mouse_throttle(offset * 4.0, 1.0), 1.0);
+    }
+}
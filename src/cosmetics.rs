@@ -0,0 +1,241 @@
+use crate::save::SaveData;
+use bevy::prelude::*;
+
+pub struct CosmeticsPlugin;
+
+impl Plugin for CosmeticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SkinRegistry>()
+            .init_resource::<AccessoryRegistry>()
+            .add_system(sync_attachments);
+    }
+}
+
+pub enum Unlock {
+    Always,
+    // Complete this many different levels
+    LevelsCompleted(usize),
+    // Complete any level within this many seconds
+    TimeUnder(f32),
+    // Play while a seasonal event is on, after which it stays unlocked
+    Event(String),
+}
+
+impl Unlock {
+    pub fn is_met(&self, save: &SaveData) -> bool {
+        match self {
+            Unlock::Always => true,
+            Unlock::LevelsCompleted(count) => save.best_times.len() >= *count,
+            Unlock::TimeUnder(seconds) => save.best_times.values().any(|time| time < seconds),
+            Unlock::Event(event) => save.events_seen.contains(event),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Unlock::Always => "Unlocked".into(),
+            Unlock::LevelsCompleted(1) => "Complete a level".into(),
+            Unlock::LevelsCompleted(count) => format!("Complete {count} levels"),
+            Unlock::TimeUnder(seconds) => format!("Finish a level in under {seconds}s"),
+            Unlock::Event(event) => format!("Play during {event}"),
+        }
+    }
+}
+
+pub struct Skin {
+    pub name: String,
+    // Path of a 6 frame sprite sheet with the same layout as bee.png
+    pub path: String,
+    pub unlock: Unlock,
+}
+
+pub struct SkinRegistry {
+    pub skins: Vec<Skin>,
+}
+
+impl SkinRegistry {
+    // The player's chosen skin, falling back to the default if it isn't unlocked
+    pub fn selected(&self, save: &SaveData) -> &Skin {
+        save.skin
+            .as_ref()
+            .and_then(|name| self.skins.iter().find(|skin| skin.name == *name))
+            .filter(|skin| skin.unlock.is_met(save))
+            .unwrap_or(&self.skins[0])
+    }
+}
+
+impl Default for SkinRegistry {
+    fn default() -> Self {
+        Self {
+            skins: vec![
+                Skin {
+                    name: "Classic".into(),
+                    path: "bee.png".into(),
+                    unlock: Unlock::Always,
+                },
+                Skin {
+                    name: "Frost".into(),
+                    path: "skins/frost.png".into(),
+                    unlock: Unlock::LevelsCompleted(1),
+                },
+                Skin {
+                    name: "Rose".into(),
+                    path: "skins/rose.png".into(),
+                    unlock: Unlock::TimeUnder(2.0),
+                },
+                Skin {
+                    name: "Shadow".into(),
+                    path: "skins/shadow.png".into(),
+                    unlock: Unlock::LevelsCompleted(2),
+                },
+            ],
+        }
+    }
+}
+
+pub struct Accessory {
+    pub name: &'static str,
+    pub path: &'static str,
+    pub size: Vec2,
+    // Position relative to the wearer's attachment point
+    pub offset: Vec2,
+    pub unlock: Unlock,
+}
+
+pub struct AccessoryRegistry {
+    pub accessories: Vec<Accessory>,
+}
+
+impl AccessoryRegistry {
+    // The player's chosen accessory, or none if it isn't unlocked
+    pub fn selected(&self, save: &SaveData) -> Option<&Accessory> {
+        save.accessory
+            .as_ref()
+            .and_then(|name| {
+                self.accessories
+                    .iter()
+                    .find(|accessory| accessory.name == name)
+            })
+            .filter(|accessory| accessory.unlock.is_met(save))
+    }
+}
+
+impl Default for AccessoryRegistry {
+    fn default() -> Self {
+        Self {
+            accessories: vec![
+                Accessory {
+                    name: "Scarf",
+                    path: "accessories/scarf.png",
+                    size: Vec2::new(16.0, 6.0),
+                    offset: Vec2::new(0.0, -5.0),
+                    unlock: Unlock::Always,
+                },
+                Accessory {
+                    name: "Top Hat",
+                    path: "accessories/top-hat.png",
+                    size: Vec2::new(12.0, 10.0),
+                    offset: Vec2::new(0.0, 4.0),
+                    unlock: Unlock::LevelsCompleted(1),
+                },
+                Accessory {
+                    name: "Crown",
+                    path: "accessories/crown.png",
+                    size: Vec2::new(12.0, 8.0),
+                    offset: Vec2::new(0.0, 3.0),
+                    unlock: Unlock::TimeUnder(2.0),
+                },
+            ],
+        }
+    }
+}
+
+// Where accessories sit on an entity, in the entity's unscaled local space
+#[derive(Component)]
+pub struct AttachmentPoint(pub Vec2);
+
+#[derive(Component)]
+pub struct AttachedAccessory {
+    offset: Vec2,
+}
+
+// Spawn an accessory as a child of an entity with an AttachmentPoint
+pub fn attach_accessory(
+    commands: &mut Commands,
+    parent: Entity,
+    accessory: &Accessory,
+    asset_server: &AssetServer,
+) {
+    commands.entity(parent).with_children(|parent| {
+        parent
+            .spawn_bundle(SpriteBundle {
+                texture: asset_server.load(accessory.path),
+                sprite: Sprite {
+                    custom_size: Some(accessory.size),
+                    ..Sprite::default()
+                },
+                ..SpriteBundle::default()
+            })
+            .insert(AttachedAccessory {
+                offset: accessory.offset,
+            });
+    });
+}
+
+// Keep accessories on their wearer's attachment point. Only the local offset
+// is set here; rotation and the shrink upgrade's scale come from the parent
+// transform, so a shrunk bee wears a shrunk hat in the same spot.
+fn sync_attachments(
+    points: Query<(&AttachmentPoint, ChangeTrackers<AttachmentPoint>)>,
+    mut accessories: Query<(
+        &Parent,
+        &AttachedAccessory,
+        ChangeTrackers<AttachedAccessory>,
+        &mut Transform,
+    )>,
+) {
+    for (parent, accessory, accessory_tracker, mut transform) in accessories.iter_mut() {
+        if let Ok((point, point_tracker)) = points.get(parent.0) {
+            if accessory_tracker.is_changed() || point_tracker.is_changed() {
+                // Draw just above the wearer
+                transform.translation = (point.0 + accessory.offset).extend(0.1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_skins_fall_back_to_default() {
+        let skins = SkinRegistry::default();
+        let mut save = SaveData {
+            skin: Some("Frost".into()),
+            ..SaveData::default()
+        };
+        assert_eq!(skins.selected(&save).name, "Classic");
+
+        save.best_times.insert("Level 0".into(), 5.0);
+        assert_eq!(skins.selected(&save).name, "Frost");
+
+        save.skin = Some("Rose".into());
+        assert_eq!(skins.selected(&save).name, "Classic");
+        save.best_times.insert("Level 1".into(), 1.5);
+        assert_eq!(skins.selected(&save).name, "Rose");
+    }
+
+    #[test]
+    fn locked_accessories_are_not_worn() {
+        let accessories = AccessoryRegistry::default();
+        let mut save = SaveData::default();
+        assert!(accessories.selected(&save).is_none());
+
+        save.accessory = Some("Top Hat".into());
+        assert!(accessories.selected(&save).is_none());
+
+        save.best_times.insert("Level 0".into(), 5.0);
+        assert_eq!(accessories.selected(&save).unwrap().name, "Top Hat");
+    }
+}
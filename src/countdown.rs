@@ -0,0 +1,137 @@
+use crate::{
+    change_state,
+    cutscene::{ActiveCutscene, LoadingCutscene},
+    game_overlay::GameTimer,
+    ui::GameFont,
+    AppState, GameState,
+};
+use bevy::prelude::*;
+
+pub struct CountdownPlugin;
+
+impl Plugin for CountdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Resuming>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(start_countdown))
+            .add_system_set(SystemSet::on_enter(GameState::Countdown).with_system(show_countdown))
+            .add_system_set(SystemSet::on_update(GameState::Countdown).with_system(tick_countdown))
+            .add_system_set(SystemSet::on_exit(GameState::Countdown).with_system(start_run))
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(fade_go_text));
+    }
+}
+
+const COUNTDOWN_SECONDS: f32 = 3.0;
+// How long "GO!" stays up once the level has started
+const GO_SECONDS: f32 = 0.5;
+
+// Set when counting back into a run that was paused, rather than starting one
+#[derive(Default)]
+pub struct Resuming(pub bool);
+
+#[derive(Component)]
+struct CountdownText(Timer);
+
+fn start_countdown(mut resuming: ResMut<Resuming>, mut state: ResMut<State<GameState>>) {
+    resuming.0 = false;
+    change_state(&mut state, GameState::Countdown);
+}
+
+fn show_countdown(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(40.0),
+                    left: Val::Percent(47.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                countdown_label(COUNTDOWN_SECONDS),
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 120.0,
+                    ..TextStyle::default()
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(CountdownText(Timer::from_seconds(COUNTDOWN_SECONDS, false)));
+}
+
+fn countdown_label(remaining: f32) -> String {
+    if remaining > 0.0 {
+        format!("{}", remaining.ceil())
+    } else {
+        "GO!".to_string()
+    }
+}
+
+fn tick_countdown(
+    time: Res<Time>,
+    mut state: ResMut<State<GameState>>,
+    intro: Option<Res<ActiveCutscene>>,
+    loading_intro: Option<Res<LoadingCutscene>>,
+    mut text: Query<(&mut Text, &mut CountdownText)>,
+) {
+    // The countdown holds until the level's intro has played
+    if intro.is_some() || loading_intro.is_some() {
+        return;
+    }
+    let (mut text, mut countdown) = match text.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    countdown.0.tick(time.delta());
+    let remaining = countdown.0.duration().as_secs_f32() - countdown.0.elapsed_secs();
+    text.sections[0].value = countdown_label(remaining);
+    if countdown.0.finished() {
+        countdown.0 = Timer::from_seconds(GO_SECONDS, false);
+        change_state(&mut state, GameState::Playing);
+    }
+}
+
+// The run only starts counting once the countdown is over
+fn start_run(mut commands: Commands, mut resuming: ResMut<Resuming>) {
+    if !resuming.0 {
+        commands.insert_resource(GameTimer::default());
+    }
+    resuming.0 = false;
+}
+
+fn fade_go_text(
+    mut commands: Commands,
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut text: Query<(Entity, &mut Text, &mut CountdownText)>,
+) {
+    if *state.current() == GameState::Countdown {
+        return;
+    }
+    for (entity, mut text, mut countdown) in text.iter_mut() {
+        if countdown.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        } else {
+            text.sections[0]
+                .style
+                .color
+                .set_a(countdown.0.percent_left());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_rounds_up_to_go() {
+        assert_eq!(countdown_label(3.0), "3");
+        assert_eq!(countdown_label(2.1), "3");
+        assert_eq!(countdown_label(0.2), "1");
+        assert_eq!(countdown_label(0.0), "GO!");
+    }
+}
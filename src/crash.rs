@@ -0,0 +1,262 @@
+use crate::{
+    pack::LevelRegistry,
+    replay::RunSeed,
+    ui::GameFont,
+    world::{GameWorld, WorldType},
+    AppState, GameState,
+};
+use bevy::prelude::*;
+use std::{
+    backtrace::Backtrace,
+    fs, panic,
+    sync::{Arc, Mutex},
+};
+
+// Written when the game panics, and offered to the player on the next launch
+const CRASH_REPORT: &str = "crash-report.txt";
+// Where the report is moved once the player has seen it, so it's only offered once
+const SEEN_CRASH_REPORT: &str = "crash-report.old.txt";
+// Lines of the report shown in game, the rest is only in the file
+const SHOWN_LINES: usize = 12;
+
+// What the game was doing, kept up to date for the panic hook
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    state: String::new(),
+    level: None,
+    seed: 0,
+});
+
+pub struct CrashPlugin;
+
+impl Plugin for CrashPlugin {
+    fn build(&self, app: &mut App) {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            // The panic may have happened while the context was being updated
+            let context = match CONTEXT.lock() {
+                Ok(context) => context.clone(),
+                Err(poisoned) => poisoned.into_inner().clone(),
+            };
+            let report = context.report(&info.to_string(), &Backtrace::force_capture());
+            if let Err(err) = fs::write(CRASH_REPORT, report) {
+                eprintln!("Failed to write {CRASH_REPORT}: {err}");
+            }
+            default_hook(info);
+        }));
+
+        app.add_system_to_stage(CoreStage::Last, track_context)
+            .add_system_set(SystemSet::on_enter(AppState::Menu).with_system(offer_crash_report))
+            .add_system_set(SystemSet::on_update(AppState::Menu).with_system(manage_crash_buttons));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CrashContext {
+    state: String,
+    level: Option<String>,
+    seed: u64,
+}
+
+impl CrashContext {
+    fn report(&self, panic: &str, backtrace: &Backtrace) -> String {
+        let level = match &self.level {
+            Some(level) => format!("Level: {level}\nSeed: {}\n", self.seed),
+            None => String::new(),
+        };
+        format!(
+            "beeline {} crashed\nState: {}\n{level}\n{panic}\n\nBacktrace:\n{backtrace}",
+            env!("CARGO_PKG_VERSION"),
+            self.state
+        )
+    }
+}
+
+fn track_context(
+    app_state: Res<State<AppState>>,
+    game_state: Res<State<GameState>>,
+    world: Option<Res<Arc<GameWorld>>>,
+    levels: Res<LevelRegistry>,
+    seed: Res<RunSeed>,
+) {
+    let world_changed = world.as_ref().is_some_and(|world| world.is_changed());
+    if !(app_state.is_changed() || game_state.is_changed() || world_changed || seed.is_changed()) {
+        return;
+    }
+
+    let state = match app_state.current() {
+        AppState::Game => format!("Game ({:?})", game_state.current()),
+        state => format!("{state:?}"),
+    };
+    let level = world.map(|world| match world.world_type {
+        WorldType::Level { index } => world
+            .variant
+            .level_name(&levels.levels[index].display_name()),
+        WorldType::Endless => "Endless".to_string(),
+    });
+    if let Ok(mut context) = CONTEXT.lock() {
+        *context = CrashContext {
+            state,
+            level,
+            seed: seed.0,
+        };
+    }
+}
+
+#[derive(Component)]
+struct CrashNotice;
+
+#[derive(Component)]
+struct CrashNoticeText;
+
+#[derive(Component, Clone, Copy)]
+enum CrashButton {
+    View,
+    Continue,
+}
+
+fn offer_crash_report(mut commands: Commands, font: Res<GameFont>) {
+    if fs::metadata(CRASH_REPORT).is_err() {
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(10.0),
+                    left: Val::Percent(15.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Percent(70.0), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                padding: Rect::all(Val::Px(20.0)),
+                ..Style::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+            ..NodeBundle::default()
+        })
+        .insert(CrashNotice)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        format!(
+                            "The game crashed last time. A report was saved to {CRASH_REPORT}."
+                        ),
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(CrashNoticeText);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(20.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    color: Color::NONE.into(),
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (CrashButton::View, "View report"),
+                        (CrashButton::Continue, "Continue"),
+                    ] {
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(200.0), Val::Px(50.0)),
+                                    margin: Rect::all(Val::Px(10.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Style::default()
+                                },
+                                ..ButtonBundle::default()
+                            })
+                            .insert(button)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        label,
+                                        TextStyle {
+                                            font: font.get_handle(),
+                                            font_size: 35.0,
+                                            color: Color::BLACK,
+                                        },
+                                        TextAlignment::default(),
+                                    ),
+                                    ..TextBundle::default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+fn manage_crash_buttons(
+    mut commands: Commands,
+    interaction: Query<(&Interaction, &CrashButton), Changed<Interaction>>,
+    notice: Query<Entity, With<CrashNotice>>,
+    mut text: Query<&mut Text, With<CrashNoticeText>>,
+) {
+    for (interaction, button) in interaction.iter() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        match button {
+            CrashButton::View => {
+                let report = fs::read_to_string(CRASH_REPORT).unwrap_or_default();
+                let shown: Vec<&str> = report.lines().take(SHOWN_LINES).collect();
+                for mut text in text.iter_mut() {
+                    text.sections[0].value = shown.join("\n");
+                    text.sections[0].style.font_size = 20.0;
+                }
+            }
+            CrashButton::Continue => {
+                if let Err(err) = fs::rename(CRASH_REPORT, SEEN_CRASH_REPORT) {
+                    warn!("Failed to move {CRASH_REPORT}: {err}");
+                }
+                for entity in notice.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_say_what_was_being_played() {
+        let context = CrashContext {
+            state: "Game (Playing)".into(),
+            level: Some("Level 1 (Flipped)".into()),
+            seed: 42,
+        };
+        let report = context.report("panicked at src/main.rs:1:1", &Backtrace::disabled());
+        assert!(report.starts_with("beeline"));
+        assert!(report.contains("State: Game (Playing)\nLevel: Level 1 (Flipped)\nSeed: 42\n"));
+        assert!(report.contains("panicked at src/main.rs:1:1"));
+
+        // Outside of a level there's nothing to seed
+        let menu = CrashContext {
+            state: "Menu".into(),
+            level: None,
+            seed: 0,
+        };
+        assert!(!menu.report("", &Backtrace::disabled()).contains("Seed"));
+    }
+}
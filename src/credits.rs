@@ -0,0 +1,125 @@
+use crate::{
+    atlas::asset_path,
+    change_state,
+    ui::{spawn_back_button, GameFont},
+    AppState,
+};
+use bevy::prelude::*;
+use std::fs;
+
+const CREDITS_FILE: &str = "credits.txt";
+// Shown if the credits file can't be read, e.g. when the assets directory is missing
+const EMBEDDED_CREDITS: &str = include_str!("../assets/credits.txt");
+// Scroll speed in pixels per second
+const SCROLL_SPEED: f32 = 40.0;
+// Scroll speed multiplier while the mouse or space is held
+const FAST_SCROLL_MULTIPLIER: f32 = 5.0;
+
+pub struct CreditsPlugin;
+
+impl Plugin for CreditsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Credits).with_system(create_credits))
+            .add_system_set(
+                SystemSet::on_update(AppState::Credits)
+                    .with_system(scroll_credits)
+                    .with_system(skip_credits),
+            );
+    }
+}
+
+#[derive(Component)]
+struct CreditsText {
+    offset: f32,
+}
+
+// Read each time they're shown, so changes to the file show up without a rebuild
+fn load_credits() -> String {
+    let path = asset_path(CREDITS_FILE);
+    match fs::read_to_string(&path) {
+        Ok(credits) => credits,
+        Err(err) => {
+            warn!("Failed to read credits from {}: {err}", path.display());
+            EMBEDDED_CREDITS.to_owned()
+        }
+    }
+}
+
+fn create_credits(mut commands: Commands, windows: Res<Windows>, font: Res<GameFont>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    spawn_back_button(&mut commands, font.get_handle());
+
+    // Start just below the bottom of the window
+    let offset = windows.get_primary().map_or(0.0, |window| window.height());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Px(offset),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    text: Text::with_section(
+                        load_credits(),
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 40.0,
+                            ..TextStyle::default()
+                        },
+                        TextAlignment {
+                            horizontal: HorizontalAlign::Center,
+                            ..TextAlignment::default()
+                        },
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(CreditsText { offset });
+        });
+}
+
+fn scroll_credits(
+    time: Res<Time>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut credits: Query<(&mut Style, &Node, &mut CreditsText)>,
+) {
+    let speed = if mouse_buttons.pressed(MouseButton::Left) || keys.pressed(KeyCode::Space) {
+        SCROLL_SPEED * FAST_SCROLL_MULTIPLIER
+    } else {
+        SCROLL_SPEED
+    };
+
+    let (mut style, node, mut credits) = match credits.get_single_mut() {
+        Ok(credits) => credits,
+        Err(_) => return,
+    };
+    credits.offset -= speed * time.delta_seconds();
+    style.position.top = Val::Px(credits.offset);
+
+    // Go back to the menu once the credits have scrolled off the top
+    if node.size.y > 0.0 && credits.offset < -node.size.y {
+        change_state(&mut state, AppState::Menu);
+    }
+}
+
+fn skip_credits(keys: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        change_state(&mut state, AppState::Menu);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    camera::MainCamera,
+    player::Player,
+    sequencer::Sequencer,
+    ui::GameFont,
+    world::{GameWorld, Goal, WorldOrigin},
+    AppState,
+};
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// Scripted sequences of moves, waits, text and fades, built in code or loaded from
+// .cutscene RON files. One plays at a time.
+pub struct CutscenePlugin;
+
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Cutscene>()
+            .init_asset_loader::<CutsceneLoader>()
+            .add_event::<CutsceneEvent>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(play_level_intro))
+            .add_system(start_loaded_cutscene)
+            .add_system(run_cutscene);
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "6c1d9e4a-3f2b-4a8e-b7d5-0e9c8f1a2b34"]
+pub struct Cutscene {
+    actions: Vec<Action>,
+}
+
+impl Cutscene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
+
+// Each action plays in turn, once the last has finished
+#[derive(Clone, Debug, Deserialize)]
+pub enum Action {
+    // Glide an actor to a place, easing out, and optionally to a new scale on the way
+    Move {
+        actor: Actor,
+        to: Place,
+        #[serde(default)]
+        scale: Option<f32>,
+        seconds: f32,
+    },
+    Wait {
+        seconds: f32,
+    },
+    // Show a message in the middle of the screen
+    Text {
+        message: String,
+        seconds: f32,
+    },
+    // Darken the screen to this opacity of black, which lasts until the cutscene ends
+    Fade {
+        alpha: f32,
+        seconds: f32,
+    },
+    // A step for the code playing the cutscene to act on, which is told when it starts
+    // and can follow its progress
+    Step {
+        name: String,
+        seconds: f32,
+    },
+}
+
+impl Action {
+    fn seconds(&self) -> f32 {
+        match self {
+            Action::Move { seconds, .. }
+            | Action::Wait { seconds }
+            | Action::Text { seconds, .. }
+            | Action::Fade { seconds, .. }
+            | Action::Step { seconds, .. } => *seconds,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Actor {
+    Player,
+    Camera,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Place {
+    // Layout coordinates (x, y)
+    Tile(usize, usize),
+    World(Vec2),
+    Goal,
+}
+
+pub enum CutsceneEvent {
+    StepStarted(String),
+    Finished,
+}
+
+#[derive(Default)]
+struct CutsceneLoader;
+
+impl AssetLoader for CutsceneLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let cutscene: Cutscene = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(cutscene));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cutscene"]
+    }
+}
+
+// The cutscene playing
+pub struct ActiveCutscene {
+    actions: Vec<Action>,
+    sequencer: Sequencer<usize>,
+    // Where the actor of the current Move started from, as (translation, scale)
+    move_from: Option<(Vec3, Vec3)>,
+    fade_from: f32,
+}
+
+impl ActiveCutscene {
+    // How far through a Step with this name the cutscene is, if it's playing
+    pub fn step_progress(&self, step: &str) -> Option<f32> {
+        let (index, progress) = self.sequencer.current()?;
+        match &self.actions[index] {
+            Action::Step { name, .. } if name == step => Some(progress),
+            _ => None,
+        }
+    }
+}
+
+// A cutscene file waiting to load before it plays
+pub struct LoadingCutscene(Handle<Cutscene>);
+
+// Start a cutscene, replacing any that's playing
+pub fn play_cutscene(commands: &mut Commands, cutscene: Cutscene) {
+    let sequencer = cutscene
+        .actions
+        .iter()
+        .enumerate()
+        .fold(Sequencer::new(), |sequencer, (index, action)| {
+            sequencer.then(index, action.seconds())
+        });
+    commands.insert_resource(ActiveCutscene {
+        actions: cutscene.actions,
+        sequencer,
+        move_from: None,
+        fade_from: 0.0,
+    });
+}
+
+pub fn play_cutscene_file(commands: &mut Commands, asset_server: &AssetServer, path: &str) {
+    commands.insert_resource(LoadingCutscene(asset_server.load(path)));
+}
+
+fn play_level_intro(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    world: Res<Arc<GameWorld>>,
+) {
+    if let Some(intro) = &world.metadata.intro {
+        play_cutscene_file(&mut commands, &asset_server, intro);
+    }
+}
+
+fn start_loaded_cutscene(
+    mut commands: Commands,
+    loading: Option<Res<LoadingCutscene>>,
+    asset_server: Res<AssetServer>,
+    cutscenes: Res<Assets<Cutscene>>,
+) {
+    let loading = match loading {
+        Some(loading) => loading,
+        None => return,
+    };
+    if let Some(cutscene) = cutscenes.get(&loading.0) {
+        play_cutscene(&mut commands, cutscene.clone());
+        commands.remove_resource::<LoadingCutscene>();
+    } else if asset_server.get_load_state(&loading.0) == bevy::asset::LoadState::Failed {
+        warn!("Skipping a cutscene that failed to load");
+        commands.remove_resource::<LoadingCutscene>();
+    }
+}
+
+#[derive(Component)]
+struct CutsceneText;
+
+#[derive(Component)]
+struct CutsceneFade;
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn run_cutscene(
+    mut commands: Commands,
+    time: Res<Time>,
+    font: Res<GameFont>,
+    origin: Res<WorldOrigin>,
+    cutscene: Option<ResMut<ActiveCutscene>>,
+    mut events: EventWriter<CutsceneEvent>,
+    mut actors: Query<(&mut Transform, Option<&Player>), Or<(With<Player>, With<MainCamera>)>>,
+    goal: Query<&GlobalTransform, With<Goal>>,
+    texts: Query<Entity, With<CutsceneText>>,
+    mut fade: Query<(Entity, &mut UiColor), With<CutsceneFade>>,
+) {
+    let mut cutscene = match cutscene {
+        Some(cutscene) => cutscene,
+        None => return,
+    };
+
+    for index in cutscene.sequencer.advance(time.delta_seconds()) {
+        // Text only lasts as long as its action
+        for text in texts.iter() {
+            commands.entity(text).despawn_recursive();
+        }
+        match cutscene.actions[index].clone() {
+            Action::Move { actor, .. } => {
+                cutscene.move_from = actor_transform(&mut actors, actor)
+                    .map(|transform| (transform.translation, transform.scale));
+            }
+            Action::Text { message, .. } => spawn_text(&mut commands, &font, message),
+            Action::Fade { .. } => {
+                cutscene.fade_from = match fade.get_single() {
+                    Ok((_, color)) => color.0.a(),
+                    Err(_) => {
+                        spawn_fade(&mut commands);
+                        0.0
+                    }
+                };
+            }
+            Action::Step { name, .. } => events.send(CutsceneEvent::StepStarted(name)),
+            Action::Wait { .. } => {}
+        }
+    }
+
+    let (index, progress) = match cutscene.sequencer.current() {
+        Some(current) => current,
+        None => {
+            for text in texts.iter() {
+                commands.entity(text).despawn_recursive();
+            }
+            for (entity, _) in fade.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            commands.remove_resource::<ActiveCutscene>();
+            events.send(CutsceneEvent::Finished);
+            return;
+        }
+    };
+    let eased = 1.0 - (1.0 - progress).powi(2);
+    match &cutscene.actions[index] {
+        Action::Move {
+            actor, to, scale, ..
+        } => {
+            let target = match to {
+                Place::Tile(x, y) => Some(origin.tile_to_world((*x, *y))),
+                Place::World(position) => Some(*position),
+                // The goal nearest where the actor set off, if the level has more than one
+                Place::Goal => {
+                    let from = cutscene
+                        .move_from
+                        .map_or(Vec2::ZERO, |(translation, _)| translation.truncate());
+                    goal.iter()
+                        .map(|goal| goal.translation.truncate())
+                        .min_by(|a, b| {
+                            a.distance_squared(from)
+                                .total_cmp(&b.distance_squared(from))
+                        })
+                }
+            };
+            let from = cutscene.move_from;
+            if let (Some(mut transform), Some(target), Some((translation, from_scale))) =
+                (actor_transform(&mut actors, *actor), target, from)
+            {
+                let position = translation.truncate().lerp(target, eased);
+                transform.translation = position.extend(translation.z);
+                if let Some(scale) = scale {
+                    transform.scale = from_scale.lerp(Vec3::splat(*scale), eased);
+                }
+            }
+        }
+        Action::Fade { alpha, .. } => {
+            if let Ok((_, mut color)) = fade.get_single_mut() {
+                let from = cutscene.fade_from;
+                color.0.set_a(from + (alpha - from) * eased);
+            }
+        }
+        Action::Wait { .. } | Action::Text { .. } | Action::Step { .. } => {}
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn actor_transform<'a>(
+    actors: &'a mut Query<(&mut Transform, Option<&Player>), Or<(With<Player>, With<MainCamera>)>>,
+    actor: Actor,
+) -> Option<Mut<'a, Transform>> {
+    let want_player = matches!(actor, Actor::Player);
+    actors
+        .iter_mut()
+        .find(|(_, player)| player.is_some() == want_player)
+        .map(|(transform, _)| transform)
+}
+
+fn spawn_text(commands: &mut Commands, font: &GameFont, message: String) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .insert(CutsceneText)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    message,
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 50.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        });
+}
+
+fn spawn_fade(commands: &mut Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..Style::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.0).into(),
+            ..NodeBundle::default()
+        })
+        .insert(CutsceneFade);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cutscenes_load_from_ron() {
+        let cutscene: Cutscene = ron::from_str(
+            "(actions: [
+                Move(actor: Camera, to: Tile(4, 2), seconds: 1.5),
+                Text(message: \"Watch out!\", seconds: 2.0),
+                Move(actor: Player, to: Goal, scale: Some(0.0), seconds: 0.5),
+                Fade(alpha: 1.0, seconds: 0.5),
+                Wait(seconds: 0.25),
+            ])",
+        )
+        .unwrap();
+        let seconds: f32 = cutscene.actions.iter().map(Action::seconds).sum();
+        assert_eq!(cutscene.actions.len(), 5);
+        assert_eq!(seconds, 4.75);
+        assert!(ron::from_str::<Cutscene>("(actions: [Dance(seconds: 1.0)])").is_err());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
+    animation::{AnimationEvent, AnimationState, Motion, SpriteSheetAnimation},
+    change_state,
     player::Player,
     util::{AnimatedSprite, AnimatedSpriteData},
     AppState,
 };
-use benimator::{AnimationMode, Play, SpriteSheetAnimation};
 use bevy::prelude::*;
 use rand::random;
 use std::{f32::consts::PI, time::Duration};
@@ -37,34 +38,25 @@ struct DeathTimer;
 
 fn spawn_death_anim(
     mut commands: Commands,
-    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
-    player_info: Query<(Entity, &Transform), With<Player>>,
-    asset_server: Res<AssetServer>,
-    mut textures: ResMut<Assets<TextureAtlas>>,
+    mut animation_events: EventWriter<AnimationEvent>,
+    player: Query<(Entity, Option<&Children>), With<Player>>,
 ) {
     commands.spawn_bundle((Timer::from_seconds(3.5, false), DeathTimer));
 
-    let (player_entity, player_transform) = player_info.single();
-    let player_transform = *player_transform;
-
-    commands.entity(player_entity).despawn_recursive();
-
-    commands
-        .spawn_bundle(AnimatedSprite::new(
-            &mut animations,
-            &mut textures,
-            &asset_server,
-            AnimatedSpriteData {
-                path: "bee-dead.png".into(),
-                frames: 73,
-                size: Vec2::splat(Player::SIZE),
-                transform: player_transform,
-                delay: Duration::from_millis(35),
-                mode: AnimationMode::Once,
-            },
-        ))
-        .insert(Player)
-        .insert(Play);
+    let (player, children) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    // Accessories and upgrade effects don't survive the fall
+    if let Some(children) = children {
+        for child in children.iter() {
+            commands.entity(*child).despawn_recursive();
+        }
+    }
+    animation_events.send(AnimationEvent {
+        entity: player,
+        motion: Motion::Death,
+    });
 }
 
 const SHARD_SPEED: f32 = 700.0;
@@ -74,12 +66,11 @@ fn end_death_anim(
     mut animations: ResMut<Assets<SpriteSheetAnimation>>,
     mut textures: ResMut<Assets<TextureAtlas>>,
     asset_server: Res<AssetServer>,
-    query: Query<(Entity, &Transform, Option<&Play>), With<Player>>,
+    query: Query<(Entity, &Transform, &AnimationState), With<Player>>,
 ) {
-    if let Ok((player_entity, player_transform, playing)) = query.get_single() {
+    if let Ok((player_entity, player_transform, animation)) = query.get_single() {
         let player_transform = *player_transform;
-        if playing.is_none() {
-            // the animation is no longer playing (AnimationMode::Once)
+        if animation.is_dead() {
             // destroy the player death entity
             commands.entity(player_entity).despawn_recursive();
             // create death flakes
@@ -132,8 +123,11 @@ fn update_death_timer(
     time: Res<Time>,
     mut timer: Query<&mut Timer, With<DeathTimer>>,
 ) {
-    let mut timer = timer.single_mut();
+    let mut timer = match timer.get_single_mut() {
+        Ok(timer) => timer,
+        Err(_) => return,
+    };
     if timer.tick(time.delta()).just_finished() {
-        state.set(AppState::Retry).unwrap();
+        change_state(&mut state, AppState::Retry);
     }
 }
@@ -0,0 +1,256 @@
+use crate::{
+    challenges::{ActiveChallenges, Challenge},
+    change_state,
+    controls::Controls,
+    pack::LevelRegistry,
+    player::Player,
+    ui::{GameFont, NORMAL_BUTTON_COLOR},
+    upgrades::{Upgrade, UpgradeTracker},
+    world::{GameWorld, Variant},
+    AppState,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+
+// Debug menu for getting straight to the screen being worked on: F1 lists every state and
+// level to jump to, along with shortcuts for setting up and finishing a run. Middle clicking
+// during a run teleports the bee to the cursor.
+pub struct DebugMenuPlugin;
+
+impl Plugin for DebugMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(toggle_debug_menu)
+            .add_system(manage_debug_buttons)
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(teleport_to_cursor));
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F1;
+const TELEPORT_BUTTON: MouseButton = MouseButton::Middle;
+
+const STATES: [AppState; 14] = [
+    AppState::ProfileSelect,
+    AppState::Menu,
+    AppState::UpgradeSelect,
+    AppState::Wardrobe,
+    AppState::LevelSelect,
+    AppState::Help,
+    AppState::Settings,
+    AppState::Credits,
+    AppState::NameEntry,
+    AppState::ControlSelect,
+    AppState::Game,
+    AppState::Death,
+    AppState::Retry,
+    AppState::Victory,
+];
+
+#[derive(Component)]
+struct DebugMenu;
+
+#[derive(Component, Clone)]
+enum DebugButton {
+    State(AppState),
+    Level(usize),
+    Grant(Upgrade),
+    Complete,
+}
+
+// Whether `to` can be jumped to straight from `from`. The states around a run need a level
+// loaded, and dying or winning also need the run itself.
+fn can_jump(from: &AppState, to: &AppState, level_loaded: bool) -> bool {
+    match to {
+        _ if from == to => false,
+        AppState::UpgradeSelect | AppState::Game | AppState::Retry => level_loaded,
+        AppState::Death | AppState::Victory => *from == AppState::Game,
+        _ => true,
+    }
+}
+
+// Put an upgrade in the loadout whatever it costs, in the first free slot or else in place
+// of the secondary one
+fn grant(upgrades: &mut UpgradeTracker, upgrade: Upgrade) {
+    if upgrades.primary == Some(upgrade) || upgrades.secondary == Some(upgrade) {
+        return;
+    }
+    if upgrades.primary.is_none() {
+        upgrades.primary = Some(upgrade);
+    } else {
+        upgrades.secondary = Some(upgrade);
+    }
+}
+
+fn toggle_debug_menu(
+    mut commands: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    font: Res<GameFont>,
+    levels: Res<LevelRegistry>,
+    menu: Query<Entity, With<DebugMenu>>,
+) {
+    if !keys.clear_just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    if let Ok(menu) = menu.get_single() {
+        commands.entity(menu).despawn_recursive();
+        return;
+    }
+
+    let buttons = STATES
+        .iter()
+        .map(|state| (DebugButton::State(state.clone()), format!("{state:?}")))
+        .chain(
+            levels
+                .levels
+                .iter()
+                .enumerate()
+                .map(|(index, level)| (DebugButton::Level(index), level.display_name())),
+        )
+        .chain(
+            Upgrade::iter()
+                .map(|upgrade| (DebugButton::Grant(upgrade), format!("Grant {upgrade}"))),
+        )
+        .chain([(DebugButton::Complete, "Complete level".to_string())]);
+
+    let text_style = TextStyle {
+        font: font.get_handle(),
+        font_size: 16.0,
+        color: Color::BLACK,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(1.0),
+                    left: Val::Percent(1.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Auto, Val::Percent(98.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                flex_wrap: FlexWrap::Wrap,
+                align_content: AlignContent::FlexStart,
+                padding: Rect::all(Val::Px(4.0)),
+                ..Style::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            ..NodeBundle::default()
+        })
+        .insert(DebugMenu)
+        .with_children(|parent| {
+            for (button, label) in buttons {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(180.0), Val::Px(24.0)),
+                            margin: Rect::all(Val::Px(2.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        color: NORMAL_BUTTON_COLOR.into(),
+                        ..ButtonBundle::default()
+                    })
+                    .insert(button)
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                label,
+                                text_style.clone(),
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        });
+                    });
+            }
+        });
+}
+
+fn manage_debug_buttons(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut levels: ResMut<LevelRegistry>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    challenges: Res<ActiveChallenges>,
+    world: Option<Res<Arc<GameWorld>>>,
+    interaction: Query<(&Interaction, &DebugButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in interaction.iter() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        match button {
+            DebugButton::State(next) => {
+                if can_jump(state.current(), next, world.is_some()) {
+                    change_state(&mut state, next.clone());
+                } else {
+                    warn!("Can't jump to {next:?} from {:?}", state.current());
+                }
+            }
+            // Straight into the level with the loadout as it is, skipping upgrade select
+            DebugButton::Level(index) => {
+                match levels.load_cached(
+                    *index,
+                    Variant::Original,
+                    challenges.is_active(Challenge::Mirrored),
+                ) {
+                    Ok(world) => {
+                        commands.insert_resource(world);
+                        change_state(&mut state, AppState::Game);
+                    }
+                    Err(err) => warn!("Failed to load level: {err}"),
+                }
+            }
+            DebugButton::Grant(upgrade) => grant(&mut upgrades, *upgrade),
+            DebugButton::Complete => {
+                if *state.current() == AppState::Game {
+                    change_state(&mut state, AppState::Victory);
+                }
+            }
+        }
+    }
+}
+
+fn teleport_to_cursor(
+    buttons: Res<Input<MouseButton>>,
+    controls: Res<Controls>,
+    mut player: Query<&mut Transform, With<Player>>,
+) {
+    if !buttons.just_pressed(TELEPORT_BUTTON) {
+        return;
+    }
+    if let (Some(cursor), Ok(mut transform)) = (controls.cursor, player.get_single_mut()) {
+        transform.translation = cursor.extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_needed_to_die_or_win() {
+        assert!(can_jump(&AppState::Menu, &AppState::Credits, false));
+        assert!(!can_jump(&AppState::Menu, &AppState::Menu, true));
+        assert!(!can_jump(&AppState::Menu, &AppState::Game, false));
+        assert!(can_jump(&AppState::Menu, &AppState::Game, true));
+        assert!(!can_jump(&AppState::Menu, &AppState::Victory, true));
+        assert!(can_jump(&AppState::Game, &AppState::Victory, true));
+        assert!(can_jump(&AppState::Game, &AppState::Death, true));
+    }
+
+    #[test]
+    fn granted_upgrades_fill_the_loadout() {
+        let mut upgrades = UpgradeTracker::default();
+        grant(&mut upgrades, Upgrade::Teleport);
+        grant(&mut upgrades, Upgrade::Teleport);
+        assert_eq!(upgrades.primary, Some(Upgrade::Teleport));
+        assert_eq!(upgrades.secondary, None);
+
+        // Over budget is fine
+        grant(&mut upgrades, Upgrade::DoubleSpeed);
+        grant(&mut upgrades, Upgrade::Shield);
+        assert_eq!(upgrades.primary, Some(Upgrade::Teleport));
+        assert_eq!(upgrades.secondary, Some(Upgrade::Shield));
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::{prelude::*, utils::HashSet};
+
+// Defers despawning game entities to the end of the frame.
+// Commands from every Update system are applied before anything queued here is
+// despawned, so one system removing an entity can't make another system's
+// commands for it panic, and an entity queued twice is only despawned once.
+pub struct DespawnPlugin;
+
+impl Plugin for DespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DespawnQueue>()
+            .add_system_to_stage(CoreStage::Last, despawn_queued);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DespawnQueue(HashSet<Entity>);
+
+impl DespawnQueue {
+    // Despawn the entity and its children at the end of the frame
+    pub fn push(&mut self, entity: Entity) {
+        self.0.insert(entity);
+    }
+
+    // Whether the entity is about to be despawned, for systems that should stop using it now
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.contains(&entity)
+    }
+}
+
+fn despawn_queued(
+    mut commands: Commands,
+    mut queue: ResMut<DespawnQueue>,
+    entities: Query<Entity>,
+    parents: Query<&Parent>,
+) {
+    let queued = std::mem::take(&mut queue.0);
+    for &entity in &queued {
+        // Skip entities that were already removed some other way
+        if entities.get(entity).is_err() {
+            continue;
+        }
+        // Children queued alongside an ancestor go with it
+        let mut ancestor = parents.get(entity).ok().map(|parent| parent.0);
+        let mut covered = false;
+        while let Some(parent) = ancestor {
+            covered |= queued.contains(&parent);
+            ancestor = parents.get(parent).ok().map(|parent| parent.0);
+        }
+        if !covered {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_entities_are_despawned_once() {
+        let mut world = World::new();
+        world.insert_resource(DespawnQueue::default());
+        let parent = world.spawn().id();
+        let child = world.spawn().id();
+        world.entity_mut(parent).push_children(&[child]);
+        let removed = world.spawn().id();
+
+        let mut queue = world.get_resource_mut::<DespawnQueue>().unwrap();
+        queue.push(parent);
+        queue.push(child);
+        queue.push(parent);
+        queue.push(removed);
+        world.despawn(removed);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(despawn_queued);
+        stage.run(&mut world);
+
+        assert!(world.get_entity(parent).is_none());
+        assert!(world.get_entity(child).is_none());
+        assert!(world.get_resource::<DespawnQueue>().unwrap().0.is_empty());
+    }
+}
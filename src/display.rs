@@ -0,0 +1,161 @@
+use crate::{input_map::Action, save::SaveData, AppState};
+use bevy::{prelude::*, window::WindowMode, winit::WinitWindows};
+
+// Opens the window on the monitor chosen in settings, fullscreen if it was last time the
+// game ran there. The fullscreen hotkey, F11 unless rebound, switches it on and off. Cursor
+// positions need nothing extra when the window moves between monitors, since Bevy keeps
+// them in logical pixels for whichever scale factor the window has now.
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Monitors>()
+            .add_startup_system(list_monitors)
+            .add_system_set(SystemSet::on_enter(AppState::Settings).with_system(list_monitors))
+            .add_system(toggle_fullscreen)
+            .add_system(apply_display_settings);
+    }
+}
+
+// A connected monitor, in physical pixels on the desktop
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: String,
+    pub position: IVec2,
+    pub size: UVec2,
+}
+
+// Monitors that were connected when last checked, on startup and on opening settings
+#[derive(Debug, Default)]
+pub struct Monitors(pub Vec<Monitor>);
+
+impl Monitors {
+    // The monitor after `current` to choose in settings, going through letting the system
+    // decide between the last and the first
+    pub fn next(&self, current: Option<&str>) -> Option<String> {
+        let index =
+            current.and_then(|current| self.0.iter().position(|monitor| monitor.name == current));
+        let next = match index {
+            Some(index) => self.0.get(index + 1),
+            None if current.is_some() => None,
+            None => self.0.first(),
+        };
+        next.map(|monitor| monitor.name.clone())
+    }
+
+    fn get(&self, name: &str) -> Option<&Monitor> {
+        self.0.iter().find(|monitor| monitor.name == name)
+    }
+}
+
+// Where a window `size` big goes to be in the middle of `monitor`, or its top left corner if
+// the window is too big
+fn centered_position(monitor: &Monitor, size: UVec2) -> IVec2 {
+    let spare = monitor.size.as_ivec2() - size.as_ivec2();
+    monitor.position + spare.max(IVec2::ZERO) / 2
+}
+
+fn list_monitors(
+    windows: Res<Windows>,
+    winit_windows: Option<Res<WinitWindows>>,
+    mut monitors: ResMut<Monitors>,
+) {
+    let window = match (windows.get_primary(), &winit_windows) {
+        (Some(window), Some(winit_windows)) => match winit_windows.get_window(window.id()) {
+            Some(window) => window,
+            None => return,
+        },
+        _ => return,
+    };
+    monitors.0 = window
+        .available_monitors()
+        .enumerate()
+        .map(|(index, monitor)| Monitor {
+            name: monitor
+                .name()
+                .unwrap_or_else(|| format!("Monitor {}", index + 1)),
+            position: IVec2::new(monitor.position().x, monitor.position().y),
+            size: UVec2::new(monitor.size().width, monitor.size().height),
+        })
+        .collect();
+}
+
+fn toggle_fullscreen(keys: Res<Input<KeyCode>>, mut save: ResMut<SaveData>) {
+    if save
+        .settings
+        .input_map
+        .just_pressed(&keys, Action::Fullscreen)
+    {
+        let fullscreen = save.settings.fullscreen();
+        save.settings.set_fullscreen(!fullscreen);
+    }
+}
+
+// Move the window when the monitor or fullscreen settings change, including when a profile
+// with different ones is loaded
+fn apply_display_settings(
+    save: Res<SaveData>,
+    monitors: Res<Monitors>,
+    mut windows: ResMut<Windows>,
+    mut applied: Local<Option<(Option<String>, bool)>>,
+) {
+    let wanted = (save.settings.monitor.clone(), save.settings.fullscreen());
+    if applied.as_ref() == Some(&wanted) {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+
+    // Fullscreen windows stay on their monitor, so come out of it to move
+    if window.mode() != WindowMode::Windowed {
+        window.set_mode(WindowMode::Windowed);
+    }
+    // A monitor that's been unplugged leaves the window where it is
+    if let Some(monitor) = wanted.0.as_deref().and_then(|name| monitors.get(name)) {
+        let size = UVec2::new(window.physical_width(), window.physical_height());
+        window.set_position(centered_position(monitor, size));
+    }
+    if wanted.1 {
+        window.set_mode(WindowMode::BorderlessFullscreen);
+    }
+    *applied = Some(wanted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, x: i32) -> Monitor {
+        Monitor {
+            name: name.into(),
+            position: IVec2::new(x, 0),
+            size: UVec2::new(1920, 1080),
+        }
+    }
+
+    #[test]
+    fn monitors_cycle_through_automatic() {
+        let monitors = Monitors(vec![monitor("Left", -1920), monitor("Right", 0)]);
+        assert_eq!(monitors.next(None), Some("Left".into()));
+        assert_eq!(monitors.next(Some("Left")), Some("Right".into()));
+        assert_eq!(monitors.next(Some("Right")), None);
+        // One that's been unplugged goes back to letting the system decide
+        assert_eq!(monitors.next(Some("Projector")), None);
+        assert_eq!(Monitors::default().next(None), None);
+    }
+
+    #[test]
+    fn windows_open_in_the_middle_of_the_monitor() {
+        let left = monitor("Left", -1920);
+        assert_eq!(
+            centered_position(&left, UVec2::new(1280, 720)),
+            IVec2::new(-1600, 180)
+        );
+        assert_eq!(
+            centered_position(&left, UVec2::new(2560, 1440)),
+            IVec2::new(-1920, 0)
+        );
+    }
+}
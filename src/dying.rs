@@ -0,0 +1,157 @@
+use crate::{
+    change_state,
+    cutscene::{play_cutscene, Action, Cutscene},
+    player::Player,
+    render_layer::RenderLayer,
+    sound::{Sounds, Tone},
+    AppState, GameState,
+};
+use bevy::prelude::*;
+use rand::random;
+use std::f32::consts::TAU;
+
+// Plays out the hit that killed the player, with the run frozen and projectiles slowed,
+// before moving on to AppState::Death
+pub struct DyingPlugin;
+
+impl Plugin for DyingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Dying).with_system(start_dying))
+            .add_system_set(
+                SystemSet::on_update(GameState::Dying)
+                    .with_system(fall)
+                    .with_system(update_particles)
+                    .with_system(finish_dying),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Dying).with_system(clear_particles))
+            .add_system_set(SystemSet::on_enter(AppState::Death).with_system(stop_dying));
+    }
+}
+
+const DYING_SECONDS: f32 = 1.0;
+// Radians per second the bee spins as it falls
+const SPIN_SPEED: f32 = 3.0 * TAU;
+// Pixels per second squared
+const FALL_GRAVITY: f32 = 600.0;
+// Starts with a little hop before falling
+const FALL_HOP: f32 = 150.0;
+// How dark the screen gets behind the falling bee
+const DIM_ALPHA: f32 = 0.4;
+const PARTICLES: usize = 14;
+const PARTICLE_SIZE: f32 = 4.0;
+const PARTICLE_SPEED: (f32, f32) = (80.0, 260.0);
+const PARTICLE_COLORS: [Color; 2] = [Color::rgb(1.0, 0.8, 0.1), Color::rgb(0.4, 0.25, 0.1)];
+
+struct DyingTimer(Timer);
+
+#[derive(Component)]
+struct Falling {
+    velocity: f32,
+}
+
+#[derive(Component)]
+struct DyingParticle {
+    velocity: Vec2,
+}
+
+fn start_dying(
+    mut commands: Commands,
+    audio: Res<Audio<Tone>>,
+    sounds: Res<Sounds>,
+    player: Query<(Entity, &Transform), With<Player>>,
+) {
+    commands.insert_resource(DyingTimer(Timer::from_seconds(DYING_SECONDS, false)));
+    audio.play(sounds.player_hit.clone());
+    play_cutscene(
+        &mut commands,
+        Cutscene::new().then(Action::Fade {
+            alpha: DIM_ALPHA,
+            seconds: DYING_SECONDS,
+        }),
+    );
+
+    let (player, transform) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    commands
+        .entity(player)
+        .insert(Falling { velocity: FALL_HOP });
+
+    for i in 0..PARTICLES {
+        let angle = random::<f32>() * TAU;
+        let speed = PARTICLE_SPEED.0 + random::<f32>() * (PARTICLE_SPEED.1 - PARTICLE_SPEED.0);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: PARTICLE_COLORS[i % PARTICLE_COLORS.len()],
+                    custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                    ..Sprite::default()
+                },
+                transform: Transform::from_translation(transform.translation),
+                ..SpriteBundle::default()
+            })
+            .insert(RenderLayer::Particles)
+            .insert(DyingParticle {
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+            });
+    }
+}
+
+fn fall(time: Res<Time>, mut player: Query<(&mut Transform, &mut Falling), With<Player>>) {
+    let delta = time.delta_seconds();
+    for (mut transform, mut falling) in player.iter_mut() {
+        transform.rotate(Quat::from_rotation_z(SPIN_SPEED * delta));
+        falling.velocity -= FALL_GRAVITY * delta;
+        transform.translation.y += falling.velocity * delta;
+    }
+}
+
+// Particles slow down and fade out over the sequence
+fn update_particles(
+    time: Res<Time>,
+    timer: Option<Res<DyingTimer>>,
+    mut particles: Query<(&mut Transform, &mut Sprite, &mut DyingParticle)>,
+) {
+    let delta = time.delta_seconds();
+    let progress = timer.map_or(1.0, |timer| timer.0.percent());
+    for (mut transform, mut sprite, mut particle) in particles.iter_mut() {
+        transform.translation += (particle.velocity * delta).extend(0.0);
+        particle.velocity *= 1.0 - (3.0 * delta).min(1.0);
+        sprite.color.set_a(1.0 - progress);
+    }
+}
+
+fn finish_dying(
+    mut commands: Commands,
+    time: Res<Time>,
+    timer: Option<ResMut<DyingTimer>>,
+    mut state: ResMut<State<AppState>>,
+    player: Query<Entity, With<Falling>>,
+) {
+    let mut timer = match timer {
+        Some(timer) => timer,
+        None => return,
+    };
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    for player in player.iter() {
+        commands.entity(player).remove::<Falling>();
+    }
+    change_state(&mut state, AppState::Death);
+}
+
+// Leave Dying once the death screen is up, so gameplay can't resume for a frame in between
+fn stop_dying(mut state: ResMut<State<GameState>>) {
+    if *state.current() == GameState::Dying {
+        change_state(&mut state, GameState::Playing);
+    }
+}
+
+fn clear_particles(mut commands: Commands, particles: Query<Entity, With<DyingParticle>>) {
+    commands.remove_resource::<DyingTimer>();
+    for particle in particles.iter() {
+        commands.entity(particle).despawn();
+    }
+}
@@ -0,0 +1,1036 @@
+use crate::{
+    camera::{self, MainCamera},
+    render_layer::RenderLayer,
+    ui::GameFont,
+    world::{self, Diagnostic, GameWorld, Tile, WorldOrigin},
+    AppState,
+};
+use bevy::{app::AppExit, prelude::*};
+use std::{collections::HashSet, fs, path::PathBuf};
+
+// Size of a level started from scratch, in tiles
+const NEW_LEVEL_SIZE: (usize, usize) = (24, 16);
+// Codes placed with the number keys, in order, as (code, name)
+const BRUSHES: [(&str, &str); 8] = [
+    ("#", "Wall"),
+    ("M", "Missile spawner"),
+    ("L:0", "Laser spawner"),
+    ("T", "Trap"),
+    ("G", "Goal"),
+    ("*", "Start"),
+    ("C", "Checkpoint"),
+    ("F", "Flower"),
+];
+const BRUSH_KEYS: [KeyCode; 8] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+];
+// Tools for the left mouse button, as (key, tool, name)
+const TOOLS: [(KeyCode, Tool, &str); 4] = [
+    (KeyCode::P, Tool::Paint, "Paint"),
+    (KeyCode::L, Tool::Line, "Line"),
+    (KeyCode::F, Tool::Fill, "Fill"),
+    (KeyCode::R, Tool::Select, "Select"),
+];
+// Flag settings toggled from the keyboard
+const SETTING_KEYS: [(KeyCode, &str); 3] = [
+    (KeyCode::B, "boundary"),
+    (KeyCode::N, "stamina"),
+    (KeyCode::O, "fog"),
+];
+// There's only one of each of these, so placing one moves it
+const UNIQUE_CODES: [&str; 2] = ["*", "G"];
+const EMPTY: &str = ".";
+// Pixels per second the view pans with the arrow keys
+const PAN_SPEED: f32 = 400.0;
+const BACKGROUND_COLOR: Color = Color::rgb(0.2, 0.2, 0.4);
+const CURSOR_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.3);
+const SELECTION_COLOR: Color = Color::rgba(0.4, 0.7, 1.0, 0.25);
+
+// A simple level editor, opened with `--edit <level.tsv>`, with painting, line, fill and
+// rectangle selection tools. Every change goes through an undo history, and quitting with
+// unsaved changes asks first.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Brush>()
+            .init_resource::<Tools>()
+            .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(open_editor))
+            .add_system_set(
+                SystemSet::on_update(AppState::Editor)
+                    .with_system(pick_brush)
+                    .with_system(pick_tool)
+                    .with_system(use_tool)
+                    .with_system(undo_redo)
+                    .with_system(copy_paste)
+                    .with_system(toggle_settings)
+                    .with_system(save_level)
+                    .with_system(quit_editor)
+                    .with_system(pan_view)
+                    .with_system(redraw_tiles)
+                    .with_system(update_status),
+            );
+    }
+}
+
+// The level file being edited, which is created on the first save if it doesn't exist yet
+pub struct EditorFile(pub PathBuf);
+
+// A level as the codes written in its file, so anything the editor doesn't know about is
+// saved back untouched
+#[derive(Debug, PartialEq)]
+struct EditorLevel {
+    // `@<key>\t<value>` lines, in the order they were written
+    settings: Vec<(String, String)>,
+    rows: Vec<Vec<String>>,
+}
+
+impl EditorLevel {
+    fn parse(source: &str) -> Self {
+        let mut settings = Vec::new();
+        let mut rows = Vec::new();
+        for line in world::normalize_line_endings(source)
+            .trim_end()
+            .lines()
+            .map(str::trim_end)
+        {
+            if let Some(entry) = line.strip_prefix('@') {
+                let (key, value) = entry.split_once('\t').unwrap_or((entry, ""));
+                settings.push((key.to_string(), value.to_string()));
+            } else {
+                rows.push(
+                    world::row_tiles(line)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                );
+            }
+        }
+        Self { settings, rows }
+    }
+
+    fn blank((width, height): (usize, usize)) -> Self {
+        Self {
+            settings: Vec::new(),
+            rows: vec![vec![EMPTY.to_string(); width]; height],
+        }
+    }
+
+    fn serialize(&self) -> String {
+        let mut output = String::new();
+        for (key, value) in &self.settings {
+            output.push('@');
+            output.push_str(key);
+            if !value.is_empty() {
+                output.push('\t');
+                output.push_str(value);
+            }
+            output.push('\n');
+        }
+        for row in &self.rows {
+            output.push_str(&row.join("\t"));
+            output.push('\n');
+        }
+        output
+    }
+
+    fn get(&self, (x, y): (usize, usize)) -> &str {
+        self.rows
+            .get(y)
+            .and_then(|row| row.get(x))
+            .map_or(EMPTY, String::as_str)
+    }
+
+    // Set a tile, growing the layout to fit it, and return what was there before
+    fn set(&mut self, (x, y): (usize, usize), code: &str) -> String {
+        if self.rows.len() <= y {
+            self.rows.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, EMPTY.to_string());
+        }
+        std::mem::replace(&mut row[x], code.to_string())
+    }
+
+    fn tiles(&self) -> impl Iterator<Item = ((usize, usize), &str)> {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, code)| ((x, y), code.as_str()))
+        })
+    }
+
+    fn setting(&self, key: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|(setting, _)| setting == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn set_setting(&mut self, key: &str, value: Option<String>) {
+        let index = self.settings.iter().position(|(setting, _)| setting == key);
+        match (index, value) {
+            (Some(index), Some(value)) => self.settings[index].1 = value,
+            (Some(index), None) => {
+                self.settings.remove(index);
+            }
+            (None, Some(value)) => self.settings.push((key.to_string(), value)),
+            (None, None) => {}
+        }
+    }
+}
+
+// One undoable step
+#[derive(Debug)]
+enum Edit {
+    // Tiles as (coordinates, before, after), in the order they were changed
+    Tiles(Vec<((usize, usize), String, String)>),
+    // All the settings before and after, so a removed one goes back where it was
+    Settings {
+        before: Vec<(String, String)>,
+        after: Vec<(String, String)>,
+    },
+}
+
+// The level being edited along with its undo history
+struct Editor {
+    level: EditorLevel,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    // How many edits were on the undo stack when the level was last saved, None once that
+    // can't be got back to by undoing or redoing
+    saved: Option<usize>,
+    // Set by the first Escape with unsaved changes, so a second one quits without saving
+    confirming_quit: bool,
+    // Result of the last save
+    message: Option<String>,
+}
+
+impl Editor {
+    fn new(level: EditorLevel) -> Self {
+        Self {
+            level,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: Some(0),
+            confirming_quit: false,
+            message: None,
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.saved != Some(self.undo.len())
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+    }
+
+    fn push(&mut self, edit: Edit) {
+        self.redo.clear();
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        self.undo.push(edit);
+        self.confirming_quit = false;
+    }
+
+    // Change tiles as one edit, or as part of the last one when continuing a stroke of
+    // the brush
+    fn set_tiles(
+        &mut self,
+        tiles: impl IntoIterator<Item = ((usize, usize), String)>,
+        continue_stroke: bool,
+    ) {
+        let mut changes = Vec::new();
+        for (coordinates, code) in tiles {
+            if self.level.get(coordinates) == code {
+                continue;
+            }
+            // Moving the start or goal clears it from where it was
+            if UNIQUE_CODES.contains(&code.as_str()) {
+                let others: Vec<_> = self
+                    .level
+                    .tiles()
+                    .filter(|(_, other)| *other == code)
+                    .map(|(other, _)| other)
+                    .collect();
+                for other in others {
+                    let before = self.level.set(other, EMPTY);
+                    changes.push((other, before, EMPTY.to_string()));
+                }
+            }
+            let before = self.level.set(coordinates, &code);
+            changes.push((coordinates, before, code));
+        }
+        if changes.is_empty() {
+            return;
+        }
+
+        match self.undo.last_mut() {
+            Some(Edit::Tiles(stroke)) if continue_stroke && self.redo.is_empty() => {
+                stroke.extend(changes);
+                if self.saved == Some(self.undo.len()) {
+                    self.saved = None;
+                }
+                self.confirming_quit = false;
+            }
+            _ => self.push(Edit::Tiles(changes)),
+        }
+    }
+
+    fn paint(&mut self, coordinates: (usize, usize), code: &str, continue_stroke: bool) {
+        self.set_tiles([(coordinates, code.to_string())], continue_stroke);
+    }
+
+    // Fill the area of same tiles around `start` with `code`, within the layout
+    fn fill(&mut self, start: (usize, usize), code: &str) {
+        let target = self.level.get(start).to_string();
+        let width = self.level.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let height = self.level.rows.len();
+        if target == code || start.0 >= width || start.1 >= height {
+            return;
+        }
+
+        let mut filled = HashSet::from([start]);
+        let mut frontier = vec![start];
+        while let Some((x, y)) = frontier.pop() {
+            let neighbors = [
+                (x.checked_sub(1), Some(y)),
+                (Some(x + 1).filter(|x| *x < width), Some(y)),
+                (Some(x), y.checked_sub(1)),
+                (Some(x), Some(y + 1).filter(|y| *y < height)),
+            ];
+            for neighbor in neighbors {
+                if let (Some(x), Some(y)) = neighbor {
+                    if self.level.get((x, y)) == target && filled.insert((x, y)) {
+                        frontier.push((x, y));
+                    }
+                }
+            }
+        }
+        let mut filled: Vec<_> = filled.into_iter().collect();
+        filled.sort_unstable_by_key(|(x, y)| (*y, *x));
+        self.set_tiles(
+            filled.into_iter().map(|tile| (tile, code.to_string())),
+            false,
+        );
+    }
+
+    fn draw_line(&mut self, from: (usize, usize), to: (usize, usize), code: &str) {
+        self.set_tiles(
+            line(from, to)
+                .into_iter()
+                .map(|tile| (tile, code.to_string())),
+            false,
+        );
+    }
+
+    // Tiles in the rectangle between two corners, by row
+    fn copy(&self, (a, b): Selection) -> Clipboard {
+        let (min, max) = ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)));
+        (min.1..=max.1)
+            .map(|y| {
+                (min.0..=max.0)
+                    .map(|x| self.level.get((x, y)).to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn cut(&mut self, selection: Selection) -> Clipboard {
+        let clipboard = self.copy(selection);
+        let ((a, b), empty) = (selection, EMPTY.to_string());
+        let (min, max) = ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)));
+        self.set_tiles(
+            (min.1..=max.1)
+                .flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+                .map(|tile| (tile, empty.clone())),
+            false,
+        );
+        clipboard
+    }
+
+    // Paste with the clipboard's top-left tile at `at`
+    fn paste(&mut self, clipboard: &Clipboard, at: (usize, usize)) {
+        self.set_tiles(
+            clipboard.iter().enumerate().flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(move |(x, code)| ((at.0 + x, at.1 + y), code.clone()))
+            }),
+            false,
+        );
+    }
+
+    fn set_setting(&mut self, key: &str, value: Option<String>) {
+        let before = self.level.settings.clone();
+        self.level.set_setting(key, value);
+        if before != self.level.settings {
+            self.push(Edit::Settings {
+                before,
+                after: self.level.settings.clone(),
+            });
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(edit) = self.undo.pop() {
+            match &edit {
+                Edit::Tiles(changes) => {
+                    for (coordinates, before, _) in changes.iter().rev() {
+                        self.level.set(*coordinates, before);
+                    }
+                }
+                Edit::Settings { before, .. } => self.level.settings = before.clone(),
+            }
+            self.redo.push(edit);
+            self.confirming_quit = false;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(edit) = self.redo.pop() {
+            match &edit {
+                Edit::Tiles(changes) => {
+                    for (coordinates, _, after) in changes {
+                        self.level.set(*coordinates, after);
+                    }
+                }
+                Edit::Settings { after, .. } => self.level.settings = after.clone(),
+            }
+            self.undo.push(edit);
+            self.confirming_quit = false;
+        }
+    }
+}
+
+// Index into BRUSHES of the code the left mouse button places
+#[derive(Default)]
+struct Brush(usize);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Tool {
+    // Paint tiles under the cursor while the button is held
+    #[default]
+    Paint,
+    // Draw a straight line from where the button is pressed to where it's released
+    Line,
+    // Fill the area of same tiles that's clicked
+    Fill,
+    // Drag out a rectangle to copy or cut
+    Select,
+}
+
+// Corners of a rectangle of tiles, in any order
+type Selection = ((usize, usize), (usize, usize));
+
+// Copied tiles, by row
+type Clipboard = Vec<Vec<String>>;
+
+#[derive(Default)]
+struct Tools {
+    tool: Tool,
+    // Where the button went down, for tools that drag
+    anchor: Option<(usize, usize)>,
+    selection: Option<Selection>,
+    clipboard: Option<Clipboard>,
+}
+
+// Tiles on a straight line between two tiles, inclusive, from Bresenham's algorithm
+fn line(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+    let (dx, dy) = (
+        to.0.abs_diff(from.0) as isize,
+        -(to.1.abs_diff(from.1) as isize),
+    );
+    let step = (
+        if from.0 < to.0 { 1 } else { -1 },
+        if from.1 < to.1 { 1 } else { -1 },
+    );
+    let mut error = dx + dy;
+    let mut tiles = vec![(x as usize, y as usize)];
+    while (x as usize, y as usize) != to {
+        if 2 * error >= dy {
+            error += dy;
+            x += step.0;
+        }
+        if 2 * error <= dx {
+            error += dx;
+            y += step.1;
+        }
+        tiles.push((x as usize, y as usize));
+    }
+    tiles
+}
+
+#[derive(Component)]
+struct EditorTile;
+
+#[derive(Component)]
+struct EditorCursor;
+
+#[derive(Component)]
+struct EditorStatus;
+
+#[derive(Component)]
+struct EditorSelection;
+
+fn open_editor(mut commands: Commands, file: Res<EditorFile>, font: Res<GameFont>) {
+    // A file that doesn't exist yet is a new level
+    let level = match fs::read_to_string(&file.0) {
+        Ok(source) => EditorLevel::parse(&source),
+        Err(_) => EditorLevel::blank(NEW_LEVEL_SIZE),
+    };
+    let height = level.rows.len();
+    let width = level.rows.iter().map(Vec::len).max().unwrap_or(0);
+    commands.insert_resource(Editor::new(level));
+
+    let center = WorldOrigin::default().tile_to_world((width / 2, height / 2));
+    camera::spawn_camera(&mut commands, center);
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: CURSOR_COLOR,
+                custom_size: Some(Vec2::splat(Tile::SIZE)),
+                ..Sprite::default()
+            },
+            ..SpriteBundle::default()
+        })
+        .insert(RenderLayer::Particles)
+        .insert(EditorCursor);
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: SELECTION_COLOR,
+                ..Sprite::default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..SpriteBundle::default()
+        })
+        .insert(RenderLayer::Particles)
+        .insert(EditorSelection);
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(EditorStatus);
+}
+
+fn ctrl_pressed(keys: &Input<KeyCode>) -> bool {
+    keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl)
+}
+
+fn shift_pressed(keys: &Input<KeyCode>) -> bool {
+    keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift)
+}
+
+// Tile under the mouse cursor, if it's over the layout's side of the origin
+fn hovered_tile(
+    windows: &Windows,
+    camera: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<(usize, usize)> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let (camera, transform) = camera.get_single().ok()?;
+    let position = camera::cursor_to_world(
+        cursor,
+        Vec2::new(window.width(), window.height()),
+        camera,
+        transform,
+    );
+    WorldOrigin::default().world_to_tile(position)
+}
+
+fn pick_brush(keys: Res<Input<KeyCode>>, mut brush: ResMut<Brush>) {
+    if ctrl_pressed(&keys) {
+        return;
+    }
+    if let Some(index) = BRUSH_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        brush.0 = index;
+    }
+}
+
+fn pick_tool(keys: Res<Input<KeyCode>>, mut tools: ResMut<Tools>) {
+    if ctrl_pressed(&keys) {
+        return;
+    }
+    if let Some((_, tool, _)) = TOOLS.iter().find(|(key, _, _)| keys.just_pressed(*key)) {
+        tools.tool = *tool;
+        tools.anchor = None;
+    }
+}
+
+// The left button uses the tool with the brush, and the right always erases. Dragging
+// paints a stroke that undoes all at once.
+fn use_tool(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    brush: Res<Brush>,
+    mut tools: ResMut<Tools>,
+    mut editor: ResMut<Editor>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut cursor: Query<&mut Transform, With<EditorCursor>>,
+    mut selection: Query<
+        (&mut Transform, &mut Sprite, &mut Visibility),
+        (With<EditorSelection>, Without<EditorCursor>),
+    >,
+) {
+    let tile = hovered_tile(&windows, &camera);
+    if let (Some(tile), Ok(mut cursor)) = (tile, cursor.get_single_mut()) {
+        let position = WorldOrigin::default().tile_to_world(tile);
+        cursor.translation.x = position.x;
+        cursor.translation.y = position.y;
+    }
+
+    if let Some(tile) = tile {
+        let code = BRUSHES[brush.0].0;
+        if mouse.pressed(MouseButton::Right) && editor.level.get(tile) != EMPTY {
+            editor.paint(tile, EMPTY, !mouse.just_pressed(MouseButton::Right));
+        }
+        match tools.tool {
+            Tool::Paint => {
+                if mouse.pressed(MouseButton::Left) && editor.level.get(tile) != code {
+                    editor.paint(tile, code, !mouse.just_pressed(MouseButton::Left));
+                }
+            }
+            // There's only one start and goal, so they're placed as with the paint tool
+            _ if UNIQUE_CODES.contains(&code) => {
+                if mouse.just_pressed(MouseButton::Left) {
+                    editor.paint(tile, code, false);
+                }
+            }
+            Tool::Fill => {
+                if mouse.just_pressed(MouseButton::Left) {
+                    editor.fill(tile, code);
+                }
+            }
+            Tool::Line => {
+                if mouse.just_pressed(MouseButton::Left) {
+                    tools.anchor = Some(tile);
+                }
+                if mouse.just_released(MouseButton::Left) {
+                    if let Some(anchor) = tools.anchor.take() {
+                        editor.draw_line(anchor, tile, code);
+                    }
+                }
+            }
+            Tool::Select => {
+                if mouse.just_pressed(MouseButton::Left) {
+                    tools.anchor = Some(tile);
+                }
+                if mouse.pressed(MouseButton::Left) {
+                    tools.selection = tools.anchor.map(|anchor| (anchor, tile));
+                }
+            }
+        }
+    }
+
+    if let Ok((mut transform, mut sprite, mut visibility)) = selection.get_single_mut() {
+        visibility.is_visible = tools.selection.is_some();
+        if let Some((a, b)) = tools.selection {
+            let origin = WorldOrigin::default();
+            let (a, b) = (origin.tile_to_world(a), origin.tile_to_world(b));
+            transform.translation = ((a + b) / 2.0).extend(0.0);
+            sprite.custom_size = Some((a - b).abs() + Vec2::splat(Tile::SIZE));
+        }
+    }
+}
+
+// Copying and cutting take the selection, and pasting puts it down under the cursor
+fn copy_paste(
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut tools: ResMut<Tools>,
+    mut editor: ResMut<Editor>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    if !ctrl_pressed(&keys) {
+        return;
+    }
+    if let Some(selection) = tools.selection {
+        if keys.just_pressed(KeyCode::C) {
+            tools.clipboard = Some(editor.copy(selection));
+        } else if keys.just_pressed(KeyCode::X) {
+            tools.clipboard = Some(editor.cut(selection));
+        }
+    }
+    if keys.just_pressed(KeyCode::V) {
+        if let (Some(clipboard), Some(tile)) = (&tools.clipboard, hovered_tile(&windows, &camera)) {
+            editor.paste(clipboard, tile);
+        }
+    }
+}
+
+fn undo_redo(keys: Res<Input<KeyCode>>, mut editor: ResMut<Editor>) {
+    if !ctrl_pressed(&keys) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Y) || (keys.just_pressed(KeyCode::Z) && shift_pressed(&keys)) {
+        editor.redo();
+    } else if keys.just_pressed(KeyCode::Z) {
+        editor.undo();
+    }
+}
+
+fn toggle_settings(keys: Res<Input<KeyCode>>, mut editor: ResMut<Editor>) {
+    if ctrl_pressed(&keys) {
+        return;
+    }
+    for (key, setting) in SETTING_KEYS {
+        if keys.just_pressed(key) {
+            let value = match editor.level.setting(setting) {
+                Some(_) => None,
+                None => Some(String::new()),
+            };
+            editor.set_setting(setting, value);
+        }
+    }
+}
+
+fn save_level(keys: Res<Input<KeyCode>>, file: Res<EditorFile>, mut editor: ResMut<Editor>) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::S)) {
+        return;
+    }
+    let source = editor.level.serialize();
+    editor.message = Some(match fs::write(&file.0, &source) {
+        Ok(()) => {
+            editor.mark_saved();
+            // Saved either way, so a level can be worked on over several sittings
+            match GameWorld::validate(&source)
+                .into_iter()
+                .find(|diagnostic| matches!(diagnostic, Diagnostic::Error(_)))
+            {
+                Some(Diagnostic::Error(message)) => format!("Saved, but {message}"),
+                _ => "Saved".to_string(),
+            }
+        }
+        Err(err) => format!("Couldn't save: {err}"),
+    });
+}
+
+// Escape quits, asking first if there are unsaved changes
+fn quit_editor(
+    keys: Res<Input<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) {
+        if editor.confirming_quit && keys.get_just_pressed().next().is_some() {
+            editor.confirming_quit = false;
+        }
+        return;
+    }
+    if editor.is_dirty() && !editor.confirming_quit {
+        editor.confirming_quit = true;
+    } else {
+        exit.send(AppExit);
+    }
+}
+
+fn pan_view(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    let mut direction = Vec2::ZERO;
+    for (key, offset) in [
+        (KeyCode::Up, Vec2::Y),
+        (KeyCode::Down, -Vec2::Y),
+        (KeyCode::Left, -Vec2::X),
+        (KeyCode::Right, Vec2::X),
+    ] {
+        if keys.pressed(key) {
+            direction += offset;
+        }
+    }
+    if let Ok(mut transform) = camera.get_single_mut() {
+        transform.translation += (direction * PAN_SPEED * time.delta_seconds()).extend(0.0);
+    }
+}
+
+fn tile_color(code: &str) -> Option<Color> {
+    Some(match code.chars().next()? {
+        '#' => Color::rgb_u8(40, 40, 40),
+        'L' => Color::rgb_u8(220, 50, 50),
+        'M' => Color::rgb_u8(240, 140, 30),
+        'T' => Color::rgb_u8(150, 60, 170),
+        'G' => Color::rgb_u8(166, 204, 112),
+        'C' => Color::rgb_u8(120, 200, 200),
+        'F' => Color::rgb_u8(240, 120, 170),
+        'P' => Color::rgb_u8(90, 170, 230),
+        '*' => Color::rgb_u8(250, 209, 122),
+        _ => return None,
+    })
+}
+
+// Tiles are drawn as flat squares, colored like level thumbnails
+fn redraw_tiles(
+    mut commands: Commands,
+    editor: Res<Editor>,
+    tiles: Query<Entity, With<EditorTile>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+    for entity in tiles.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let origin = WorldOrigin::default();
+    let height = editor.level.rows.len();
+    let width = editor.level.rows.iter().map(Vec::len).max().unwrap_or(0);
+    let center = (origin.tile_to_world((0, 0))
+        + origin.tile_to_world((width.saturating_sub(1), height.saturating_sub(1))))
+        / 2.0;
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: BACKGROUND_COLOR,
+                custom_size: Some(Vec2::new(width as f32, height as f32) * Tile::SIZE),
+                ..Sprite::default()
+            },
+            transform: Transform::from_translation(center.extend(0.0)),
+            ..SpriteBundle::default()
+        })
+        .insert(RenderLayer::Background)
+        .insert(EditorTile);
+    for (coordinates, code) in editor.level.tiles() {
+        if let Some(color) = tile_color(code) {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(Tile::SIZE)),
+                        ..Sprite::default()
+                    },
+                    transform: Transform::from_translation(
+                        origin.tile_to_world(coordinates).extend(0.0),
+                    ),
+                    ..SpriteBundle::default()
+                })
+                .insert(RenderLayer::Tiles)
+                .insert(EditorTile);
+        }
+    }
+}
+
+fn update_status(
+    file: Res<EditorFile>,
+    brush: Res<Brush>,
+    tools: Res<Tools>,
+    editor: Res<Editor>,
+    mut status: Query<&mut Text, With<EditorStatus>>,
+) {
+    if !(editor.is_changed() || brush.is_changed() || tools.is_changed()) {
+        return;
+    }
+    let mut text = match status.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    let settings: Vec<_> = SETTING_KEYS
+        .iter()
+        .map(|(key, setting)| {
+            let state = if editor.level.setting(setting).is_some() {
+                "on"
+            } else {
+                "off"
+            };
+            format!("{setting} {state} [{key:?}]")
+        })
+        .collect();
+    let mut lines = vec![
+        format!(
+            "{}{}",
+            file.0.display(),
+            if editor.is_dirty() { " (unsaved)" } else { "" }
+        ),
+        format!("Brush: {} [1-8]", BRUSHES[brush.0].1),
+        TOOLS
+            .iter()
+            .map(|(key, tool, name)| {
+                let marker = if *tool == tools.tool { ">" } else { "" };
+                format!("{marker}{name} [{key:?}]")
+            })
+            .collect::<Vec<_>>()
+            .join("  "),
+        settings.join(", "),
+        "Undo [Ctrl+Z]  Redo [Ctrl+Y]  Save [Ctrl+S]  Quit [Esc]".to_string(),
+        "Copy [Ctrl+C]  Cut [Ctrl+X]  Paste [Ctrl+V]".to_string(),
+    ];
+    if editor.confirming_quit {
+        lines.push("Unsaved changes! Press Esc again to quit without saving".to_string());
+    } else if let Some(message) = &editor.message {
+        lines.push(message.clone());
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = "@boundary\n@fog\t80\n*\t.\t#\n.\tL:90!/a\tG\n";
+
+    #[test]
+    fn levels_are_saved_as_they_were_read() {
+        assert_eq!(EditorLevel::parse(LEVEL).serialize(), LEVEL);
+    }
+
+    #[test]
+    fn edits_undo_and_redo() {
+        let mut editor = Editor::new(EditorLevel::parse(LEVEL));
+        editor.paint((1, 0), "#", false);
+        editor.paint((0, 1), "#", true);
+        editor.paint((2, 1), "T", false);
+        editor.set_setting("boundary", None);
+        assert_eq!(
+            editor.level.serialize(),
+            "@fog\t80\n*\t#\t#\n#\tL:90!/a\tT\n"
+        );
+
+        editor.undo();
+        editor.undo();
+        assert_eq!(
+            editor.level.serialize(),
+            "@boundary\n@fog\t80\n*\t#\t#\n#\tL:90!/a\tG\n"
+        );
+        // The stroke undoes all at once
+        editor.undo();
+        assert_eq!(editor.level.serialize(), LEVEL);
+
+        editor.redo();
+        editor.redo();
+        editor.redo();
+        assert_eq!(
+            editor.level.serialize(),
+            "@fog\t80\n*\t#\t#\n#\tL:90!/a\tT\n"
+        );
+    }
+
+    #[test]
+    fn placing_the_start_moves_it() {
+        let mut editor = Editor::new(EditorLevel::parse(LEVEL));
+        editor.paint((1, 0), "*", false);
+        assert_eq!(editor.level.get((0, 0)), EMPTY);
+        assert_eq!(editor.level.get((1, 0)), "*");
+        editor.undo();
+        assert_eq!(editor.level.serialize(), LEVEL);
+    }
+
+    #[test]
+    fn changes_since_saving_are_tracked() {
+        let mut editor = Editor::new(EditorLevel::parse(LEVEL));
+        assert!(!editor.is_dirty());
+        editor.paint((1, 0), "#", false);
+        assert!(editor.is_dirty());
+        editor.undo();
+        assert!(!editor.is_dirty());
+
+        editor.redo();
+        editor.mark_saved();
+        editor.undo();
+        assert!(editor.is_dirty());
+        // The saved level can't be got back to once something else is done instead
+        editor.paint((0, 1), "#", false);
+        editor.undo();
+        assert!(editor.is_dirty());
+
+        // Painting the same tile again changes nothing
+        editor.mark_saved();
+        editor.paint((1, 0), EMPTY, false);
+        assert!(!editor.is_dirty());
+    }
+
+    #[test]
+    fn lines_cover_every_tile_between_their_ends() {
+        assert_eq!(line((0, 0), (3, 0)), [(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(line((2, 2), (0, 0)), [(2, 2), (1, 1), (0, 0)]);
+        assert_eq!(line((0, 0), (1, 3)), [(0, 0), (0, 1), (1, 2), (1, 3)]);
+        assert_eq!(line((4, 1), (4, 1)), [(4, 1)]);
+    }
+
+    #[test]
+    fn fills_stop_at_other_tiles() {
+        let mut editor = Editor::new(EditorLevel::parse(".\t.\t#\t.\n.\t#\t.\t.\n#\t.\t.\tG\n"));
+        editor.fill((0, 0), "#");
+        assert_eq!(
+            editor.level.serialize(),
+            "#\t#\t#\t.\n#\t#\t.\t.\n#\t.\t.\tG\n"
+        );
+        editor.fill((3, 0), "#");
+        assert_eq!(
+            editor.level.serialize(),
+            "#\t#\t#\t#\n#\t#\t#\t#\n#\t#\t#\tG\n"
+        );
+        // Each fill undoes in one go
+        editor.undo();
+        editor.undo();
+        assert_eq!(
+            editor.level.serialize(),
+            ".\t.\t#\t.\n.\t#\t.\t.\n#\t.\t.\tG\n"
+        );
+    }
+
+    #[test]
+    fn selections_are_cut_and_pasted() {
+        let mut editor = Editor::new(EditorLevel::parse(LEVEL));
+        let clipboard = editor.cut(((2, 1), (1, 0)));
+        assert_eq!(clipboard, [[".", "#"], ["L:90!/a", "G"]]);
+        assert_eq!(
+            editor.level.serialize(),
+            "@boundary\n@fog\t80\n*\t.\t.\n.\t.\t.\n"
+        );
+
+        editor.paste(&clipboard, (2, 1));
+        assert_eq!(
+            editor.level.serialize(),
+            "@boundary\n@fog\t80\n*\t.\t.\n.\t.\t.\t#\n.\t.\tL:90!/a\tG\n"
+        );
+        editor.undo();
+        editor.undo();
+        // The room the paste made past the edge is kept, empty
+        assert_eq!(
+            editor.level.serialize(),
+            "@boundary\n@fog\t80\n*\t.\t#\n.\tL:90!/a\tG\t.\n.\t.\t.\t.\n"
+        );
+    }
+
+    #[test]
+    fn painting_outside_the_layout_grows_it() {
+        let mut editor = Editor::new(EditorLevel::blank((2, 1)));
+        editor.paint((3, 1), "#", false);
+        assert_eq!(editor.level.serialize(), ".\t.\n.\t.\t.\t#\n");
+        editor.undo();
+        assert_eq!(editor.level.get((3, 1)), EMPTY);
+    }
+}
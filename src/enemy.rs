@@ -1,33 +1,110 @@
 use crate::{
+    animation::{AnimationSpeed, AnimationState, Motion, SpriteSheetAnimation},
+    challenges::ActiveChallenges,
+    clock::GameClock,
+    collision::CollisionShape,
+    despawn::DespawnQueue,
+    highlight::Highlight,
+    movement::{self, WallCollider},
     player::Player,
+    playing,
+    powerups::{self, ActivePowerUps},
     pursue::pursue,
-    upgrades::{Upgrade, UpgradeTracker},
+    render_layer::RenderLayer,
+    shadow::Shadow,
+    sound::{Sounds, Tone},
+    upgrades::UpgradeTracker,
     util::polar_to_cartesian,
     util::{AnimatedSprite, AnimatedSpriteData},
-    AppState,
+    world::{GameWorld, OpenWalls},
+    AppState, GameState,
 };
-use benimator::SpriteSheetAnimation;
-use bevy::prelude::*;
-use impacted::CollisionShape;
-use std::f32::consts::PI;
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use std::{f32::consts::PI, sync::Arc, time::Duration};
 
-const LASER_SCALE_INTERPOLATION: f32 = 0.08;
+// Projectiles this far from the player are never coming back
+pub const PROJECTILE_RANGE: f32 = 3000.0;
+
+// Sent by spawners and level scripts to launch a projectile. Senders run before SpawnEnemies
+// so it goes out on the same frame.
+pub struct SpawnEnemy {
+    pub projectile: Projectile,
+    pub position: Vec2,
+}
+
+// Spawns everything asked for with SpawnEnemy
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpawnEnemies;
+
+// Sent when a projectile is launched. Its entity is only spawned once commands are applied,
+// so systems adding to it run after SpawnEnemies.
+pub struct EnemySpawned {
+    pub entity: Entity,
+    pub projectile: Projectile,
+}
+
+// Sent when a projectile is cleared away during a level, having missed the player. Ones
+// still flying when the level ends just go with everything else.
+pub struct EnemyDespawned {
+    pub projectile: Projectile,
+}
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(follow_player)
-                .with_system(move_bullet_enemies),
-        );
+        app.add_event::<SpawnEnemy>()
+            .add_event::<EnemySpawned>()
+            .add_event::<EnemyDespawned>()
+            .register_type::<Projectile>()
+            .register_type::<Pursuer>()
+            .register_type::<Bullet>()
+            .register_type::<Spawning>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(projectiles_moving)
+                    .with_system(follow_player)
+                    .with_system(move_bullet_enemies)
+                    .with_system(pace_enemy_animations),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(spawn_enemies.label(SpawnEnemies))
+                    .with_system(despawn_distant_projectiles)
+                    .with_system(grow_spawning_projectiles)
+                    .with_system(play_launch_sounds),
+            );
     }
 }
 
-#[derive(Component)]
-struct Pursuer {
-    velocity: f32,
+// Projectiles carry on at this fraction of their speed while the player is dying
+const DYING_SLOW_MOTION: f32 = 0.2;
+
+fn projectiles_moving(
+    app_state: Res<State<AppState>>,
+    game_state: Res<State<GameState>>,
+) -> ShouldRun {
+    let moving = matches!(game_state.current(), GameState::Playing | GameState::Dying);
+    if *app_state.current() == AppState::Game && moving {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn time_scale(game_state: &State<GameState>) -> f32 {
+    if *game_state.current() == GameState::Dying {
+        DYING_SLOW_MOTION
+    } else {
+        1.0
+    }
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Pursuer {
+    pub velocity: f32,
 }
 
 impl Pursuer {
@@ -37,120 +114,272 @@ impl Pursuer {
 }
 
 // Simple moving enemy, only travels in the given angle
-#[derive(Component)]
-struct Bullet {
-    velocity: f32,
-    angle: f32,
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Bullet {
+    pub velocity: f32,
+    pub angle: f32,
+    // How much further it goes before hitting the wall that stops it, if one does
+    pub range: Option<f32>,
 }
 
 impl Bullet {
     fn new(velocity: f32, angle: f32) -> Self {
-        Self { velocity, angle }
+        Self {
+            velocity,
+            angle,
+            range: None,
+        }
     }
 }
 
 #[derive(Component)]
 pub struct Enemy;
 
-#[derive(Component, Clone, Debug)]
+// Projectiles grow in over this time and can't hurt the player until they're done,
+// so a spawner next to the start can't kill on the first frame
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Spawning(Timer);
+
+impl Spawning {
+    const SECONDS: f32 = 0.3;
+
+    // From 0 when the projectile appears to 1 when it's dangerous
+    pub fn progress(&self) -> f32 {
+        self.0.percent()
+    }
+}
+
+impl Default for Spawning {
+    fn default() -> Self {
+        Self(Timer::from_seconds(Self::SECONDS, false))
+    }
+}
+
+// The default is only for reflection, which needs something to make a component from
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect_value(Component, PartialEq)]
 pub enum Projectile {
+    #[default]
     Missile,
-    Laser { angle: f32 },
+    // Blocked lasers stop at the first wall in their way, the rest fly over walls
+    Laser {
+        angle: f32,
+        blocked: bool,
+    },
 }
 
 impl Projectile {
     const MISSILE_SIZE: (f32, f32) = (24.0, 24.0);
     const MISSILE_VELOCITY: f32 = 400.0;
+    const MISSILE_HEIGHT: f32 = 8.0;
     pub const MISSILE_COOLDOWN: f32 = 1.0;
 
     const LASER_SIZE: (f32, f32) = (12.0, 24.0);
     const LASER_VELOCITY: f32 = 300.0;
     pub const LASER_COOLDOWN: f32 = 0.1;
+}
+
+// What every enemy has: it grows in, is telegraphed, and hurts the player on contact.
+// Each kind wraps this in its own bundle with whatever drives its movement.
+#[derive(Bundle)]
+pub struct EnemyBundle {
+    projectile: Projectile,
+    enemy: Enemy,
+    spawning: Spawning,
+    highlight: Highlight,
+    shape: CollisionShape,
+    layer: RenderLayer,
+
+    #[bundle]
+    sprite: AnimatedSprite,
+}
 
-    pub fn spawn(
-        &self,
-        commands: &mut Commands,
+impl EnemyBundle {
+    pub fn new(projectile: Projectile, size: Vec2, sprite: AnimatedSprite) -> Self {
+        Self {
+            projectile,
+            enemy: Enemy,
+            spawning: Spawning::default(),
+            highlight: Highlight::telegraph(),
+            shape: CollisionShape::new_rectangle(size.x, size.y),
+            layer: RenderLayer::Enemies,
+            sprite,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct MissileBundle {
+    animation_state: AnimationState,
+    pursuer: Pursuer,
+    shadow: Shadow,
+    wall_collider: WallCollider,
+
+    #[bundle]
+    enemy: EnemyBundle,
+}
+
+impl MissileBundle {
+    pub fn new(
         animations: &mut ResMut<Assets<SpriteSheetAnimation>>,
         textures: &mut ResMut<Assets<TextureAtlas>>,
         asset_server: &Res<AssetServer>,
-        spawn_position: Vec2,
-    ) {
-        // Set z-ordering to 2.0 to ensure that enemies are spawned above the player and spawners
-        let spawn_position = spawn_position.extend(2.0);
-        match self {
-            Projectile::Missile => {
-                commands
-                    .spawn_bundle(AnimatedSprite::new(
-                        animations,
-                        textures,
-                        asset_server,
-                        AnimatedSpriteData {
-                            path: "rocket.png".into(),
-                            frames: 8,
-                            size: Self::MISSILE_SIZE.into(),
-                            transform: Transform::from_translation(spawn_position),
-                            ..AnimatedSpriteData::default()
-                        },
-                    ))
-                    .insert(CollisionShape::new_rectangle(
-                        Self::MISSILE_SIZE.0,
-                        Self::MISSILE_SIZE.1,
-                    ))
-                    .insert(Pursuer::new(Self::MISSILE_VELOCITY))
-                    .insert(self.clone())
-                    .insert(Enemy);
-            }
-            Projectile::Laser { angle } => {
-                commands
-                    .spawn_bundle(AnimatedSprite::new(
-                        animations,
-                        textures,
-                        asset_server,
-                        AnimatedSpriteData {
-                            path: "laser.png".into(),
-                            frames: 4,
-                            size: Self::LASER_SIZE.into(),
-                            transform: Transform {
-                                translation: spawn_position,
-                                rotation: Quat::from_rotation_z(*angle - PI / 2.0),
-                                // Spawn with zero scale
-                                scale: Vec3::ZERO,
-                            },
-                            ..AnimatedSpriteData::default()
-                        },
-                    ))
-                    .insert(CollisionShape::new_rectangle(
-                        Self::LASER_SIZE.0,
-                        Self::LASER_SIZE.1,
-                    ))
-                    .insert(Bullet::new(Self::LASER_VELOCITY, *angle))
-                    .insert(self.clone())
-                    .insert(Enemy);
-            }
+        position: Vec2,
+    ) -> Self {
+        let size = Vec2::from(Projectile::MISSILE_SIZE);
+        let rocket = |delay| AnimatedSpriteData {
+            path: "rocket.png".into(),
+            frames: 8,
+            size,
+            transform: Transform {
+                translation: position.extend(0.0),
+                scale: Vec3::ZERO,
+                ..Transform::default()
+            },
+            delay: Duration::from_millis(delay),
+            ..AnimatedSpriteData::default()
+        };
+        // The engine idles until the missile gets going
+        let animation_state = AnimationState::new(f32::INFINITY)
+            .with_clip(animations, Motion::Idle, rocket(200).timing(), None)
+            .with_clip(animations, Motion::Flying, rocket(100).timing(), None);
+        let sprite = AnimatedSprite::new(animations, textures, asset_server, rocket(100));
+
+        Self {
+            animation_state,
+            pursuer: Pursuer::new(Projectile::MISSILE_VELOCITY),
+            shadow: Shadow::new(size * 0.6, Projectile::MISSILE_HEIGHT),
+            // Missiles are big enough to have to steer around walls, lasers fly over them
+            wall_collider: WallCollider { size },
+            enemy: EnemyBundle::new(Projectile::Missile, size, sprite),
         }
     }
 }
 
+#[derive(Bundle)]
+pub struct LaserBundle {
+    bullet: Bullet,
+
+    #[bundle]
+    enemy: EnemyBundle,
+}
+
+impl LaserBundle {
+    pub fn new(
+        animations: &mut ResMut<Assets<SpriteSheetAnimation>>,
+        textures: &mut ResMut<Assets<TextureAtlas>>,
+        asset_server: &Res<AssetServer>,
+        world: &GameWorld,
+        position: Vec2,
+        angle: f32,
+        blocked: bool,
+    ) -> Self {
+        let size = Vec2::from(Projectile::LASER_SIZE);
+        // Worked out once, since walls never move
+        let range = blocked.then(|| {
+            let direction = polar_to_cartesian(angle, 1.0);
+            world
+                .ray_to_wall(position, direction, PROJECTILE_RANGE)
+                .map_or(PROJECTILE_RANGE, |hit| (hit - size.y / 2.0).max(0.0))
+        });
+        let sprite = AnimatedSprite::new(
+            animations,
+            textures,
+            asset_server,
+            AnimatedSpriteData {
+                path: "laser.png".into(),
+                frames: 4,
+                size,
+                transform: Transform {
+                    translation: position.extend(0.0),
+                    rotation: Quat::from_rotation_z(angle - PI / 2.0),
+                    scale: Vec3::ZERO,
+                },
+                ..AnimatedSpriteData::default()
+            },
+        );
+
+        Self {
+            bullet: Bullet {
+                range,
+                ..Bullet::new(Projectile::LASER_VELOCITY, angle)
+            },
+            enemy: EnemyBundle::new(Projectile::Laser { angle, blocked }, size, sprite),
+        }
+    }
+}
+
+// Every enemy is spawned here, from the SpawnEnemy events sent by spawners and level scripts
+fn spawn_enemies(
+    mut commands: Commands,
+    world: Res<Arc<GameWorld>>,
+    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
+    mut textures: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+    mut requests: EventReader<SpawnEnemy>,
+    mut spawned: EventWriter<EnemySpawned>,
+) {
+    for SpawnEnemy {
+        projectile,
+        position,
+    } in requests.iter()
+    {
+        let position = *position;
+        let entity = match *projectile {
+            Projectile::Missile => commands
+                .spawn_bundle(MissileBundle::new(
+                    &mut animations,
+                    &mut textures,
+                    &asset_server,
+                    position,
+                ))
+                .id(),
+            Projectile::Laser { angle, blocked } => commands
+                .spawn_bundle(LaserBundle::new(
+                    &mut animations,
+                    &mut textures,
+                    &asset_server,
+                    &world,
+                    position,
+                    angle,
+                    blocked,
+                ))
+                .id(),
+        };
+        spawned.send(EnemySpawned {
+            entity,
+            projectile: projectile.clone(),
+        });
+    }
+}
+
 fn follow_player(
-    time: Res<Time>,
+    clock: Res<GameClock>,
+    world: Res<Arc<GameWorld>>,
+    open_walls: Res<OpenWalls>,
     player_transform: Query<&Transform, (With<Player>, Without<Projectile>)>,
-    mut enemies: Query<(&mut Transform, &Pursuer), With<Projectile>>,
+    mut enemies: Query<(&mut Transform, &Pursuer, Option<&WallCollider>), With<Projectile>>,
     upgrades: Res<UpgradeTracker>,
+    power_ups: Res<ActivePowerUps>,
+    challenges: Res<ActiveChallenges>,
+    game_state: Res<State<GameState>>,
 ) {
-    for (mut transform, follow) in enemies.iter_mut() {
-        let player_transform = player_transform.single();
+    for (mut transform, follow, collider) in enemies.iter_mut() {
+        let player_transform = match player_transform.get_single() {
+            Ok(player_transform) => player_transform,
+            Err(_) => return,
+        };
         let velocity = pursue(
             transform.translation.truncate(),
             player_transform.translation.truncate(),
             follow.velocity,
-        ) * time.delta_seconds()
-            * if upgrades.has_upgrade(Upgrade::SlowEnemies) {
-                0.5
-            } else {
-                1.0
-            };
-        transform.translation.x += velocity.x;
-        transform.translation.y += velocity.y;
+        ) * clock.delta_seconds()
+            * time_scale(&game_state)
+            * powerups::enemy_speed(&upgrades, &power_ups)
+            * challenges.projectile_speed();
+        movement::move_entity(&world, &open_walls, &mut transform, collider, velocity);
 
         transform.rotation =
             Quat::from_rotation_z(-velocity.angle_between(Vec2::AXES[0]) - PI / 2.0);
@@ -158,20 +387,105 @@ fn follow_player(
 }
 
 fn move_bullet_enemies(
-    time: Res<Time>,
-    mut enemies: Query<(&mut Transform, &Bullet), With<Projectile>>,
+    clock: Res<GameClock>,
+    world: Res<Arc<GameWorld>>,
+    open_walls: Res<OpenWalls>,
+    mut enemies: Query<(&mut Transform, &mut Bullet, Option<&WallCollider>), With<Projectile>>,
     upgrades: Res<UpgradeTracker>,
+    power_ups: Res<ActivePowerUps>,
+    challenges: Res<ActiveChallenges>,
+    game_state: Res<State<GameState>>,
 ) {
-    for (mut transform, bullet) in enemies.iter_mut() {
-        transform.translation += (polar_to_cartesian(bullet.angle, 1.0)
+    for (mut transform, mut bullet, collider) in enemies.iter_mut() {
+        let mut velocity = polar_to_cartesian(bullet.angle, 1.0)
             * bullet.velocity
-            * time.delta_seconds()
-            * if upgrades.has_upgrade(Upgrade::SlowEnemies) {
-                0.5
+            * clock.delta_seconds()
+            * time_scale(&game_state)
+            * powerups::enemy_speed(&upgrades, &power_ups)
+            * challenges.projectile_speed();
+        if let Some(range) = &mut bullet.range {
+            if velocity.length() >= *range {
+                velocity = velocity.clamp_length_max(*range);
+                *range = 0.0;
             } else {
-                1.0
-            })
-        .extend(0.0);
-        transform.scale = transform.scale.lerp(Vec3::ONE, LASER_SCALE_INTERPOLATION);
+                *range -= velocity.length();
+            }
+        }
+        movement::move_entity(&world, &open_walls, &mut transform, collider, velocity);
+    }
+}
+
+// Enemies animate as much slower or faster as they move, like in slow motion
+fn pace_enemy_animations(
+    mut commands: Commands,
+    upgrades: Res<UpgradeTracker>,
+    power_ups: Res<ActivePowerUps>,
+    challenges: Res<ActiveChallenges>,
+    game_state: Res<State<GameState>>,
+    mut enemies: Query<(Entity, Option<&mut AnimationSpeed>), With<Enemy>>,
+) {
+    let speed = time_scale(&game_state)
+        * powerups::enemy_speed(&upgrades, &power_ups)
+        * challenges.projectile_speed();
+    for (entity, animation_speed) in enemies.iter_mut() {
+        match animation_speed {
+            Some(mut animation_speed) if animation_speed.0 != speed => animation_speed.0 = speed,
+            Some(_) => {}
+            None => {
+                commands.entity(entity).insert(AnimationSpeed(speed));
+            }
+        }
+    }
+}
+
+// Also gets rid of blocked lasers once they reach their wall
+fn despawn_distant_projectiles(
+    mut despawn: ResMut<DespawnQueue>,
+    mut despawned: EventWriter<EnemyDespawned>,
+    player: Query<&Transform, With<Player>>,
+    projectiles: Query<(Entity, &Transform, &Projectile, Option<&Bullet>)>,
+) {
+    let player = match player.get_single() {
+        Ok(player) => player.translation.truncate(),
+        Err(_) => return,
+    };
+    for (entity, transform, projectile, bullet) in projectiles.iter() {
+        let stopped = bullet.and_then(|bullet| bullet.range) == Some(0.0);
+        let distant = transform.translation.truncate().distance(player) > PROJECTILE_RANGE;
+        if (stopped || distant) && !despawn.contains(entity) {
+            despawn.push(entity);
+            despawned.send(EnemyDespawned {
+                projectile: projectile.clone(),
+            });
+        }
+    }
+}
+
+fn play_launch_sounds(
+    audio: Res<Audio<Tone>>,
+    sounds: Res<Sounds>,
+    mut spawned: EventReader<EnemySpawned>,
+) {
+    // Lasers fire too often to each have a sound
+    let missiles = spawned
+        .iter()
+        .filter(|event| event.projectile == Projectile::Missile);
+    if missiles.count() > 0 {
+        audio.play(sounds.missile_launch.clone());
+    }
+}
+
+// Ease the projectile in from nothing, then make it dangerous
+fn grow_spawning_projectiles(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut projectiles: Query<(Entity, &mut Transform, &mut Spawning)>,
+) {
+    for (entity, mut transform, mut spawning) in projectiles.iter_mut() {
+        let progress = spawning.0.tick(clock.delta()).percent();
+        transform.scale = Vec3::splat(1.0 - (1.0 - progress).powi(2));
+        if spawning.0.finished() {
+            commands.entity(entity).remove::<Spawning>();
+        }
     }
 }
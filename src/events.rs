@@ -0,0 +1,255 @@
+use crate::{
+    cosmetics::{Skin, SkinRegistry, Unlock},
+    pack::{Level, LevelFile, LevelRegistry},
+    save::SaveData,
+};
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Seasonal events, read from events.calendar. Each one runs between two dates every year,
+// adding skins that unlock by playing while it's on and a bonus level that's only around for
+// it. Events can be added to the file ahead of time and start on their own.
+pub struct EventsPlugin;
+
+impl Plugin for EventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EventCalendar>()
+            .init_asset_loader::<EventCalendarLoader>()
+            .add_startup_system(load_calendar)
+            .add_system(start_events)
+            .add_system(add_event_levels)
+            .add_system(mark_events_seen);
+    }
+}
+
+const CALENDAR_PATH: &str = "events.calendar";
+
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "9a4c2e71-0b8d-4f36-a5e2-7c1d3b8f6e09"]
+pub struct EventCalendar {
+    events: Vec<SeasonalEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeasonalEvent {
+    name: String,
+    // First and last days of the event as (month, day), which can wrap around the new year
+    start: (u32, u32),
+    end: (u32, u32),
+    #[serde(default)]
+    skins: Vec<EventSkin>,
+    #[serde(default)]
+    level: Option<EventLevel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventSkin {
+    name: String,
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventLevel {
+    name: String,
+    path: String,
+}
+
+impl SeasonalEvent {
+    // Whether the event is on, on a (month, day)
+    fn is_on(&self, date: (u32, u32)) -> bool {
+        if self.start <= self.end {
+            self.start <= date && date <= self.end
+        } else {
+            self.start <= date || date <= self.end
+        }
+    }
+}
+
+#[derive(Default)]
+struct EventCalendarLoader;
+
+impl AssetLoader for EventCalendarLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let calendar: EventCalendar = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(calendar));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["calendar"]
+    }
+}
+
+// Keeps the calendar loaded
+struct Calendar {
+    _handle: Handle<EventCalendar>,
+}
+
+// Names of the events on today
+struct ActiveEvents(Vec<String>);
+
+// Bonus levels still loading, as (event, level name, file)
+struct PendingEventLevels(Vec<(String, String, Handle<LevelFile>)>);
+
+// Today's date in UTC as (month, day)
+fn today() -> (u32, u32) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default();
+    let (_, month, day) = civil_date((seconds / 86400) as i64);
+    (month, day)
+}
+
+// The (year, month, day) a number of days after 1970-01-01, from Howard Hinnant's
+// civil_from_days
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn load_calendar(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Calendar {
+        _handle: asset_server.load(CALENDAR_PATH),
+    });
+}
+
+// Add every event's skins once the calendar has loaded, so they can be shown locked or
+// kept by players who earned them, and start the events that are on today
+fn start_events(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<EventCalendar>>,
+    calendars: Res<Assets<EventCalendar>>,
+    asset_server: Res<AssetServer>,
+    mut skins: ResMut<SkinRegistry>,
+) {
+    for event in events.iter() {
+        let calendar = match event {
+            AssetEvent::Created { handle } => match calendars.get(handle) {
+                Some(calendar) => calendar,
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let today = today();
+        let mut active = Vec::new();
+        let mut levels = Vec::new();
+        for event in &calendar.events {
+            skins.skins.extend(event.skins.iter().map(|skin| Skin {
+                name: skin.name.clone(),
+                path: skin.path.clone(),
+                unlock: Unlock::Event(event.name.clone()),
+            }));
+            if !event.is_on(today) {
+                continue;
+            }
+            info!("{} is on", event.name);
+            active.push(event.name.clone());
+            if let Some(level) = &event.level {
+                levels.push((
+                    event.name.clone(),
+                    level.name.clone(),
+                    asset_server.load(&*level.path),
+                ));
+            }
+        }
+        commands.insert_resource(ActiveEvents(active));
+        commands.insert_resource(PendingEventLevels(levels));
+    }
+}
+
+fn add_event_levels(
+    mut pending: Option<ResMut<PendingEventLevels>>,
+    files: Res<Assets<LevelFile>>,
+    mut registry: ResMut<LevelRegistry>,
+) {
+    let pending = match pending.as_mut() {
+        Some(pending) => pending,
+        None => return,
+    };
+    pending
+        .0
+        .retain(|(event, name, file)| match files.get(file) {
+            Some(file) => {
+                registry
+                    .levels
+                    .push(Level::new(name.clone(), event.clone(), file.0.clone()));
+                false
+            }
+            None => true,
+        });
+}
+
+fn mark_events_seen(active: Option<Res<ActiveEvents>>, mut save: ResMut<SaveData>) {
+    let active = match active {
+        Some(active) => active,
+        None => return,
+    };
+    for event in &active.0 {
+        // Only touched when new, since any change writes the save
+        if !save.events_seen.contains(event) {
+            save.events_seen.insert(event.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_come_from_days_since_the_epoch() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(59), (1970, 3, 1));
+        assert_eq!(civil_date(11016), (2000, 2, 29));
+        assert_eq!(civil_date(20741), (2026, 10, 15));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn events_can_wrap_around_the_new_year() {
+        let calendar: EventCalendar = ron::from_str(
+            "(events: [
+                (name: \"Spring\", start: (3, 20), end: (4, 10)),
+                (
+                    name: \"Winter\",
+                    start: (12, 15),
+                    end: (1, 6),
+                    skins: [(name: \"Holly\", path: \"skins/holly.png\")],
+                ),
+            ])",
+        )
+        .unwrap();
+        let (spring, winter) = (&calendar.events[0], &calendar.events[1]);
+        assert!(spring.is_on((3, 20)) && spring.is_on((4, 10)));
+        assert!(!spring.is_on((4, 11)) && !spring.is_on((12, 25)));
+        assert!(winter.is_on((12, 31)) && winter.is_on((1, 1)) && winter.is_on((1, 6)));
+        assert!(!winter.is_on((1, 7)) && !winter.is_on((12, 14)));
+        assert_eq!(winter.skins[0].name, "Holly");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    camera::MainCamera,
+    challenges::{ActiveChallenges, Challenge},
+    enemy::{EnemySpawned, Projectile, SpawnEnemies},
+    player::Player,
+    render_layer::RenderLayer,
+    world::{GameWorld, LevelMetadata},
+    AppState,
+};
+use bevy::{
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        render_asset::{PrepareAssetError, RenderAsset},
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer,
+            BufferBindingType, BufferInitDescriptor, BufferSize, BufferUsages, ShaderStages,
+        },
+        renderer::RenderDevice,
+    },
+    sprite::{Material2d, Material2dPipeline, Material2dPlugin, MaterialMesh2dBundle},
+};
+use std::sync::Arc;
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<FogMaterial>::default())
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(spawn_fog))
+            .add_system_set(
+                SystemSet::on_update(AppState::Game)
+                    .with_system(add_fog_lights.after(SpawnEnemies))
+                    .with_system(update_fog),
+            );
+    }
+}
+
+const MAX_LIGHTS: usize = 32;
+// Light radius of projectiles as they fly past
+const LASER_LIGHT_RADIUS: f32 = 30.0;
+const MISSILE_LIGHT_RADIUS: f32 = 45.0;
+
+// Reveals the fog in a radius around an entity
+#[derive(Component)]
+pub struct FogLight {
+    pub radius: f32,
+    pub intensity: f32,
+}
+
+#[derive(Component)]
+struct Fog {
+    // How far the player can see
+    radius: f32,
+}
+
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "6c1d3a52-5a8e-4a3f-9d77-0f2b8e4c1a90"]
+pub struct FogMaterial {
+    color: Color,
+    // (x, y, radius, intensity) of each light
+    lights: Vec<Vec4>,
+}
+
+impl FogMaterial {
+    // Matches the std140 layout of FogMaterial in fog.wgsl
+    const UNIFORM_SIZE: u64 = 16 + 16 * MAX_LIGHTS as u64 + 16;
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::UNIFORM_SIZE as usize);
+        let push_vec4 = |bytes: &mut Vec<u8>, value: Vec4| {
+            for component in value.to_array() {
+                bytes.extend_from_slice(&component.to_ne_bytes());
+            }
+        };
+        push_vec4(&mut bytes, self.color.as_linear_rgba_f32().into());
+        for i in 0..MAX_LIGHTS {
+            push_vec4(&mut bytes, self.lights.get(i).copied().unwrap_or_default());
+        }
+        let count = self.lights.len().min(MAX_LIGHTS) as u32;
+        bytes.extend_from_slice(&count.to_ne_bytes());
+        bytes.resize(Self::UNIFORM_SIZE as usize, 0);
+        bytes
+    }
+}
+
+pub struct GpuFogMaterial {
+    _buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for FogMaterial {
+    type ExtractedAsset = FogMaterial;
+    type PreparedAsset = GpuFogMaterial;
+    type Param = (SRes<RenderDevice>, SRes<Material2dPipeline<FogMaterial>>);
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        material: Self::ExtractedAsset,
+        (render_device, pipeline): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("fog_material_uniform_buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: &material.uniform_bytes(),
+        });
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("fog_material_bind_group"),
+            layout: &pipeline.material2d_layout,
+        });
+
+        Ok(GpuFogMaterial {
+            _buffer: buffer,
+            bind_group,
+        })
+    }
+}
+
+impl Material2d for FogMaterial {
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/fog.wgsl"))
+    }
+
+    fn bind_group(material: &GpuFogMaterial) -> &BindGroup {
+        &material.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(FogMaterial::UNIFORM_SIZE),
+                },
+                count: None,
+            }],
+            label: Some("fog_material_layout"),
+        })
+    }
+}
+
+// Cover the level in fog if it asks for it or the fog challenge is on
+fn spawn_fog(
+    mut commands: Commands,
+    world: Res<Arc<GameWorld>>,
+    challenges: Res<ActiveChallenges>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<FogMaterial>>,
+) {
+    let radius = match world.metadata.fog {
+        Some(radius) => radius,
+        None if challenges.is_active(Challenge::Fog) => LevelMetadata::DEFAULT_FOG_RADIUS,
+        None => return,
+    };
+
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            // Large enough to cover the screen, and kept centered on the camera
+            mesh: meshes
+                .add(Mesh::from(shape::Quad::new(Vec2::splat(4096.0))))
+                .into(),
+            material: materials.add(FogMaterial {
+                color: Color::rgba(0.03, 0.03, 0.08, 0.97),
+                lights: Vec::new(),
+            }),
+            ..MaterialMesh2dBundle::default()
+        })
+        .insert(RenderLayer::Fog)
+        .insert(Fog { radius });
+}
+
+// The player carries the main light, and projectiles light up their surroundings as they pass
+fn add_fog_lights(
+    mut commands: Commands,
+    fog: Query<&Fog>,
+    player: Query<Entity, (With<Player>, Without<FogLight>)>,
+    mut spawned: EventReader<EnemySpawned>,
+) {
+    let fog = match fog.get_single() {
+        Ok(fog) => fog,
+        Err(_) => return,
+    };
+    if let Ok(player) = player.get_single() {
+        commands.entity(player).insert(FogLight {
+            radius: fog.radius,
+            intensity: 1.0,
+        });
+    }
+    for event in spawned.iter() {
+        let radius = match event.projectile {
+            Projectile::Laser { .. } => LASER_LIGHT_RADIUS,
+            Projectile::Missile => MISSILE_LIGHT_RADIUS,
+        };
+        commands.entity(event.entity).insert(FogLight {
+            radius,
+            intensity: 0.8,
+        });
+    }
+}
+
+fn update_fog(
+    mut materials: ResMut<Assets<FogMaterial>>,
+    mut fog: Query<(&Handle<FogMaterial>, &mut Transform), With<Fog>>,
+    camera: Query<&Transform, (With<MainCamera>, Without<Fog>)>,
+    lights: Query<(&GlobalTransform, &FogLight)>,
+) {
+    let (handle, mut transform) = match fog.get_single_mut() {
+        Ok(fog) => fog,
+        Err(_) => return,
+    };
+    if let Ok(camera) = camera.get_single() {
+        transform.translation.x = camera.translation.x;
+        transform.translation.y = camera.translation.y;
+    }
+
+    if let Some(material) = materials.get_mut(handle) {
+        material.lights = lights
+            .iter()
+            .map(|(transform, light)| {
+                transform
+                    .translation
+                    .truncate()
+                    .extend(light.radius)
+                    .extend(light.intensity)
+            })
+            .collect();
+        // Keep the biggest lights, which includes the player's, if there are too many
+        material
+            .lights
+            .sort_by(|a, b| b.z.partial_cmp(&a.z).unwrap_or(std::cmp::Ordering::Equal));
+        material.lights.truncate(MAX_LIGHTS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_matches_shader_layout() {
+        let material = FogMaterial {
+            color: Color::BLACK,
+            lights: vec![Vec4::new(1.0, 2.0, 3.0, 4.0); MAX_LIGHTS + 5],
+        };
+        let bytes = material.uniform_bytes();
+        assert_eq!(bytes.len() as u64, FogMaterial::UNIFORM_SIZE);
+
+        let float =
+            |offset: usize| f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        assert_eq!(float(16), 1.0);
+        assert_eq!(float(16 + 16 * (MAX_LIGHTS - 1) + 12), 4.0);
+        let count_offset = 16 + 16 * MAX_LIGHTS;
+        let count = u32::from_ne_bytes(bytes[count_offset..count_offset + 4].try_into().unwrap());
+        assert_eq!(count, MAX_LIGHTS as u32);
+    }
+}
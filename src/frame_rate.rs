@@ -0,0 +1,61 @@
+use crate::save::SaveData;
+use bevy::{prelude::*, utils::Instant};
+use std::{thread, time::Duration};
+
+// Applies the vsync setting and holds frames back to the frame rate cap
+pub struct FrameRatePlugin;
+
+impl Plugin for FrameRatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_vsync)
+            .add_system_to_stage(CoreStage::Last, limit_frame_rate);
+    }
+}
+
+fn apply_vsync(save: Res<SaveData>, mut windows: ResMut<Windows>) {
+    if !save.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        if window.vsync() != save.settings.vsync {
+            window.set_vsync(save.settings.vsync);
+        }
+    }
+}
+
+// How long to wait for the frame that started at `start` to last `frame_time`
+fn remaining(start: Instant, now: Instant, frame_time: Duration) -> Duration {
+    frame_time.saturating_sub(now - start)
+}
+
+// Runs last, so sleeping here stretches the whole frame
+fn limit_frame_rate(save: Res<SaveData>, mut frame_start: Local<Option<Instant>>) {
+    if let (Some(fps), Some(start)) = (
+        save.settings.frame_rate_cap.frames_per_second(),
+        *frame_start,
+    ) {
+        let frame_time = Duration::from_secs_f64(1.0 / fps as f64);
+        thread::sleep(remaining(start, Instant::now(), frame_time));
+    }
+    *frame_start = Some(Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_frames_wait_out_the_rest() {
+        let start = Instant::now();
+        let frame_time = Duration::from_millis(16);
+        assert_eq!(
+            remaining(start, start + Duration::from_millis(10), frame_time),
+            Duration::from_millis(6)
+        );
+        // Slow frames don't wait at all
+        assert_eq!(
+            remaining(start, start + Duration::from_millis(20), frame_time),
+            Duration::ZERO
+        );
+    }
+}
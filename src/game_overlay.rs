@@ -1,28 +1,118 @@
 use bevy::utils::Instant;
 
-use crate::{ui::GameFont, upgrades::create_upgrades_overlay, AppState};
+use crate::{
+    change_state,
+    clock::GameClock,
+    collision::CollisionShape,
+    hud::{HudElement, HudItem},
+    player::Player,
+    playing,
+    save::SaveData,
+    ui::GameFont,
+    upgrades::create_upgrades_overlay,
+    world::{Checkpoint, GameWorld},
+    AppState, GameState,
+};
 use bevy::prelude::*;
+use std::{sync::Arc, time::Duration};
 
 pub struct GameOverlayPlugin;
 
 impl Plugin for GameOverlayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(create_game_overlay))
-            .add_system_set(SystemSet::on_update(AppState::Game).with_system(update_game_timer));
+        app.init_resource::<PauseReason>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(create_game_overlay))
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(update_game_timer))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(pause_game)
+                    .with_system(tick_game_timer.before(SplitAtCheckpoints))
+                    .with_system(split_at_checkpoints.label(SplitAtCheckpoints)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(show_pause_text))
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(resume_game))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(hide_pause_text));
     }
 }
 
-#[derive(Component)]
-struct GameTimer(Instant);
+// Splits are taken after the timer has counted this frame
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct SplitAtCheckpoints;
+
+// How long the current run has gone on for. It only counts while playing, and goes by the
+// gameplay clock so hit-stops and reduced game speeds slow it down along with everything else.
+pub struct GameTimer {
+    elapsed: Duration,
+    // Not adjusted for pauses or the gameplay clock
+    real_start: Instant,
+    // Checkpoint numbers and the time they were reached, in the order they were reached
+    splits: Vec<(usize, f32)>,
+}
+
+impl GameTimer {
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    // Checkpoint numbers and the time they were reached, in the order they were reached
+    pub fn splits(&self) -> &[(usize, f32)] {
+        &self.splits
+    }
+
+    pub fn real_elapsed(&self) -> f32 {
+        self.real_start.elapsed().as_secs_f32()
+    }
+
+    // Time shown to the player, which can include pauses for speedrunners timing by wall clock
+    pub fn display_time(&self, real_time: bool) -> f32 {
+        if real_time {
+            self.real_elapsed()
+        } else {
+            self.elapsed()
+        }
+    }
+}
 
 impl Default for GameTimer {
     fn default() -> Self {
-        Self(Instant::now())
+        Self {
+            elapsed: Duration::ZERO,
+            real_start: Instant::now(),
+            splits: Vec::new(),
+        }
     }
 }
 
+// Why the game paused itself, if it wasn't the player pressing Escape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutoPause {
+    Inactive,
+    Unfocused,
+}
+
+impl AutoPause {
+    fn notice(self) -> &'static str {
+        match self {
+            AutoPause::Inactive => "Paused due to inactivity",
+            AutoPause::Unfocused => "Paused while in the background",
+        }
+    }
+}
+
+// Set before pausing, and cleared on resuming
+#[derive(Debug, Default)]
+pub struct PauseReason(pub Option<AutoPause>);
+
+#[derive(Component)]
+struct GameTimerText;
+
+#[derive(Component)]
+struct PauseText;
+
 fn create_game_overlay(mut commands: Commands, font: Res<GameFont>) {
     commands.spawn_bundle(UiCameraBundle::default());
+    commands.insert_resource(GameTimer::default());
     create_upgrades_overlay(&mut commands, &font);
 
     commands
@@ -47,11 +137,169 @@ fn create_game_overlay(mut commands: Commands, font: Res<GameFont>) {
             ),
             ..TextBundle::default()
         })
-        .insert(GameTimer::default());
+        .insert(GameTimerText)
+        .insert(HudElement(HudItem::Timer));
+}
+
+fn update_game_timer(
+    timer: Res<GameTimer>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    mut text: Query<&mut Text, With<GameTimerText>>,
+) {
+    let mut text = match text.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let time = timer.display_time(save.settings.real_time);
+    text.sections[0].value = if save.settings.speedrun_timer {
+        let mut lines = vec![format_time(time)];
+        for (checkpoint, split) in &timer.splits {
+            lines.push(format!(
+                "{}/{} {}",
+                checkpoint + 1,
+                world.checkpoint_count(),
+                format_time(*split)
+            ));
+        }
+        lines.join("\n")
+    } else {
+        format!("{time:.2}")
+    };
+}
+
+// Minutes, seconds and milliseconds, like 1:02.345
+pub fn format_time(seconds: f32) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+// Record a split the first time the player crosses each checkpoint
+fn split_at_checkpoints(
+    mut timer: ResMut<GameTimer>,
+    save: Res<SaveData>,
+    player: Query<&CollisionShape, With<Player>>,
+    mut checkpoints: Query<(&CollisionShape, &Checkpoint, &mut Sprite)>,
+) {
+    let player = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    for (shape, checkpoint, mut sprite) in checkpoints.iter_mut() {
+        let reached = timer.splits.iter().any(|(index, _)| *index == checkpoint.0);
+        if !reached && player.is_collided_with(shape) {
+            let time = timer.display_time(save.settings.real_time);
+            timer.splits.push((checkpoint.0, time));
+            sprite.color = Checkpoint::REACHED_COLOR;
+        }
+    }
+}
+
+fn tick_game_timer(clock: Res<GameClock>, mut timer: ResMut<GameTimer>) {
+    timer.elapsed += clock.delta();
+}
+
+fn pause_game(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    // Clear the press so the paused state doesn't see it and resume straight away
+    if keys.clear_just_pressed(KeyCode::Escape) {
+        change_state(&mut state, GameState::Paused);
+    }
+}
+
+fn resume_game(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keys.clear_just_pressed(KeyCode::Escape) {
+        change_state(&mut state, GameState::Playing);
+    }
+}
+
+fn show_pause_text(mut commands: Commands, font: Res<GameFont>, reason: Res<PauseReason>) {
+    let text_style = |size| TextStyle {
+        font: font.get_handle(),
+        font_size: size,
+        ..TextStyle::default()
+    };
+    let mut sections = vec![TextSection {
+        value: "Paused".to_string(),
+        style: text_style(90.0),
+    }];
+    if let Some(reason) = reason.0 {
+        sections.push(TextSection {
+            value: format!("\n{}\nPress Escape to resume", reason.notice()),
+            style: text_style(36.0),
+        });
+    }
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(40.0),
+                    left: Val::Percent(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text {
+                sections,
+                alignment: TextAlignment::default(),
+            },
+            ..TextBundle::default()
+        })
+        .insert(PauseText);
 }
 
-fn update_game_timer(mut text: Query<(&mut Text, &GameTimer)>) {
-    let (mut text, timer) = text.single_mut();
-    let time = (Instant::now() - timer.0).as_secs_f32();
-    text.sections[0].value = format!("{time:.2}");
+fn hide_pause_text(
+    mut commands: Commands,
+    mut reason: ResMut<PauseReason>,
+    text: Query<Entity, With<PauseText>>,
+) {
+    reason.0 = None;
+    for entity in text.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_show_milliseconds() {
+        assert_eq!(format_time(0.0), "0:00.000");
+        assert_eq!(format_time(5.25), "0:05.250");
+        assert_eq!(format_time(62.345), "1:02.345");
+        assert_eq!(format_time(3599.9996), "60:00.000");
+    }
+
+    #[test]
+    fn the_timer_follows_the_gameplay_clock() {
+        let mut world = World::new();
+        world.insert_resource(GameTimer::default());
+        let mut clock = GameClock::default();
+        // A frame at 70% speed
+        clock.set_delta(Duration::from_millis(70));
+        world.insert_resource(clock);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(tick_game_timer);
+        stage.run(&mut world);
+        stage.run(&mut world);
+        let timer = world.get_resource::<GameTimer>().unwrap();
+        assert!((timer.elapsed() - 0.14).abs() < 1e-6);
+
+        // Hit-stops freeze it
+        world
+            .get_resource_mut::<GameClock>()
+            .unwrap()
+            .set_delta(Duration::ZERO);
+        stage.run(&mut world);
+        let timer = world.get_resource::<GameTimer>().unwrap();
+        assert!((timer.elapsed() - 0.14).abs() < 1e-6);
+    }
 }
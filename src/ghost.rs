@@ -0,0 +1,481 @@
+use crate::{
+    animation::SpriteSheetAnimation,
+    challenges::{ActiveChallenges, Challenge},
+    clock::GameClock,
+    pack::LevelRegistry,
+    player::Player,
+    playing,
+    profile::Profile,
+    render_layer::RenderLayer,
+    replay::ReplayPlayback,
+    save::SaveData,
+    util::{AnimatedSprite, AnimatedSpriteData},
+    world::{GameWorld, Variant, WorldOrigin, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// Directory in the profile best ghosts are saved in, one file per level
+const GHOSTS_DIRECTORY: &str = "ghosts";
+
+const MAGIC: &[u8; 4] = b"BGST";
+const VERSION: u16 = 1;
+// Ghosts are stored as player positions sampled at a fixed rate
+const SAMPLE_RATE: f32 = 30.0;
+// Cap recordings at ten minutes so ghost files stay small enough to share
+const MAX_SAMPLES: usize = 30 * 60 * 10;
+
+// Runs by the developers shipped with the built-in levels, by level index. To add one,
+// beat the level and copy its ghost from your profile into assets/ghosts.
+const DEV_GHOSTS: [(usize, &[u8]); 1] = [(0, include_bytes!("../assets/ghosts/level0.ghost"))];
+const GHOST_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+const DEV_GHOST_COLOR: Color = Color::rgba(1.0, 0.8, 0.3, 0.5);
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnGhost>()
+            .init_resource::<GhostRecorder>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Game)
+                    .with_system(start_recording)
+                    .with_system(spawn_saved_ghosts),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(spawn_ghosts))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(record_ghost)
+                    .with_system(play_ghost),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(save_ghost));
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ghost {
+    samples: Vec<Vec2>,
+}
+
+impl Ghost {
+    pub fn duration(&self) -> f32 {
+        self.samples.len() as f32 / SAMPLE_RATE
+    }
+
+    // Position at the given time, interpolated between samples
+    pub fn position(&self, time: f32) -> Option<Vec2> {
+        let sample = time * SAMPLE_RATE;
+        let index = sample as usize;
+        match (self.samples.get(index), self.samples.get(index + 1)) {
+            (Some(a), Some(b)) => Some(a.lerp(*b, sample.fract())),
+            (Some(a), None) => Some(*a),
+            _ => None,
+        }
+    }
+
+    // Encode as: magic, version (u16), sample count (u32), then x, y (f32) per sample,
+    // all little endian
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(10 + self.samples.len() * 8);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.samples.len() as u32).to_le_bytes());
+        for sample in &self.samples {
+            bytes.extend_from_slice(&sample.x.to_le_bytes());
+            bytes.extend_from_slice(&sample.y.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason);
+
+        let (magic, rest) = bytes.split_at(MAGIC.len().min(bytes.len()));
+        if magic != MAGIC {
+            return Err(invalid("not a ghost file"));
+        }
+        let (version, rest) = read_u16(rest).ok_or_else(|| invalid("truncated header"))?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported ghost version {version}")));
+        }
+        let (count, rest) = read_u32(rest).ok_or_else(|| invalid("truncated header"))?;
+        if count as usize > MAX_SAMPLES {
+            return Err(invalid("ghost is too long"));
+        }
+        if rest.len() != count as usize * 8 {
+            return Err(invalid("sample count doesn't match file size"));
+        }
+
+        let samples = rest
+            .chunks_exact(8)
+            .map(|sample| {
+                let (x, y) = sample.split_at(4);
+                Vec2::new(
+                    f32::from_le_bytes(x.try_into().unwrap()),
+                    f32::from_le_bytes(y.try_into().unwrap()),
+                )
+            })
+            .collect();
+        Ok(Self { samples })
+    }
+}
+
+// The developers' run of a built-in level, if there is one
+pub fn dev_ghost(level: usize) -> Option<Ghost> {
+    let (_, bytes) = DEV_GHOSTS.iter().find(|(index, _)| *index == level)?;
+    match Ghost::decode(bytes) {
+        Ok(ghost) => Some(ghost),
+        Err(err) => {
+            warn!("Ignoring the dev ghost for level {level}: {err}");
+            None
+        }
+    }
+}
+
+// Awarded for times close to the developers' on a built-in level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Medal {
+    pub fn name(self) -> &'static str {
+        match self {
+            Medal::Bronze => "Bronze",
+            Medal::Silver => "Silver",
+            Medal::Gold => "Gold",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Medal::Bronze => Color::rgb(0.8, 0.5, 0.25),
+            Medal::Silver => Color::rgb(0.8, 0.8, 0.85),
+            Medal::Gold => Color::rgb(1.0, 0.8, 0.2),
+        }
+    }
+
+    // The medal for a time, as a multiple of the developers' time it has to beat
+    fn threshold(self) -> f32 {
+        match self {
+            Medal::Bronze => 1.5,
+            Medal::Silver => 1.2,
+            Medal::Gold => 1.0,
+        }
+    }
+
+    pub fn for_time(time: f32, dev_time: f32) -> Option<Self> {
+        [Medal::Gold, Medal::Silver, Medal::Bronze]
+            .into_iter()
+            .find(|medal| time <= dev_time * medal.threshold())
+    }
+}
+
+// The developers' time for a level, which medals are measured against
+pub fn dev_time(world: &GameWorld, levels: &LevelRegistry) -> Option<f32> {
+    match world.world_type {
+        WorldType::Level { index } => dev_time_for_level(levels, index),
+        WorldType::Endless => None,
+    }
+}
+
+pub fn dev_time_for_level(levels: &LevelRegistry, index: usize) -> Option<f32> {
+    if levels.levels[index].pack.is_some() {
+        return None;
+    }
+    dev_ghost(index).map(|ghost| ghost.duration())
+}
+
+fn read_u16(bytes: &[u8]) -> Option<(u16, &[u8])> {
+    let (value, rest) = (bytes.get(..2)?, &bytes[2..]);
+    Some((u16::from_le_bytes(value.try_into().ok()?), rest))
+}
+
+fn read_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let (value, rest) = (bytes.get(..4)?, &bytes[4..]);
+    Some((u32::from_le_bytes(value.try_into().ok()?), rest))
+}
+
+// Path of the player's best ghost for the level currently being played
+fn ghost_path(
+    profile: Option<&Profile>,
+    world: &GameWorld,
+    levels: &LevelRegistry,
+    challenges: &ActiveChallenges,
+) -> Option<PathBuf> {
+    let profile = profile?;
+    match world.world_type {
+        WorldType::Level { index } => {
+            let name: String = challenges
+                .run_name(
+                    &world
+                        .variant
+                        .level_name(&levels.levels[index].display_name()),
+                )
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            Some(profile.path(GHOSTS_DIRECTORY).join(format!("{name}.ghost")))
+        }
+        WorldType::Endless => None,
+    }
+}
+
+fn load_ghost(path: &Path) -> io::Result<Ghost> {
+    Ghost::decode(&fs::read(path)?)
+}
+
+// Races a ghost against the player, caught up with however far into the run they are
+pub struct SpawnGhost {
+    pub ghost: Ghost,
+    pub color: Color,
+}
+
+// The run being played, recorded as a ghost
+#[derive(Default)]
+pub struct GhostRecorder {
+    ghost: Ghost,
+    elapsed: f32,
+}
+
+impl GhostRecorder {
+    // The finished run, unless it went on too long to be recorded whole
+    pub fn ghost(&self) -> Option<&Ghost> {
+        (self.ghost.samples.len() < MAX_SAMPLES).then_some(&self.ghost)
+    }
+}
+
+#[derive(Component)]
+struct GhostPlayback {
+    ghost: Ghost,
+    elapsed: f32,
+}
+
+// Reset in place rather than through Commands, so ghosts spawned as the level starts
+// don't catch up with the last run
+fn start_recording(mut recorder: ResMut<GhostRecorder>) {
+    *recorder = GhostRecorder::default();
+}
+
+fn record_ghost(
+    clock: Res<GameClock>,
+    mut recorder: ResMut<GhostRecorder>,
+    origin: Res<WorldOrigin>,
+    player: Query<&Transform, With<Player>>,
+) {
+    recorder.elapsed += clock.delta_seconds();
+    // Kept relative to the layout, so ghosts still line up if the level is moved
+    let position = match player.get_single() {
+        Ok(player) => player.translation.truncate() - origin.0,
+        Err(_) => return,
+    };
+    let wanted = ((recorder.elapsed * SAMPLE_RATE) as usize).min(MAX_SAMPLES);
+    while recorder.ghost.samples.len() < wanted {
+        recorder.ghost.samples.push(position);
+    }
+}
+
+fn save_ghost(
+    recorder: Res<GhostRecorder>,
+    profile: Option<Res<Profile>>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    let path = match ghost_path(profile.as_deref(), &world, &levels, &challenges) {
+        Some(path) => path,
+        None => return,
+    };
+    let ghost = match recorder.ghost() {
+        Some(ghost) => ghost,
+        None => return,
+    };
+
+    // Only keep the ghost if it beats the saved one
+    if let Ok(best) = load_ghost(&path) {
+        if best.duration() <= ghost.duration() {
+            return;
+        }
+    }
+
+    let result =
+        fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(&path, ghost.encode()));
+    if let Err(err) = result {
+        warn!("Failed to save ghost to {}: {err}", path.display());
+    }
+}
+
+fn spawn_saved_ghosts(
+    profile: Option<Res<Profile>>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    mut ghosts: EventWriter<SpawnGhost>,
+) {
+    match ghost_path(profile.as_deref(), &world, &levels, &challenges).map(|path| load_ghost(&path))
+    {
+        Some(Ok(ghost)) => ghosts.send(SpawnGhost {
+            ghost,
+            color: GHOST_COLOR,
+        }),
+        Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => {
+            warn!("Ignoring saved ghost: {err}");
+        }
+        _ => {}
+    }
+
+    // Dev ghosts were recorded on the level as written
+    let as_written =
+        world.variant == Variant::Original && !challenges.is_active(Challenge::Mirrored);
+    if let (true, true, WorldType::Level { index }) =
+        (save.settings.dev_ghost, as_written, &world.world_type)
+    {
+        if levels.levels[*index].pack.is_none() {
+            if let Some(ghost) = dev_ghost(*index) {
+                ghosts.send(SpawnGhost {
+                    ghost,
+                    color: DEV_GHOST_COLOR,
+                });
+            }
+        }
+    }
+}
+
+// Every ghost is spawned here, from the SpawnGhost events sent as the level starts and by
+// the online module once a record ghost has been fetched
+fn spawn_ghosts(
+    mut commands: Commands,
+    mut requests: EventReader<SpawnGhost>,
+    recorder: Res<GhostRecorder>,
+    world: Res<Arc<GameWorld>>,
+    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
+    mut textures: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+) {
+    let origin = world.origin();
+    for SpawnGhost { ghost, color } in requests.iter() {
+        let start = match ghost.position(recorder.elapsed) {
+            Some(position) => origin.0 + position,
+            // Already at the goal
+            None => continue,
+        };
+        commands
+            .spawn_bundle(AnimatedSprite::new(
+                &mut animations,
+                &mut textures,
+                &asset_server,
+                AnimatedSpriteData {
+                    path: "bee.png".into(),
+                    frames: 6,
+                    size: Vec2::splat(Player::SIZE),
+                    transform: Transform::from_translation(start.extend(0.0)),
+                    ..AnimatedSpriteData::default()
+                },
+            ))
+            .insert(RenderLayer::Ghosts)
+            .insert(TextureAtlasSprite {
+                color: *color,
+                ..TextureAtlasSprite::default()
+            })
+            .insert(GhostPlayback {
+                ghost: ghost.clone(),
+                elapsed: recorder.elapsed,
+            });
+    }
+}
+
+fn play_ghost(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    origin: Res<WorldOrigin>,
+    mut ghosts: Query<(Entity, &mut Transform, &mut GhostPlayback)>,
+) {
+    for (entity, mut transform, mut playback) in ghosts.iter_mut() {
+        playback.elapsed += clock.delta_seconds();
+        match playback.ghost.position(playback.elapsed) {
+            Some(position) => {
+                let position = origin.0 + position;
+                let velocity = position - transform.translation.truncate();
+                if velocity != Vec2::ZERO {
+                    transform.rotation = Quat::from_rotation_z(
+                        velocity.y.atan2(velocity.x) - std::f32::consts::PI / 2.0,
+                    );
+                }
+                transform.translation = position.extend(transform.translation.z);
+            }
+            // The ghost has reached the goal
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghosts_round_trip() {
+        let ghost = Ghost {
+            samples: (0..100)
+                .map(|i| Vec2::new(i as f32 * 1.5, -(i as f32)))
+                .collect(),
+        };
+        assert_eq!(Ghost::decode(&ghost.encode()).unwrap(), ghost);
+    }
+
+    #[test]
+    fn invalid_ghosts_are_rejected() {
+        let mut bytes = Ghost::default().encode();
+        bytes[4] = 99;
+        assert!(Ghost::decode(&bytes).is_err());
+        assert!(Ghost::decode(b"BGST").is_err());
+        assert!(Ghost::decode(b"not a ghost").is_err());
+
+        let mut bytes = Ghost::default().encode();
+        bytes[6..10].copy_from_slice(&(MAX_SAMPLES as u32 + 1).to_le_bytes());
+        assert!(Ghost::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn medals_follow_the_dev_time() {
+        assert_eq!(Medal::for_time(9.5, 10.0), Some(Medal::Gold));
+        assert_eq!(Medal::for_time(10.0, 10.0), Some(Medal::Gold));
+        assert_eq!(Medal::for_time(11.0, 10.0), Some(Medal::Silver));
+        assert_eq!(Medal::for_time(15.0, 10.0), Some(Medal::Bronze));
+        assert_eq!(Medal::for_time(15.1, 10.0), None);
+    }
+
+    #[test]
+    fn dev_ghosts_are_valid() {
+        for (level, _) in DEV_GHOSTS {
+            let ghost = dev_ghost(level).unwrap();
+            assert!(ghost.duration() > 0.0);
+        }
+    }
+
+    #[test]
+    fn positions_are_interpolated() {
+        let ghost = Ghost {
+            samples: vec![Vec2::ZERO, Vec2::new(10.0, 0.0)],
+        };
+        assert_eq!(ghost.position(0.5 / SAMPLE_RATE), Some(Vec2::new(5.0, 0.0)));
+        assert_eq!(
+            ghost.position(1.0 / SAMPLE_RATE),
+            Some(Vec2::new(10.0, 0.0))
+        );
+        assert_eq!(ghost.position(2.0 / SAMPLE_RATE), None);
+    }
+}
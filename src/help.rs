@@ -62,14 +62,20 @@ fn create_help(mut commands: Commands, font: Res<GameFont>) {
                     ..NodeBundle::default()
                 })
                 .with_children(|parent| {
-                    let text = vec![
+                    let text = [
                         "Welcome to Beeline.\n",
                         "\n",
-                        "Head over to the upgrades menu to gain an \"unfair\" advantage!\n",
+                        "Pick a loadout before each level to gain an \"unfair\" advantage!\n",
                         "\n",
                         "Some upgrades can be activated using mouse buttons:\n",
                         "Left Click - Use primary upgrade\n",
-                        "Right Click - Use secondary upgrade",
+                        "Right Click - Use secondary upgrade\n",
+                        "Space - Use a slow motion power-up\n",
+                        "With keyboard controls, fly with WASD or the arrow keys\n",
+                        "and use upgrades with Q and E\n",
+                        "Escape - Pause\n",
+                        "Mouse Wheel - Zoom in and out\n",
+                        "P - Photo mode, move the camera with WASD",
                     ];
 
                     parent.spawn_bundle(TextBundle {
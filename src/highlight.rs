@@ -0,0 +1,422 @@
+use crate::{
+    clock::GameClock, despawn::DespawnQueue, playing, save::SaveData, settings::EnemyVisibility,
+};
+use bevy::{
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        render_asset::{PrepareAssetError, RenderAsset, RenderAssets},
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+            BufferBindingType, BufferInitDescriptor, BufferSize, BufferUsages, SamplerBindingType,
+            ShaderStages, TextureSampleType, TextureViewDimension,
+        },
+        renderer::RenderDevice,
+    },
+    sprite::{
+        Material2d, Material2dPipeline, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle,
+    },
+};
+
+pub struct HighlightPlugin;
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<HighlightMaterial>::default())
+            .init_resource::<HighlightMesh>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(add_highlight_overlays)
+                    .with_system(update_highlights),
+            );
+    }
+}
+
+// Outline width in sprite pixels
+const OUTLINE_WIDTH: f32 = 1.0;
+const TELEGRAPH_SECONDS: f32 = 0.3;
+const TELEGRAPH_COLOR: Color = Color::rgb(1.0, 0.45, 0.1);
+const BLINK_COLOR: Color = Color::WHITE;
+// Blinks per second while invincible
+const BLINK_RATE: f32 = 5.0;
+// Telegraphs settle into this outline instead of fading out, when enemies are made easier to see
+const CONTRAST_OUTLINE_WIDTH: f32 = 3.0;
+const CONTRAST_COLOR: Color = Color::rgb(1.0, 0.1, 0.9);
+
+enum HighlightKind {
+    // A bright outline and flash that fades out, warning that something just appeared
+    Telegraph,
+    // Flashes on and off for the whole duration
+    Blink,
+}
+
+// Draws a flash and outline over an animated sprite for a while
+#[derive(Component)]
+pub struct Highlight {
+    kind: HighlightKind,
+    timer: Timer,
+    overlay: Option<Entity>,
+    // From the settings when the overlay is added
+    visibility: EnemyVisibility,
+}
+
+impl Highlight {
+    pub fn telegraph() -> Self {
+        Self::new(HighlightKind::Telegraph, TELEGRAPH_SECONDS)
+    }
+
+    pub fn blink(seconds: f32) -> Self {
+        Self::new(HighlightKind::Blink, seconds)
+    }
+
+    fn new(kind: HighlightKind, seconds: f32) -> Self {
+        Self {
+            kind,
+            timer: Timer::from_seconds(seconds, false),
+            overlay: None,
+            visibility: EnemyVisibility::Normal,
+        }
+    }
+
+    // Whether the overlay stays once the highlight is over
+    fn lasts(&self) -> bool {
+        matches!(self.kind, HighlightKind::Telegraph) && self.visibility != EnemyVisibility::Normal
+    }
+
+    fn outline_width(&self) -> f32 {
+        if self.lasts() {
+            CONTRAST_OUTLINE_WIDTH
+        } else {
+            OUTLINE_WIDTH
+        }
+    }
+
+    // Color with the outline strength as alpha, and how strongly the sprite is flashed
+    fn appearance(&self) -> (Color, f32) {
+        match self.kind {
+            HighlightKind::Telegraph if self.lasts() => {
+                let strength = self.timer.percent_left();
+                let color = Vec4::from(CONTRAST_COLOR).lerp(Vec4::from(TELEGRAPH_COLOR), strength);
+                let flash = match self.visibility {
+                    EnemyVisibility::Solid => 1.0,
+                    _ => strength * 0.6,
+                };
+                (Color::from(color), flash)
+            }
+            HighlightKind::Telegraph => {
+                let strength = self.timer.percent_left();
+                let mut color = TELEGRAPH_COLOR;
+                color.set_a(strength);
+                (color, strength * 0.6)
+            }
+            HighlightKind::Blink => {
+                let on = (self.timer.elapsed_secs() * BLINK_RATE).fract() < 0.5;
+                let mut color = BLINK_COLOR;
+                color.set_a(if on { 0.8 } else { 0.0 });
+                (color, if on { 0.5 } else { 0.0 })
+            }
+        }
+    }
+}
+
+// Unit quad shared by all overlays, scaled to the size of each sprite
+struct HighlightMesh(Mesh2dHandle);
+
+impl FromWorld for HighlightMesh {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.get_resource_mut::<Assets<Mesh>>().unwrap();
+        Self(meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE))).into())
+    }
+}
+
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "0e9d5f6b-3c1a-4b7e-a2d8-71f4c6e8b953"]
+pub struct HighlightMaterial {
+    texture: Handle<Image>,
+    color: Color,
+    // Min and max corners of the current frame, in atlas pixels
+    frame: Vec4,
+    atlas_size: Vec2,
+    flash: f32,
+    // Outline width in sprite pixels
+    outline: f32,
+}
+
+impl HighlightMaterial {
+    // Matches the std140 layout of HighlightMaterial in highlight.wgsl
+    const UNIFORM_SIZE: u64 = 48;
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        let color: [f32; 4] = self.color.as_linear_rgba_f32();
+        color
+            .into_iter()
+            .chain(self.frame.to_array())
+            .chain(self.atlas_size.to_array())
+            .chain([self.flash, self.outline])
+            .flat_map(|value| value.to_ne_bytes())
+            .collect()
+    }
+}
+
+pub struct GpuHighlightMaterial {
+    _buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for HighlightMaterial {
+    type ExtractedAsset = HighlightMaterial;
+    type PreparedAsset = GpuHighlightMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<Material2dPipeline<HighlightMaterial>>,
+        SRes<RenderAssets<Image>>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        material: Self::ExtractedAsset,
+        (render_device, pipeline, gpu_images): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
+        // Wait for the sprite's texture to be uploaded
+        let (texture_view, sampler) = match pipeline
+            .mesh2d_pipeline
+            .get_image_texture(gpu_images, &Some(material.texture.clone()))
+        {
+            Some(texture) => texture,
+            None => return Err(PrepareAssetError::RetryNextUpdate(material)),
+        };
+
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("highlight_material_uniform_buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: &material.uniform_bytes(),
+        });
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(texture_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("highlight_material_bind_group"),
+            layout: &pipeline.material2d_layout,
+        });
+
+        Ok(GpuHighlightMaterial {
+            _buffer: buffer,
+            bind_group,
+        })
+    }
+}
+
+impl Material2d for HighlightMaterial {
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/highlight.wgsl"))
+    }
+
+    fn bind_group(material: &GpuHighlightMaterial) -> &BindGroup {
+        &material.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(HighlightMaterial::UNIFORM_SIZE),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("highlight_material_layout"),
+        })
+    }
+}
+
+// Min and max corners of the sprite's current frame
+fn frame_rect(atlas: &TextureAtlas, sprite: &TextureAtlasSprite) -> Vec4 {
+    atlas
+        .textures
+        .get(sprite.index)
+        .map(|rect| rect.min.extend(rect.max.x).extend(rect.max.y))
+        .unwrap_or_default()
+}
+
+fn add_highlight_overlays(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    mesh: Res<HighlightMesh>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut materials: ResMut<Assets<HighlightMaterial>>,
+    mut highlighted: Query<
+        (
+            Entity,
+            &mut Highlight,
+            &TextureAtlasSprite,
+            &Handle<TextureAtlas>,
+        ),
+        Added<Highlight>,
+    >,
+) {
+    for (entity, mut highlight, sprite, atlas) in highlighted.iter_mut() {
+        highlight.visibility = save.settings.enemy_visibility;
+        // The overlays are drawn with a shader, only worth it on slow machines when they're
+        // needed to see what's coming
+        if save.settings.low_spec && !highlight.lasts() {
+            continue;
+        }
+        let atlas = match atlases.get(atlas) {
+            Some(atlas) => atlas,
+            None => continue,
+        };
+        let frame = frame_rect(atlas, sprite);
+        let (color, flash) = highlight.appearance();
+        let outline = highlight.outline_width();
+
+        // Drawn just above the sprite, with room around it for the outline
+        let size = Vec2::new(frame.z - frame.x, frame.w - frame.y) + Vec2::splat(outline * 2.0);
+        let overlay = commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: mesh.0.clone(),
+                material: materials.add(HighlightMaterial {
+                    texture: atlas.texture.clone(),
+                    color,
+                    frame,
+                    atlas_size: atlas.size,
+                    flash,
+                    outline,
+                }),
+                transform: Transform {
+                    translation: Vec3::new(0.0, 0.0, 0.05),
+                    scale: size.extend(1.0),
+                    ..Transform::default()
+                },
+                ..MaterialMesh2dBundle::default()
+            })
+            .id();
+        commands.entity(entity).add_child(overlay);
+        highlight.overlay = Some(overlay);
+    }
+}
+
+// Follow the sprite's animation, and remove the overlay once the highlight is over unless
+// it lasts
+fn update_highlights(
+    mut commands: Commands,
+    mut despawn: ResMut<DespawnQueue>,
+    clock: Res<GameClock>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut materials: ResMut<Assets<HighlightMaterial>>,
+    mut highlighted: Query<(
+        Entity,
+        &mut Highlight,
+        &TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+    )>,
+    overlays: Query<&Handle<HighlightMaterial>>,
+) {
+    for (entity, mut highlight, sprite, atlas) in highlighted.iter_mut() {
+        let overlay = match highlight.overlay {
+            Some(overlay) => overlay,
+            None => continue,
+        };
+
+        if highlight.timer.tick(clock.delta()).finished() && !highlight.lasts() {
+            commands.entity(entity).remove::<Highlight>();
+            despawn.push(overlay);
+            continue;
+        }
+
+        let material = overlays
+            .get(overlay)
+            .ok()
+            .and_then(|handle| materials.get_mut(handle));
+        if let (Some(material), Some(atlas)) = (material, atlases.get(atlas)) {
+            material.frame = frame_rect(atlas, sprite);
+            let (color, flash) = highlight.appearance();
+            material.color = color;
+            material.flash = flash;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn uniform_matches_shader_layout() {
+        let material = HighlightMaterial {
+            texture: Handle::default(),
+            color: Color::WHITE,
+            frame: Vec4::new(24.0, 0.0, 48.0, 24.0),
+            atlas_size: Vec2::new(144.0, 24.0),
+            flash: 0.5,
+            outline: OUTLINE_WIDTH,
+        };
+        let bytes = material.uniform_bytes();
+        assert_eq!(bytes.len() as u64, HighlightMaterial::UNIFORM_SIZE);
+
+        let float =
+            |offset: usize| f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        assert_eq!(float(16), 24.0);
+        assert_eq!(float(32), 144.0);
+        assert_eq!(float(40), 0.5);
+        assert_eq!(float(44), OUTLINE_WIDTH);
+    }
+
+    #[test]
+    fn telegraphs_last_when_enemies_are_made_easier_to_see() {
+        let mut highlight = Highlight::telegraph();
+        highlight.timer.tick(Duration::from_secs(1));
+        assert!(!highlight.lasts());
+        assert_eq!(highlight.appearance().0.a(), 0.0);
+
+        highlight.visibility = EnemyVisibility::Outlined;
+        assert!(highlight.lasts());
+        assert_eq!(highlight.outline_width(), CONTRAST_OUTLINE_WIDTH);
+        assert_eq!(highlight.appearance(), (CONTRAST_COLOR, 0.0));
+        highlight.visibility = EnemyVisibility::Solid;
+        assert_eq!(highlight.appearance(), (CONTRAST_COLOR, 1.0));
+
+        // Only telegraphs, not the player's blinking
+        let mut blink = Highlight::blink(1.0);
+        blink.visibility = EnemyVisibility::Solid;
+        assert!(!blink.lasts());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{
+    hud::{HudElement, HudItem},
+    input_map::Action,
+    pack::LevelRegistry,
+    player::Player,
+    render_layer::RenderLayer,
+    replay::ReplayPlayback,
+    save::SaveData,
+    ui::GameFont,
+    world::{GameWorld, Tile, WorldOrigin, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use std::{f32::consts::PI, sync::Arc};
+
+// Deaths within this many tiles of each other count as the same trouble spot
+const HINT_RADIUS: usize = 2;
+// Deaths in one spot before a hint is offered
+const HINT_DEATHS: usize = 3;
+// Only the latest deaths on each level are kept, so a spot that's been got past stops
+// offering hints
+const REMEMBERED_DEATHS: usize = 20;
+// Tiles the hinted route keeps away from spawners where it can
+const SPAWNER_MARGIN: usize = 2;
+// Arrows drawn either side of the trouble spot
+const HINT_REACH: usize = 4;
+const ARROW_COLOR: Color = Color::rgba(1.0, 0.9, 0.3, 0.7);
+// Of each of the two strokes making up an arrow
+const ARROW_LENGTH: f32 = 10.0;
+const ARROW_WIDTH: f32 = 3.0;
+
+// After a few deaths in the same place on a level, offers an arrow along a safer route past
+// it, keeping away from spawners. Never offered with the speedrun timer on, or in replays.
+pub struct HintsPlugin;
+
+impl Plugin for HintsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Death).with_system(record_death_tile))
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(offer_hint))
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(toggle_hint))
+            .add_system_set(SystemSet::on_exit(AppState::Game).with_system(clear_hint));
+    }
+}
+
+// Tiles the hint leads along, in the order they're flown through
+struct Hint(Vec<(usize, usize)>);
+
+#[derive(Component)]
+struct HintPrompt;
+
+#[derive(Component)]
+struct HintArrow;
+
+// Name of the level being played, if it's one deaths are remembered for
+fn level_name(
+    world: &GameWorld,
+    levels: &LevelRegistry,
+    playback: Option<&ReplayPlayback>,
+) -> Option<String> {
+    match world.world_type {
+        WorldType::Level { index } if playback.is_none() => Some(
+            world
+                .variant
+                .level_name(&levels.levels[index].display_name()),
+        ),
+        _ => None,
+    }
+}
+
+fn record_death_tile(
+    mut save: ResMut<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    origin: Res<WorldOrigin>,
+    levels: Res<LevelRegistry>,
+    playback: Option<Res<ReplayPlayback>>,
+    player: Query<&Transform, With<Player>>,
+) {
+    let name = match level_name(&world, &levels, playback.as_deref()) {
+        Some(name) => name,
+        None => return,
+    };
+    let tile = player
+        .get_single()
+        .ok()
+        .and_then(|transform| origin.world_to_tile(transform.translation.truncate()));
+    if let Some(tile) = tile {
+        let deaths = save.death_tiles.entry(name).or_default();
+        deaths.push(tile);
+        if deaths.len() > REMEMBERED_DEATHS {
+            deaths.remove(0);
+        }
+    }
+}
+
+fn is_near(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1)) <= HINT_RADIUS
+}
+
+// The death with the most others around it, if there are enough to call it a trouble spot
+fn trouble_spot(deaths: &[(usize, usize)]) -> Option<(usize, usize)> {
+    deaths
+        .iter()
+        .map(|death| {
+            let nearby = deaths.iter().filter(|other| is_near(*death, **other));
+            (*death, nearby.count())
+        })
+        .filter(|(_, nearby)| *nearby >= HINT_DEATHS)
+        .max_by_key(|(_, nearby)| *nearby)
+        .map(|(death, _)| death)
+}
+
+// The part of the route passing closest to the spot
+fn route_past(route: &[(usize, usize)], spot: (usize, usize)) -> &[(usize, usize)] {
+    let closest = route
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, tile)| {
+            let (dx, dy) = (tile.0.abs_diff(spot.0), tile.1.abs_diff(spot.1));
+            (dx.max(dy), dx + dy)
+        })
+        .map_or(0, |(index, _)| index);
+    let end = (closest + HINT_REACH + 1).min(route.len());
+    &route[closest.saturating_sub(HINT_REACH)..end]
+}
+
+fn offer_hint(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    font: Res<GameFont>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if save.settings.speedrun_timer {
+        return;
+    }
+    let spot = level_name(&world, &levels, playback.as_deref())
+        .and_then(|name| save.death_tiles.get(&name))
+        .and_then(|deaths| trouble_spot(deaths));
+    let route = match spot.zip(world.path_avoiding_spawners(SPAWNER_MARGIN)) {
+        Some((spot, route)) => route_past(&route, spot).to_vec(),
+        None => return,
+    };
+    commands.insert_resource(Hint(route));
+
+    let key = save.settings.input_map.key(Action::Hint);
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(2.0),
+                    left: Val::Percent(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                format!("Stuck? Press {key:?} for a hint"),
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(HintPrompt)
+        .insert(HudElement(HudItem::Hint));
+}
+
+// The first press shows the hint and the second dismisses it for the rest of the attempt
+fn toggle_hint(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    save: Res<SaveData>,
+    hint: Option<Res<Hint>>,
+    origin: Res<WorldOrigin>,
+    arrows: Query<Entity, With<HintArrow>>,
+    mut prompt: Query<(Entity, &mut Text), With<HintPrompt>>,
+) {
+    let hint = match hint {
+        Some(hint) if save.settings.input_map.just_pressed(&keys, Action::Hint) => hint,
+        _ => return,
+    };
+    if !arrows.is_empty() {
+        commands.remove_resource::<Hint>();
+        for entity in arrows.iter().chain(prompt.iter().map(|(prompt, _)| prompt)) {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    for pair in hint.0.windows(2) {
+        let (from, to) = (origin.tile_to_world(pair[0]), origin.tile_to_world(pair[1]));
+        let direction = to - from;
+        let angle = direction.y.atan2(direction.x);
+        spawn_arrow(&mut commands, (from + to) / 2.0, angle);
+    }
+    if let Ok((_, mut text)) = prompt.get_single_mut() {
+        let key = save.settings.input_map.key(Action::Hint);
+        text.sections[0].value = format!("Press {key:?} to hide the hint");
+    }
+}
+
+// A chevron pointing along `angle`, drawn from two strokes
+fn spawn_arrow(commands: &mut Commands, position: Vec2, angle: f32) {
+    commands
+        .spawn_bundle((
+            Transform {
+                translation: position.extend(0.0),
+                rotation: Quat::from_rotation_z(angle),
+                ..Transform::default()
+            },
+            GlobalTransform::default(),
+        ))
+        .insert(RenderLayer::Pickups)
+        .insert(HintArrow)
+        .with_children(|parent| {
+            for side in [-1.0, 1.0] {
+                let stroke = Quat::from_rotation_z(side * PI / 4.0);
+                parent.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: ARROW_COLOR,
+                        custom_size: Some(Vec2::new(ARROW_LENGTH, ARROW_WIDTH)),
+                        ..Sprite::default()
+                    },
+                    transform: Transform {
+                        // Each stroke's front end meets at the tip
+                        translation: (stroke * Vec3::new(-ARROW_LENGTH / 2.0, 0.0, 0.0))
+                            + Vec3::new(Tile::SIZE / 8.0, 0.0, 0.0),
+                        rotation: stroke,
+                        ..Transform::default()
+                    },
+                    ..SpriteBundle::default()
+                });
+            }
+        });
+}
+
+// The world stays up behind the death screen, but the hint goes
+fn clear_hint(
+    mut commands: Commands,
+    entities: Query<Entity, Or<(With<HintArrow>, With<HintPrompt>)>>,
+) {
+    commands.remove_resource::<Hint>();
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hints_need_several_deaths_in_one_spot() {
+        assert_eq!(trouble_spot(&[(0, 0), (10, 10), (20, 20)]), None);
+        assert_eq!(trouble_spot(&[(0, 0), (10, 10), (11, 9)]), None);
+        assert_eq!(
+            trouble_spot(&[(0, 0), (10, 10), (11, 9), (12, 12), (30, 30)]),
+            Some((10, 10))
+        );
+    }
+
+    #[test]
+    fn hints_cover_the_route_near_the_spot() {
+        let route: Vec<_> = (0..20).map(|x| (x, 3)).collect();
+        assert_eq!(route_past(&route, (10, 0)), &route[6..15]);
+        assert_eq!(route_past(&route, (1, 3)), &route[0..6]);
+        assert_eq!(route_past(&route, (40, 3)), &route[15..20]);
+    }
+}
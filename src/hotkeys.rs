@@ -0,0 +1,160 @@
+use crate::{
+    input_map::Action,
+    player::Player,
+    profile::Profile,
+    save::SaveData,
+    ui::GameFont,
+    world::{GameWorld, Tile, WorldOrigin},
+};
+use bevy::prelude::*;
+use image::Rgb;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Hotkeys that work whatever the game is doing: screenshots, muting and the FPS counter.
+// Their keys are in the same input map as the gameplay ones, so they can be rebound in
+// settings without clashing. Fullscreen is handled with the rest of the display settings.
+pub struct HotkeysPlugin;
+
+impl Plugin for HotkeysPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(toggle_mute)
+            .add_system(toggle_fps_overlay)
+            .add_system(show_fps_overlay)
+            .add_system(take_screenshot);
+    }
+}
+
+// Where screenshots go in the player's profile, or next to the game without one
+const SCREENSHOTS_DIRECTORY: &str = "screenshots";
+const SCREENSHOT_TILE_PIXELS: u32 = 16;
+const SCREENSHOT_BEE_COLOR: Rgb<u8> = Rgb([255, 230, 0]);
+// How quickly the FPS counter follows changes, from 0 to 1
+const FPS_SMOOTHING: f32 = 0.05;
+
+#[derive(Component)]
+struct FpsText;
+
+fn toggle_mute(keys: Res<Input<KeyCode>>, mut save: ResMut<SaveData>) {
+    if save.settings.input_map.just_pressed(&keys, Action::Mute) {
+        save.settings.muted ^= true;
+    }
+}
+
+fn toggle_fps_overlay(keys: Res<Input<KeyCode>>, mut save: ResMut<SaveData>) {
+    if save
+        .settings
+        .input_map
+        .just_pressed(&keys, Action::FpsOverlay)
+    {
+        save.settings.fps_overlay ^= true;
+    }
+}
+
+// Leaving menus despawns everything, so the counter is spawned again whenever it's missing
+fn show_fps_overlay(
+    mut commands: Commands,
+    time: Res<Time>,
+    save: Res<SaveData>,
+    font: Res<GameFont>,
+    mut average: Local<Option<f32>>,
+    mut text: Query<(Entity, &mut Text), With<FpsText>>,
+) {
+    if time.delta_seconds() > 0.0 {
+        let fps = 1.0 / time.delta_seconds();
+        *average = Some(average.map_or(fps, |average| average + (fps - average) * FPS_SMOOTHING));
+    }
+    let shown = format!("{:.0} FPS", average.unwrap_or_default());
+
+    match (text.get_single_mut(), save.settings.fps_overlay) {
+        (Ok((_, mut text)), true) => text.sections[0].value = shown,
+        (Ok((entity, _)), false) => commands.entity(entity).despawn(),
+        (Err(_), true) => {
+            commands
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            bottom: Val::Px(5.0),
+                            right: Val::Px(5.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    text: Text::with_section(
+                        shown,
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(FpsText);
+        }
+        (Err(_), false) => {}
+    }
+}
+
+// Bevy can't read back what's been drawn to the window yet, so screenshots are drawn from
+// the level's layout the same way thumbnails are, with the bee marked where it is
+fn take_screenshot(
+    keys: Res<Input<KeyCode>>,
+    save: Res<SaveData>,
+    profile: Option<Res<Profile>>,
+    world: Option<Res<Arc<GameWorld>>>,
+    origin: Option<Res<WorldOrigin>>,
+    player: Query<&Transform, With<Player>>,
+) {
+    if !save
+        .settings
+        .input_map
+        .just_pressed(&keys, Action::Screenshot)
+    {
+        return;
+    }
+    let world = match world {
+        Some(world) => world,
+        None => {
+            info!("Nothing to take a screenshot of outside a level");
+            return;
+        }
+    };
+
+    let mut image = world.thumbnail(SCREENSHOT_TILE_PIXELS);
+    if let (Some(origin), Ok(transform)) = (origin, player.get_single()) {
+        // Layout pixels run right and down from the top left tile's corner
+        let offset = (transform.translation.truncate() - origin.0) / Tile::SIZE + 0.5;
+        let center = Vec2::new(offset.x, 1.0 - offset.y) * SCREENSHOT_TILE_PIXELS as f32;
+        let half = SCREENSHOT_TILE_PIXELS as f32 / 2.0;
+        let (min, max) = (center - half, center + half);
+        for y in min.y.max(0.0) as u32..(max.y.max(0.0) as u32).min(image.height()) {
+            for x in min.x.max(0.0) as u32..(max.x.max(0.0) as u32).min(image.width()) {
+                image.put_pixel(x, y, SCREENSHOT_BEE_COLOR);
+            }
+        }
+    }
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis())
+        .unwrap_or_default();
+    let directory = match &profile {
+        Some(profile) => profile.path(SCREENSHOTS_DIRECTORY),
+        None => PathBuf::from(SCREENSHOTS_DIRECTORY),
+    };
+    let path = directory.join(format!("{millis}.png"));
+    let result = fs::create_dir_all(&directory)
+        .map_err(|err| err.to_string())
+        .and_then(|_| image.save(&path).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => info!("Saved screenshot to {}", path.display()),
+        Err(err) => warn!("Failed to save screenshot: {err}"),
+    }
+}
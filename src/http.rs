@@ -0,0 +1,113 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+// Just enough HTTP for uploading telemetry, fetching the news and sharing ghosts, without
+// pulling in a client library. Only plain `http://` URLs are supported.
+
+// Requests give up after this long without an answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+// Responses longer than this are refused rather than read into memory
+const MAX_RESPONSE_SIZE: u64 = 1024 * 1024;
+
+// Host, port and path of a plain `http://` URL
+fn parse_endpoint(endpoint: &str) -> Option<(&str, u16, &str)> {
+    let rest = endpoint.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    (!host.is_empty()).then_some((host, port, path))
+}
+
+// Minimal HTTP POST, succeeding on any 2xx response
+pub fn post(endpoint: &str, body: &str) -> io::Result<()> {
+    request("POST", endpoint, "text/plain", body.as_bytes()).map(|_| ())
+}
+
+// POST of binary data, like an encoded ghost
+pub fn post_bytes(endpoint: &str, body: &[u8]) -> io::Result<()> {
+    request("POST", endpoint, "application/octet-stream", body).map(|_| ())
+}
+
+// Minimal HTTP GET of a plain `http://` URL, returning the body of a 2xx response
+pub fn get(url: &str) -> io::Result<String> {
+    get_bytes(url).map(|body| String::from_utf8_lossy(&body).into_owned())
+}
+
+// GET of binary data, returned as it was sent
+pub fn get_bytes(url: &str) -> io::Result<Vec<u8>> {
+    request("GET", url, "text/plain", &[])
+}
+
+fn request(method: &str, endpoint: &str, content_type: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidInput, reason);
+    let (host, port, path) =
+        parse_endpoint(endpoint).ok_or_else(|| invalid("only http:// endpoints are supported"))?;
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid("endpoint has no address"))?;
+
+    let mut stream = TcpStream::connect_timeout(&address, REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    // HTTP/1.0, so responses come back whole rather than chunked
+    write!(
+        stream,
+        "{method} {path} HTTP/1.0\r\nHost: {host}\r\nContent-Type: {content_type}\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+
+    // Read as bytes, since nothing promises the response is UTF-8
+    let mut response = Vec::new();
+    (&mut stream)
+        .take(MAX_RESPONSE_SIZE + 1)
+        .read_to_end(&mut response)?;
+    if response.len() as u64 > MAX_RESPONSE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "response is too long",
+        ));
+    }
+    let (head, body) = match response.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(end) => (&response[..end], &response[end + 4..]),
+        None => (&response[..], &[][..]),
+    };
+    let head = String::from_utf8_lossy(head);
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(body.to_vec())
+    } else {
+        Err(io::Error::other(format!(
+            "server responded with {status:?}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_are_parsed() {
+        assert_eq!(
+            parse_endpoint("http://example.com:8080/beeline/upload"),
+            Some(("example.com", 8080, "/beeline/upload"))
+        );
+        assert_eq!(
+            parse_endpoint("http://example.com"),
+            Some(("example.com", 80, "/"))
+        );
+        assert_eq!(parse_endpoint("https://example.com/"), None);
+        assert_eq!(parse_endpoint("http://example.com:http/"), None);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{save::SaveData, AppState};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+pub const SCALE_STEPS: [f32; 4] = [0.75, 1.0, 1.25, 1.5];
+
+// Lays out the in-level HUD from the player's settings: the timer, wall hug score and power
+// ups go in whichever corner was picked for them, stacking if they share one, and everything
+// is scaled up or down. Minimal mode hides all but the essential warnings.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(create_hud_corners))
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(lay_out_hud));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "Top left",
+            Corner::TopRight => "Top right",
+            Corner::BottomLeft => "Bottom left",
+            Corner::BottomRight => "Bottom right",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        Corner::iter()
+            .cycle()
+            .skip_while(|corner| *corner != self)
+            .nth(1)
+            .unwrap()
+    }
+
+    fn is_top(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::TopRight)
+    }
+
+    fn is_left(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::BottomLeft)
+    }
+}
+
+// Kept in settings, so each profile has its own
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudSettings {
+    // One of SCALE_STEPS
+    pub scale: f32,
+    pub timer: Corner,
+    pub score: Corner,
+    pub power_ups: Corner,
+    pub minimal: bool,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            timer: Corner::TopLeft,
+            score: Corner::TopRight,
+            power_ups: Corner::TopRight,
+            minimal: false,
+        }
+    }
+}
+
+impl HudSettings {
+    // The scale after the current one, back to the smallest after the largest
+    pub fn next_scale(&mut self) {
+        self.scale = SCALE_STEPS
+            .into_iter()
+            .find(|step| *step > self.scale)
+            .unwrap_or(SCALE_STEPS[0]);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HudItem {
+    Timer,
+    Score,
+    PowerUps,
+    Upgrades,
+    Stamina,
+    InputDisplay,
+    Hint,
+}
+
+impl HudItem {
+    // Where it goes, for the items that can be moved
+    fn corner(self, settings: &HudSettings) -> Option<Corner> {
+        match self {
+            HudItem::Timer => Some(settings.timer),
+            HudItem::Score => Some(settings.score),
+            HudItem::PowerUps => Some(settings.power_ups),
+            HudItem::Upgrades | HudItem::Stamina | HudItem::InputDisplay | HudItem::Hint => None,
+        }
+    }
+
+    // Kept in minimal mode, since running out of stamina drops the bee out of the sky
+    fn is_essential(self) -> bool {
+        matches!(self, HudItem::Stamina)
+    }
+}
+
+// A top-level piece of the HUD, laid out once it's spawned
+#[derive(Component)]
+pub struct HudElement(pub HudItem);
+
+// Holds the HUD elements placed in a corner
+#[derive(Component)]
+struct HudCorner(Corner);
+
+fn create_hud_corners(mut commands: Commands) {
+    for corner in Corner::iter() {
+        let margin = Val::Percent(2.0);
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: if corner.is_top() { margin } else { Val::Auto },
+                        bottom: if corner.is_top() { Val::Auto } else { margin },
+                        left: if corner.is_left() { margin } else { Val::Auto },
+                        right: if corner.is_left() { Val::Auto } else { margin },
+                    },
+                    // Stacked away from the edge of the screen
+                    flex_direction: if corner.is_top() {
+                        FlexDirection::ColumnReverse
+                    } else {
+                        FlexDirection::Column
+                    },
+                    align_items: if corner.is_left() {
+                        AlignItems::FlexStart
+                    } else {
+                        AlignItems::FlexEnd
+                    },
+                    ..Style::default()
+                },
+                color: Color::NONE.into(),
+                ..NodeBundle::default()
+            })
+            .insert(HudCorner(corner));
+    }
+}
+
+fn scale_val(val: &mut Val, scale: f32) {
+    if let Val::Px(pixels) = val {
+        *pixels *= scale;
+    }
+}
+
+// Scale the text and pixel sizes of an element and everything in it
+fn scale_element(
+    entity: Entity,
+    scale: f32,
+    hierarchy: &Query<&Children>,
+    nodes: &mut Query<(Option<&mut Text>, &mut Style), Without<HudCorner>>,
+) {
+    if let Ok((text, mut style)) = nodes.get_mut(entity) {
+        if let Some(mut text) = text {
+            for section in &mut text.sections {
+                section.style.font_size *= scale;
+            }
+        }
+        scale_val(&mut style.size.width, scale);
+        scale_val(&mut style.size.height, scale);
+    }
+    if let Ok(children) = hierarchy.get(entity) {
+        for child in children.iter() {
+            scale_element(*child, scale, hierarchy, nodes);
+        }
+    }
+}
+
+fn lay_out_hud(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    corners: Query<(Entity, &HudCorner)>,
+    elements: Query<(Entity, &HudElement), Added<HudElement>>,
+    hierarchy: Query<&Children>,
+    mut nodes: Query<(Option<&mut Text>, &mut Style), Without<HudCorner>>,
+) {
+    let settings = &save.settings.hud;
+    for (entity, element) in elements.iter() {
+        scale_element(entity, settings.scale, &hierarchy, &mut nodes);
+        let mut style = match nodes.get_mut(entity) {
+            Ok((_, style)) => style,
+            Err(_) => continue,
+        };
+        if settings.minimal && !element.0.is_essential() {
+            style.display = Display::None;
+        }
+
+        let corner = element
+            .0
+            .corner(settings)
+            .and_then(|wanted| corners.iter().find(|(_, corner)| corner.0 == wanted));
+        if let Some((corner, _)) = corner {
+            // Laid out by the corner from here on, a little apart from its neighbours
+            style.position_type = PositionType::Relative;
+            style.position = Rect::default();
+            style.margin = Rect::all(Val::Px(4.0 * settings.scale));
+            commands.entity(corner).add_child(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lay_out(settings: HudSettings, items: &[HudItem]) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        let mut save = SaveData::default();
+        save.settings.hud = settings;
+        world.insert_resource(save);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(create_hud_corners);
+        stage.run(&mut world);
+
+        let elements = items
+            .iter()
+            .map(|item| {
+                world
+                    .spawn()
+                    .insert_bundle(TextBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..Style::default()
+                        },
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font_size: 40.0,
+                                ..TextStyle::default()
+                            },
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    })
+                    .insert(HudElement(*item))
+                    .id()
+            })
+            .collect();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(lay_out_hud);
+        stage.run(&mut world);
+        (world, elements)
+    }
+
+    fn corner_of(world: &World, entity: Entity) -> Option<Corner> {
+        let parent = world.get::<Parent>(entity)?;
+        world.get::<HudCorner>(parent.0).map(|corner| corner.0)
+    }
+
+    #[test]
+    fn elements_go_in_their_corners() {
+        let settings = HudSettings {
+            scale: 1.5,
+            timer: Corner::BottomRight,
+            ..HudSettings::default()
+        };
+        let (world, elements) = lay_out(
+            settings,
+            &[HudItem::Timer, HudItem::Score, HudItem::Stamina],
+        );
+        assert_eq!(corner_of(&world, elements[0]), Some(Corner::BottomRight));
+        assert_eq!(corner_of(&world, elements[1]), Some(Corner::TopRight));
+        assert_eq!(corner_of(&world, elements[2]), None);
+
+        let text = world.get::<Text>(elements[0]).unwrap();
+        assert_eq!(text.sections[0].style.font_size, 60.0);
+        let style = world.get::<Style>(elements[0]).unwrap();
+        assert_eq!(style.position_type, PositionType::Relative);
+        assert_eq!(style.display, Display::Flex);
+    }
+
+    #[test]
+    fn minimal_mode_keeps_essential_warnings() {
+        let settings = HudSettings {
+            minimal: true,
+            ..HudSettings::default()
+        };
+        let (world, elements) = lay_out(settings, &[HudItem::Timer, HudItem::Stamina]);
+        assert_eq!(
+            world.get::<Style>(elements[0]).unwrap().display,
+            Display::None
+        );
+        assert_eq!(
+            world.get::<Style>(elements[1]).unwrap().display,
+            Display::Flex
+        );
+    }
+
+    #[test]
+    fn scale_steps_wrap_around() {
+        let mut settings = HudSettings::default();
+        settings.next_scale();
+        assert_eq!(settings.scale, 1.25);
+        settings.next_scale();
+        settings.next_scale();
+        assert_eq!(settings.scale, 0.75);
+        assert_eq!(Corner::BottomRight.next(), Corner::TopLeft);
+    }
+}
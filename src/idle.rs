@@ -0,0 +1,104 @@
+use crate::{
+    change_state,
+    countdown::Resuming,
+    game_overlay::{AutoPause, PauseReason},
+    playing,
+    replay::ReplayPlayback,
+    save::SaveData,
+    AppState, GameState,
+};
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*,
+    window::{WindowFocused, WindowResized},
+};
+
+// Pauses a level when nobody's playing it, so stepping away from the keyboard or tabbing
+// out doesn't end in a death. How long counts as idle, and whether losing focus pauses, are
+// both in settings. Coming back to the window counts back in before play carries on.
+pub struct IdlePlugin;
+
+impl Plugin for IdlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<IdleTime>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(reset_idle_time))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_idle_time))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(pause_when_idle)
+                    .with_system(pause_when_unfocused),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused).with_system(resume_when_focused),
+            );
+    }
+}
+
+// Seconds of real time since the last input while playing
+#[derive(Default)]
+struct IdleTime(f32);
+
+fn reset_idle_time(mut idle: ResMut<IdleTime>) {
+    idle.0 = 0.0;
+}
+
+fn pause_when_idle(
+    time: Res<Time>,
+    save: Res<SaveData>,
+    playback: Option<Res<ReplayPlayback>>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut idle: ResMut<IdleTime>,
+    mut reason: ResMut<PauseReason>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // Held keys count, since steering with the keyboard can mean holding one for a while
+    let input = mouse_motion.iter().count() > 0
+        || keys.get_pressed().next().is_some()
+        || mouse_buttons.get_pressed().next().is_some();
+    // Replays play themselves
+    if input || playback.is_some() || save.settings.idle_pause_seconds == 0 {
+        idle.0 = 0.0;
+        return;
+    }
+
+    idle.0 += time.delta_seconds();
+    if idle.0 >= save.settings.idle_pause_seconds as f32 {
+        reason.0 = Some(AutoPause::Inactive);
+        change_state(&mut state, GameState::Paused);
+    }
+}
+
+// Minimizing doesn't always take focus away, but it does shrink the window to nothing
+fn pause_when_unfocused(
+    save: Res<SaveData>,
+    mut focused: EventReader<WindowFocused>,
+    mut resized: EventReader<WindowResized>,
+    mut reason: ResMut<PauseReason>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let lost_focus = focused.iter().any(|event| !event.focused);
+    let minimized = resized
+        .iter()
+        .any(|event| event.width == 0.0 || event.height == 0.0);
+    if (lost_focus || minimized) && save.settings.pause_when_unfocused {
+        reason.0 = Some(AutoPause::Unfocused);
+        change_state(&mut state, GameState::Paused);
+    }
+}
+
+// Only pauses that focus caused end on their own, through the countdown
+fn resume_when_focused(
+    mut focused: EventReader<WindowFocused>,
+    reason: Res<PauseReason>,
+    mut resuming: ResMut<Resuming>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let gained_focus = focused.iter().any(|event| event.focused);
+    if gained_focus && reason.0 == Some(AutoPause::Unfocused) {
+        resuming.0 = true;
+        change_state(&mut state, GameState::Countdown);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    controls::{ButtonState, Controls},
+    hud::{HudElement, HudItem},
+    powerups::{self, ActivePowerUps},
+    save::SaveData,
+    ui::{GameFont, ACTIVE_BUTTON_COLOR, INACTIVE_BUTTON_COLOR},
+    upgrades::UpgradeTracker,
+    util::polar_to_cartesian,
+    AppState,
+};
+use bevy::prelude::*;
+
+// Shows what the player is pressing, for streaming and checking replays
+pub struct InputDisplayPlugin;
+
+impl Plugin for InputDisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(create_input_display))
+            .add_system_set(
+                SystemSet::on_update(AppState::Game)
+                    .with_system(update_stick)
+                    .with_system(update_speed_bar)
+                    .with_system(update_button_indicators),
+            );
+    }
+}
+
+const PAD_SIZE: f32 = 80.0;
+const DOT_SIZE: f32 = 12.0;
+const BACKGROUND_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.4);
+
+#[derive(Component)]
+struct StickDot;
+
+#[derive(Component)]
+struct SpeedBar;
+
+#[derive(Component, Clone, Copy)]
+enum ButtonIndicator {
+    Primary,
+    Secondary,
+    SlowMotion,
+}
+
+impl ButtonIndicator {
+    fn label(self) -> &'static str {
+        match self {
+            ButtonIndicator::Primary => "L",
+            ButtonIndicator::Secondary => "R",
+            ButtonIndicator::SlowMotion => "Space",
+        }
+    }
+
+    fn state(self, controls: &Controls) -> ButtonState {
+        match self {
+            ButtonIndicator::Primary => controls.primary,
+            ButtonIndicator::Secondary => controls.secondary,
+            ButtonIndicator::SlowMotion => controls.slow_motion,
+        }
+    }
+}
+
+// Where the dot sits relative to the middle of the pad
+fn stick_offset(controls: &Controls) -> Vec2 {
+    match controls.aim {
+        Some(aim) => polar_to_cartesian(aim, controls.throttle * (PAD_SIZE - DOT_SIZE) / 2.0),
+        None => Vec2::ZERO,
+    }
+}
+
+fn create_input_display(mut commands: Commands, font: Res<GameFont>, save: Res<SaveData>) {
+    if !save.settings.input_display {
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                // The upgrade slots are in the bottom left
+                position: Rect {
+                    bottom: Val::Percent(2.0),
+                    right: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .insert(HudElement(HudItem::InputDisplay))
+        .with_children(|parent| {
+            // Cursor direction, as a stick that leans further the faster the player flies
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PAD_SIZE), Val::Px(PAD_SIZE)),
+                        ..Style::default()
+                    },
+                    color: BACKGROUND_COLOR.into(),
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                size: Size::new(Val::Px(DOT_SIZE), Val::Px(DOT_SIZE)),
+                                ..Style::default()
+                            },
+                            color: ACTIVE_BUTTON_COLOR.into(),
+                            ..NodeBundle::default()
+                        })
+                        .insert(StickDot);
+                });
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PAD_SIZE), Val::Px(8.0)),
+                        margin: Rect {
+                            top: Val::Px(4.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    color: BACKGROUND_COLOR.into(),
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Style::default()
+                            },
+                            color: ACTIVE_BUTTON_COLOR.into(),
+                            ..NodeBundle::default()
+                        })
+                        .insert(SpeedBar);
+                });
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(4.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    color: Color::NONE.into(),
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    for indicator in [
+                        ButtonIndicator::Primary,
+                        ButtonIndicator::Secondary,
+                        ButtonIndicator::SlowMotion,
+                    ] {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    padding: Rect::all(Val::Px(4.0)),
+                                    margin: Rect {
+                                        right: Val::Px(4.0),
+                                        ..Rect::default()
+                                    },
+                                    ..Style::default()
+                                },
+                                color: INACTIVE_BUTTON_COLOR.into(),
+                                ..NodeBundle::default()
+                            })
+                            .insert(indicator)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        indicator.label(),
+                                        TextStyle {
+                                            font: font.get_handle(),
+                                            font_size: 20.0,
+                                            color: Color::BLACK,
+                                        },
+                                        TextAlignment::default(),
+                                    ),
+                                    ..TextBundle::default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+fn update_stick(controls: Res<Controls>, mut dot: Query<&mut Style, With<StickDot>>) {
+    // UI positions grow downwards
+    let position =
+        Vec2::splat((PAD_SIZE - DOT_SIZE) / 2.0) + stick_offset(&controls) * Vec2::new(1.0, -1.0);
+    for mut style in dot.iter_mut() {
+        style.position = Rect {
+            left: Val::Px(position.x),
+            top: Val::Px(position.y),
+            ..Rect::default()
+        };
+    }
+}
+
+// Fill the bar by the player's speed, relative to the fastest they can go
+fn update_speed_bar(
+    controls: Res<Controls>,
+    upgrades: Res<UpgradeTracker>,
+    power_ups: Res<ActivePowerUps>,
+    mut bar: Query<&mut Style, With<SpeedBar>>,
+) {
+    let speed = match controls.aim {
+        Some(_) => controls.throttle * powerups::player_speed(&upgrades, &power_ups) / 2.0,
+        None => 0.0,
+    };
+    for mut style in bar.iter_mut() {
+        style.size.width = Val::Percent(speed * 100.0);
+    }
+}
+
+fn update_button_indicators(
+    controls: Res<Controls>,
+    mut indicators: Query<(&ButtonIndicator, &mut UiColor)>,
+) {
+    for (indicator, mut color) in indicators.iter_mut() {
+        *color = if indicator.state(&controls).pressed {
+            ACTIVE_BUTTON_COLOR
+        } else {
+            INACTIVE_BUTTON_COLOR
+        }
+        .into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn stick_leans_with_throttle() {
+        let mut controls = Controls {
+            aim: Some(PI / 2.0),
+            throttle: 0.5,
+            ..Controls::default()
+        };
+        let offset = stick_offset(&controls);
+        assert!(offset.abs_diff_eq(Vec2::new(0.0, (PAD_SIZE - DOT_SIZE) / 4.0), 1e-4));
+
+        controls.aim = None;
+        assert_eq!(stick_offset(&controls), Vec2::ZERO);
+    }
+}
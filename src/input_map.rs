@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+// Something a key can be bound to, in settings. Steering and menu keys aren't rebindable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumIter, Serialize, Deserialize)]
+pub enum Action {
+    // Upgrade slots and slow motion, while steering with the keyboard
+    Primary,
+    Secondary,
+    SlowMotion,
+    // Shows and dismisses the hint offered after dying in the same place
+    Hint,
+    // Handled whatever the game is doing, so they default to keys that aren't typed into
+    // names and codes
+    Screenshot,
+    Mute,
+    Fullscreen,
+    FpsOverlay,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Primary => "First upgrade",
+            Action::Secondary => "Second upgrade",
+            Action::SlowMotion => "Slow motion",
+            Action::Hint => "Hint",
+            Action::Screenshot => "Screenshot",
+            Action::Mute => "Mute",
+            Action::Fullscreen => "Fullscreen",
+            Action::FpsOverlay => "FPS counter",
+        }
+    }
+
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::Primary => KeyCode::Q,
+            Action::Secondary => KeyCode::E,
+            Action::SlowMotion => KeyCode::Space,
+            Action::Hint => KeyCode::H,
+            Action::Screenshot => KeyCode::F12,
+            Action::Mute => KeyCode::F9,
+            Action::Fullscreen => KeyCode::F11,
+            Action::FpsOverlay => KeyCode::F10,
+        }
+    }
+}
+
+// Keys with fixed jobs that can't be bound: steering, pausing, confirming and photo mode
+pub const RESERVED_KEYS: [KeyCode; 11] = [
+    KeyCode::W,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Escape,
+    KeyCode::Return,
+    KeyCode::P,
+];
+
+// The key bound to each action, kept in settings. Actions missing from a save, like ones
+// added since it was written, use their default key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputMap(BTreeMap<Action, KeyCode>);
+
+impl InputMap {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.0
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_key())
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.just_pressed(self.key(action))
+    }
+
+    // Bind `key` to `action`, giving whatever action had it the old key so no two share
+    // one. Reserved keys are refused.
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> bool {
+        if RESERVED_KEYS.contains(&key) {
+            return false;
+        }
+        let old = self.key(action);
+        if let Some(other) = Action::iter().find(|other| self.key(*other) == key) {
+            self.0.insert(other, old);
+        }
+        self.0.insert(action, key);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_free_of_reserved_keys_and_each_other() {
+        let map = InputMap::default();
+        for action in Action::iter() {
+            assert!(!RESERVED_KEYS.contains(&map.key(action)));
+            let sharing = Action::iter().filter(|other| map.key(*other) == map.key(action));
+            assert_eq!(sharing.count(), 1);
+        }
+    }
+
+    #[test]
+    fn binding_a_taken_key_swaps() {
+        let mut map = InputMap::default();
+        assert!(map.bind(Action::Mute, KeyCode::F12));
+        assert_eq!(map.key(Action::Mute), KeyCode::F12);
+        assert_eq!(map.key(Action::Screenshot), KeyCode::F9);
+        assert!(map.bind(Action::Mute, KeyCode::F5));
+        assert_eq!(map.key(Action::Screenshot), KeyCode::F9);
+
+        assert!(!map.bind(Action::SlowMotion, KeyCode::W));
+        assert_eq!(map.key(Action::SlowMotion), KeyCode::Space);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    controls::Controls,
+    enemy::{Bullet, Enemy, Projectile, Pursuer},
+    player::Player,
+    pursue::pursue,
+    util::polar_to_cartesian,
+    AppState,
+};
+use bevy::{ecs::component::ComponentId, prelude::*, reflect::TypeRegistryArc, window::WindowId};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContext, egui, reflect::ui_for_reflect, Context, InspectableRegistry,
+};
+
+// Debug window for the last enemy hovered with the cursor, showing how it's moving and
+// letting every reflected component on it be changed while it flies. It stays on the enemy
+// when the cursor moves off, so the cursor can go over to the window to edit it.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.world
+            .get_resource_or_insert_with(InspectableRegistry::default)
+            .register_raw(projectile_widget);
+
+        app.init_resource::<Inspected>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(clear_inspected))
+            .add_system_set(
+                SystemSet::on_update(AppState::Game)
+                    .with_system(inspect_hovered_enemy)
+                    .with_system(show_inspector.exclusive_system()),
+            );
+    }
+}
+
+// How close the cursor has to be to an enemy's center to inspect it
+const HOVER_RADIUS: f32 = 20.0;
+// Components from the game are shown open, Bevy's own start folded away
+const GAME_TYPES: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+// The enemy being inspected
+#[derive(Default)]
+struct Inspected(Option<Entity>);
+
+fn projectile_widget(projectile: &mut Projectile, ui: &mut egui::Ui, _: &mut Context) -> bool {
+    match projectile {
+        Projectile::Missile => {
+            ui.label("Missile");
+            false
+        }
+        Projectile::Laser { angle, blocked } => {
+            ui.vertical(|ui| {
+                ui.label("Laser");
+                let mut degrees = angle.to_degrees();
+                let turned = ui
+                    .add(egui::DragValue::new(&mut degrees).suffix("°"))
+                    .changed();
+                if turned {
+                    *angle = degrees.to_radians();
+                }
+                turned | ui.checkbox(blocked, "Blocked").changed()
+            })
+            .inner
+        }
+    }
+}
+
+// The enemy closest to the cursor, if it's close enough
+fn hovered<'a>(
+    cursor: Option<Vec2>,
+    enemies: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Option<Entity> {
+    let cursor = cursor?;
+    enemies
+        .map(|(entity, transform)| (entity, transform.translation.truncate().distance(cursor)))
+        .filter(|(_, distance)| *distance <= HOVER_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity)
+}
+
+fn clear_inspected(mut inspected: ResMut<Inspected>) {
+    inspected.0 = None;
+}
+
+fn inspect_hovered_enemy(
+    controls: Res<Controls>,
+    mut egui: ResMut<EguiContext>,
+    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    mut inspected: ResMut<Inspected>,
+) {
+    // Enemies passed over on the way to the window don't take it over
+    let over_window = egui
+        .try_ctx_for_window_mut(WindowId::primary())
+        .is_some_and(|ctx| ctx.is_pointer_over_area());
+    if over_window {
+        return;
+    }
+    if let Some(entity) = hovered(controls.cursor, enemies.iter()) {
+        inspected.0 = Some(entity);
+    }
+}
+
+// What the enemy is doing, worked out from its components rather than stored on it
+fn behavior(world: &mut World, entity: Entity) -> Vec<String> {
+    let player = world
+        .query_filtered::<&Transform, With<Player>>()
+        .iter(world)
+        .next()
+        .map(|transform| transform.translation.truncate());
+    let position = world
+        .get::<Transform>(entity)
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    let mut lines = Vec::new();
+    if let Some(pursuer) = world.get::<Pursuer>(entity) {
+        let target = player.unwrap_or(position);
+        let velocity = pursue(position, target, pursuer.velocity);
+        lines.push("Behavior: pursue the player".to_string());
+        lines.push(format!("Target: {:.0}, {:.0}", target.x, target.y));
+        lines.push(format!("Velocity: {:.0}, {:.0}", velocity.x, velocity.y));
+    }
+    if let Some(bullet) = world.get::<Bullet>(entity) {
+        let velocity = polar_to_cartesian(bullet.angle, bullet.velocity);
+        lines.push("Behavior: fly straight".to_string());
+        lines.push(format!("Velocity: {:.0}, {:.0}", velocity.x, velocity.y));
+    }
+    lines
+}
+
+// Exclusive, as the components shown are only known at runtime. Each is edited as a copy
+// that's applied back when it's changed.
+fn show_inspector(world: &mut World) {
+    let entity = match world.get_resource::<Inspected>().unwrap().0 {
+        Some(entity) if world.get_entity(entity).is_some() => entity,
+        _ => return,
+    };
+    let ctx = match world
+        .get_resource_mut::<EguiContext>()
+        .and_then(|mut egui| egui.try_ctx_for_window_mut(WindowId::primary()).cloned())
+    {
+        Some(ctx) => ctx,
+        None => return,
+    };
+
+    let behavior = behavior(world, entity);
+    let registry = world.get_resource::<TypeRegistryArc>().unwrap().clone();
+    let component_ids: Vec<ComponentId> = world.entity(entity).archetype().components().collect();
+    let mut components: Vec<_> = {
+        let registry = registry.read();
+        component_ids
+            .into_iter()
+            .filter_map(|id| {
+                let type_id = world.components().get_info(id)?.type_id()?;
+                let registration = registry.get(type_id)?;
+                let component = registration.data::<ReflectComponent>()?.clone();
+                Some((
+                    registration.name(),
+                    registration.short_name().to_string(),
+                    component,
+                ))
+            })
+            .collect()
+    };
+    components.sort_by_key(|(name, _, _)| (!name.starts_with(GAME_TYPES), *name));
+
+    let mut open = true;
+    egui::Window::new(format!("Enemy {entity:?}"))
+        .open(&mut open)
+        .vscroll(true)
+        .show(&ctx, |ui| {
+            for line in &behavior {
+                ui.label(line);
+            }
+            for (name, short_name, component) in &components {
+                let mut value = match component.reflect_component(world, entity) {
+                    Some(value) => value.clone_value(),
+                    None => continue,
+                };
+                let changed = egui::CollapsingHeader::new(short_name)
+                    .default_open(name.starts_with(GAME_TYPES))
+                    .show(ui, |ui| {
+                        let mut context = Context::new_world_access(Some(&ctx), world);
+                        ui_for_reflect(&mut *value, ui, &mut context)
+                    })
+                    .body_returned
+                    .unwrap_or(false);
+                if changed {
+                    component.apply_component(world, entity, &*value);
+                }
+            }
+        });
+    if !open {
+        world.get_resource_mut::<Inspected>().unwrap().0 = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::reflect::{GetTypeRegistration, ReflectMut};
+
+    #[test]
+    fn the_closest_enemy_is_hovered() {
+        let near = Transform::from_xyz(110.0, 0.0, 0.0);
+        let nearer = Transform::from_xyz(95.0, 5.0, 0.0);
+        let far = Transform::from_xyz(0.0, 0.0, 0.0);
+        let enemies = [
+            (Entity::from_raw(1), &near),
+            (Entity::from_raw(2), &nearer),
+            (Entity::from_raw(3), &far),
+        ];
+        let cursor = Some(Vec2::new(100.0, 0.0));
+        assert_eq!(
+            hovered(cursor, enemies.into_iter()),
+            Some(Entity::from_raw(2))
+        );
+        assert_eq!(
+            hovered(Some(Vec2::new(50.0, 0.0)), enemies.into_iter()),
+            None
+        );
+        assert_eq!(hovered(None, enemies.into_iter()), None);
+    }
+
+    #[test]
+    fn enemy_fields_are_edited_through_the_registry() {
+        let registration = Bullet::get_type_registration();
+        let component = registration.data::<ReflectComponent>().unwrap();
+
+        let mut world = World::new();
+        let entity = world.spawn().insert(Bullet::default()).id();
+        let mut value = component
+            .reflect_component(&world, entity)
+            .unwrap()
+            .clone_value();
+        match value.reflect_mut() {
+            ReflectMut::Struct(bullet) => {
+                *bullet.get_field_mut::<f32>("angle").unwrap() = 1.5;
+            }
+            _ => panic!("bullets should reflect as a struct"),
+        }
+        component.apply_component(&mut world, entity, &*value);
+        assert_eq!(world.get::<Bullet>(entity).unwrap().angle, 1.5);
+    }
+}
@@ -1,21 +1,30 @@
 use crate::{
+    challenges::{spawn_challenge_panel, ActiveChallenges, Challenge},
+    change_state,
+    ghost::{self, Medal},
+    pack::LevelRegistry,
+    save::SaveData,
     ui::{spawn_back_button, GameFont},
-    world::{GameWorld, LEVELS},
+    upgrades::UpgradeTracker,
+    world::Variant,
     AppState,
 };
 use bevy::prelude::*;
-use std::{ffi::OsStr, fs, io, path::PathBuf};
 
 pub struct LevelSelectPlugin;
 
 impl Plugin for LevelSelectPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_enter(AppState::LevelSelect).with_system(create_level_select),
-        )
-        .add_system_set(
-            SystemSet::on_update(AppState::LevelSelect).with_system(manage_level_select_buttons),
-        );
+        app.insert_resource(SelectedVariant(Variant::Original))
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelSelect).with_system(create_level_select),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::LevelSelect)
+                    .with_system(manage_level_select_buttons)
+                    .with_system(manage_variant_button)
+                    .with_system(manage_dev_ghost_button),
+            );
     }
 }
 
@@ -24,7 +33,33 @@ struct LevelSelectButton {
     level: usize,
 }
 
-fn create_level_select(mut commands: Commands, font: Res<GameFont>) {
+// Layout variant that levels are started in
+struct SelectedVariant(Variant);
+
+#[derive(Component)]
+struct VariantButton;
+
+#[derive(Component)]
+struct DevGhostButton;
+
+fn dev_ghost_label(save: &SaveData) -> String {
+    let value = if save.settings.dev_ghost { "On" } else { "Off" };
+    format!("Dev ghost: {value}")
+}
+
+// The best medal earned on a level, from the best time on its original layout
+fn medal(save: &SaveData, levels: &LevelRegistry, level: usize) -> Option<Medal> {
+    let best = save.best_times.get(&levels.levels[level].display_name())?;
+    Medal::for_time(*best, ghost::dev_time_for_level(levels, level)?)
+}
+
+fn create_level_select(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    levels: Res<LevelRegistry>,
+    variant: Res<SelectedVariant>,
+    save: Res<SaveData>,
+) {
     commands.spawn_bundle(UiCameraBundle::default());
 
     spawn_back_button(&mut commands, font.get_handle());
@@ -68,8 +103,13 @@ fn create_level_select(mut commands: Commands, font: Res<GameFont>) {
             parent
                 .spawn_bundle(NodeBundle {
                     style: Style {
-                        size: Size::new(Val::Percent(60.0), Val::Percent(LEVEL_SELECT_HEIGHT)),
-                        margin: Rect::all(Val::Auto),
+                        size: Size::new(Val::Percent(45.0), Val::Percent(LEVEL_SELECT_HEIGHT)),
+                        margin: Rect {
+                            left: Val::Percent(5.0),
+                            right: Val::Auto,
+                            top: Val::Auto,
+                            bottom: Val::Auto,
+                        },
                         flex_direction: FlexDirection::ColumnReverse,
                         ..Style::default()
                     },
@@ -77,59 +117,150 @@ fn create_level_select(mut commands: Commands, font: Res<GameFont>) {
                     ..NodeBundle::default()
                 })
                 .with_children(|parent| {
-                    // Set button height dynamically (based on number of levels)
-                    let button_height = LEVEL_SELECT_HEIGHT / LEVELS.len() as f32;
+                    // Set button height dynamically (based on number of levels, plus the
+                    // variant and dev ghost buttons)
+                    let button_height = LEVEL_SELECT_HEIGHT / (levels.levels.len() + 2) as f32;
+                    let button_style = Style {
+                        size: Size::new(Val::Percent(80.0), Val::Percent(button_height)),
+                        margin: Rect::all(Val::Auto),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Style::default()
+                    };
+                    let text_style = TextStyle {
+                        font: font.get_handle(),
+                        font_size: 30.0,
+                        color: Color::BLACK,
+                    };
 
-                    for (level, (name, _)) in LEVELS.iter().enumerate() {
+                    for (level, entry) in levels.levels.iter().enumerate() {
+                        let name = match medal(&save, &levels, level) {
+                            Some(medal) => format!("{} - {}", entry.display_name(), medal.name()),
+                            None if save.skipped_levels.contains(&entry.display_name()) => {
+                                format!("{} - Skipped", entry.display_name())
+                            }
+                            None => entry.display_name(),
+                        };
                         parent
                             .spawn_bundle(ButtonBundle {
-                                style: Style {
-                                    size: Size::new(
-                                        Val::Percent(80.0),
-                                        Val::Percent(button_height),
-                                    ),
-                                    margin: Rect::all(Val::Auto),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..Style::default()
-                                },
+                                style: button_style.clone(),
                                 ..ButtonBundle::default()
                             })
-                            .insert(LevelSelectButton {
-                                level,
-                            })
+                            .insert(LevelSelectButton { level })
                             .with_children(|parent| {
                                 parent.spawn_bundle(TextBundle {
                                     text: Text::with_section(
-                                        *name,
-                                        TextStyle {
-                                            font: font.get_handle(),
-                                            font_size: 30.0,
-                                            color: Color::BLACK,
-                                        },
+                                        name,
+                                        text_style.clone(),
                                         TextAlignment::default(),
                                     ),
                                     ..TextBundle::default()
                                 });
                             });
                     }
+
+                    parent
+                        .spawn_bundle(ButtonBundle {
+                            style: button_style.clone(),
+                            ..ButtonBundle::default()
+                        })
+                        .insert(VariantButton)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    format!("Variant: {}", variant.0.name()),
+                                    text_style.clone(),
+                                    TextAlignment::default(),
+                                ),
+                                ..TextBundle::default()
+                            });
+                        });
+
+                    parent
+                        .spawn_bundle(ButtonBundle {
+                            style: button_style,
+                            ..ButtonBundle::default()
+                        })
+                        .insert(DevGhostButton)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    dev_ghost_label(&save),
+                                    text_style,
+                                    TextAlignment::default(),
+                                ),
+                                ..TextBundle::default()
+                            });
+                        });
                 });
+
+            spawn_challenge_panel(parent, &font, LEVEL_SELECT_HEIGHT);
         });
 }
 
 fn manage_level_select_buttons(
     mut commands: Commands,
     mut state: ResMut<State<AppState>>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    mut levels: ResMut<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    variant: Res<SelectedVariant>,
     interaction: Query<(&Interaction, &LevelSelectButton), (Changed<Interaction>, With<Button>)>,
 ) {
     for (interaction, level_select_button) in interaction.iter() {
         // Check if the button has been clicked
         if matches!(interaction, Interaction::Clicked) {
-            commands.insert_resource(
-                GameWorld::load_level(level_select_button.level)
-                    .unwrap(),
-            );
-            state.set(AppState::Game).unwrap();
+            let world = match levels.load_cached(
+                level_select_button.level,
+                variant.0,
+                challenges.is_active(Challenge::Mirrored),
+            ) {
+                Ok(world) => world,
+                // Pack levels can be edited into something broken while the game runs
+                Err(err) => {
+                    warn!("Failed to load level: {err}");
+                    continue;
+                }
+            };
+            commands.insert_resource(world);
+
+            if challenges.is_active(Challenge::NoUpgrades) {
+                *upgrades = UpgradeTracker::default();
+                change_state(&mut state, AppState::Game);
+            } else {
+                // Pick upgrades for this attempt before starting
+                change_state(&mut state, AppState::UpgradeSelect);
+            }
+        }
+    }
+}
+
+fn manage_variant_button(
+    mut variant: ResMut<SelectedVariant>,
+    interaction: Query<(&Interaction, &Children), (Changed<Interaction>, With<VariantButton>)>,
+    mut text: Query<&mut Text>,
+) {
+    for (interaction, children) in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            variant.0 = variant.0.next();
+            if let Ok(mut text) = text.get_mut(children[0]) {
+                text.sections[0].value = format!("Variant: {}", variant.0.name());
+            }
+        }
+    }
+}
+
+fn manage_dev_ghost_button(
+    mut save: ResMut<SaveData>,
+    interaction: Query<(&Interaction, &Children), (Changed<Interaction>, With<DevGhostButton>)>,
+    mut text: Query<&mut Text>,
+) {
+    for (interaction, children) in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            save.settings.dev_ghost ^= true;
+            if let Ok(mut text) = text.get_mut(children[0]) {
+                text.sections[0].value = dev_ghost_label(&save);
+            }
         }
     }
 }
@@ -1,47 +1,214 @@
-#![allow(clippy::type_complexity)]
+// Bevy's derive(Bundle) forgets each field after moving it into the world, which clippy
+// flags for every field that isn't Drop
+#![allow(
+    clippy::type_complexity,
+    clippy::too_many_arguments,
+    clippy::forget_non_drop
+)]
 
+mod animation;
+mod atlas;
+mod attract;
+#[cfg(feature = "autopilot")]
+mod autopilot;
+mod bounds;
 mod camera;
+mod challenge_code;
+mod challenges;
+mod cli;
+mod clock;
 mod collision;
+mod controls;
+mod cosmetics;
+mod countdown;
+mod crash;
+mod credits;
+mod cutscene;
 mod death;
+#[cfg(feature = "dev-tools")]
+mod debug_menu;
+mod despawn;
+mod display;
+mod dying;
+mod editor;
 mod enemy;
+mod events;
+mod fog;
+mod frame_rate;
 mod game_overlay;
+mod ghost;
 mod help;
+mod highlight;
+mod hints;
+mod hotkeys;
+mod http;
+mod hud;
+mod idle;
+mod input_display;
+mod input_map;
+#[cfg(feature = "dev-tools")]
+mod inspector;
 mod level_select;
 mod menu;
+mod movement;
+mod news;
+mod onboarding;
+mod online;
+mod pack;
+mod pathfinding;
+mod pattern;
+mod performance;
 mod player;
+mod powerups;
+mod profile;
 mod pursue;
+mod render_layer;
+mod replay;
+#[cfg(feature = "dev-tools")]
+mod replay_scrubber;
+#[cfg(feature = "dev-tools")]
+mod resource_inspector;
 mod retry;
+mod run_log;
+mod save;
+mod screen_effects;
+#[cfg(feature = "scripting")]
+mod script;
+mod sequencer;
+mod settings;
+mod shadow;
+mod sound;
+mod spawner_art;
+#[cfg(feature = "dev-tools")]
+mod spectate;
+mod stamina;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod tips;
+mod trail;
 mod ui;
+mod upgrade_effects;
 mod upgrade_select;
 mod upgrades;
 mod util;
 mod victory;
+mod wall_contact;
+mod wall_hug;
+mod wardrobe;
 mod world;
 
-use benimator::AnimationPlugin;
-use bevy::prelude::*;
+use animation::{AnimationStatePlugin, SpriteAnimationPlugin};
+use atlas::AtlasPlugin;
+use bevy::{
+    ecs::schedule::{ShouldRun, StateData},
+    prelude::*,
+};
 
+use bounds::BoundsPlugin;
 use camera::CameraPlugin;
+use challenge_code::ChallengeCodePlugin;
+use challenges::ChallengesPlugin;
+use clock::ClockPlugin;
 use collision::CollisionPlugin;
+use controls::ControlsPlugin;
+use cosmetics::CosmeticsPlugin;
+use crash::CrashPlugin;
+use cutscene::CutscenePlugin;
 use death::DeathPlugin;
+use despawn::DespawnPlugin;
+use display::DisplayPlugin;
+use dying::DyingPlugin;
+use editor::{EditorFile, EditorPlugin};
 use enemy::EnemyPlugin;
+use events::EventsPlugin;
+use fog::FogPlugin;
+use frame_rate::FrameRatePlugin;
+use ghost::GhostPlugin;
+use highlight::HighlightPlugin;
+use hints::HintsPlugin;
+use hotkeys::HotkeysPlugin;
+use hud::HudPlugin;
+use idle::IdlePlugin;
+use online::OnlinePlugin;
+use pack::PackPlugin;
+use performance::PerformancePlugin;
 use player::PlayerPlugin;
+use powerups::PowerUpPlugin;
+use render_layer::RenderLayerPlugin;
+use replay::ReplayPlugin;
+use run_log::RunLogPlugin;
+use save::SavePlugin;
+use screen_effects::ScreenEffectsPlugin;
+#[cfg(feature = "scripting")]
+use script::ScriptPlugin;
+use shadow::ShadowPlugin;
+use sound::SoundPlugin;
+use spawner_art::SpawnerArtPlugin;
+use stamina::StaminaPlugin;
+use telemetry::TelemetryPlugin;
+use tips::TipsPlugin;
+use trail::TrailPlugin;
 use ui::UiPlugins;
+use upgrade_effects::UpgradeEffectsPlugin;
 use upgrades::UpgradesPlugin;
+use wall_contact::WallContactPlugin;
+use wall_hug::WallHugPlugin;
 use world::WorldPlugin;
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub enum AppState {
+    ProfileSelect,
     Menu,
     UpgradeSelect,
+    Wardrobe,
     LevelSelect,
     Help,
+    Settings,
+    Credits,
+    // The first-run introduction, which goes on to the tutorial level
+    NameEntry,
+    ControlSelect,
+    // The level editor, only opened from the command line
+    Editor,
     Game,
     Death,
     Retry,
     Victory,
 }
 
+// What's happening within AppState::Game. Gameplay only advances while Playing,
+// everything else freezes the run in place.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum GameState {
+    // Counting in at the start of a level
+    Countdown,
+    Playing,
+    Paused,
+    Dialogue,
+    PhotoMode,
+    // The player has been hit and is falling, on the way to AppState::Death
+    Dying,
+}
+
+// Run criteria for gameplay systems, use instead of SystemSet::on_update(AppState::Game)
+// for anything that should stop during the other game states
+pub fn playing(app_state: Res<State<AppState>>, game_state: Res<State<GameState>>) -> ShouldRun {
+    if *app_state.current() == AppState::Game && *game_state.current() == GameState::Playing {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+// Move to another state, logging instead of panicking if that isn't possible, e.g. when
+// another transition is already queued this frame
+pub fn change_state<T: StateData>(state: &mut State<T>, next: T) {
+    if let Err(err) = state.set(next.clone()) {
+        warn!("Couldn't change state to {next:?}: {err:?}");
+    }
+}
+
 pub fn despawn_all(mut commands: Commands, entities: Query<Entity>) {
     for entity in entities.iter() {
         commands.entity(entity).despawn_recursive();
@@ -49,23 +216,105 @@ pub fn despawn_all(mut commands: Commands, entities: Query<Entity>) {
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_state(AppState::Menu)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
+        // Left out of tests, which would otherwise write crash reports for their own panics
+        .add_plugin(CrashPlugin);
+    match args.as_slice() {
+        [command, level] if command == "--edit" => {
+            app.insert_resource(EditorFile(level.into()))
+                .add_state(AppState::Editor);
+        }
+        _ => {
+            app.add_state(profile::first_state());
+        }
+    }
+    add_game(&mut app);
+    #[cfg(feature = "autopilot")]
+    if args.first().is_some_and(|arg| arg == "--autopilot") {
+        app.add_plugin(autopilot::AutoPilotPlugin);
+    }
+    // The inspectors draw their windows with egui
+    #[cfg(feature = "dev-tools")]
+    app.add_plugin(bevy_inspector_egui::bevy_egui::EguiPlugin)
+        .add_plugin(debug_menu::DebugMenuPlugin)
+        .add_plugin(inspector::InspectorPlugin)
+        .add_plugin(replay_scrubber::ReplayScrubberPlugin)
+        .add_plugin(resource_inspector::ResourceInspectorPlugin)
+        .add_plugin(spectate::SpectatePlugin);
+    app.run();
+}
+
+// Everything that makes up the game, on top of Bevy's own plugins and the starting state
+fn add_game(app: &mut App) {
+    app.add_state(GameState::Playing)
+        .add_system_set(SystemSet::on_exit(AppState::ProfileSelect).with_system(despawn_all))
         .add_system_set(SystemSet::on_exit(AppState::Menu).with_system(despawn_all))
         .add_system_set(SystemSet::on_exit(AppState::UpgradeSelect).with_system(despawn_all))
+        .add_system_set(SystemSet::on_exit(AppState::Wardrobe).with_system(despawn_all))
         .add_system_set(SystemSet::on_exit(AppState::LevelSelect).with_system(despawn_all))
         .add_system_set(SystemSet::on_exit(AppState::Help).with_system(despawn_all))
+        .add_system_set(SystemSet::on_exit(AppState::Settings).with_system(despawn_all))
+        .add_system_set(SystemSet::on_exit(AppState::Credits).with_system(despawn_all))
+        .add_system_set(SystemSet::on_exit(AppState::NameEntry).with_system(despawn_all))
+        .add_system_set(SystemSet::on_exit(AppState::ControlSelect).with_system(despawn_all))
         .add_system_set(SystemSet::on_exit(AppState::Retry).with_system(despawn_all))
         .add_system_set(SystemSet::on_exit(AppState::Victory).with_system(despawn_all))
-        .add_plugin(AnimationPlugin::default())
+        .add_plugin(AnimationStatePlugin)
+        .add_plugin(AtlasPlugin)
+        .add_plugin(BoundsPlugin)
         .add_plugin(CameraPlugin)
+        .add_plugin(ChallengeCodePlugin)
+        .add_plugin(ChallengesPlugin)
+        .add_plugin(ClockPlugin)
         .add_plugin(CollisionPlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(CosmeticsPlugin)
+        .add_plugin(CutscenePlugin)
+        .add_plugin(DespawnPlugin)
+        .add_plugin(DisplayPlugin)
+        .add_plugin(EditorPlugin)
         .add_plugin(EnemyPlugin)
+        .add_plugin(EventsPlugin)
+        .add_plugin(FogPlugin)
+        .add_plugin(FrameRatePlugin)
+        .add_plugin(GhostPlugin)
+        .add_plugin(HighlightPlugin)
+        .add_plugin(HintsPlugin)
+        .add_plugin(HotkeysPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(IdlePlugin)
+        .add_plugin(OnlinePlugin)
+        .add_plugin(PackPlugin)
+        .add_plugin(PerformancePlugin)
         .add_plugin(PlayerPlugin)
+        .add_plugin(PowerUpPlugin)
+        .add_plugin(RenderLayerPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(RunLogPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(ScreenEffectsPlugin)
+        .add_plugin(ShadowPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(SpawnerArtPlugin)
+        .add_plugin(SpriteAnimationPlugin)
+        .add_plugin(StaminaPlugin)
+        .add_plugin(TelemetryPlugin)
+        .add_plugin(TipsPlugin)
+        .add_plugin(TrailPlugin)
         .add_plugins(UiPlugins)
+        .add_plugin(UpgradeEffectsPlugin)
         .add_plugin(UpgradesPlugin)
+        .add_plugin(WallContactPlugin)
+        .add_plugin(WallHugPlugin)
         .add_plugin(WorldPlugin)
         .add_plugin(DeathPlugin)
-        .run();
+        .add_plugin(DyingPlugin);
+    #[cfg(feature = "scripting")]
+    app.add_plugin(ScriptPlugin);
 }
@@ -1,75 +1,188 @@
 use crate::{
+    animation::SpriteSheetAnimation,
+    change_state,
     player::Player,
+    render_layer::RenderLayer,
+    save::SaveData,
     ui::GameFont,
     util::{AnimatedSprite, AnimatedSpriteData},
     AppState,
 };
-use benimator::SpriteSheetAnimation;
 use bevy::prelude::*;
+use rand::random;
+use std::f32::consts::PI;
+
+const CLOUDS: usize = 8;
+const DRIFTING_BEES: usize = 5;
+// Distance past the edge of the window at which drifting sprites wrap around
+const DRIFT_MARGIN: f32 = 100.0;
 
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(AppState::Menu).with_system(create_menu))
-            .add_system_set(SystemSet::on_update(AppState::Menu).with_system(manage_menu_buttons));
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu)
+                    .with_system(manage_menu_buttons)
+                    .with_system(drift_background)
+                    .with_system(bob_title),
+            );
     }
 }
 
+#[derive(Component)]
+pub struct MenuCamera;
+
+// Decorative menu sprites, hidden while the attract demo plays
+#[derive(Component)]
+pub struct MenuBackground;
+
+// Background sprite moving across the menu, bobbing up and down as it goes
+#[derive(Component)]
+struct Drift {
+    velocity: Vec2,
+    bob: f32,
+}
+
+#[derive(Component)]
+struct Title;
+
 #[derive(Component)]
 enum ButtonType {
     Play,
-    Upgrades,
+    Wardrobe,
     Help,
+    Settings,
+    Credits,
+    // Shows who's playing, and goes to the profile screen to switch
+    Profile,
 }
 
 fn create_menu(
     mut commands: Commands,
+    windows: Res<Windows>,
+    save: Res<SaveData>,
     font: Res<GameFont>,
     mut animations: ResMut<Assets<SpriteSheetAnimation>>,
     mut textures: ResMut<Assets<TextureAtlas>>,
     asset_server: Res<AssetServer>,
 ) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MenuCamera);
 
     // Spawn player sprite
-    commands.spawn_bundle(AnimatedSprite::new(
-        &mut animations,
-        &mut textures,
-        &asset_server,
-        AnimatedSpriteData {
-            path: "bee.png".into(),
-            frames: 6,
-            size: Vec2::splat(Player::SIZE),
-            transform: Transform::from_scale(Vec3::new(3.0, 3.0, 0.0)),
-            ..AnimatedSpriteData::default()
-        },
-    ));
+    commands
+        .spawn_bundle(AnimatedSprite::new(
+            &mut animations,
+            &mut textures,
+            &asset_server,
+            AnimatedSpriteData {
+                path: "bee.png".into(),
+                frames: 6,
+                size: Vec2::splat(Player::SIZE),
+                transform: Transform::from_scale(Vec3::new(3.0, 3.0, 0.0)),
+                ..AnimatedSpriteData::default()
+            },
+        ))
+        .insert(RenderLayer::Player)
+        .insert(MenuBackground);
+
+    let window_size = windows.get_primary().map_or(Vec2::ZERO, |window| {
+        Vec2::new(window.width(), window.height())
+    });
+    let random_position = || {
+        Vec2::new(
+            (random::<f32>() - 0.5) * window_size.x,
+            (random::<f32>() - 0.5) * window_size.y,
+        )
+    };
+
+    // Spawn clouds in two layers, with the nearer layer larger and faster for parallax
+    let clouds = if save.settings.low_spec { 0 } else { CLOUDS };
+    for i in 0..clouds {
+        let near = i % 2 == 0;
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, if near { 0.5 } else { 0.25 }),
+                    ..Sprite::default()
+                },
+                texture: asset_server.load("cloud.png"),
+                transform: Transform {
+                    translation: random_position().extend(0.0),
+                    scale: Vec3::splat(if near { 2.5 } else { 1.5 }),
+                    ..Transform::default()
+                },
+                ..SpriteBundle::default()
+            })
+            .insert(if near {
+                RenderLayer::Decorations
+            } else {
+                RenderLayer::Background
+            })
+            .insert(Drift {
+                velocity: Vec2::new(if near { -35.0 } else { -15.0 }, 0.0),
+                bob: 0.0,
+            })
+            .insert(MenuBackground);
+    }
+
+    for _ in 0..DRIFTING_BEES {
+        commands
+            .spawn_bundle(AnimatedSprite::new(
+                &mut animations,
+                &mut textures,
+                &asset_server,
+                AnimatedSpriteData {
+                    path: "bee.png".into(),
+                    frames: 6,
+                    size: Vec2::splat(Player::SIZE),
+                    transform: Transform {
+                        translation: random_position().extend(0.0),
+                        // Face right, the direction the bees drift in
+                        rotation: Quat::from_rotation_z(-PI / 2.0),
+                        ..Transform::default()
+                    },
+                    ..AnimatedSpriteData::default()
+                },
+            ))
+            // Drawn like ghosts, over the scenery but under the title bee
+            .insert(RenderLayer::Ghosts)
+            .insert(Drift {
+                velocity: Vec2::new(60.0 + random::<f32>() * 60.0, 0.0),
+                bob: 20.0 + random::<f32>() * 30.0,
+            })
+            .insert(MenuBackground);
+    }
 
     commands.spawn_bundle(UiCameraBundle::default());
     commands.insert_resource(ClearColor(Color::rgb(0.2, 0.2, 0.4)));
 
-    commands.spawn_bundle(TextBundle {
-        style: Style {
-            position_type: PositionType::Absolute,
-            position: Rect {
-                left: Val::Percent(10.0),
-                bottom: Val::Percent(60.0),
-                ..Rect::default()
-            },
-            ..Style::default()
-        },
-        text: Text::with_section(
-            "Beeline",
-            TextStyle {
-                font: font.get_handle(),
-                font_size: 130.0,
-                ..TextStyle::default()
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(10.0),
+                    bottom: Val::Percent(60.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
             },
-            TextAlignment::default(),
-        ),
-        ..TextBundle::default()
-    });
+            text: Text::with_section(
+                "Beeline",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 130.0,
+                    ..TextStyle::default()
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(Title);
 
     commands
         .spawn_bundle(ButtonBundle {
@@ -77,7 +190,7 @@ fn create_menu(
                 position_type: PositionType::Absolute,
                 position: Rect {
                     left: Val::Percent(10.0),
-                    bottom: Val::Percent(45.0),
+                    bottom: Val::Percent(50.0),
                     ..Rect::default()
                 },
                 size: Size::new(Val::Px(200.0), Val::Px(65.0)),
@@ -109,7 +222,7 @@ fn create_menu(
                 position_type: PositionType::Absolute,
                 position: Rect {
                     left: Val::Percent(10.0),
-                    bottom: Val::Percent(30.0),
+                    bottom: Val::Percent(40.0),
                     ..Rect::default()
                 },
                 size: Size::new(Val::Px(200.0), Val::Px(65.0)),
@@ -119,11 +232,11 @@ fn create_menu(
             },
             ..ButtonBundle::default()
         })
-        .insert(ButtonType::Upgrades)
+        .insert(ButtonType::Wardrobe)
         .with_children(|parent| {
             parent.spawn_bundle(TextBundle {
                 text: Text::with_section(
-                    "Upgrades",
+                    "Wardrobe",
                     TextStyle {
                         font: font.get_handle(),
                         font_size: 60.0,
@@ -141,7 +254,7 @@ fn create_menu(
                 position_type: PositionType::Absolute,
                 position: Rect {
                     left: Val::Percent(10.0),
-                    bottom: Val::Percent(15.0),
+                    bottom: Val::Percent(30.0),
                     ..Rect::default()
                 },
                 size: Size::new(Val::Px(200.0), Val::Px(65.0)),
@@ -166,6 +279,102 @@ fn create_menu(
                 ..TextBundle::default()
             });
         });
+
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(10.0),
+                    bottom: Val::Percent(20.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Px(200.0), Val::Px(65.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(ButtonType::Settings)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Settings",
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 60.0,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        });
+
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(10.0),
+                    bottom: Val::Percent(10.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Px(200.0), Val::Px(65.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(ButtonType::Credits)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Credits",
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 60.0,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        });
+
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(2.0),
+                    right: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Px(250.0), Val::Px(50.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(ButtonType::Profile)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    save.player_name.as_deref().unwrap_or("Profiles"),
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 40.0,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        });
 }
 
 fn manage_menu_buttons(
@@ -175,15 +384,57 @@ fn manage_menu_buttons(
     for (interaction, button_type) in interaction.iter() {
         match (interaction, button_type) {
             (Interaction::Clicked, ButtonType::Play) => {
-                state.set(AppState::LevelSelect).unwrap();
+                change_state(&mut state, AppState::LevelSelect);
             }
-            (Interaction::Clicked, ButtonType::Upgrades) => {
-                state.set(AppState::UpgradeSelect).unwrap();
+            (Interaction::Clicked, ButtonType::Wardrobe) => {
+                change_state(&mut state, AppState::Wardrobe);
             }
             (Interaction::Clicked, ButtonType::Help) => {
-                state.set(AppState::Help).unwrap();
+                change_state(&mut state, AppState::Help);
+            }
+            (Interaction::Clicked, ButtonType::Settings) => {
+                change_state(&mut state, AppState::Settings);
+            }
+            (Interaction::Clicked, ButtonType::Credits) => {
+                change_state(&mut state, AppState::Credits);
+            }
+            (Interaction::Clicked, ButtonType::Profile) => {
+                change_state(&mut state, AppState::ProfileSelect);
             }
             _ => {}
         }
     }
 }
+
+fn drift_background(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    mut sprites: Query<(&mut Transform, &Drift)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let half_width = window.width() / 2.0 + DRIFT_MARGIN;
+    let elapsed = time.seconds_since_startup() as f32;
+
+    for (mut transform, drift) in sprites.iter_mut() {
+        let bob = (elapsed * 2.0 + transform.translation.x / 50.0).cos() * drift.bob;
+        transform.translation.x += drift.velocity.x * time.delta_seconds();
+        transform.translation.y += (drift.velocity.y + bob) * time.delta_seconds();
+
+        // Wrap around to the other side of the window
+        if transform.translation.x > half_width {
+            transform.translation.x = -half_width;
+        } else if transform.translation.x < -half_width {
+            transform.translation.x = half_width;
+        }
+    }
+}
+
+fn bob_title(time: Res<Time>, mut title: Query<&mut Style, With<Title>>) {
+    let offset = (time.seconds_since_startup() as f32 * 2.0).sin() * 1.5;
+    for mut style in title.iter_mut() {
+        style.position.bottom = Val::Percent(60.0 + offset);
+    }
+}
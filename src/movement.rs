@@ -0,0 +1,322 @@
+use crate::{
+    collision::CollisionShape,
+    world::{GameWorld, OpenWalls, Tile},
+};
+use bevy::prelude::*;
+
+// Walls the player clips by less than this are slipped around instead of stopping them
+const CORNER_FORGIVENESS: f32 = 8.0;
+// Resolution passes per axis, more than enough for a box no bigger than a tile
+const MAX_PUSHES: usize = 4;
+// Longest single step, so fast movement or a long frame can't skip over a wall
+const MAX_STEP: f32 = Tile::SIZE / 2.0;
+// Downward pull with gravity on, in pixels per second squared
+const GRAVITY: f32 = 600.0;
+// Acceleration at full thrust, in top speeds per second
+const THRUST: f32 = 2.5;
+
+// Axis-aligned box with the given center and half size
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Aabb {
+    center: Vec2,
+    half_size: Vec2,
+}
+
+impl Aabb {
+    fn min(&self) -> Vec2 {
+        self.center - self.half_size
+    }
+
+    fn max(&self) -> Vec2 {
+        self.center + self.half_size
+    }
+
+    // Touching edges don't count, so the player fits a corridor exactly their size
+    fn overlaps(&self, other: &Aabb) -> bool {
+        self.min().cmplt(other.max()).all() && other.min().cmplt(self.max()).all()
+    }
+
+    fn shape(&self) -> CollisionShape {
+        CollisionShape::new_rectangle(self.half_size.x * 2.0, self.half_size.y * 2.0)
+            .with_transform(GlobalTransform::from_translation(self.center.extend(0.0)))
+    }
+
+    fn overlap_area(&self, other: &Aabb) -> f32 {
+        let overlap = self.max().min(other.max()) - self.min().max(other.min());
+        overlap.x * overlap.y
+    }
+}
+
+// Where a box ended up, and the normal of the last wall it pressed into on the way, pointing
+// into the wall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Moved {
+    pub position: Vec2,
+    pub contact: Option<Vec2>,
+}
+
+fn tile_box((x, y): (isize, isize)) -> Aabb {
+    Aabb {
+        center: Vec2::new(x as f32, -(y as f32)) * Tile::SIZE,
+        half_size: Vec2::splat(Tile::SIZE / 2.0),
+    }
+}
+
+// Wall tile overlapping the box the most, so flat walls made of several tiles are
+// pushed out of before the seams between them can snag
+fn find_wall(aabb: &Aabb, is_wall: &impl Fn((isize, isize)) -> bool) -> Option<Aabb> {
+    // Tile (x, y) is centered on (x, -y) * Tile::SIZE
+    let tile = |position: Vec2| {
+        let tile = (position / Tile::SIZE + 0.5).floor();
+        (tile.x as isize, -tile.y as isize)
+    };
+    let (min_x, max_y) = tile(aabb.min());
+    let (max_x, min_y) = tile(aabb.max());
+    (min_y..=max_y)
+        .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+        .filter(|coordinates| is_wall(*coordinates))
+        .map(tile_box)
+        .filter(|wall| aabb.overlaps(wall))
+        .max_by(|a, b| aabb.overlap_area(a).total_cmp(&aabb.overlap_area(b)))
+}
+
+// Move a box one step, pushing it out of walls along their contact normals. The part of
+// the step going into a wall is dropped so the rest slides along it.
+fn slide(mut aabb: Aabb, step: &mut Vec2, is_wall: &impl Fn((isize, isize)) -> bool) -> Moved {
+    aabb.center += *step;
+    let mut pressed = None;
+
+    for _ in 0..MAX_PUSHES {
+        let wall = match find_wall(&aabb, is_wall) {
+            Some(wall) => wall,
+            None => break,
+        };
+        let contact = match aabb.shape().contact(&wall.shape()) {
+            Some(contact) => contact,
+            None => break,
+        };
+
+        // Only just caught the corner, so nudge around it if there's room
+        let tangent = contact.normal.perp();
+        let offset = (aabb.center - wall.center).dot(tangent);
+        let clipped = (aabb.half_size + wall.half_size).dot(tangent.abs()) - offset.abs();
+        if clipped <= CORNER_FORGIVENESS {
+            let mut nudged = aabb;
+            nudged.center += tangent * offset.signum() * clipped;
+            if find_wall(&nudged, is_wall).is_none() {
+                aabb = nudged;
+                break;
+            }
+        }
+
+        aabb.center -= contact.normal * contact.depth;
+        *step -= contact.normal * step.dot(contact.normal).max(0.0);
+        pressed = Some(contact.normal);
+    }
+    Moved {
+        position: aabb.center,
+        contact: pressed,
+    }
+}
+
+// Move in steps no longer than MAX_STEP, resolving walls after each one
+fn substep(mut aabb: Aabb, displacement: Vec2, is_wall: &impl Fn((isize, isize)) -> bool) -> Moved {
+    let steps = (displacement.abs().max_element() / MAX_STEP)
+        .ceil()
+        .max(1.0);
+    let mut step = displacement / steps;
+    let mut contact = None;
+    for _ in 0..steps as usize {
+        let moved = slide(aabb, &mut step, is_wall);
+        aabb.center = moved.position;
+        contact = moved.contact.or(contact);
+    }
+    Moved {
+        position: aabb.center,
+        contact,
+    }
+}
+
+// Entities with this stop at walls and slide along them, anything else passes over them
+#[derive(Component)]
+pub struct WallCollider {
+    // Size of the box that hits walls, before the entity's scale
+    pub size: Vec2,
+}
+
+// Pixels per second an entity is moving, carried from frame to frame
+#[derive(Component, Default)]
+pub struct Velocity(pub Vec2);
+
+// Lander-style flight: gravity pulls down while thrust, up to 1 in any direction, pushes
+// against it, all without going past the top speed
+pub fn accelerate(velocity: Vec2, thrust: Vec2, max_speed: f32, seconds: f32) -> Vec2 {
+    let acceleration = thrust * max_speed * THRUST - Vec2::Y * GRAVITY;
+    (velocity + acceleration * seconds).clamp_length_max(max_speed)
+}
+
+// Shared by everything that moves, so walls treat the player and enemies alike. Returns the
+// normal of the wall pressed into, if any.
+pub fn move_entity(
+    world: &GameWorld,
+    open_walls: &OpenWalls,
+    transform: &mut Transform,
+    collider: Option<&WallCollider>,
+    displacement: Vec2,
+) -> Option<Vec2> {
+    let position = transform.translation.truncate();
+    let moved = match collider {
+        Some(collider) if !open_walls.0 => move_through_walls(
+            world,
+            position,
+            collider.size / 2.0 * transform.scale.truncate().abs(),
+            displacement,
+        ),
+        _ => Moved {
+            position: position + displacement,
+            contact: None,
+        },
+    };
+    transform.translation.x = moved.position.x;
+    transform.translation.y = moved.position.y;
+    moved.contact
+}
+
+// Where a box of the given half size ends up after trying to move through the level
+fn move_through_walls(
+    world: &GameWorld,
+    position: Vec2,
+    half_size: Vec2,
+    displacement: Vec2,
+) -> Moved {
+    // Walls are found in layout space, with the top-left tile at (0, 0)
+    let origin = world.origin().0;
+    let moved = substep(
+        Aabb {
+            center: position - origin,
+            half_size,
+        },
+        displacement,
+        &|coordinates| world.is_wall(coordinates),
+    );
+    Moved {
+        position: origin + moved.position,
+        ..moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF: Vec2 = bevy::math::const_vec2!([12.0, 12.0]);
+
+    fn walls(tiles: &'static [(isize, isize)]) -> impl Fn((isize, isize)) -> bool {
+        move |coordinates| tiles.contains(&coordinates)
+    }
+
+    fn player(center: Vec2) -> Aabb {
+        Aabb {
+            center,
+            half_size: HALF,
+        }
+    }
+
+    #[test]
+    fn walls_stop_head_on_movement() {
+        let walls = walls(&[(1, 0)]);
+        assert_eq!(
+            slide(player(Vec2::ZERO), &mut Vec2::new(10.0, 0.0), &walls).position,
+            Vec2::ZERO
+        );
+        assert_eq!(
+            slide(player(Vec2::ZERO), &mut Vec2::new(-10.0, 5.0), &walls).position,
+            Vec2::new(-10.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn pressing_into_walls_reports_the_contact() {
+        let walls = walls(&[(1, 0), (1, 1)]);
+        let pressed = substep(player(Vec2::ZERO), Vec2::new(10.0, -10.0), &walls);
+        assert_eq!(pressed.contact, Some(Vec2::X));
+        let away = substep(player(Vec2::ZERO), Vec2::new(-10.0, -10.0), &walls);
+        assert_eq!(away.contact, None);
+    }
+
+    #[test]
+    fn clipped_corners_are_slipped_around() {
+        let walls = walls(&[(1, 0)]);
+        // Overlapping the corner by 4 pixels is forgiven
+        assert_eq!(
+            slide(
+                player(Vec2::new(0.0, 20.0)),
+                &mut Vec2::new(10.0, 0.0),
+                &walls
+            )
+            .position,
+            Vec2::new(10.0, 24.0)
+        );
+        // 12 pixels isn't
+        assert_eq!(
+            slide(
+                player(Vec2::new(0.0, 12.0)),
+                &mut Vec2::new(10.0, 0.0),
+                &walls
+            )
+            .position,
+            Vec2::new(0.0, 12.0)
+        );
+    }
+
+    #[test]
+    fn fast_movement_doesnt_tunnel() {
+        let walls = walls(&[(2, 0)]);
+        // Would land past the wall in a single step
+        assert_eq!(
+            slide(player(Vec2::ZERO), &mut Vec2::new(80.0, 0.0), &walls).position,
+            Vec2::new(80.0, 0.0)
+        );
+        assert_eq!(
+            substep(player(Vec2::ZERO), Vec2::new(80.0, 0.0), &walls).position,
+            Vec2::new(24.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn diagonal_movement_slides_along_walls() {
+        let walls = walls(&[(1, -4), (1, -3), (1, -2), (1, -1), (1, 0), (1, 1)]);
+        assert_eq!(
+            substep(player(Vec2::ZERO), Vec2::new(40.0, 40.0), &walls).position,
+            Vec2::new(0.0, 40.0)
+        );
+    }
+
+    #[test]
+    fn gravity_pulls_against_thrust() {
+        // Falling speeds up until it hits the top speed
+        let falling = accelerate(Vec2::ZERO, Vec2::ZERO, 500.0, 0.5);
+        assert_eq!(falling, Vec2::new(0.0, -300.0));
+        let falling = accelerate(falling, Vec2::ZERO, 500.0, 1.0);
+        assert!((falling.y + 500.0).abs() < 1e-3);
+        // Full thrust upwards beats gravity, and sideways thrust drifts down
+        assert!(accelerate(Vec2::ZERO, Vec2::Y, 500.0, 0.1).y > 0.0);
+        let drifting = accelerate(Vec2::ZERO, Vec2::X, 500.0, 0.1);
+        assert!(drifting.x > 0.0 && drifting.y < 0.0);
+    }
+
+    #[test]
+    fn corridors_can_be_entered_off_center() {
+        // Vertical corridor one tile wide at x = 0, entered from below
+        let walls = walls(&[(-1, 0), (1, 0)]);
+        assert_eq!(
+            slide(
+                player(Vec2::new(5.0, -24.0)),
+                &mut Vec2::new(0.0, 10.0),
+                &walls
+            )
+            .position,
+            Vec2::new(0.0, -14.0)
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{http, save::SaveData, ui::GameFont, AppState};
+use bevy::prelude::*;
+use std::{
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+};
+
+const CHANGELOG: &str = include_str!("../assets/changelog.txt");
+// Releases shown in the panel, newest first
+const SHOWN_RELEASES: usize = 3;
+const UNREAD_COLOR: Color = Color::rgb(1.0, 0.8, 0.1);
+
+// A "What's new" panel on the main menu, listing the changelog bundled with the game or a
+// newer one fetched from the URL in the save file. A dot on its button shows until the
+// newest release has been read.
+pub struct NewsPlugin;
+
+impl Plugin for NewsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Changelog::parse(CHANGELOG))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Menu)
+                    .with_system(fetch_news)
+                    .with_system(create_news_button),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu)
+                    .with_system(receive_news)
+                    .with_system(manage_news_buttons)
+                    .with_system(show_unread),
+            );
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub changes: Vec<String>,
+}
+
+// Releases, newest first
+#[derive(Debug, PartialEq)]
+pub struct Changelog(Vec<Release>);
+
+impl Changelog {
+    // Each release starts with a `# version` line, followed by its changes one per line,
+    // with or without a leading dash
+    fn parse(text: &str) -> Self {
+        let mut releases: Vec<Release> = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(version) = line.strip_prefix('#') {
+                releases.push(Release {
+                    version: version.trim().to_string(),
+                    changes: Vec::new(),
+                });
+            } else if let Some(release) = releases.last_mut() {
+                let change = line.strip_prefix('-').unwrap_or(line).trim();
+                release.changes.push(change.to_string());
+            }
+        }
+        Self(releases)
+    }
+
+    fn latest(&self) -> Option<&str> {
+        self.0.first().map(|release| release.version.as_str())
+    }
+
+    fn is_unread(&self, save: &SaveData) -> bool {
+        self.latest().is_some() && self.latest() != save.news_seen.as_deref()
+    }
+
+    fn text(&self) -> String {
+        self.0
+            .iter()
+            .take(SHOWN_RELEASES)
+            .map(|release| {
+                let changes: Vec<_> = release
+                    .changes
+                    .iter()
+                    .map(|change| format!("- {change}"))
+                    .collect();
+                format!("{}\n{}", release.version, changes.join("\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+// The changelog being fetched, once per session
+struct PendingNews(Mutex<Receiver<String>>);
+
+#[derive(Component)]
+struct NewsButton;
+
+#[derive(Component)]
+struct UnreadDot;
+
+#[derive(Component)]
+struct NewsPanel;
+
+#[derive(Component)]
+struct NewsText;
+
+#[derive(Component)]
+struct CloseNewsButton;
+
+// Fetched in the background so the menu doesn't wait on the network
+fn fetch_news(mut commands: Commands, save: Res<SaveData>, mut fetched: Local<bool>) {
+    let url = match &save.settings.news_url {
+        Some(url) if !*fetched => url.clone(),
+        _ => return,
+    };
+    *fetched = true;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || match http::get(&url) {
+        Ok(text) => {
+            let _ = sender.send(text);
+        }
+        Err(err) => warn!("Failed to fetch news from {url}: {err}"),
+    });
+    commands.insert_resource(PendingNews(Mutex::new(receiver)));
+}
+
+fn receive_news(
+    mut commands: Commands,
+    pending: Option<Res<PendingNews>>,
+    mut changelog: ResMut<Changelog>,
+    mut text: Query<&mut Text, With<NewsText>>,
+) {
+    let received = match pending.and_then(|pending| pending.0.lock().unwrap().try_recv().ok()) {
+        Some(received) => received,
+        None => return,
+    };
+    commands.remove_resource::<PendingNews>();
+    // Anything that isn't a changelog, like an error page, leaves the bundled one
+    let fetched = Changelog::parse(&received);
+    if fetched.latest().is_some() {
+        *changelog = fetched;
+        if let Ok(mut text) = text.get_single_mut() {
+            text.sections[0].value = changelog.text();
+        }
+    }
+}
+
+fn create_news_button(mut commands: Commands, font: Res<GameFont>, changelog: Res<Changelog>) {
+    let text_style = |size| TextStyle {
+        font: font.get_handle(),
+        font_size: size,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Percent(2.0),
+                    bottom: Val::Percent(10.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Px(250.0), Val::Px(50.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(NewsButton)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("What's new", text_style(40.0), TextAlignment::default()),
+                ..TextBundle::default()
+            });
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Px(-6.0),
+                            right: Val::Px(-6.0),
+                            ..Rect::default()
+                        },
+                        size: Size::new(Val::Px(16.0), Val::Px(16.0)),
+                        display: Display::None,
+                        ..Style::default()
+                    },
+                    color: UNREAD_COLOR.into(),
+                    ..NodeBundle::default()
+                })
+                .insert(UnreadDot);
+        });
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(20.0),
+                    top: Val::Percent(15.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Percent(60.0), Val::Percent(70.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                padding: Rect::all(Val::Px(20.0)),
+                display: Display::None,
+                ..Style::default()
+            },
+            color: Color::GRAY.into(),
+            ..NodeBundle::default()
+        })
+        .insert(NewsPanel)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("What's new", text_style(50.0), TextAlignment::default()),
+                ..TextBundle::default()
+            });
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        flex_grow: 1.0,
+                        margin: Rect {
+                            top: Val::Px(10.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    text: Text::with_section(
+                        changelog.text(),
+                        text_style(26.0),
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(NewsText);
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(45.0)),
+                        align_self: AlignSelf::Center,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(CloseNewsButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Close",
+                            text_style(36.0),
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    });
+                });
+        });
+}
+
+// Opening the panel marks the newest release as read
+fn manage_news_buttons(
+    mut save: ResMut<SaveData>,
+    changelog: Res<Changelog>,
+    open: Query<&Interaction, (Changed<Interaction>, With<NewsButton>)>,
+    close: Query<&Interaction, (Changed<Interaction>, With<CloseNewsButton>)>,
+    mut panel: Query<&mut Style, With<NewsPanel>>,
+) {
+    let clicked = |interaction: &Interaction| matches!(interaction, Interaction::Clicked);
+    let display = if open.iter().any(clicked) {
+        if changelog.is_unread(&save) {
+            save.news_seen = changelog.latest().map(str::to_string);
+        }
+        Display::Flex
+    } else if close.iter().any(clicked) {
+        Display::None
+    } else {
+        return;
+    };
+    if let Ok(mut style) = panel.get_single_mut() {
+        style.display = display;
+    }
+}
+
+fn show_unread(
+    save: Res<SaveData>,
+    changelog: Res<Changelog>,
+    mut dot: Query<&mut Style, With<UnreadDot>>,
+) {
+    let display = if changelog.is_unread(&save) {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    if let Ok(mut style) = dot.get_single_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changelogs_are_parsed() {
+        let changelog = Changelog::parse(
+            "# 0.2.0\n- Faster bees\r\n\n  Louder bumps\n# 0.1.0\n- First release\n",
+        );
+        assert_eq!(changelog.latest(), Some("0.2.0"));
+        assert_eq!(
+            changelog.0[0].changes,
+            vec!["Faster bees".to_string(), "Louder bumps".to_string()]
+        );
+        assert_eq!(
+            changelog.text(),
+            "0.2.0\n- Faster bees\n- Louder bumps\n\n0.1.0\n- First release"
+        );
+        // Lines before the first version aren't part of any release
+        assert_eq!(Changelog::parse("Hello\n").latest(), None);
+    }
+
+    #[test]
+    fn news_is_unread_until_the_newest_release_is_seen() {
+        let changelog = Changelog::parse(CHANGELOG);
+        let mut save = SaveData::default();
+        assert!(changelog.is_unread(&save));
+        save.news_seen = Some("0.0.1".to_string());
+        assert!(changelog.is_unread(&save));
+        save.news_seen = changelog.latest().map(str::to_string);
+        assert!(!changelog.is_unread(&save));
+        assert!(!Changelog::parse("").is_unread(&SaveData::default()));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::{
+    change_state, pack::LevelRegistry, profile::Profile, save::SaveData, settings::ControlScheme,
+    ui::GameFont, upgrades::UpgradeTracker, world::Variant, AppState,
+};
+use bevy::prelude::*;
+
+// Walks new players through picking a name and a control scheme, then drops them into the
+// tutorial level. Shown for new profiles, and again from the settings screen.
+pub struct OnboardingPlugin;
+
+impl Plugin for OnboardingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::NameEntry).with_system(create_name_entry))
+            .add_system_set(
+                SystemSet::on_update(AppState::NameEntry)
+                    .with_system(type_name)
+                    .with_system(manage_name_buttons)
+                    .with_system(manage_skip_button),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::ControlSelect).with_system(create_control_select),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::ControlSelect)
+                    .with_system(manage_control_buttons)
+                    .with_system(manage_skip_button),
+            );
+    }
+}
+
+const MAX_NAME_LENGTH: usize = 16;
+// The first built-in level teaches the basics
+const TUTORIAL_LEVEL: usize = 0;
+
+#[derive(Component)]
+struct NameText;
+
+#[derive(Component)]
+struct ContinueButton;
+
+#[derive(Component)]
+struct SkipButton;
+
+#[derive(Component)]
+struct ControlSchemeButton(ControlScheme);
+
+// Apply a typed character to a name, deleting on backspace and ignoring other control
+// characters and anything past the length limit
+fn edit_name(name: &mut String, typed: char) {
+    match typed {
+        '\u{8}' | '\u{7f}' => {
+            name.pop();
+        }
+        _ if typed.is_control() => {}
+        _ if name.chars().count() >= MAX_NAME_LENGTH => {}
+        _ => name.push(typed),
+    }
+}
+
+fn spawn_title(parent: &mut ChildBuilder, font: &GameFont, title: &str) {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Percent(5.0),
+                ..Rect::default()
+            },
+            ..Style::default()
+        },
+        text: Text::with_section(
+            title,
+            TextStyle {
+                font: font.get_handle(),
+                font_size: 70.0,
+                ..TextStyle::default()
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    font: &GameFont,
+    label: &str,
+    size: Size<Val>,
+    button: impl Component,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size,
+                margin: Rect::all(Val::Px(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    label,
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 40.0,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment {
+                        horizontal: HorizontalAlign::Center,
+                        ..TextAlignment::default()
+                    },
+                ),
+                ..TextBundle::default()
+            });
+        });
+}
+
+fn spawn_screen(commands: &mut Commands, build: impl FnOnce(&mut ChildBuilder)) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(build);
+}
+
+fn create_name_entry(mut commands: Commands, font: Res<GameFont>, save: Res<SaveData>) {
+    let name = save.player_name.clone().unwrap_or_default();
+    let button_size = Size::new(Val::Px(300.0), Val::Px(65.0));
+    spawn_screen(&mut commands, |parent| {
+        spawn_title(parent, &font, "Welcome to Beeline");
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "What should we call you?",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 45.0,
+                    ..TextStyle::default()
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+        parent
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(20.0)),
+                    ..Style::default()
+                },
+                text: Text::with_section(
+                    format!("{name}_"),
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 60.0,
+                        color: Color::rgb(0.98, 0.82, 0.48),
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            })
+            .insert(NameText);
+
+        spawn_button(parent, &font, "Continue", button_size, ContinueButton);
+        spawn_button(parent, &font, "Skip", button_size, SkipButton);
+    });
+}
+
+fn type_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut save: ResMut<SaveData>,
+    mut text: Query<&mut Text, With<NameText>>,
+) {
+    let mut name = save.player_name.clone().unwrap_or_default();
+    let before = name.clone();
+    for character in characters.iter() {
+        edit_name(&mut name, character.char);
+    }
+    if name == before {
+        return;
+    }
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = format!("{name}_");
+    }
+    save.player_name = (!name.is_empty()).then_some(name);
+}
+
+// A new player's profile is made as soon as they're past giving their name
+fn ensure_profile(commands: &mut Commands, save: &SaveData, profile: Option<&Profile>) {
+    if profile.is_some() {
+        return;
+    }
+    match Profile::create(save.player_name.as_deref().unwrap_or_default()) {
+        Ok(profile) => {
+            save.write(&profile);
+            commands.insert_resource(profile);
+        }
+        Err(err) => warn!("Failed to create a profile, progress won't be saved: {err}"),
+    }
+}
+
+fn manage_name_buttons(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    save: Res<SaveData>,
+    profile: Option<Res<Profile>>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<ContinueButton>)>,
+) {
+    let clicked = interaction
+        .iter()
+        .any(|interaction| matches!(interaction, Interaction::Clicked));
+    if clicked || keys.clear_just_pressed(KeyCode::Return) {
+        ensure_profile(&mut commands, &save, profile.as_deref());
+        change_state(&mut state, AppState::ControlSelect);
+    }
+}
+
+fn create_control_select(mut commands: Commands, font: Res<GameFont>, save: Res<SaveData>) {
+    let greeting = match &save.player_name {
+        Some(name) => format!("How do you want to fly, {name}?"),
+        None => "How do you want to fly?".to_string(),
+    };
+    spawn_screen(&mut commands, |parent| {
+        spawn_title(parent, &font, &greeting);
+        for (scheme, label) in [
+            (
+                ControlScheme::Mouse,
+                "Mouse\nFly towards the cursor, click to use upgrades",
+            ),
+            (
+                ControlScheme::Keyboard,
+                "Keyboard\nWASD or arrow keys, Q and E for upgrades",
+            ),
+        ] {
+            spawn_button(
+                parent,
+                &font,
+                label,
+                Size::new(Val::Px(700.0), Val::Px(110.0)),
+                ControlSchemeButton(scheme),
+            );
+        }
+        spawn_button(
+            parent,
+            &font,
+            "Skip",
+            Size::new(Val::Px(300.0), Val::Px(65.0)),
+            SkipButton,
+        );
+    });
+}
+
+// Choosing controls finishes the introduction and starts the tutorial with no upgrades
+fn manage_control_buttons(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut save: ResMut<SaveData>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    mut levels: ResMut<LevelRegistry>,
+    interaction: Query<(&Interaction, &ControlSchemeButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in interaction.iter() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        save.settings.control_scheme = button.0;
+        save.onboarded = true;
+        match levels.load_cached(TUTORIAL_LEVEL, Variant::Original, false) {
+            Ok(world) => {
+                commands.insert_resource(world);
+                *upgrades = UpgradeTracker::default();
+                change_state(&mut state, AppState::Game);
+            }
+            Err(err) => {
+                warn!("Failed to load the tutorial level: {err}");
+                change_state(&mut state, AppState::Menu);
+            }
+        }
+    }
+}
+
+fn manage_skip_button(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut save: ResMut<SaveData>,
+    profile: Option<Res<Profile>>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<SkipButton>)>,
+) {
+    for interaction in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            save.onboarded = true;
+            ensure_profile(&mut commands, &save, profile.as_deref());
+            change_state(&mut state, AppState::Menu);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_typed_and_erased() {
+        let mut name = String::new();
+        for typed in "Bee\r\tzz\u{8}\u{8}!".chars() {
+            edit_name(&mut name, typed);
+        }
+        assert_eq!(name, "Bee!");
+
+        for typed in "abcdefghijklmnopqrstuvwxyz".chars() {
+            edit_name(&mut name, typed);
+        }
+        assert_eq!(name.chars().count(), MAX_NAME_LENGTH);
+        assert!(name.starts_with("Bee!abc"));
+    }
+}
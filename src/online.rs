@@ -0,0 +1,264 @@
+use crate::{
+    challenges::ActiveChallenges,
+    ghost::{Ghost, GhostRecorder, SpawnGhost},
+    http,
+    pack::LevelRegistry,
+    replay::ReplayPlayback,
+    save::SaveData,
+    world::{GameWorld, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+// Racing the fastest run anyone has shared. Runs go to the ghost server set in the save
+// file, so nothing is sent without one. The server keeps the record ghost for each level at
+// {server}/ghosts/{level}, answering GETs with it and taking finished runs POSTed there.
+
+const RECORD_GHOST_COLOR: Color = Color::rgba(0.4, 0.8, 1.0, 0.5);
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+        app.init_resource::<RecordGhosts>()
+            .insert_resource(Uploads {
+                sender: Mutex::new(sender),
+                receiver: Mutex::new(receiver),
+            })
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(fetch_record_ghost))
+            .add_system_set(SystemSet::on_update(AppState::Game).with_system(receive_record_ghost))
+            .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(upload_ghost))
+            .add_system(receive_uploads);
+    }
+}
+
+// Record ghosts fetched this session by URL, None where the server didn't have a usable one
+#[derive(Default)]
+struct RecordGhosts(HashMap<String, Option<Ghost>>);
+
+// The record ghost being fetched for the level being played
+struct PendingRecord {
+    url: String,
+    ghost: Mutex<Receiver<Option<Ghost>>>,
+}
+
+// URLs runs have been accepted at, which can have a new record ghost by now
+struct Uploads {
+    sender: Mutex<Sender<String>>,
+    receiver: Mutex<Receiver<String>>,
+}
+
+// Where the record ghost for a level is kept. The layout hash is part of it, so edited
+// levels and packs that reuse a name don't share records.
+fn ghost_url(server: &str, level: &str, hash: u64) -> String {
+    let level: String = level
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!(
+        "{}/ghosts/{level}-{hash:016x}",
+        server.trim_end_matches('/')
+    )
+}
+
+// The record ghost URL for the level being played. Records are only kept for levels played
+// without challenges, where runs can be compared.
+fn record_url(
+    save: &SaveData,
+    world: &GameWorld,
+    levels: &LevelRegistry,
+    challenges: &ActiveChallenges,
+) -> Option<String> {
+    let server = save.settings.ghost_server.as_ref()?;
+    match world.world_type {
+        WorldType::Level { index } if challenges.is_empty() => {
+            let level = world
+                .variant
+                .level_name(&levels.levels[index].display_name());
+            Some(ghost_url(server, &level, world.hash()))
+        }
+        _ => None,
+    }
+}
+
+// Ghost::decode checks the version and MAX_SAMPLES, so whatever the server sends back goes
+// no further than a ghost file from disk would
+fn parse_record(url: &str, bytes: &[u8]) -> Option<Ghost> {
+    match Ghost::decode(bytes) {
+        Ok(ghost) if ghost.duration() > 0.0 => Some(ghost),
+        Ok(_) => None,
+        Err(err) => {
+            warn!("Ignoring the record ghost from {url}: {err}");
+            None
+        }
+    }
+}
+
+// Fetched in the background so the level doesn't wait on the network, once per level each
+// session
+#[allow(clippy::too_many_arguments)]
+fn fetch_record_ghost(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    records: Res<RecordGhosts>,
+    pending: Option<Res<PendingRecord>>,
+    mut ghosts: EventWriter<SpawnGhost>,
+) {
+    let url = match record_url(&save, &world, &levels, &challenges) {
+        Some(url) => url,
+        None => return,
+    };
+    match records.0.get(&url) {
+        Some(Some(ghost)) => ghosts.send(SpawnGhost {
+            ghost: ghost.clone(),
+            color: RECORD_GHOST_COLOR,
+        }),
+        Some(None) => {}
+        // Already on its way from an earlier attempt
+        None if pending.is_some_and(|pending| pending.url == url) => {}
+        None => {
+            let (sender, receiver) = mpsc::channel();
+            let fetching = url.clone();
+            thread::spawn(move || {
+                let ghost = match http::get_bytes(&fetching) {
+                    Ok(bytes) => parse_record(&fetching, &bytes),
+                    Err(err) => {
+                        warn!("Failed to fetch the record ghost from {fetching}: {err}");
+                        None
+                    }
+                };
+                let _ = sender.send(ghost);
+            });
+            commands.insert_resource(PendingRecord {
+                url,
+                ghost: Mutex::new(receiver),
+            });
+        }
+    }
+}
+
+// Race the record ghost as soon as it arrives, if it's still for the level being played
+#[allow(clippy::too_many_arguments)]
+fn receive_record_ghost(
+    mut commands: Commands,
+    pending: Option<Res<PendingRecord>>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    mut records: ResMut<RecordGhosts>,
+    mut ghosts: EventWriter<SpawnGhost>,
+) {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    let ghost = match pending.ghost.lock().unwrap().try_recv() {
+        Ok(ghost) => ghost,
+        Err(_) => return,
+    };
+    commands.remove_resource::<PendingRecord>();
+    if let Some(ghost) = &ghost {
+        if record_url(&save, &world, &levels, &challenges).as_ref() == Some(&pending.url) {
+            ghosts.send(SpawnGhost {
+                ghost: ghost.clone(),
+                color: RECORD_GHOST_COLOR,
+            });
+        }
+    }
+    records.0.insert(pending.url.clone(), ghost);
+}
+
+// Send the run in the background. The server decides what counts as a record, but runs
+// slower than the record that was fetched aren't worth sending.
+#[allow(clippy::too_many_arguments)]
+fn upload_ghost(
+    recorder: Res<GhostRecorder>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    records: Res<RecordGhosts>,
+    uploads: Res<Uploads>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    // Slowed down runs are easier, so they don't compete for records
+    if playback.is_some() || save.settings.game_speed != 100 {
+        return;
+    }
+    let (url, ghost) = match (
+        record_url(&save, &world, &levels, &challenges),
+        recorder.ghost(),
+    ) {
+        (Some(url), Some(ghost)) => (url, ghost),
+        _ => return,
+    };
+    if let Some(Some(record)) = records.0.get(&url) {
+        if record.duration() <= ghost.duration() {
+            return;
+        }
+    }
+
+    let body = ghost.encode();
+    let sender = uploads.sender.lock().unwrap().clone();
+    thread::spawn(move || match http::post_bytes(&url, &body) {
+        Ok(()) => {
+            let _ = sender.send(url);
+        }
+        Err(err) => warn!("Failed to upload ghost to {url}: {err}"),
+    });
+}
+
+// Forget the record a run was sent in against, so the next attempt races the new one
+fn receive_uploads(uploads: Res<Uploads>, mut records: ResMut<RecordGhosts>) {
+    for url in uploads.receiver.lock().unwrap().try_iter() {
+        records.0.remove(&url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_urls_name_the_level_and_layout() {
+        assert_eq!(
+            ghost_url("http://example.com/beeline/", "Level 3 (Mirrored)", 0xbee),
+            "http://example.com/beeline/ghosts/Level_3__Mirrored_-0000000000000bee"
+        );
+    }
+
+    #[test]
+    fn unusable_records_are_ignored() {
+        let url = "http://example.com/ghosts/level";
+        let mut bytes = Ghost::default().encode();
+        bytes[6..10].copy_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        assert!(parse_record(url, &bytes).is_some());
+
+        assert_eq!(parse_record(url, &Ghost::default().encode()), None);
+        assert_eq!(parse_record(url, b"<html>Not found</html>"), None);
+
+        // From a newer version of the game
+        let mut bytes = Ghost::default().encode();
+        bytes[4..6].copy_from_slice(&99u16.to_le_bytes());
+        assert_eq!(parse_record(url, &bytes), None);
+
+        // Claiming more samples than a recording can hold
+        let mut bytes = Ghost::default().encode();
+        bytes[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(parse_record(url, &bytes), None);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::world::{GameWorld, Variant, WorldType, LEVELS};
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// Directory searched for level packs at startup, one pack per subdirectory
+const PACKS_DIRECTORY: &str = "packs";
+const MANIFEST_FILE: &str = "manifest.tsv";
+
+pub struct PackPlugin;
+
+impl Plugin for PackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelRegistry>()
+            .add_asset::<LevelFile>()
+            .init_asset_loader::<LevelFileLoader>();
+
+        // Pick up edits to the built-in levels while developing
+        if cfg!(debug_assertions) {
+            let asset_server = app.world.get_resource::<AssetServer>().unwrap();
+            if let Err(err) = asset_server.watch_for_changes() {
+                warn!("Level hot reloading is unavailable: {err:?}");
+            }
+            app.add_startup_system(watch_builtin_levels)
+                .add_system(reload_levels);
+        }
+    }
+}
+
+// Source of a level, loaded through the asset server so it can be hot reloaded
+#[derive(TypeUuid)]
+#[uuid = "2f6b8e0c-7d4a-4c1e-9b35-8a1f0d6e4c72"]
+pub struct LevelFile(pub String);
+
+#[derive(Default)]
+struct LevelFileLoader;
+
+impl AssetLoader for LevelFileLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let source = String::from_utf8(bytes.to_vec())?;
+            load_context.set_default_asset(LoadedAsset::new(LevelFile(source)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tsv"]
+    }
+}
+
+pub struct Level {
+    pub name: String,
+    // Name of the pack or seasonal event the level came from, None for built-in levels
+    pub pack: Option<String>,
+    source: String,
+    // Rhai script attached to the level
+    script: Option<String>,
+    // Sprite overrides, from the game's asset path to the pack's file
+    sprites: HashMap<String, PathBuf>,
+    // Watched file the source is reloaded from, in development builds
+    file: Option<Handle<LevelFile>>,
+}
+
+impl Level {
+    // A level from outside a pack, like a seasonal event's, shown under `group`
+    pub fn new(name: String, group: String, source: String) -> Self {
+        Self {
+            name,
+            pack: Some(group),
+            source,
+            script: None,
+            sprites: HashMap::new(),
+            file: None,
+        }
+    }
+
+    // Name shown in level select
+    pub fn display_name(&self) -> String {
+        match &self.pack {
+            Some(pack) => format!("{pack}: {}", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+// Every playable level, built-in levels first followed by discovered packs
+pub struct LevelRegistry {
+    pub levels: Vec<Level>,
+    // Parsed levels, keyed by index, variant and whether they're mirrored
+    cache: HashMap<(usize, Variant, bool), Arc<GameWorld>>,
+}
+
+impl LevelRegistry {
+    pub fn load(&self, index: usize) -> io::Result<GameWorld> {
+        let level = &self.levels[index];
+        let mut world = GameWorld::parse(&level.source, WorldType::Level { index })?;
+        world.sprites = level.sprites.clone();
+        world.script = level.script.clone();
+        #[cfg(not(feature = "scripting"))]
+        if level.script.is_some() {
+            warn!(
+                "{} has a script, which only runs with the scripting feature",
+                level.name
+            );
+        }
+        Ok(world)
+    }
+
+    // Load a level in the given orientation, reusing it if it's been loaded before
+    pub fn load_cached(
+        &mut self,
+        index: usize,
+        variant: Variant,
+        mirrored: bool,
+    ) -> io::Result<Arc<GameWorld>> {
+        if let Some(world) = self.cache.get(&(index, variant, mirrored)) {
+            return Ok(world.clone());
+        }
+
+        let mut world = self.load(index)?;
+        variant.apply(&mut world);
+        if mirrored {
+            world.mirror();
+        }
+        let world = Arc::new(world);
+        self.cache.insert((index, variant, mirrored), world.clone());
+        Ok(world)
+    }
+
+    // Replace a level's source, dropping any parsed copies of the old one
+    fn set_source(&mut self, index: usize, source: String) {
+        self.levels[index].source = source;
+        self.cache.retain(|(cached, ..), _| *cached != index);
+    }
+}
+
+impl Default for LevelRegistry {
+    fn default() -> Self {
+        let mut levels: Vec<Level> = LEVELS
+            .iter()
+            .map(|(name, _, source)| Level {
+                name: name.to_string(),
+                pack: None,
+                source: source.to_string(),
+                script: None,
+                sprites: HashMap::new(),
+                file: None,
+            })
+            .collect();
+
+        if let Ok(entries) = fs::read_dir(PACKS_DIRECTORY) {
+            let mut directories: Vec<PathBuf> = entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.is_dir())
+                .collect();
+            directories.sort();
+
+            for directory in directories {
+                match load_pack(&directory) {
+                    Ok(pack) => levels.extend(pack),
+                    Err(err) => warn!("Skipping pack {}: {err}", directory.display()),
+                }
+            }
+        }
+
+        Self {
+            levels,
+            cache: HashMap::new(),
+        }
+    }
+}
+
+fn watch_builtin_levels(asset_server: Res<AssetServer>, mut registry: ResMut<LevelRegistry>) {
+    for (level, (_, path, _)) in registry.levels.iter_mut().zip(LEVELS) {
+        level.file = Some(asset_server.load(path));
+    }
+}
+
+fn reload_levels(
+    mut events: EventReader<AssetEvent<LevelFile>>,
+    files: Res<Assets<LevelFile>>,
+    mut registry: ResMut<LevelRegistry>,
+) {
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+        let index = registry
+            .levels
+            .iter()
+            .position(|level| level.file.as_ref() == Some(handle));
+        if let (Some(index), Some(file)) = (index, files.get(handle)) {
+            info!("Reloading {}", registry.levels[index].name);
+            registry.set_source(index, file.0.clone());
+        }
+    }
+}
+
+// Read a pack's manifest, a tab-separated file with one entry per line:
+//   name    <pack name>
+//   level   <level name>    <level file>    [Rhai script file]
+//   sprite  <game asset>    <replacement file>
+// Files are relative to the pack directory
+fn load_pack(directory: &Path) -> io::Result<Vec<Level>> {
+    let manifest = fs::read_to_string(directory.join(MANIFEST_FILE))?;
+    let invalid = |line: usize, reason: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{MANIFEST_FILE} line {line}: {reason}"),
+        )
+    };
+
+    let mut name = None;
+    let mut levels = Vec::new();
+    let mut sprites = HashMap::new();
+    for (i, line) in manifest.lines().enumerate() {
+        match line.split('\t').collect::<Vec<_>>().as_slice() {
+            [""] => {}
+            ["name", pack_name] => name = Some(pack_name.to_string()),
+            ["level", level_name, file, script @ ..] if script.len() <= 1 => levels.push((
+                level_name.to_string(),
+                fs::read_to_string(directory.join(file))?,
+                match script.first() {
+                    Some(script) => Some(fs::read_to_string(directory.join(script))?),
+                    None => None,
+                },
+            )),
+            ["sprite", asset, file] => {
+                sprites.insert(asset.to_string(), fs::canonicalize(directory.join(file))?);
+            }
+            _ => return Err(invalid(i + 1, &format!("invalid entry {line:?}"))),
+        }
+    }
+
+    let name = name.ok_or_else(|| invalid(1, "missing pack name"))?;
+    Ok(levels
+        .into_iter()
+        .map(|(level_name, source, script)| Level {
+            name: level_name,
+            pack: Some(name.clone()),
+            source,
+            script,
+            sprites: sprites.clone(),
+            file: None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_manifest_is_loaded() {
+        let directory = std::env::temp_dir().join("beeline-test-pack");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join(MANIFEST_FILE),
+            "name\tTest Pack\nlevel\tFirst\tfirst.tsv\nsprite\tgoal.png\thoney.png\n",
+        )
+        .unwrap();
+        fs::write(directory.join("first.tsv"), "*\tG\n").unwrap();
+        fs::write(directory.join("honey.png"), "").unwrap();
+
+        let levels = load_pack(&directory).unwrap();
+        assert_eq!(levels.len(), 1);
+        assert_eq!(levels[0].display_name(), "Test Pack: First");
+        assert_eq!(
+            levels[0].sprites["goal.png"],
+            fs::canonicalize(directory.join("honey.png")).unwrap()
+        );
+
+        fs::write(directory.join(MANIFEST_FILE), "level\tFirst\tfirst.tsv\n").unwrap();
+        assert!(load_pack(&directory).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn cached_levels_are_refreshed_on_edit() {
+        let mut registry = LevelRegistry::default();
+        let first = registry.load_cached(0, Variant::Original, false).unwrap();
+        let again = registry.load_cached(0, Variant::Original, false).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        let mirrored = registry.load_cached(0, Variant::Original, true).unwrap();
+        assert!(!Arc::ptr_eq(&first, &mirrored));
+
+        registry.set_source(0, "*\tG\n".into());
+        let edited = registry.load_cached(0, Variant::Original, false).unwrap();
+        assert!(!Arc::ptr_eq(&first, &edited));
+        assert_eq!(edited.serialize(), "*\tG\n");
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::{HashMap, VecDeque};
+
+// Shortest path between two tiles moving in the four directions, including both ends.
+// Tiles outside the area `is_open` accepts are never entered.
+pub fn find_path(
+    start: (usize, usize),
+    goal: (usize, usize),
+    is_open: impl Fn((usize, usize)) -> bool,
+) -> Option<Vec<(usize, usize)>> {
+    // Tile each visited tile was first reached from
+    let mut came_from = HashMap::from([(start, start)]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(current) = queue.pop_front() {
+        if current == goal {
+            let mut path = vec![goal];
+            let mut tile = goal;
+            while tile != start {
+                tile = came_from[&tile];
+                path.push(tile);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let (x, y) = current;
+        let neighbors = [
+            (x.checked_sub(1), Some(y)),
+            (Some(x + 1), Some(y)),
+            (Some(x), y.checked_sub(1)),
+            (Some(x), Some(y + 1)),
+        ];
+        for neighbor in neighbors {
+            if let (Some(x), Some(y)) = neighbor {
+                if is_open((x, y)) && !came_from.contains_key(&(x, y)) {
+                    came_from.insert((x, y), current);
+                    queue.push_back((x, y));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // '#' is blocked, anything else in the grid is open
+    fn grid(rows: &'static [&'static str]) -> impl Fn((usize, usize)) -> bool {
+        move |(x, y)| {
+            rows.get(y)
+                .and_then(|row| row.as_bytes().get(x))
+                .is_some_and(|tile| *tile != b'#')
+        }
+    }
+
+    #[test]
+    fn paths_go_around_walls() {
+        let open = grid(&["...", "##.", "..."]);
+        assert_eq!(
+            find_path((0, 0), (0, 2), open),
+            Some(vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2)])
+        );
+    }
+
+    #[test]
+    fn enclosed_goals_are_unreachable() {
+        let open = grid(&["..#.", "..#.", "..#."]);
+        assert_eq!(find_path((0, 0), (3, 1), &open), None);
+        assert_eq!(find_path((1, 1), (1, 1), &open), Some(vec![(1, 1)]));
+    }
+}
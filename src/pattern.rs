@@ -0,0 +1,225 @@
+use bevy::prelude::*;
+use std::fmt;
+
+// Bullet patterns, written in level metadata as `@pattern\t<name>\t<steps>` with steps
+// separated by semicolons, e.g. `spiral 12 0.5 0.05; wait 1; fan 5 1.2`.
+// Angles are in radians relative to the spawner's own aim, and patterns loop forever.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern(Vec<Step>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    // A single shot
+    Fire,
+    // Shots spread evenly across an arc centered on the aim
+    Fan {
+        count: u32,
+        spread: f32,
+    },
+    // Shots one after another
+    Burst {
+        count: u32,
+        interval: f32,
+    },
+    // Like a burst, but turning between shots
+    Spiral {
+        count: u32,
+        turn: f32,
+        interval: f32,
+    },
+    Turn(f32),
+    Wait(f32),
+}
+
+impl Pattern {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let steps = source
+            .split(';')
+            .map(|step| Step::parse(step.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Without any time passing the pattern would fire forever within a single frame
+        if !steps.iter().any(|step| step.duration() > 0.0) {
+            return Err(format!("pattern {source:?} never waits"));
+        }
+        Ok(Self(steps))
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps: Vec<String> = self.0.iter().map(Step::to_string).collect();
+        write!(f, "{}", steps.join("; "))
+    }
+}
+
+impl Step {
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut words = source.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        let invalid = || format!("invalid pattern step {source:?}");
+
+        let count = |arg: &str| {
+            arg.parse::<u32>()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(invalid)
+        };
+        let number = |arg: &str| {
+            arg.parse::<f32>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .ok_or_else(invalid)
+        };
+        let seconds =
+            |arg: &str| number(arg).and_then(|n| (n >= 0.0).then_some(n).ok_or_else(invalid));
+
+        Ok(match (name, args.as_slice()) {
+            ("fire", []) => Step::Fire,
+            ("fan", [n, spread]) => Step::Fan {
+                count: count(n)?,
+                spread: number(spread)?,
+            },
+            ("burst", [n, interval]) => Step::Burst {
+                count: count(n)?,
+                interval: seconds(interval)?,
+            },
+            ("spiral", [n, turn, interval]) => Step::Spiral {
+                count: count(n)?,
+                turn: number(turn)?,
+                interval: seconds(interval)?,
+            },
+            ("turn", [angle]) => Step::Turn(number(angle)?),
+            ("wait", [time]) => Step::Wait(seconds(time)?),
+            _ => return Err(invalid()),
+        })
+    }
+
+    // Seconds the step takes before the next one starts
+    fn duration(&self) -> f32 {
+        match *self {
+            Step::Burst { count, interval }
+            | Step::Spiral {
+                count, interval, ..
+            } => (count - 1) as f32 * interval,
+            Step::Wait(seconds) => seconds,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Fire => write!(f, "fire"),
+            Step::Fan { count, spread } => write!(f, "fan {count} {spread}"),
+            Step::Burst { count, interval } => write!(f, "burst {count} {interval}"),
+            Step::Spiral {
+                count,
+                turn,
+                interval,
+            } => write!(f, "spiral {count} {turn} {interval}"),
+            Step::Turn(angle) => write!(f, "turn {angle}"),
+            Step::Wait(seconds) => write!(f, "wait {seconds}"),
+        }
+    }
+}
+
+// Shots further behind than this are dropped, so a spawner waking up from sleeping
+// out of range doesn't unload everything it missed at once
+const MAX_CATCH_UP: f64 = 0.25;
+
+// Plays a pattern on a spawner, in place of its cooldown
+#[derive(Component, Clone, Debug)]
+pub struct PatternRunner {
+    pattern: Pattern,
+    step: usize,
+    // Shots fired so far in the current step
+    shot: u32,
+    // SpawnerClock time the runner continues at
+    pub next: f64,
+    // Turned so far, relative to the spawner's aim
+    aim: f32,
+}
+
+impl PatternRunner {
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            step: 0,
+            shot: 0,
+            next: 0.0,
+            aim: 0.0,
+        }
+    }
+
+    // Angles, relative to the spawner's aim, of the shots fired up to the given time
+    pub fn run(&mut self, now: f64) -> Vec<f32> {
+        let mut shots = Vec::new();
+        if self.next < now - MAX_CATCH_UP {
+            self.next = now;
+        }
+
+        while self.next <= now {
+            let mut finished = true;
+            match self.pattern.0[self.step] {
+                Step::Fire => shots.push(self.aim),
+                Step::Fan { count: 1, .. } => shots.push(self.aim),
+                Step::Fan { count, spread } => {
+                    let gap = spread / (count - 1) as f32;
+                    let first = self.aim - spread / 2.0;
+                    shots.extend((0..count).map(|i| first + gap * i as f32));
+                }
+                Step::Burst { count, interval }
+                | Step::Spiral {
+                    count, interval, ..
+                } => {
+                    shots.push(self.aim);
+                    if let Step::Spiral { turn, .. } = self.pattern.0[self.step] {
+                        self.aim += turn;
+                    }
+                    self.shot += 1;
+                    if self.shot < count {
+                        self.next += interval as f64;
+                        finished = false;
+                    }
+                }
+                Step::Turn(angle) => self.aim += angle,
+                Step::Wait(seconds) => self.next += seconds as f64,
+            }
+            if finished {
+                self.shot = 0;
+                self.step = (self.step + 1) % self.pattern.0.len();
+            }
+        }
+        shots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_round_trip() {
+        let source = "fire; fan 3 1.5; burst 2 0.1; spiral 4 0.25 0.05; turn -1; wait 0.5";
+        assert_eq!(Pattern::parse(source).unwrap().to_string(), source);
+
+        assert!(Pattern::parse("fan 3").is_err());
+        assert!(Pattern::parse("wait -1").is_err());
+        assert!(Pattern::parse("loop; wait 1").is_err());
+        assert!(Pattern::parse("fire; turn 0.1").is_err());
+    }
+
+    #[test]
+    fn runner_follows_the_pattern() {
+        let pattern = Pattern::parse("fan 3 2; spiral 3 0.5 0.125; wait 1").unwrap();
+        let mut runner = PatternRunner::new(pattern);
+        assert_eq!(runner.run(0.0), vec![-1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(runner.run(0.2), vec![0.5]);
+        assert_eq!(runner.run(0.25), vec![1.0]);
+        // Waits until 1.25, then loops back to the fan with the aim turned
+        assert!(runner.run(1.2).is_empty());
+        assert_eq!(runner.run(1.25), vec![0.5, 1.5, 2.5, 1.5]);
+    }
+}
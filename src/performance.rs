@@ -0,0 +1,174 @@
+use crate::{
+    animation::{AnimationState, SpriteSheetAnimation},
+    playing,
+    save::SaveData,
+    ui::GameFont,
+    util::AnimationTiming,
+    world::Wall,
+};
+use bevy::prelude::*;
+
+// Low-spec mode details that aren't up to the systems they affect, and suggesting it
+// when the game runs slowly
+pub struct PerformancePlugin;
+
+impl Plugin for PerformancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameRateMonitor>()
+            .add_system(slow_down_animations)
+            .add_system(simplify_walls)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(suggest_low_spec),
+            )
+            .add_system(hide_suggestion);
+    }
+}
+
+// Animations play at a fraction of their frame rate in low-spec mode
+const LOW_SPEC_ANIMATION_SLOWDOWN: u32 = 2;
+// Plain walls, the darkest of the wall sprites
+const LOW_SPEC_WALL_COLOR: Color = Color::rgb(0.16, 0.16, 0.16);
+// Low-spec mode is suggested after spending this long below this frame rate
+const SLOW_FPS: f32 = 30.0;
+const SLOW_SECONDS: f32 = 5.0;
+// How quickly the average frame rate follows the current one, per frame
+const FPS_SMOOTHING: f32 = 0.05;
+const SUGGESTION_SECONDS: f32 = 6.0;
+
+#[derive(Default)]
+struct FrameRateMonitor {
+    average_fps: Option<f32>,
+    // Seconds the average has been below SLOW_FPS
+    slow_for: f32,
+    // Only suggested once a session
+    suggested: bool,
+}
+
+impl FrameRateMonitor {
+    // Track another frame, true once the game has been slow for long enough
+    fn update(&mut self, delta: f32) -> bool {
+        if delta <= 0.0 {
+            return false;
+        }
+        let fps = 1.0 / delta;
+        let average = match self.average_fps {
+            Some(average) => average + (fps - average) * FPS_SMOOTHING,
+            None => fps,
+        };
+        self.average_fps = Some(average);
+        self.slow_for = if average < SLOW_FPS {
+            self.slow_for + delta
+        } else {
+            0.0
+        };
+        self.slow_for >= SLOW_SECONDS
+    }
+}
+
+fn slow_down_animations(
+    save: Res<SaveData>,
+    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
+    sprites: Query<(&Handle<SpriteSheetAnimation>, &AnimationTiming), Added<AnimationTiming>>,
+    states: Query<&AnimationState, Added<AnimationState>>,
+) {
+    if !save.settings.low_spec {
+        return;
+    }
+    let clips = states.iter().flat_map(AnimationState::clips);
+    for (handle, timing) in sprites.iter().chain(clips) {
+        animations.set_untracked(handle, timing.animation(LOW_SPEC_ANIMATION_SLOWDOWN));
+    }
+}
+
+// Draw walls as flat squares, which all batch together, instead of joined up sprites
+fn simplify_walls(
+    save: Res<SaveData>,
+    mut walls: Query<(&mut Handle<Image>, &mut Sprite), Added<Wall>>,
+) {
+    if !save.settings.low_spec {
+        return;
+    }
+    for (mut texture, mut sprite) in walls.iter_mut() {
+        *texture = Handle::default();
+        sprite.color = LOW_SPEC_WALL_COLOR;
+    }
+}
+
+#[derive(Component)]
+struct LowSpecSuggestion(Timer);
+
+fn suggest_low_spec(
+    mut commands: Commands,
+    time: Res<Time>,
+    save: Res<SaveData>,
+    font: Res<GameFont>,
+    mut monitor: ResMut<FrameRateMonitor>,
+) {
+    if save.settings.low_spec || monitor.suggested || !monitor.update(time.delta_seconds()) {
+        return;
+    }
+    monitor.suggested = true;
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(8.0),
+                    left: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "Running slowly? Try low-spec mode in the settings",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(LowSpecSuggestion(Timer::from_seconds(
+            SUGGESTION_SECONDS,
+            false,
+        )));
+}
+
+fn hide_suggestion(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut suggestions: Query<(Entity, &mut LowSpecSuggestion)>,
+) {
+    for (entity, mut suggestion) in suggestions.iter_mut() {
+        if suggestion.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frame_rates_are_noticed() {
+        let mut monitor = FrameRateMonitor::default();
+        // A few slow frames among fast ones don't count
+        for _ in 0..100 {
+            assert!(!monitor.update(1.0 / 60.0));
+        }
+        assert!(!monitor.update(0.5));
+
+        let mut monitor = FrameRateMonitor::default();
+        let slow_frames = (SLOW_SECONDS * 16.0) as usize;
+        for _ in 1..slow_frames {
+            assert!(!monitor.update(1.0 / 16.0));
+        }
+        assert!(monitor.update(1.0 / 16.0));
+    }
+}
@@ -1,15 +1,28 @@
 use crate::{
-    camera::MainCamera,
-    enemy::Enemy,
+    animation::{AnimationEvent, AnimationMode, AnimationState, Motion, SpriteSheetAnimation},
+    challenges::{ActiveChallenges, Challenge},
+    change_state,
+    clock::GameClock,
+    collision::CollisionShape,
+    controls::Controls,
+    cosmetics::AttachmentPoint,
+    enemy::{Enemy, Spawning},
+    highlight::Highlight,
+    movement::{self, Velocity, WallCollider},
+    playing,
+    powerups::{self, ActivePowerUps, PowerUp},
+    render_layer::RenderLayer,
+    shadow::Shadow,
+    stamina::Stamina,
+    upgrade_effects,
     upgrades::{Upgrade, UpgradeTracker},
     util::{polar_to_cartesian, AnimatedSprite, AnimatedSpriteData},
-    world::Goal,
-    AppState,
+    wall_contact::WallContact,
+    world::{GameWorld, Goal, OpenWalls},
+    AppState, GameState,
 };
-use benimator::SpriteSheetAnimation;
-use bevy::prelude::*;
-use impacted::CollisionShape;
-use std::f32::consts::PI;
+use bevy::{math::const_vec2, prelude::*};
+use std::{f32::consts::PI, sync::Arc, time::Duration};
 
 pub struct PlayerPlugin;
 
@@ -28,8 +41,10 @@ impl Plugin for PlayerPlugin {
             SystemSet::on_enter(AppState::Game).with_system(create_invincibility_timer),
         )
         .add_system_set(
-            SystemSet::on_update(AppState::Game)
+            SystemSet::new()
+                .with_run_criteria(playing)
                 .with_system(tick_invincibility_timer)
+                .with_system(blink_while_invincible)
                 .with_system(move_player)
                 .with_system(detect_collision)
                 .with_system(teleport),
@@ -41,16 +56,103 @@ fn create_invincibility_timer(mut commands: Commands) {
     commands.insert_resource(InvincibilityTimer::default());
 }
 
-fn tick_invincibility_timer(time: Res<Time>, mut timer: ResMut<InvincibilityTimer>) {
-    timer.0.tick(time.delta());
+fn tick_invincibility_timer(clock: Res<GameClock>, mut timer: ResMut<InvincibilityTimer>) {
+    timer.0.tick(clock.delta());
+}
+
+// Make it obvious when the player can't be hurt
+fn blink_while_invincible(
+    mut commands: Commands,
+    timer: Res<InvincibilityTimer>,
+    player: Query<Entity, (With<Player>, Without<Highlight>)>,
+) {
+    if timer.0.finished() {
+        return;
+    }
+    if let Ok(player) = player.get_single() {
+        let remaining = timer.0.duration() - timer.0.elapsed();
+        commands
+            .entity(player)
+            .insert(Highlight::blink(remaining.as_secs_f32()));
+    }
 }
 
 #[derive(Component)]
 pub struct Player;
 
+// Absorbs one enemy hit
+#[derive(Component)]
+pub struct Shield;
+
+// Grace period after the shield breaks
+const SHIELD_BREAK_INVINCIBILITY: f32 = 1.0;
+// Gameplay freezes for a beat when the shield takes the hit
+const SHIELD_BREAK_HIT_STOP: Duration = Duration::from_millis(80);
+
 impl Player {
     pub const SIZE: f32 = 24.0;
+    // How high the bee flies, for its shadow
+    const HEIGHT: f32 = 12.0;
+    // Top of the bee's head in the unrotated sprite, where hats sit
+    pub const HEAD: Vec2 = const_vec2!([0.0, 6.0]);
     const VELOCITY: f32 = 500.0;
+    // Speed-ups push the bee past this, flapping faster
+    const FAST_VELOCITY: f32 = Self::VELOCITY * 1.2;
+}
+
+// The bee hovers, flies, flaps harder when sped up, flinches when the shield breaks and
+// falls apart on death
+fn player_animations(
+    animations: &mut Assets<SpriteSheetAnimation>,
+    textures: &mut Assets<TextureAtlas>,
+    asset_server: &AssetServer,
+    skin_path: &str,
+) -> AnimationState {
+    let bee = |delay, mode| {
+        AnimatedSpriteData {
+            path: skin_path.into(),
+            frames: 6,
+            delay: Duration::from_millis(delay),
+            mode,
+            ..AnimatedSpriteData::default()
+        }
+        .timing()
+    };
+    let death = AnimatedSpriteData {
+        path: "bee-dead.png".into(),
+        frames: 73,
+        size: Vec2::splat(Player::SIZE),
+        delay: Duration::from_millis(35),
+        mode: AnimationMode::Once,
+        ..AnimatedSpriteData::default()
+    };
+
+    AnimationState::new(Player::FAST_VELOCITY)
+        .with_clip(
+            animations,
+            Motion::Idle,
+            bee(180, AnimationMode::PingPong),
+            None,
+        )
+        .with_clip(
+            animations,
+            Motion::Flying,
+            bee(100, AnimationMode::Repeat),
+            None,
+        )
+        .with_clip(
+            animations,
+            Motion::FastFlying,
+            bee(50, AnimationMode::Repeat),
+            None,
+        )
+        .with_clip(animations, Motion::Hit, bee(35, AnimationMode::Once), None)
+        .with_clip(
+            animations,
+            Motion::Death,
+            death.timing(),
+            Some(death.atlas(textures, asset_server)),
+        )
 }
 
 // Spawn the player in the given start location
@@ -61,13 +163,14 @@ pub fn spawn_player(
     mut textures: ResMut<Assets<TextureAtlas>>,
     asset_server: &Res<AssetServer>,
     upgrades: Res<UpgradeTracker>,
+    skin_path: &str,
     start_location: Vec2,
-) {
+) -> Entity {
     // Define player size
     let size = Vec2::splat(Player::SIZE);
 
     let transform = Transform {
-        translation: start_location.extend(1.0),
+        translation: start_location.extend(0.0),
         scale: if upgrades.has_upgrade(Upgrade::Shrink) {
             // Half player scale if shrink upgrade is active
             Vec2::splat(0.5)
@@ -84,14 +187,17 @@ pub fn spawn_player(
         CollisionShape::new_rectangle(size.x, size.y)
     };
 
+    let animation_state =
+        player_animations(&mut animations, &mut textures, asset_server, skin_path);
+
     // Spawn player
-    commands
+    let player = commands
         .spawn_bundle(AnimatedSprite::new(
             &mut animations,
             &mut textures,
-            &asset_server,
+            asset_server,
             AnimatedSpriteData {
-                path: "bee.png".into(),
+                path: skin_path.into(),
                 frames: 6,
                 size,
                 transform,
@@ -99,92 +205,133 @@ pub fn spawn_player(
             },
         ))
         .insert(collision_shape)
-        .insert(Player);
+        .insert(WallCollider { size })
+        .insert(WallContact::default())
+        .insert(Velocity::default())
+        .insert(AttachmentPoint(Player::HEAD))
+        .insert(Shadow::new(Vec2::splat(Player::SIZE * 0.7), Player::HEIGHT))
+        .insert(animation_state)
+        .insert(Player)
+        .insert(RenderLayer::Player)
+        .id();
+    upgrade_effects::add_upgrade_effects(commands, player, &upgrades);
+
+    player
 }
 
+#[allow(clippy::too_many_arguments)]
 fn move_player(
-    windows: Res<Windows>,
-    time: Res<Time>,
+    clock: Res<GameClock>,
+    controls: Res<Controls>,
     upgrades: Res<UpgradeTracker>,
-    camera: Query<&Camera, With<MainCamera>>,
-    mut transform: Query<&mut Transform, (With<Player>, Without<MainCamera>)>,
+    power_ups: Res<ActivePowerUps>,
+    stamina: Res<Stamina>,
+    challenges: Res<ActiveChallenges>,
+    world: Res<Arc<GameWorld>>,
+    open_walls: Res<OpenWalls>,
+    mut player: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &WallCollider,
+            &mut WallContact,
+        ),
+        With<Player>,
+    >,
 ) {
-    let camera = camera.single();
-    let window = windows.get(camera.window).unwrap();
-    // Some(_) if the cursor is in the window
-    if let Some(cursor_pos) = window.cursor_position() {
-        let relative_pos = Vec2::new(
-            cursor_pos.x - window.width() / 2.,
-            cursor_pos.y - window.height() / 2.,
+    let (mut transform, mut velocity, collider, mut contact) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let seconds = clock.delta_seconds();
+    let max_speed = Player::VELOCITY * powerups::player_speed(&upgrades, &power_ups);
+    let thrust = controls
+        .aim
+        .map(|aim| polar_to_cartesian(aim, stamina.throttle(controls.throttle)))
+        .unwrap_or_default();
+
+    let gravity = challenges.is_active(Challenge::Gravity);
+    velocity.0 = if gravity {
+        movement::accelerate(velocity.0, thrust, max_speed, seconds)
+    } else {
+        thrust * max_speed
+    };
+    contact.normal = None;
+    if velocity.0 != Vec2::ZERO {
+        let start = transform.translation.truncate();
+        contact.normal = movement::move_entity(
+            &world,
+            &open_walls,
+            &mut transform,
+            Some(collider),
+            velocity.0 * seconds,
         );
-        let velocity_angle = relative_pos.y.atan2(relative_pos.x);
-        let magnitude_cap = window.width().min(window.height()) / 4.;
-        // between 0 and 1
-        let velocity_scale = relative_pos.length().min(magnitude_cap) / magnitude_cap;
-
-        let velocity = polar_to_cartesian(velocity_angle, velocity_scale * Player::VELOCITY)
-            * time.delta_seconds()
-            * if upgrades.has_upgrade(Upgrade::DoubleSpeed) {
-                // Double velocity if player has double speed upgrade
-                2.0
-            } else {
-                1.0
-            };
-
-        let mut transform = transform.single_mut();
-        transform.translation.x += velocity.x;
-        transform.translation.y += velocity.y;
-
-        transform.rotation = Quat::from_rotation_z(velocity_angle - PI / 2.0);
+        // Whatever a wall stopped is lost, so the bee lands instead of pressing into the floor
+        if gravity && seconds > 0.0 {
+            velocity.0 = (transform.translation.truncate() - start) / seconds;
+        }
+    }
+
+    if let Some(aim) = controls.aim {
+        transform.rotation = Quat::from_rotation_z(aim - PI / 2.0);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn detect_collision(
-    invincibility_timer: Res<InvincibilityTimer>,
+    mut commands: Commands,
+    mut clock: ResMut<GameClock>,
+    mut invincibility_timer: ResMut<InvincibilityTimer>,
+    power_ups: Res<ActivePowerUps>,
     mut state: ResMut<State<AppState>>,
-    enemies: Query<&CollisionShape, With<Enemy>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut animation_events: EventWriter<AnimationEvent>,
+    // Projectiles that are still spawning can't hurt the player yet
+    enemies: Query<&CollisionShape, (With<Enemy>, Without<Spawning>)>,
     goal: Query<&CollisionShape, With<Goal>>,
-    player: Query<&CollisionShape, With<Player>>,
+    player: Query<(Entity, &CollisionShape, Option<&Shield>), With<Player>>,
 ) {
     if invincibility_timer.0.finished() {
-        if let Ok(player) = player.get_single() {
+        if let Ok((entity, player, shield)) = player.get_single() {
             for enemy in enemies.iter() {
-                if player.is_collided_with(enemy) {
-                    state.set(AppState::Death).unwrap();
+                // Enemies can't hurt the player while the shield power-up lasts
+                if player.is_collided_with(enemy) && !power_ups.is_active(PowerUp::Shield) {
+                    if shield.is_some() {
+                        commands.entity(entity).remove::<Shield>();
+                        animation_events.send(AnimationEvent {
+                            entity,
+                            motion: Motion::Hit,
+                        });
+                        invincibility_timer.0 =
+                            Timer::from_seconds(SHIELD_BREAK_INVINCIBILITY, false);
+                        clock.hit_stop(SHIELD_BREAK_HIT_STOP);
+                    } else {
+                        change_state(&mut game_state, GameState::Dying);
+                    }
                     return;
                 }
             }
-            if let Ok(goal) = goal.get_single() {
-                if player.is_collided_with(goal) {
-                    state.set(AppState::Victory).unwrap();
-                }
+            if goal.iter().any(|goal| player.is_collided_with(goal)) {
+                change_state(&mut state, AppState::Victory);
             }
         }
     }
 }
 
 fn teleport(
-    windows: Res<Windows>,
-    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-    button_input: Res<Input<MouseButton>>,
+    controls: Res<Controls>,
     upgrades: Res<UpgradeTracker>,
     mut player: Query<&mut Transform, With<Player>>,
 ) {
-    if upgrades.was_upgrade_activated(button_input, Upgrade::Teleport) {
-        let (camera, camera_transform) = camera.single();
-        let window = windows.get(camera.window).unwrap();
-
-        if let Some(cursor_pos) = window.cursor_position() {
-            // Calculate the cursor's world position
-            let window_size = Vec2::new(window.width() as f32, window.height() as f32);
-            let ndc = (cursor_pos / window_size) * 2.0 - Vec2::ONE;
-            let ndc_to_world =
-                camera_transform.compute_matrix() * camera.projection_matrix.inverse();
-            let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
-
-            // Set player translation to the cursor's world position
-            let mut player_transform = player.single_mut();
-            player_transform.translation = world_pos.extend(player_transform.translation.z);
-        }
+    if let (true, Some(cursor)) = (
+        upgrades.was_upgrade_activated(&controls, Upgrade::Teleport),
+        controls.cursor,
+    ) {
+        // Set player translation to the cursor's world position
+        let mut player_transform = match player.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+        player_transform.translation = cursor.extend(player_transform.translation.z);
     }
 }
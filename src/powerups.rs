@@ -0,0 +1,269 @@
+use crate::{
+    clock::GameClock,
+    collision::CollisionShape,
+    controls::Controls,
+    despawn::DespawnQueue,
+    hud::{HudElement, HudItem},
+    player::Player,
+    playing,
+    ui::GameFont,
+    upgrades::{Upgrade, UpgradeTracker},
+    AppState,
+};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivePowerUps>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Game)
+                    .with_system(reset_power_ups)
+                    .with_system(create_power_up_hud),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(collect_pickups)
+                    .with_system(tick_power_ups)
+                    .with_system(use_slow_motion)
+                    .with_system(update_power_up_hud),
+            );
+    }
+}
+
+// Temporary effects picked up during a run, separate from the upgrade loadout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUp {
+    Speed,
+    Shield,
+    // Stored as a charge and activated with space
+    SlowMotion,
+}
+
+impl PowerUp {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "speed" => Some(PowerUp::Speed),
+            "shield" => Some(PowerUp::Shield),
+            "slow" => Some(PowerUp::SlowMotion),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            PowerUp::Speed => "speed",
+            PowerUp::Shield => "shield",
+            PowerUp::SlowMotion => "slow",
+        }
+    }
+
+    pub fn sprite(self) -> String {
+        format!("powerups/{}.png", self.code())
+    }
+
+    // Seconds the effect lasts once active
+    fn duration(self) -> f32 {
+        match self {
+            PowerUp::Speed => 10.0,
+            PowerUp::Shield => 5.0,
+            PowerUp::SlowMotion => 3.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Pickup(pub PowerUp);
+
+impl Pickup {
+    pub const SIZE: f32 = 16.0;
+    // Pickups hover just off the ground
+    pub const HEIGHT: f32 = 4.0;
+}
+
+const MAX_SLOW_MOTION_CHARGES: u32 = 3;
+const SLOW_MOTION_FACTOR: f32 = 0.3;
+
+#[derive(Debug, Default)]
+pub struct ActivePowerUps {
+    // Seconds left on each active effect
+    remaining: HashMap<PowerUp, f32>,
+    pub slow_motion_charges: u32,
+}
+
+impl ActivePowerUps {
+    // Picking up an active effect extends it, up to twice its duration.
+    // Slow motion is banked as a charge instead.
+    pub fn grant(&mut self, power_up: PowerUp) {
+        if power_up == PowerUp::SlowMotion {
+            self.slow_motion_charges = (self.slow_motion_charges + 1).min(MAX_SLOW_MOTION_CHARGES);
+            return;
+        }
+        let remaining = self.remaining.entry(power_up).or_default();
+        *remaining = (*remaining + power_up.duration()).min(power_up.duration() * 2.0);
+    }
+
+    // Spend a charge, unless slow motion is already running
+    pub fn use_slow_motion(&mut self) -> bool {
+        if self.slow_motion_charges == 0 || self.is_active(PowerUp::SlowMotion) {
+            return false;
+        }
+        self.slow_motion_charges -= 1;
+        self.remaining
+            .insert(PowerUp::SlowMotion, PowerUp::SlowMotion.duration());
+        true
+    }
+
+    pub fn tick(&mut self, seconds: f32) {
+        for remaining in self.remaining.values_mut() {
+            *remaining -= seconds;
+        }
+        self.remaining.retain(|_, remaining| *remaining > 0.0);
+    }
+
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.remaining.contains_key(&power_up)
+    }
+
+    pub fn remaining(&self, power_up: PowerUp) -> Option<f32> {
+        self.remaining.get(&power_up).copied()
+    }
+}
+
+// Player speed multiplier; the speed power-up doesn't stack with the upgrade
+pub fn player_speed(upgrades: &UpgradeTracker, power_ups: &ActivePowerUps) -> f32 {
+    if upgrades.has_upgrade(Upgrade::DoubleSpeed) || power_ups.is_active(PowerUp::Speed) {
+        2.0
+    } else {
+        1.0
+    }
+}
+
+// Enemy speed multiplier, using the strongest active slowdown
+pub fn enemy_speed(upgrades: &UpgradeTracker, power_ups: &ActivePowerUps) -> f32 {
+    if power_ups.is_active(PowerUp::SlowMotion) {
+        SLOW_MOTION_FACTOR
+    } else if upgrades.has_upgrade(Upgrade::SlowEnemies) {
+        0.5
+    } else {
+        1.0
+    }
+}
+
+fn reset_power_ups(mut power_ups: ResMut<ActivePowerUps>) {
+    *power_ups = ActivePowerUps::default();
+}
+
+fn collect_pickups(
+    mut despawn: ResMut<DespawnQueue>,
+    mut power_ups: ResMut<ActivePowerUps>,
+    player: Query<&CollisionShape, With<Player>>,
+    pickups: Query<(Entity, &CollisionShape, &Pickup)>,
+) {
+    if let Ok(player) = player.get_single() {
+        for (entity, shape, pickup) in pickups.iter() {
+            if player.is_collided_with(shape) && !despawn.contains(entity) {
+                power_ups.grant(pickup.0);
+                despawn.push(entity);
+            }
+        }
+    }
+}
+
+fn tick_power_ups(clock: Res<GameClock>, mut power_ups: ResMut<ActivePowerUps>) {
+    power_ups.tick(clock.delta_seconds());
+}
+
+fn use_slow_motion(controls: Res<Controls>, mut power_ups: ResMut<ActivePowerUps>) {
+    if controls.slow_motion.just_pressed {
+        power_ups.use_slow_motion();
+    }
+}
+
+#[derive(Component)]
+struct PowerUpHud;
+
+fn create_power_up_hud(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(2.0),
+                    right: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 35.0,
+                    ..TextStyle::default()
+                },
+                TextAlignment {
+                    horizontal: HorizontalAlign::Right,
+                    ..TextAlignment::default()
+                },
+            ),
+            ..TextBundle::default()
+        })
+        .insert(PowerUpHud)
+        .insert(HudElement(HudItem::PowerUps));
+}
+
+fn update_power_up_hud(
+    power_ups: Res<ActivePowerUps>,
+    mut text: Query<&mut Text, With<PowerUpHud>>,
+) {
+    let mut lines = Vec::new();
+    for (power_up, name) in [
+        (PowerUp::Speed, "Speed"),
+        (PowerUp::Shield, "Shield"),
+        (PowerUp::SlowMotion, "Slow motion"),
+    ] {
+        if let Some(remaining) = power_ups.remaining(power_up) {
+            lines.push(format!("{name} {remaining:.1}s"));
+        }
+    }
+    if power_ups.slow_motion_charges > 0 {
+        lines.push(format!(
+            "Slow motion x{} [Space]",
+            power_ups.slow_motion_charges
+        ));
+    }
+
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_ups_stack_with_limits() {
+        let mut power_ups = ActivePowerUps::default();
+        power_ups.grant(PowerUp::Speed);
+        power_ups.tick(4.0);
+        power_ups.grant(PowerUp::Speed);
+        assert_eq!(power_ups.remaining(PowerUp::Speed), Some(16.0));
+        power_ups.grant(PowerUp::Speed);
+        assert_eq!(power_ups.remaining(PowerUp::Speed), Some(20.0));
+        power_ups.tick(20.0);
+        assert!(!power_ups.is_active(PowerUp::Speed));
+
+        for _ in 0..5 {
+            power_ups.grant(PowerUp::SlowMotion);
+        }
+        assert_eq!(power_ups.slow_motion_charges, MAX_SLOW_MOTION_CHARGES);
+        assert!(power_ups.use_slow_motion());
+        assert!(!power_ups.use_slow_motion());
+        assert_eq!(power_ups.slow_motion_charges, MAX_SLOW_MOTION_CHARGES - 1);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{change_state, save::SaveData, ui::GameFont, AppState};
+use bevy::prelude::*;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Everything kept for a player lives in their own directory under this one
+const PROFILES_DIRECTORY: &str = "profiles";
+// Files and directories that were kept next to the game before there were profiles
+const LEGACY_FILES: [&str; 3] = ["save.ron", "ghosts", "replays"];
+// Name for the profile made from an old save without a player name
+const DEFAULT_PROFILE_NAME: &str = "Player";
+
+// Several people can share one machine, each with their own save, settings, cosmetics and
+// ghosts. Someone picks who's playing on startup.
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ProfileSelect).with_system(create_profile_select),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::ProfileSelect).with_system(manage_profile_buttons),
+        );
+    }
+}
+
+// The player whose progress is loaded. Missing until one is picked or made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    directory: PathBuf,
+}
+
+impl Profile {
+    // Where a file belonging to this profile is kept
+    pub fn path(&self, file: impl AsRef<Path>) -> PathBuf {
+        self.directory.join(file)
+    }
+
+    // Every profile on this machine with its save, in order of name
+    pub fn list() -> Vec<(Profile, SaveData)> {
+        let mut profiles: Vec<_> = fs::read_dir(PROFILES_DIRECTORY)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                let profile = Profile {
+                    directory: entry.path(),
+                };
+                let save = SaveData::load(&profile);
+                (profile, save)
+            })
+            .collect();
+        profiles.sort_by_cached_key(|(profile, save)| profile.display_name(save).to_lowercase());
+        profiles
+    }
+
+    // The profile kept under this name, which is made if it doesn't exist yet. Only the soak
+    // test comes back to the same profile by name.
+    #[cfg(feature = "autopilot")]
+    pub fn open(name: &str) -> io::Result<Profile> {
+        let profile = Profile {
+            directory: Path::new(PROFILES_DIRECTORY).join(directory_name(name)),
+        };
+        fs::create_dir_all(&profile.directory)?;
+        Ok(profile)
+    }
+
+    // A new profile for a new player, kept apart from any others with the same name
+    pub fn create(name: &str) -> io::Result<Profile> {
+        let base = directory_name(name);
+        let directory = (1..)
+            .map(|number| match number {
+                1 => base.clone(),
+                _ => format!("{base}_{number}"),
+            })
+            .map(|directory| Path::new(PROFILES_DIRECTORY).join(directory))
+            .find(|directory| !directory.exists())
+            .unwrap();
+        fs::create_dir_all(&directory)?;
+        Ok(Profile { directory })
+    }
+
+    pub fn display_name(&self, save: &SaveData) -> String {
+        save.player_name.clone().unwrap_or_else(|| {
+            self.directory
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+}
+
+// A name made safe to use as a directory on any platform
+fn directory_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() {
+        DEFAULT_PROFILE_NAME.to_string()
+    } else {
+        name
+    }
+}
+
+// Move the save, ghosts and replays from before profiles into a profile of their own
+fn migrate_legacy_save() -> io::Result<()> {
+    let legacy: Vec<_> = LEGACY_FILES
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.exists())
+        .collect();
+    if legacy.is_empty() || Path::new(PROFILES_DIRECTORY).exists() {
+        return Ok(());
+    }
+    let profile = Profile::create(DEFAULT_PROFILE_NAME)?;
+    for path in legacy {
+        fs::rename(path, profile.path(path))?;
+    }
+    info!(
+        "Moved the existing save into {}",
+        profile.directory.display()
+    );
+    Ok(())
+}
+
+// Where the game starts: choosing who's playing, or making the first profile
+pub fn first_state() -> AppState {
+    if let Err(err) = migrate_legacy_save() {
+        warn!("Failed to move the existing save into a profile: {err}");
+    }
+    // The soak test picks its own profile and starts from the menu
+    if cfg!(feature = "autopilot") {
+        AppState::Menu
+    } else if Profile::list().is_empty() {
+        AppState::NameEntry
+    } else {
+        AppState::ProfileSelect
+    }
+}
+
+#[derive(Component)]
+enum ProfileButton {
+    Existing(Profile),
+    New,
+}
+
+fn create_profile_select(mut commands: Commands, font: Res<GameFont>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    let text_style = TextStyle {
+        font: font.get_handle(),
+        font_size: 40.0,
+        color: Color::BLACK,
+    };
+    let buttons = Profile::list()
+        .into_iter()
+        .map(|(profile, save)| {
+            let name = profile.display_name(&save);
+            (ProfileButton::Existing(profile), name)
+        })
+        .chain([(ProfileButton::New, "New profile".to_string())]);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Percent(5.0),
+                        ..Rect::default()
+                    },
+                    ..Style::default()
+                },
+                text: Text::with_section(
+                    "Who's playing?",
+                    TextStyle {
+                        font_size: 70.0,
+                        color: Color::WHITE,
+                        ..text_style.clone()
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+
+            for (button, name) in buttons {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(400.0), Val::Px(55.0)),
+                            margin: Rect::all(Val::Px(6.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        ..ButtonBundle::default()
+                    })
+                    .insert(button)
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                name,
+                                text_style.clone(),
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        });
+                    });
+            }
+        });
+}
+
+fn manage_profile_buttons(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    interaction: Query<(&Interaction, &ProfileButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in interaction.iter() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        match button {
+            ProfileButton::Existing(profile) => {
+                let save = SaveData::load(profile);
+                // A profile can be left before its introduction is over
+                let next = if save.onboarded {
+                    AppState::Menu
+                } else {
+                    AppState::NameEntry
+                };
+                commands.insert_resource(save);
+                commands.insert_resource(profile.clone());
+                change_state(&mut state, next);
+            }
+            // Made once the new player has given their name
+            ProfileButton::New => {
+                commands.insert_resource(SaveData::default());
+                commands.remove_resource::<Profile>();
+                change_state(&mut state, AppState::NameEntry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_become_safe_directories() {
+        assert_eq!(directory_name("Ada"), "Ada");
+        assert_eq!(directory_name(" Bee keeper/../x "), "Bee_keeper____x");
+        assert_eq!(directory_name("Zoë"), "Zoë");
+        assert_eq!(directory_name("   "), DEFAULT_PROFILE_NAME);
+
+        let profile = Profile {
+            directory: Path::new(PROFILES_DIRECTORY).join("Ada"),
+        };
+        assert_eq!(
+            profile.path("save.ron"),
+            Path::new("profiles").join("Ada").join("save.ron")
+        );
+        assert_eq!(profile.display_name(&SaveData::default()), "Ada");
+    }
+}
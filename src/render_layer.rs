@@ -0,0 +1,83 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+// Draw order for everything in the world, kept in one place. Sprites are given a layer and
+// their z is set from it, so a new kind of entity can't end up flickering behind walls.
+pub struct RenderLayerPlugin;
+
+impl Plugin for RenderLayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            apply_render_layers.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+// From back to front. Children are drawn relative to their parent's layer instead.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Background,
+    Decorations,
+    Tiles,
+    Pickups,
+    // Recordings of earlier runs, under anything that can still hurt
+    Ghosts,
+    // Behind projectiles
+    Trails,
+    Enemies,
+    Player,
+    Particles,
+    Fog,
+    // Full screen effects drawn over the fog
+    PostProcessing,
+}
+
+impl RenderLayer {
+    // A whole unit apart, leaving room for children and trails to sit just above or below
+    pub fn z(self) -> f32 {
+        self as u8 as f32
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_render_layers(
+    mut sprites: Query<
+        (&RenderLayer, &mut Transform),
+        (
+            Or<(Changed<RenderLayer>, Changed<Transform>)>,
+            Without<Parent>,
+        ),
+    >,
+) {
+    for (layer, mut transform) in sprites.iter_mut() {
+        // Only written when wrong, so it doesn't mark every moving sprite as changed again
+        if transform.translation.z != layer.z() {
+            transform.translation.z = layer.z();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_stack_in_order() {
+        let layers = [
+            RenderLayer::Background,
+            RenderLayer::Decorations,
+            RenderLayer::Tiles,
+            RenderLayer::Pickups,
+            RenderLayer::Ghosts,
+            RenderLayer::Trails,
+            RenderLayer::Enemies,
+            RenderLayer::Player,
+            RenderLayer::Particles,
+            RenderLayer::Fog,
+            RenderLayer::PostProcessing,
+        ];
+        assert!(layers.windows(2).all(|pair| pair[0].z() < pair[1].z()));
+        // Everything has to stay in front of the 2D camera's near plane
+        assert!(layers.iter().all(|layer| layer.z() >= 0.0));
+    }
+}
@@ -0,0 +1,878 @@
+use crate::{
+    challenge_code::ChallengeRun,
+    challenges::{ActiveChallenges, Challenge},
+    change_state,
+    clock::{GameClock, TickGameClock},
+    controls::{ButtonState, Controls, ReadControls},
+    pack::LevelRegistry,
+    player::Player,
+    playing,
+    profile::Profile,
+    ui::GameFont,
+    upgrades::{Upgrade, UpgradeTracker},
+    world::{GameWorld, Variant, WorldType},
+    AppState,
+};
+use bevy::{prelude::*, window::FileDragAndDrop};
+use std::{fs, io, sync::Arc, time::Duration};
+use strum::IntoEnumIterator;
+
+// The player's last run is always kept here in their profile, ready to be shared
+const LATEST_REPLAY: &str = "replays/latest.replay";
+
+const MAGIC: &[u8; 4] = b"BRPL";
+// Version 2 added position checks
+const VERSION: u16 = 2;
+// Cap recordings at ten minutes of frames at a high refresh rate
+const MAX_FRAMES: usize = 144 * 60 * 10;
+// Frames between position checks
+const CHECK_INTERVAL: usize = 30;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunSeed(0))
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(start_recording))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(record_frame),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(play_replay.after(ReadControls).after(TickGameClock))
+                    .with_system(record_position),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Game).with_system(finish_replay))
+            // Kept until the run's results are done with, so they can tell it was a replay
+            .add_system_set(SystemSet::on_exit(AppState::Retry).with_system(stop_playback))
+            .add_system_set(SystemSet::on_exit(AppState::Victory).with_system(stop_playback))
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu).with_system(import_dropped_replay),
+            );
+    }
+}
+
+// Seeds anything random in a run, recorded so replays can reproduce it
+pub struct RunSeed(pub u64);
+
+// Header fields and the controls for every frame played
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    // Version of the game that recorded it
+    pub game_version: String,
+    // Display name of the level
+    pub level: String,
+    pub level_hash: u64,
+    pub seed: u64,
+    pub variant: Variant,
+    pub mirrored: bool,
+    pub upgrades: UpgradeTracker,
+    frames: Vec<Frame>,
+    checks: Vec<PositionCheck>,
+}
+
+// Where the player was when a frame started, to catch playback drifting from the recording
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PositionCheck {
+    frame: u32,
+    checksum: u32,
+}
+
+// FNV-1a over the position in sixteenths of a pixel
+fn position_checksum(position: Vec2) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for coordinate in [position.x, position.y] {
+        for byte in ((coordinate * 16.0).round() as i32).to_le_bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+    }
+    hash
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Frame {
+    // Seconds the frame lasted
+    delta: f32,
+    controls: Controls,
+}
+
+// Which fields of a frame differ from the one before, and follow its flags byte
+const CHANGED_DELTA: u8 = 1 << 0;
+const CHANGED_AIM: u8 = 1 << 1;
+const CHANGED_THROTTLE: u8 = 1 << 2;
+const CHANGED_CURSOR: u8 = 1 << 3;
+const CHANGED_BUTTONS: u8 = 1 << 4;
+
+impl Replay {
+    // Encode as: magic, version (u16), game version, level name, level hash (u64),
+    // seed (u64), variant, mirrored, primary and secondary upgrade (u8 each, 255 for none),
+    // frame count (u32), then each frame as a flags byte followed by the fields that changed
+    // since the previous frame, then the check count (u32) and each check as its frame and
+    // checksum (u32 each). Strings are a u16 length then UTF-8, all little endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        write_string(&mut bytes, &self.game_version);
+        write_string(&mut bytes, &self.level);
+        bytes.extend_from_slice(&self.level_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(Variant::iter().position(|v| v == self.variant).unwrap() as u8);
+        bytes.push(self.mirrored as u8);
+        for upgrade in [self.upgrades.primary, self.upgrades.secondary] {
+            bytes.push(match upgrade {
+                Some(upgrade) => Upgrade::iter().position(|u| u == upgrade).unwrap() as u8,
+                None => u8::MAX,
+            });
+        }
+
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        // Nothing is assumed about the first frame
+        let mut previous: Option<Frame> = None;
+        for frame in &self.frames {
+            let changed = |field: fn(&Frame) -> [u32; 2]| {
+                previous.is_none_or(|previous| field(&previous) != field(frame))
+            };
+            let mut flags = 0;
+            let mut fields = Vec::new();
+            if changed(|f| [f.delta.to_bits(), 0]) {
+                flags |= CHANGED_DELTA;
+                fields.extend_from_slice(&frame.delta.to_le_bytes());
+            }
+            if changed(|f| [optional(f.controls.aim).to_bits(), 0]) {
+                flags |= CHANGED_AIM;
+                fields.extend_from_slice(&optional(frame.controls.aim).to_le_bytes());
+            }
+            if changed(|f| [f.controls.throttle.to_bits(), 0]) {
+                flags |= CHANGED_THROTTLE;
+                fields.extend_from_slice(&frame.controls.throttle.to_le_bytes());
+            }
+            if changed(|f| cursor_bits(f.controls.cursor)) {
+                flags |= CHANGED_CURSOR;
+                let cursor = frame.controls.cursor.unwrap_or(Vec2::splat(f32::NAN));
+                fields.extend_from_slice(&cursor.x.to_le_bytes());
+                fields.extend_from_slice(&cursor.y.to_le_bytes());
+            }
+            if changed(|f| [encode_buttons(&f.controls) as u32, 0]) {
+                flags |= CHANGED_BUTTONS;
+                fields.push(encode_buttons(&frame.controls));
+            }
+            bytes.push(flags);
+            bytes.extend_from_slice(&fields);
+            previous = Some(*frame);
+        }
+
+        bytes.extend_from_slice(&(self.checks.len() as u32).to_le_bytes());
+        for check in &self.checks {
+            bytes.extend_from_slice(&check.frame.to_le_bytes());
+            bytes.extend_from_slice(&check.checksum.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason);
+        let truncated = || invalid("replay is truncated");
+        let mut reader = Reader(bytes);
+
+        if reader.take(MAGIC.len()) != Some(MAGIC) {
+            return Err(invalid("not a replay file"));
+        }
+        let version = reader.u16().ok_or_else(truncated)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(invalid(&format!("unsupported replay version {version}")));
+        }
+        let game_version = reader.string().ok_or_else(truncated)?;
+        let level = reader.string().ok_or_else(truncated)?;
+        let level_hash = reader.u64().ok_or_else(truncated)?;
+        let seed = reader.u64().ok_or_else(truncated)?;
+        let variant = reader
+            .u8()
+            .and_then(|index| Variant::iter().nth(index as usize))
+            .ok_or_else(|| invalid("unknown level variant"))?;
+        let mirrored = reader.u8().ok_or_else(truncated)? != 0;
+        let mut upgrade = || match reader.u8() {
+            Some(u8::MAX) => Ok(None),
+            Some(index) => Upgrade::iter()
+                .nth(index as usize)
+                .map(Some)
+                .ok_or_else(|| invalid("unknown upgrade")),
+            None => Err(truncated()),
+        };
+        let upgrades = UpgradeTracker {
+            primary: upgrade()?,
+            secondary: upgrade()?,
+        };
+
+        let count = reader.u32().ok_or_else(truncated)? as usize;
+        if count > MAX_FRAMES {
+            return Err(invalid("replay is too long"));
+        }
+        let mut frames = Vec::with_capacity(count);
+        let mut frame = Frame {
+            delta: 0.0,
+            controls: Controls::default(),
+        };
+        for _ in 0..count {
+            let flags = reader.u8().ok_or_else(truncated)?;
+            if flags & CHANGED_DELTA != 0 {
+                frame.delta = reader.f32().ok_or_else(truncated)?;
+            }
+            if flags & CHANGED_AIM != 0 {
+                let aim = reader.f32().ok_or_else(truncated)?;
+                frame.controls.aim = (!aim.is_nan()).then_some(aim);
+            }
+            if flags & CHANGED_THROTTLE != 0 {
+                frame.controls.throttle = reader.f32().ok_or_else(truncated)?;
+            }
+            if flags & CHANGED_CURSOR != 0 {
+                let x = reader.f32().ok_or_else(truncated)?;
+                let y = reader.f32().ok_or_else(truncated)?;
+                frame.controls.cursor = (!x.is_nan()).then_some(Vec2::new(x, y));
+            }
+            if flags & CHANGED_BUTTONS != 0 {
+                decode_buttons(&mut frame.controls, reader.u8().ok_or_else(truncated)?);
+            }
+            frames.push(frame);
+        }
+
+        let mut checks = Vec::new();
+        // Older replays can still be watched, just without noticing desyncs
+        if version >= 2 {
+            let count = reader.u32().ok_or_else(truncated)? as usize;
+            if count > MAX_FRAMES {
+                return Err(invalid("replay is too long"));
+            }
+            for _ in 0..count {
+                checks.push(PositionCheck {
+                    frame: reader.u32().ok_or_else(truncated)?,
+                    checksum: reader.u32().ok_or_else(truncated)?,
+                });
+            }
+        }
+        if !reader.0.is_empty() {
+            return Err(invalid("unexpected data after the last frame"));
+        }
+
+        Ok(Self {
+            game_version,
+            level,
+            level_hash,
+            seed,
+            variant,
+            mirrored,
+            upgrades,
+            frames,
+            checks,
+        })
+    }
+}
+
+// Missing values are stored as NaN
+fn optional(value: Option<f32>) -> f32 {
+    value.unwrap_or(f32::NAN)
+}
+
+fn cursor_bits(cursor: Option<Vec2>) -> [u32; 2] {
+    let cursor = cursor.unwrap_or(Vec2::splat(f32::NAN));
+    [cursor.x.to_bits(), cursor.y.to_bits()]
+}
+
+// Pressed and just pressed for each button, two bits apiece
+fn encode_buttons(controls: &Controls) -> u8 {
+    [controls.primary, controls.secondary, controls.slow_motion]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, button)| {
+            bits | (button.pressed as u8) << (i * 2) | (button.just_pressed as u8) << (i * 2 + 1)
+        })
+}
+
+fn decode_buttons(controls: &mut Controls, bits: u8) {
+    let button = |i: usize| ButtonState {
+        pressed: bits & 1 << (i * 2) != 0,
+        just_pressed: bits & 1 << (i * 2 + 1) != 0,
+    };
+    controls.primary = button(0);
+    controls.secondary = button(1);
+    controls.slow_motion = button(2);
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend_from_slice(&(string.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.0.get(..count)?;
+        self.0 = &self.0[count..];
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).ok()
+    }
+}
+
+// The run being recorded, if it's on a level that can be replayed
+struct ReplayRecorder(Option<Replay>);
+
+// Drives the controls from a replay instead of the player
+pub struct ReplayPlayback {
+    replay: Replay,
+    // Seconds played, and seconds covered by the frames used so far
+    elapsed: f64,
+    recorded: f64,
+    next_frame: usize,
+    controls: Controls,
+    // Index of the next position check to compare against
+    next_check: usize,
+    // Frame the player's position first differed from the recording on
+    desynced_at: Option<usize>,
+    // Recorded frames to play next update, with gameplay time following their lengths
+    // instead of real time. Set by the replay scrubber.
+    pub frames_next_update: Option<usize>,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            elapsed: 0.0,
+            recorded: 0.0,
+            next_frame: 0,
+            controls: Controls::default(),
+            next_check: 0,
+            desynced_at: None,
+            frames_next_update: None,
+        }
+    }
+}
+
+// Where playback has got to, for the replay scrubber
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+impl ReplayPlayback {
+    // Index of the next recorded frame to play
+    pub fn frame(&self) -> usize {
+        self.next_frame
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.replay.frames.len()
+    }
+
+    pub fn desynced_at(&self) -> Option<usize> {
+        self.desynced_at
+    }
+
+    // Seconds each frame still to be played lasted
+    pub fn upcoming_deltas(&self) -> impl Iterator<Item = f32> + '_ {
+        self.replay.frames[self.next_frame..]
+            .iter()
+            .map(|frame| frame.delta)
+    }
+}
+
+impl ReplayPlayback {
+    // Compare the player's position before the next frame against the recording, true
+    // the first time they differ. Checks on frames skipped over can't be compared.
+    fn check(&mut self, position: Vec2) -> bool {
+        let checks = &self.replay.checks[self.next_check..];
+        self.next_check += checks
+            .iter()
+            .take_while(|check| (check.frame as usize) < self.next_frame)
+            .count();
+        match self.replay.checks.get(self.next_check) {
+            Some(check) if check.frame as usize == self.next_frame => {
+                self.next_check += 1;
+                if check.checksum != position_checksum(position) && self.desynced_at.is_none() {
+                    self.desynced_at = Some(self.next_frame);
+                    return true;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    // Controls for the next frame played, or None once the replay is over. Frame rates
+    // don't have to match the recording: frames are used by time, and presses in any
+    // frames skipped over aren't lost.
+    fn advance(&mut self, delta: f32) -> Option<Controls> {
+        if self.next_frame >= self.replay.frames.len() {
+            return None;
+        }
+        self.elapsed += delta as f64;
+
+        let mut pressed = [false; 3];
+        let mut used = false;
+        while let Some(frame) = self.replay.frames.get(self.next_frame) {
+            if used && self.recorded + frame.delta as f64 > self.elapsed {
+                break;
+            }
+            self.use_frame(&mut pressed);
+            used = true;
+        }
+        Some(self.controls_with_presses(pressed))
+    }
+
+    // Controls for exactly the next `count` recorded frames, and how long they lasted, or
+    // None once the replay is over
+    fn advance_frames(&mut self, count: usize) -> Option<(Controls, f32)> {
+        if self.next_frame >= self.replay.frames.len() {
+            return None;
+        }
+        let start = self.recorded;
+        let mut pressed = [false; 3];
+        for _ in 0..count.min(self.replay.frames.len() - self.next_frame) {
+            self.use_frame(&mut pressed);
+        }
+        self.elapsed = self.recorded;
+        Some((
+            self.controls_with_presses(pressed),
+            (self.recorded - start) as f32,
+        ))
+    }
+
+    // Move on past the next frame, noting which buttons it pressed
+    fn use_frame(&mut self, pressed: &mut [bool; 3]) {
+        let frame = self.replay.frames[self.next_frame];
+        self.recorded += frame.delta as f64;
+        self.next_frame += 1;
+        self.controls = frame.controls;
+        for (pressed, button) in pressed.iter_mut().zip([
+            frame.controls.primary,
+            frame.controls.secondary,
+            frame.controls.slow_motion,
+        ]) {
+            *pressed |= button.just_pressed;
+        }
+    }
+
+    fn controls_with_presses(&self, pressed: [bool; 3]) -> Controls {
+        let mut controls = self.controls;
+        controls.primary.just_pressed = pressed[0];
+        controls.secondary.just_pressed = pressed[1];
+        controls.slow_motion.just_pressed = pressed[2];
+        controls
+    }
+}
+
+// While present, retrying restarts the replay being watched from the beginning instead of
+// going back to playing live
+pub struct Rewind;
+
+fn start_recording(
+    mut commands: Commands,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    upgrades: Res<UpgradeTracker>,
+    challenges: Res<ActiveChallenges>,
+    challenge_run: Res<ChallengeRun>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    // New seeds fit in a challenge code
+    let seed = match (&playback, &challenge_run.0) {
+        (Some(playback), _) => playback.replay.seed,
+        (None, Some(code)) => code.seed as u64,
+        (None, None) => rand::random::<u32>() as u64,
+    };
+    commands.insert_resource(RunSeed(seed));
+
+    let level = match world.world_type {
+        WorldType::Level { index } if playback.is_none() => levels.levels[index].display_name(),
+        _ => {
+            commands.insert_resource(ReplayRecorder(None));
+            return;
+        }
+    };
+    commands.insert_resource(ReplayRecorder(Some(Replay {
+        game_version: env!("CARGO_PKG_VERSION").to_string(),
+        level,
+        level_hash: world.hash(),
+        seed,
+        variant: world.variant,
+        mirrored: challenges.is_active(Challenge::Mirrored),
+        upgrades: upgrades.clone(),
+        frames: Vec::new(),
+        checks: Vec::new(),
+    })));
+}
+
+fn record_frame(
+    clock: Res<GameClock>,
+    controls: Res<Controls>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    if let Some(replay) = &mut recorder.0 {
+        if replay.frames.len() < MAX_FRAMES {
+            replay.frames.push(Frame {
+                delta: clock.delta_seconds(),
+                controls: *controls,
+            });
+        }
+    }
+}
+
+// Checked before each frame's movement, the same as when it was recorded
+fn record_position(mut recorder: ResMut<ReplayRecorder>, player: Query<&Transform, With<Player>>) {
+    let (replay, transform) = match (&mut recorder.0, player.get_single()) {
+        (Some(replay), Ok(transform)) => (replay, transform),
+        _ => return,
+    };
+    let frame = replay.frames.len() as u32;
+    if replay.frames.len() % CHECK_INTERVAL == 0
+        && replay
+            .checks
+            .last()
+            .is_none_or(|check| check.frame != frame)
+    {
+        replay.checks.push(PositionCheck {
+            frame,
+            checksum: position_checksum(transform.translation.truncate()),
+        });
+    }
+}
+
+fn play_replay(
+    mut commands: Commands,
+    mut clock: ResMut<GameClock>,
+    font: Res<GameFont>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    mut controls: ResMut<Controls>,
+    player: Query<&Transform, With<Player>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    if let Ok(transform) = player.get_single() {
+        if playback.check(transform.translation.truncate()) {
+            warn!("Replay desynced at frame {}", playback.next_frame);
+            commands.spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Percent(2.0),
+                        left: Val::Percent(40.0),
+                        ..Rect::default()
+                    },
+                    ..Style::default()
+                },
+                text: Text::with_section(
+                    "Replay desynced",
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 30.0,
+                        color: WARNING_COLOR,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        }
+    }
+
+    // Once it's over the bee just stops
+    *controls = match playback.frames_next_update.take() {
+        Some(count) => {
+            let (played, seconds) = playback.advance_frames(count).unwrap_or_default();
+            clock.set_delta(Duration::from_secs_f32(seconds));
+            played
+        }
+        None => playback.advance(clock.delta_seconds()).unwrap_or_default(),
+    };
+}
+
+fn finish_replay(recorder: Option<Res<ReplayRecorder>>, profile: Option<Res<Profile>>) {
+    let replay = recorder.as_ref().and_then(|recorder| recorder.0.as_ref());
+    if let (Some(replay), Some(profile)) = (replay, profile) {
+        let path = profile.path(LATEST_REPLAY);
+        let result = fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(&path, replay.encode()));
+        if let Err(err) = result {
+            warn!("Failed to save replay to {}: {err}", path.display());
+        }
+    }
+}
+
+const WARNING_COLOR: Color = Color::rgb(1.0, 0.4, 0.3);
+
+fn stop_playback(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    rewind: Option<Res<Rewind>>,
+) {
+    match (playback, rewind) {
+        (Some(playback), Some(_)) => {
+            commands.insert_resource(ReplayPlayback::new(playback.replay.clone()));
+            commands.remove_resource::<Rewind>();
+        }
+        _ => commands.remove_resource::<ReplayPlayback>(),
+    }
+}
+
+#[derive(Component)]
+struct ReplayMessage;
+
+// Dropping a replay file on the menu plays it back, if its level is still the same
+#[allow(clippy::too_many_arguments)]
+fn import_dropped_replay(
+    mut commands: Commands,
+    mut events: EventReader<FileDragAndDrop>,
+    mut state: ResMut<State<AppState>>,
+    mut levels: ResMut<LevelRegistry>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    font: Res<GameFont>,
+    mut message: Query<&mut Text, With<ReplayMessage>>,
+) {
+    let path = match events.iter().find_map(|event| match event {
+        FileDragAndDrop::DroppedFile { path_buf, .. } => Some(path_buf.clone()),
+        _ => None,
+    }) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let result = fs::read(&path)
+        .and_then(|bytes| Replay::decode(&bytes))
+        .map_err(|err| format!("Couldn't read replay: {err}"))
+        .and_then(|replay| load_replay_level(&mut levels, replay));
+    match result {
+        Ok((world, replay)) => {
+            *upgrades = replay.upgrades.clone();
+            commands.insert_resource(world);
+            commands.insert_resource(ReplayPlayback::new(replay));
+            change_state(&mut state, AppState::Game);
+        }
+        Err(reason) => {
+            warn!("Not playing replay {}: {reason}", path.display());
+            match message.get_single_mut() {
+                Ok(mut text) => text.sections[0].value = reason,
+                Err(_) => {
+                    commands
+                        .spawn_bundle(TextBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position: Rect {
+                                    bottom: Val::Percent(2.0),
+                                    left: Val::Percent(2.0),
+                                    ..Rect::default()
+                                },
+                                ..Style::default()
+                            },
+                            text: Text::with_section(
+                                reason,
+                                TextStyle {
+                                    font: font.get_handle(),
+                                    font_size: 30.0,
+                                    color: WARNING_COLOR,
+                                },
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        })
+                        .insert(ReplayMessage);
+                }
+            }
+        }
+    }
+}
+
+// The level a replay was recorded on, refusing it if the level has changed since
+fn load_replay_level(
+    levels: &mut LevelRegistry,
+    replay: Replay,
+) -> Result<(Arc<GameWorld>, Replay), String> {
+    let index = levels
+        .levels
+        .iter()
+        .position(|level| level.display_name() == replay.level)
+        .ok_or_else(|| format!("The level \"{}\" isn't installed", replay.level))?;
+    let world = levels
+        .load_cached(index, replay.variant, replay.mirrored)
+        .map_err(|err| format!("Couldn't load \"{}\": {err}", replay.level))?;
+    if world.hash() != replay.level_hash {
+        return Err(format!(
+            "This replay was recorded on a different version of \"{}\"",
+            replay.level
+        ));
+    }
+    Ok((world, replay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(frames: Vec<Frame>) -> Replay {
+        Replay {
+            game_version: "0.1.0".into(),
+            level: "Level 1".into(),
+            level_hash: 0x1234_5678_9abc_def0,
+            seed: 42,
+            variant: Variant::Rotated90,
+            mirrored: true,
+            upgrades: UpgradeTracker {
+                primary: Some(Upgrade::Teleport),
+                secondary: None,
+            },
+            frames,
+            checks: vec![PositionCheck {
+                frame: 0,
+                checksum: position_checksum(Vec2::ZERO),
+            }],
+        }
+    }
+
+    fn frame(delta: f32, aim: Option<f32>, primary: bool) -> Frame {
+        Frame {
+            delta,
+            controls: Controls {
+                aim,
+                throttle: 0.5,
+                cursor: aim.map(|aim| Vec2::new(aim, -aim)),
+                primary: ButtonState {
+                    pressed: primary,
+                    just_pressed: primary,
+                },
+                ..Controls::default()
+            },
+        }
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let replay = replay(vec![
+            frame(0.016, Some(1.0), false),
+            frame(0.016, Some(1.0), false),
+            frame(0.017, None, true),
+            frame(0.016, Some(-2.0), false),
+        ]);
+        let bytes = replay.encode();
+        assert_eq!(Replay::decode(&bytes).unwrap(), replay);
+
+        // Frames that repeat the one before are a single byte
+        let first = self::replay(vec![frame(0.016, Some(1.0), false)])
+            .encode()
+            .len();
+        let repeated = self::replay(vec![frame(0.016, Some(1.0), false); 100]);
+        assert_eq!(repeated.encode().len(), first + 99);
+    }
+
+    #[test]
+    fn invalid_replays_are_rejected() {
+        let bytes = replay(vec![frame(0.016, None, false)]).encode();
+        assert!(Replay::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Replay::decode(b"BGST").is_err());
+
+        let mut newer = bytes.clone();
+        newer[4] = VERSION as u8 + 1;
+        assert!(Replay::decode(&newer).is_err());
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(Replay::decode(&trailing).is_err());
+    }
+
+    #[test]
+    fn version_1_replays_have_no_checks() {
+        let mut replay = replay(vec![frame(0.016, None, false)]);
+        replay.checks.clear();
+        let mut bytes = replay.encode();
+        // Without the check count
+        bytes.truncate(bytes.len() - 4);
+        bytes[4] = 1;
+        assert_eq!(Replay::decode(&bytes).unwrap(), replay);
+    }
+
+    #[test]
+    fn desyncs_are_reported_once() {
+        let mut replay = replay(vec![frame(0.25, None, false); 3]);
+        replay.checks = [(1, Vec2::ZERO), (2, Vec2::X), (3, Vec2::Y)]
+            .into_iter()
+            .map(|(frame, position)| PositionCheck {
+                frame,
+                checksum: position_checksum(position),
+            })
+            .collect();
+        let mut playback = ReplayPlayback::new(replay);
+        // Nothing to check before the first frame
+        assert!(!playback.check(Vec2::ZERO));
+        playback.advance(0.25);
+        assert!(!playback.check(Vec2::ZERO));
+        playback.advance(0.25);
+        assert!(playback.check(Vec2::ZERO));
+        playback.advance(0.25);
+        assert!(!playback.check(Vec2::ZERO));
+    }
+
+    #[test]
+    fn playback_keeps_presses_from_skipped_frames() {
+        let mut playback = ReplayPlayback::new(replay(vec![
+            frame(0.25, Some(1.0), false),
+            frame(0.25, Some(2.0), true),
+            frame(0.25, Some(3.0), false),
+        ]));
+        // Twice as fast as the recording, so both frames are used at once
+        let controls = playback.advance(0.5).unwrap();
+        assert_eq!(controls.aim, Some(2.0));
+        assert!(controls.primary.just_pressed);
+        // Slower than the recording, so the last frame is held without repeating presses
+        let controls = playback.advance(0.125).unwrap();
+        assert_eq!(controls.aim, Some(3.0));
+        assert!(!controls.primary.just_pressed);
+        assert_eq!(playback.advance(0.125), None);
+    }
+
+    #[test]
+    fn frames_can_be_played_one_at_a_time() {
+        let mut playback = ReplayPlayback::new(replay(vec![
+            frame(0.25, Some(1.0), false),
+            frame(0.5, Some(2.0), true),
+            frame(0.25, Some(3.0), false),
+        ]));
+        // Paused, so nothing is used up and no time passes
+        let (controls, seconds) = playback.advance_frames(0).unwrap();
+        assert_eq!((controls.aim, seconds), (None, 0.0));
+        assert_eq!(playback.frame(), 0);
+
+        let (controls, seconds) = playback.advance_frames(2).unwrap();
+        assert_eq!((controls.aim, seconds), (Some(2.0), 0.75));
+        assert!(controls.primary.just_pressed);
+        assert_eq!(playback.upcoming_deltas().collect::<Vec<_>>(), [0.25]);
+        // Carries on by time from where stepping left off
+        assert_eq!(playback.advance(0.25).unwrap().aim, Some(3.0));
+        assert_eq!(playback.advance_frames(1), None);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    change_state,
+    replay::{ReplayPlayback, Rewind},
+    save::SaveData,
+    ui::GameFont,
+    AppState,
+};
+use bevy::prelude::*;
+
+// Debug controls for watching a replay closely, to find the frame a collision bug or desync
+// happens on. F4 shows a timeline of the replay being watched, and while it's shown the
+// replay is played frame by frame: P pauses, . steps a single frame, up and down change the
+// speed and clicking the timeline seeks to that point.
+pub struct ReplayScrubberPlugin;
+
+impl Plugin for ReplayScrubberPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayScrubber>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(create_scrubber_panel))
+            .add_system_set(
+                SystemSet::on_update(AppState::Game)
+                    .with_system(toggle_scrubber)
+                    .with_system(scrub_replay)
+                    .with_system(update_scrubber_panel),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Retry).with_system(restart_replay));
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F4;
+const PAUSE_KEY: KeyCode = KeyCode::P;
+const STEP_KEY: KeyCode = KeyCode::Period;
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+// Most recorded frames played in one update, so a long hitch doesn't turn into a jump
+const MAX_FRAMES_PER_UPDATE: usize = 8;
+
+struct ReplayScrubber {
+    shown: bool,
+    paused: bool,
+    // Index into SPEEDS
+    speed: usize,
+    // Seconds of recording owed to the next frames played
+    budget: f32,
+    step: bool,
+    // Frame being skipped to
+    seek: Option<usize>,
+}
+
+impl Default for ReplayScrubber {
+    fn default() -> Self {
+        Self {
+            shown: false,
+            paused: false,
+            speed: 2,
+            budget: 0.0,
+            step: false,
+            seek: None,
+        }
+    }
+}
+
+// How many of the frames with `deltas` fit in `budget` seconds, and the seconds left over
+fn frames_within(deltas: impl Iterator<Item = f32>, mut budget: f32) -> (usize, f32) {
+    let mut count = 0;
+    for delta in deltas {
+        if delta > budget {
+            break;
+        }
+        if count == MAX_FRAMES_PER_UPDATE {
+            // Running behind, so drop the time owed instead of catching up later
+            return (count, 0.0);
+        }
+        budget -= delta;
+        count += 1;
+    }
+    (count, budget)
+}
+
+#[derive(Component)]
+struct ScrubberPanel;
+
+#[derive(Component)]
+struct ScrubberText;
+
+#[derive(Component)]
+struct Timeline;
+
+#[derive(Component)]
+struct TimelineFill;
+
+fn create_scrubber_panel(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(10.0),
+                    left: Val::Percent(10.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Percent(80.0), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                display: Display::None,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .insert(ScrubberPanel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(ScrubberText);
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(16.0)),
+                        ..Style::default()
+                    },
+                    color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                    ..ButtonBundle::default()
+                })
+                .insert(Timeline)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Style::default()
+                            },
+                            color: Color::rgb(1.0, 0.8, 0.2).into(),
+                            ..NodeBundle::default()
+                        })
+                        .insert(TimelineFill);
+                });
+        });
+}
+
+fn toggle_scrubber(
+    keys: Res<Input<KeyCode>>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut scrubber: ResMut<ReplayScrubber>,
+    mut panel: Query<&mut Style, With<ScrubberPanel>>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) && playback.is_some() {
+        scrubber.shown = !scrubber.shown;
+    }
+    // Stays shown through rewinds, and hidden when watching the next replay starts
+    let shown = scrubber.shown && playback.is_some();
+    if let Ok(mut style) = panel.get_single_mut() {
+        let display = if shown { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+fn scrub_replay(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    save: Res<SaveData>,
+    mut windows: ResMut<Windows>,
+    mut scrubber: ResMut<ReplayScrubber>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    timeline: Query<
+        (&Interaction, &Node, &GlobalTransform),
+        (Changed<Interaction>, With<Timeline>),
+    >,
+) {
+    let mut playback = match playback {
+        Some(playback) if scrubber.shown => playback,
+        _ => return,
+    };
+
+    if keys.just_pressed(PAUSE_KEY) {
+        scrubber.paused = !scrubber.paused;
+    }
+    if keys.just_pressed(STEP_KEY) {
+        scrubber.paused = true;
+        scrubber.step = true;
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        scrubber.speed = (scrubber.speed + 1).min(SPEEDS.len() - 1);
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        scrubber.speed = scrubber.speed.saturating_sub(1);
+    }
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+    for (interaction, node, transform) in timeline.iter() {
+        if let (Interaction::Clicked, Some(cursor)) = (interaction, cursor) {
+            // UI nodes are positioned by their centers
+            let left = transform.translation.x - node.size.x / 2.0;
+            let fraction = ((cursor.x - left) / node.size.x).clamp(0.0, 1.0);
+            scrubber.seek = Some((fraction * playback.frame_count() as f32) as usize);
+        }
+    }
+
+    let frames = match scrubber.seek {
+        // Replays can only be played forwards, so seeking back starts again from the top
+        Some(target) if target < playback.frame() => {
+            commands.insert_resource(Rewind);
+            change_state(&mut state, AppState::Retry);
+            return;
+        }
+        // Skip ahead one frame at a time so nothing plays out differently, with vsync off
+        // to get there sooner
+        Some(target) if target > playback.frame() => {
+            set_vsync(&mut windows, false);
+            1
+        }
+        Some(_) => {
+            scrubber.seek = None;
+            scrubber.paused = true;
+            set_vsync(&mut windows, save.settings.vsync);
+            0
+        }
+        None if scrubber.paused => {
+            scrubber.budget = 0.0;
+            std::mem::take(&mut scrubber.step) as usize
+        }
+        None => {
+            let budget = scrubber.budget + time.delta_seconds() * SPEEDS[scrubber.speed];
+            let (frames, left) = frames_within(playback.upcoming_deltas(), budget);
+            scrubber.budget = left;
+            frames
+        }
+    };
+    playback.frames_next_update = Some(frames);
+}
+
+fn set_vsync(windows: &mut Windows, vsync: bool) {
+    if let Some(window) = windows.get_primary_mut() {
+        if window.vsync() != vsync {
+            window.set_vsync(vsync);
+        }
+    }
+}
+
+// Back into the replay being rewound, to seek forwards from the start
+fn restart_replay(mut state: ResMut<State<AppState>>, rewind: Option<Res<Rewind>>) {
+    if rewind.is_some() {
+        change_state(&mut state, AppState::Game);
+    }
+}
+
+fn update_scrubber_panel(
+    scrubber: Res<ReplayScrubber>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut text: Query<&mut Text, With<ScrubberText>>,
+    mut fill: Query<&mut Style, With<TimelineFill>>,
+) {
+    let playback = match playback {
+        Some(playback) if scrubber.shown => playback,
+        _ => return,
+    };
+    let progress = playback.frame() as f32 / playback.frame_count().max(1) as f32;
+    if let Ok(mut style) = fill.get_single_mut() {
+        style.size.width = Val::Percent(progress * 100.0);
+    }
+
+    let mut status = vec![
+        format!("Frame {}/{}", playback.frame(), playback.frame_count()),
+        format!("{}x", SPEEDS[scrubber.speed]),
+    ];
+    if let Some(target) = scrubber.seek {
+        status.push(format!("seeking to {target}"));
+    } else if scrubber.paused {
+        status.push("paused".into());
+    }
+    if let Some(frame) = playback.desynced_at() {
+        status.push(format!("desynced at frame {frame}"));
+    }
+    status.push("[F4 hide, P pause, . step, up/down speed, click to seek]".into());
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = status.join("  ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_played_as_time_allows() {
+        let deltas = [0.25, 0.25, 0.0, 0.5];
+        assert_eq!(frames_within(deltas.into_iter(), 0.2), (0, 0.2));
+        // Frames recorded during hit stops take no time
+        assert_eq!(frames_within(deltas.into_iter(), 0.625), (3, 0.125));
+        assert_eq!(frames_within(deltas.into_iter(), 2.0), (4, 1.0));
+        // Owed time past the cap is dropped
+        let long = [0.1; MAX_FRAMES_PER_UPDATE + 1];
+        assert_eq!(
+            frames_within(long.into_iter(), 5.0),
+            (MAX_FRAMES_PER_UPDATE, 0.0)
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    challenges::{ActiveChallenges, Challenge},
+    upgrades::Upgrade,
+    util::ReflectResource,
+    world::{GameWorld, WorldType},
+    AppState,
+};
+use bevy::{prelude::*, reflect::TypeRegistryArc, window::WindowId};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContext, egui, reflect::ui_for_reflect, Context, InspectableRegistry,
+};
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+
+// Debug window listing every resource registered with #[reflect(Resource)], so the ones a
+// run is played with can be checked and changed mid-level instead of going back through the
+// menus. F3 shows it. The level itself is shown but can't be changed.
+pub struct ResourceInspectorPlugin;
+
+impl Plugin for ResourceInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        let mut widgets = app
+            .world
+            .get_resource_or_insert_with(InspectableRegistry::default);
+        widgets.register_raw(upgrade_widget);
+        widgets.register_raw(challenges_widget);
+
+        app.init_resource::<ResourceInspector>().add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(toggle_resource_inspector)
+                .with_system(show_resource_inspector.exclusive_system()),
+        );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+#[derive(Default)]
+struct ResourceInspector {
+    shown: bool,
+    // The level's name, hash and layout, only serialized again when the level changes
+    layout: String,
+}
+
+// Set directly, so the loadout rules don't get in the way of trying things out
+fn upgrade_widget(upgrade: &mut Option<Upgrade>, ui: &mut egui::Ui, context: &mut Context) -> bool {
+    let name = |upgrade: Option<Upgrade>| {
+        upgrade.map_or_else(|| "None".to_string(), |upgrade| upgrade.to_string())
+    };
+    let mut changed = false;
+    egui::ComboBox::from_id_source(context.id())
+        .selected_text(name(*upgrade))
+        .show_ui(ui, |ui| {
+            for option in [None].into_iter().chain(Upgrade::iter().map(Some)) {
+                changed |= ui.selectable_value(upgrade, option, name(option)).changed();
+            }
+        });
+    changed
+}
+
+fn challenges_widget(
+    challenges: &mut ActiveChallenges,
+    ui: &mut egui::Ui,
+    _: &mut Context,
+) -> bool {
+    let mut changed = false;
+    ui.vertical(|ui| {
+        for challenge in Challenge::iter() {
+            let mut active = challenges.is_active(challenge);
+            if ui.checkbox(&mut active, challenge.name()).changed() {
+                challenges.toggle(challenge);
+                changed = true;
+            }
+        }
+    });
+    changed
+}
+
+fn toggle_resource_inspector(keys: Res<Input<KeyCode>>, mut inspector: ResMut<ResourceInspector>) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        inspector.shown = !inspector.shown;
+    }
+}
+
+fn layout_text(world: &GameWorld) -> String {
+    let level = match world.world_type {
+        WorldType::Level { index } => format!("Level {index}"),
+        WorldType::Endless => "Endless".to_string(),
+    };
+    format!("{level} #{:016x}\n{}", world.hash(), world.serialize())
+}
+
+// Exclusive, as the resources shown are only known at runtime. Each is edited as a copy
+// that's applied back when it's changed.
+fn show_resource_inspector(world: &mut World) {
+    // Checked even while hidden, as this is the only run that sees the change
+    if world.is_resource_changed::<Arc<GameWorld>>() {
+        let layout = layout_text(world.get_resource::<Arc<GameWorld>>().unwrap());
+        world
+            .get_resource_mut::<ResourceInspector>()
+            .unwrap()
+            .layout = layout;
+    }
+    if !world.get_resource::<ResourceInspector>().unwrap().shown {
+        return;
+    }
+    let ctx = match world
+        .get_resource_mut::<EguiContext>()
+        .and_then(|mut egui| egui.try_ctx_for_window_mut(WindowId::primary()).cloned())
+    {
+        Some(ctx) => ctx,
+        None => return,
+    };
+
+    let registry = world.get_resource::<TypeRegistryArc>().unwrap().clone();
+    let mut resources: Vec<_> = registry
+        .read()
+        .iter()
+        .filter_map(|registration| {
+            let resource = registration.data::<ReflectResource>()?.clone();
+            Some((registration.short_name().to_string(), resource))
+        })
+        .collect();
+    resources.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut shown = true;
+    egui::Window::new("Resources [F3]")
+        .open(&mut shown)
+        .vscroll(true)
+        .show(&ctx, |ui| {
+            for (name, resource) in &resources {
+                let mut value = match resource.reflect(world) {
+                    Some(value) => value.clone_value(),
+                    None => continue,
+                };
+                let changed = egui::CollapsingHeader::new(name)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        let mut context = Context::new_world_access(Some(&ctx), world);
+                        ui_for_reflect(&mut *value, ui, &mut context)
+                    })
+                    .body_returned
+                    .unwrap_or(false);
+                if changed {
+                    resource.apply(world, &*value);
+                }
+            }
+            egui::CollapsingHeader::new("Level").show(ui, |ui| {
+                ui.monospace(&world.get_resource::<ResourceInspector>().unwrap().layout);
+            });
+        });
+    if !shown {
+        world.get_resource_mut::<ResourceInspector>().unwrap().shown = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stamina::Stamina;
+    use bevy::reflect::{GetTypeRegistration, ReflectMut};
+
+    #[test]
+    fn resources_are_edited_through_the_registry() {
+        let registration = Stamina::get_type_registration();
+        let resource = registration.data::<ReflectResource>().unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(Stamina::default());
+        let mut value = resource.reflect(&world).unwrap().clone_value();
+        match value.reflect_mut() {
+            ReflectMut::Struct(stamina) => {
+                *stamina.get_field_mut::<bool>("enabled").unwrap() = true;
+            }
+            _ => panic!("stamina should reflect as a struct"),
+        }
+        resource.apply(&mut world, &*value);
+        assert!(world.get_resource::<Stamina>().unwrap().enabled);
+    }
+}
@@ -1,9 +1,17 @@
 use crate::{
+    challenge_code::ChallengeRun,
+    challenges::{ActiveChallenges, Challenge},
+    change_state,
+    pack::LevelRegistry,
+    replay::ReplayPlayback,
+    save::SaveData,
     ui::GameFont,
+    upgrades::UpgradeTracker,
     world::{GameWorld, WorldType},
     AppState,
 };
 use bevy::prelude::*;
+use std::sync::Arc;
 
 pub struct RetryPlugin;
 
@@ -11,7 +19,9 @@ impl Plugin for RetryPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(AppState::Retry).with_system(create_retry_menu))
             .add_system_set(
-                SystemSet::on_update(AppState::Retry).with_system(manage_retry_buttons),
+                SystemSet::on_update(AppState::Retry)
+                    .with_system(manage_retry_buttons)
+                    .with_system(manage_skip_button),
             );
     }
 }
@@ -22,8 +32,36 @@ enum ButtonType {
     Menu,
 }
 
-fn create_retry_menu(mut commands: Commands, font: Res<GameFont>) {
+#[derive(Component)]
+struct SkipButton;
+
+// Name of the level being played, if it's one that can be skipped
+fn skippable_level(
+    world: &GameWorld,
+    levels: &LevelRegistry,
+    save: &SaveData,
+    playback: Option<&ReplayPlayback>,
+) -> Option<String> {
+    let index = match world.world_type {
+        WorldType::Level { index } if playback.is_none() && save.skip_tokens > 0 => index,
+        _ => return None,
+    };
+    let name = world
+        .variant
+        .level_name(&levels.levels[index].display_name());
+    (!save.is_completed(&name)).then_some(name)
+}
+
+fn create_retry_menu(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
     commands.spawn_bundle(UiCameraBundle::default());
+    let skippable = skippable_level(&world, &levels, &save, playback.as_deref()).is_some();
 
     commands
         .spawn_bundle(NodeBundle {
@@ -119,28 +157,129 @@ fn create_retry_menu(mut commands: Commands, font: Res<GameFont>) {
                         ..TextBundle::default()
                     });
                 });
+
+            // Offered once a token's been earned by failing a level enough times
+            if !skippable {
+                return;
+            }
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Percent(80.0),
+                            ..Rect::default()
+                        },
+                        size: Size::new(Val::Px(300.0), Val::Px(65.0)),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(SkipButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Skip level",
+                            TextStyle {
+                                font: font.get_handle(),
+                                font_size: 40.0,
+                                color: Color::BLACK,
+                            },
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    });
+                    let tokens = match save.skip_tokens {
+                        1 => "1 token left".to_string(),
+                        tokens => format!("{tokens} tokens left"),
+                    };
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            tokens,
+                            TextStyle {
+                                font: font.get_handle(),
+                                font_size: 20.0,
+                                color: Color::BLACK,
+                            },
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    });
+                });
         });
 }
 
 fn manage_retry_buttons(
-    mut commands: Commands,
     mut state: ResMut<State<AppState>>,
-    world: Res<GameWorld>,
     interaction: Query<(&Interaction, &ButtonType), (Changed<Interaction>, With<Button>)>,
 ) {
     for (interaction, button_type) in interaction.iter() {
         match (interaction, button_type) {
+            // The level is left untouched by playing it, so it's reused as is
             (Interaction::Clicked, ButtonType::Retry) => {
-                if let WorldType::Level { index, } = &world.world_type {
-                    commands.insert_resource(GameWorld::load_level(*index).unwrap());
-                }
-
-                state.set(AppState::Game).unwrap();
+                change_state(&mut state, AppState::Game);
             }
             (Interaction::Clicked, ButtonType::Menu) => {
-                state.set(AppState::Menu).unwrap();
+                change_state(&mut state, AppState::Menu);
             }
             _ => {}
         }
     }
 }
+
+// Spend a token on the level, marking it complete, and move on to the next one
+fn manage_skip_button(
+    mut commands: Commands,
+    mut state: ResMut<State<AppState>>,
+    mut save: ResMut<SaveData>,
+    mut levels: ResMut<LevelRegistry>,
+    mut upgrades: ResMut<UpgradeTracker>,
+    world: Res<Arc<GameWorld>>,
+    challenges: Res<ActiveChallenges>,
+    playback: Option<Res<ReplayPlayback>>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<SkipButton>)>,
+) {
+    if !interaction
+        .iter()
+        .any(|interaction| matches!(interaction, Interaction::Clicked))
+    {
+        return;
+    }
+    let name = match skippable_level(&world, &levels, &save, playback.as_deref()) {
+        Some(name) => name,
+        None => return,
+    };
+    save.skip_level(name);
+
+    let next = match world.world_type {
+        WorldType::Level { index } if index + 1 < levels.levels.len() => index + 1,
+        _ => {
+            change_state(&mut state, AppState::Menu);
+            return;
+        }
+    };
+    match levels.load_cached(
+        next,
+        world.variant,
+        challenges.is_active(Challenge::Mirrored),
+    ) {
+        Ok(world) => {
+            commands.insert_resource(world);
+            // A shared challenge is only for the level it was shared on
+            commands.insert_resource(ChallengeRun::default());
+            if challenges.is_active(Challenge::NoUpgrades) {
+                *upgrades = UpgradeTracker::default();
+                change_state(&mut state, AppState::Game);
+            } else {
+                change_state(&mut state, AppState::UpgradeSelect);
+            }
+        }
+        Err(err) => {
+            warn!("Failed to load the next level: {err}");
+            change_state(&mut state, AppState::Menu);
+        }
+    }
+}
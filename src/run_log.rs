@@ -0,0 +1,325 @@
+use crate::{
+    clock::GameClock,
+    game_overlay::GameTimer,
+    movement::Velocity,
+    pack::LevelRegistry,
+    player::Player,
+    playing,
+    profile::Profile,
+    wall_contact::WallContact,
+    world::{GameWorld, WorldOrigin, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use std::{
+    fmt::{self, Write},
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Where exported runs are kept in the player's profile
+const RUNS_DIRECTORY: &str = "runs";
+// Seconds of game time between samples of the bee's flight
+const SAMPLE_SECONDS: f32 = 0.05;
+
+// Samples the bee's flight through the current run, so it can be exported from the victory
+// screen as CSV and JSON and graphed outside the game
+pub struct RunLogPlugin;
+
+impl Plugin for RunLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunLog>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(start_run_log))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(log_run),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Death).with_system(log_death))
+            .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(log_finish));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunEvent {
+    Start,
+    Checkpoint(usize),
+    WallHit,
+    Death,
+    Finish,
+}
+
+impl fmt::Display for RunEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunEvent::Start => write!(f, "start"),
+            RunEvent::Checkpoint(index) => write!(f, "checkpoint {}", index + 1),
+            RunEvent::WallHit => write!(f, "wall hit"),
+            RunEvent::Death => write!(f, "death"),
+            RunEvent::Finish => write!(f, "finish"),
+        }
+    }
+}
+
+// Where the bee was at a point in the run, in pixels from the level's origin with y up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub time: f32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+#[derive(Debug, Default)]
+pub struct RunLog {
+    pub level: String,
+    // Seconds of game time since the run started
+    time: f32,
+    // Seconds of game time until the next sample
+    next_sample: f32,
+    pub samples: Vec<Sample>,
+    pub events: Vec<(Sample, RunEvent)>,
+    checkpoints_logged: usize,
+    touching_wall: bool,
+}
+
+impl RunLog {
+    // One row per sample and per event, with the event column left empty for samples
+    pub fn to_csv(&self) -> String {
+        let mut rows: Vec<_> = self
+            .samples
+            .iter()
+            .map(|sample| (sample, None))
+            .chain(
+                self.events
+                    .iter()
+                    .map(|(sample, event)| (sample, Some(event))),
+            )
+            .collect();
+        // Stable, so events come after the sample taken on the same frame
+        rows.sort_by(|(a, _), (b, _)| a.time.total_cmp(&b.time));
+
+        let mut csv = String::from("time,x,y,velocity_x,velocity_y,event\n");
+        for (sample, event) in rows {
+            let event = event.map(RunEvent::to_string).unwrap_or_default();
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{event}",
+                sample.time,
+                sample.position.x,
+                sample.position.y,
+                sample.velocity.x,
+                sample.velocity.y,
+            );
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let sample_json = |sample: &Sample| {
+            format!(
+                "\"time\":{},\"x\":{},\"y\":{},\"velocity_x\":{},\"velocity_y\":{}",
+                sample.time,
+                sample.position.x,
+                sample.position.y,
+                sample.velocity.x,
+                sample.velocity.y,
+            )
+        };
+        let samples: Vec<_> = self
+            .samples
+            .iter()
+            .map(|sample| format!("{{{}}}", sample_json(sample)))
+            .collect();
+        let events: Vec<_> = self
+            .events
+            .iter()
+            .map(|(sample, event)| {
+                format!(
+                    "{{\"event\":{},{}}}",
+                    json_string(&event.to_string()),
+                    sample_json(sample)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"level\":{},\"samples\":[{}],\"events\":[{}]}}\n",
+            json_string(&self.level),
+            samples.join(","),
+            events.join(",")
+        )
+    }
+
+    // Write both formats next to each other, returning the path without an extension
+    pub fn export(&self, profile: &Profile) -> io::Result<PathBuf> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let level: String = self
+            .level
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let path = profile
+            .path(RUNS_DIRECTORY)
+            .join(format!("{level}_{seconds}"));
+        fs::create_dir_all(profile.path(RUNS_DIRECTORY))?;
+        fs::write(path.with_extension("csv"), self.to_csv())?;
+        fs::write(path.with_extension("json"), self.to_json())?;
+        Ok(path)
+    }
+
+    fn sample(&self, position: Vec2, velocity: Vec2) -> Sample {
+        Sample {
+            time: self.time,
+            position,
+            velocity,
+        }
+    }
+}
+
+// `text` quoted for JSON
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn start_run_log(mut log: ResMut<RunLog>, world: Res<Arc<GameWorld>>, levels: Res<LevelRegistry>) {
+    let level = match world.world_type {
+        WorldType::Level { index } => world
+            .variant
+            .level_name(&levels.levels[index].display_name()),
+        WorldType::Endless => "Endless".to_string(),
+    };
+    *log = RunLog {
+        level,
+        ..RunLog::default()
+    };
+}
+
+// Where the player is relative to the level, and how fast they're going
+fn player_sample(
+    log: &RunLog,
+    origin: &WorldOrigin,
+    player: &Query<(&Transform, &Velocity), With<Player>>,
+) -> Option<Sample> {
+    let (transform, velocity) = player.get_single().ok()?;
+    Some(log.sample(transform.translation.truncate() - origin.0, velocity.0))
+}
+
+fn log_run(
+    clock: Res<GameClock>,
+    timer: Res<GameTimer>,
+    origin: Res<WorldOrigin>,
+    mut log: ResMut<RunLog>,
+    player: Query<(&Transform, &Velocity), With<Player>>,
+    contact: Query<&WallContact, With<Player>>,
+) {
+    let sample = match player_sample(&log, &origin, &player) {
+        Some(sample) => sample,
+        None => return,
+    };
+    if log.events.is_empty() {
+        log.events.push((sample, RunEvent::Start));
+    }
+    if log.next_sample <= 0.0 {
+        log.samples.push(sample);
+        log.next_sample += SAMPLE_SECONDS;
+    }
+
+    for &(checkpoint, _) in &timer.splits()[log.checkpoints_logged..] {
+        log.events.push((sample, RunEvent::Checkpoint(checkpoint)));
+    }
+    log.checkpoints_logged = timer.splits().len();
+
+    let touching = contact
+        .get_single()
+        .is_ok_and(|contact| contact.normal.is_some());
+    if touching && !log.touching_wall {
+        log.events.push((sample, RunEvent::WallHit));
+    }
+    log.touching_wall = touching;
+
+    log.time += clock.delta_seconds();
+    log.next_sample -= clock.delta_seconds();
+}
+
+fn log_death(
+    mut log: ResMut<RunLog>,
+    origin: Res<WorldOrigin>,
+    player: Query<(&Transform, &Velocity), With<Player>>,
+) {
+    if let Some(sample) = player_sample(&log, &origin, &player) {
+        log.events.push((sample, RunEvent::Death));
+    }
+}
+
+fn log_finish(
+    mut log: ResMut<RunLog>,
+    origin: Res<WorldOrigin>,
+    player: Query<(&Transform, &Velocity), With<Player>>,
+) {
+    if let Some(sample) = player_sample(&log, &origin, &player) {
+        log.events.push((sample, RunEvent::Finish));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> RunLog {
+        let sample = |time, x| Sample {
+            time,
+            position: Vec2::new(x, -24.0),
+            velocity: Vec2::new(10.0, 0.5),
+        };
+        RunLog {
+            level: "The \"Hive\"".to_string(),
+            samples: vec![sample(0.0, 0.0), sample(0.5, 5.0)],
+            events: vec![
+                (sample(0.0, 0.0), RunEvent::Start),
+                (sample(0.25, 2.5), RunEvent::Checkpoint(0)),
+            ],
+            ..RunLog::default()
+        }
+    }
+
+    #[test]
+    fn runs_are_exported_as_csv() {
+        assert_eq!(
+            log().to_csv(),
+            "time,x,y,velocity_x,velocity_y,event\n\
+             0,0,-24,10,0.5,\n\
+             0,0,-24,10,0.5,start\n\
+             0.25,2.5,-24,10,0.5,checkpoint 1\n\
+             0.5,5,-24,10,0.5,\n"
+        );
+    }
+
+    #[test]
+    fn runs_are_exported_as_json() {
+        assert_eq!(
+            log().to_json(),
+            "{\"level\":\"The \\\"Hive\\\"\",\"samples\":[\
+             {\"time\":0,\"x\":0,\"y\":-24,\"velocity_x\":10,\"velocity_y\":0.5},\
+             {\"time\":0.5,\"x\":5,\"y\":-24,\"velocity_x\":10,\"velocity_y\":0.5}],\
+             \"events\":[\
+             {\"event\":\"start\",\"time\":0,\"x\":0,\"y\":-24,\"velocity_x\":10,\"velocity_y\":0.5},\
+             {\"event\":\"checkpoint 1\",\"time\":0.25,\"x\":2.5,\"y\":-24,\"velocity_x\":10,\"velocity_y\":0.5}]}\n"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    challenge_code::ChallengeRun,
+    challenges::ActiveChallenges,
+    controls::Controls,
+    enemy::{EnemyDespawned, Projectile},
+    game_overlay::GameTimer,
+    pack::LevelRegistry,
+    playing,
+    profile::Profile,
+    replay::ReplayPlayback,
+    settings::Settings,
+    upgrades::{Upgrade, UpgradeTracker},
+    world::{GameWorld, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::Arc,
+};
+
+const SAVE_FILE: &str = "save.ron";
+// Failing a level this many times earns a token to skip a level with
+pub const SKIP_TOKEN_ATTEMPTS: u32 = 10;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveData>()
+            .init_resource::<RunDodges>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(reset_dodges))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Victory)
+                    .with_system(record_victory)
+                    .with_system(record_dodges),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Death)
+                    .with_system(record_death)
+                    .with_system(record_dodges),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(record_ability_uses)
+                    .with_system(count_dodges),
+            )
+            .add_system_to_stage(CoreStage::Last, write_save);
+    }
+}
+
+// Player progress kept between sessions
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub player_name: Option<String>,
+    // Whether the first-run introduction has been finished or skipped. Saves from before it
+    // existed belong to players who know the game already.
+    #[serde(default = "existing_save_is_onboarded")]
+    pub onboarded: bool,
+    // Best completion time in seconds, keyed by level name
+    pub best_times: HashMap<String, f32>,
+    // Best times of runs with challenges, keyed by level and challenges
+    pub challenge_times: HashMap<String, f32>,
+    // Best times of runs at a reduced game speed, keyed by level, challenges and speed
+    pub reduced_speed_times: HashMap<String, f32>,
+    // Hash of the layout each best time above was set on, under the same key, so times
+    // from an edited copy of a level don't mix with times from the original
+    pub level_hashes: HashMap<String, u64>,
+    // Name of the selected bee skin
+    pub skin: Option<String>,
+    // Name of the selected accessory, if any
+    pub accessory: Option<String>,
+    // Upgrades chosen for the last level attempt
+    pub loadout: UpgradeTracker,
+    pub settings: Settings,
+    pub stats: PlayerStats,
+    // Seasonal events the player has played during, which keep their rewards unlocked
+    pub events_seen: HashSet<String>,
+    // Newest changelog version the player has opened What's new for
+    pub news_seen: Option<String>,
+    // Deaths on each level, keyed by level name like best times
+    pub failed_attempts: HashMap<String, u32>,
+    // Unspent tokens, each of which can mark a level complete without beating it
+    pub skip_tokens: u32,
+    // Levels marked complete with a skip token, by name
+    pub skipped_levels: HashSet<String>,
+    // Tiles the player died on, latest last, for each level by name
+    pub death_tiles: HashMap<String, Vec<(usize, usize)>>,
+}
+
+// Running totals of what the player has done, across all levels
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerStats {
+    pub deaths: u32,
+    pub teleports: u32,
+    // Presses of the slow motion key, whether or not a charge was left
+    pub slow_motions: u32,
+    // Projectiles that flew out of range or into a wall without hitting the player
+    pub lasers_dodged: u32,
+    pub missiles_dodged: u32,
+}
+
+// Which best times a run competes with
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Records {
+    Standard,
+    Challenges,
+    ReducedSpeed,
+}
+
+// Dodges so far this run, added to the stats when it ends rather than writing the save every
+// time a laser goes by
+#[derive(Debug, Default)]
+struct RunDodges {
+    lasers: u32,
+    missiles: u32,
+}
+
+fn existing_save_is_onboarded() -> bool {
+    true
+}
+
+impl SaveData {
+    pub fn load(profile: &Profile) -> Self {
+        match fs::read_to_string(profile.path(SAVE_FILE)) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Ignoring unreadable save file: {err}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn write(&self, profile: &Profile) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                fs::write(profile.path(SAVE_FILE), contents).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("Failed to write save file: {err}");
+        }
+    }
+}
+
+fn record_victory(
+    mut save: ResMut<SaveData>,
+    timer: Res<GameTimer>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+    challenge_run: Res<ChallengeRun>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    // Watching a replay doesn't count as beating the level
+    if playback.is_some() {
+        return;
+    }
+    if let WorldType::Level { index } = world.world_type {
+        let name = world
+            .variant
+            .level_name(&levels.levels[index].display_name());
+        // Runs from a shared code are kept apart from everyone else's, under the code
+        let with_challenges = !challenges.is_empty() || challenge_run.0.is_some();
+        let name = match &challenge_run.0 {
+            Some(code) => format!("{} #{}", challenges.run_name(&name), code.encode()),
+            None if with_challenges => challenges.run_name(&name),
+            None => name,
+        };
+        // Slowed down runs are easier, so they only compete with each other
+        let speed = save.settings.game_speed;
+        let (name, records) = match speed {
+            speed if speed < 100 => (format!("{name} [{speed}% speed]"), Records::ReducedSpeed),
+            _ if with_challenges => (name, Records::Challenges),
+            _ => (name, Records::Standard),
+        };
+        save.record_time(name, records, world.hash(), timer.elapsed());
+    }
+}
+
+impl SaveData {
+    // Keep a time if it's the best on this layout. A best time set on a different layout
+    // is replaced, while ones from before hashes were kept are assumed to match.
+    fn record_time(&mut self, name: String, records: Records, hash: u64, time: f32) {
+        let same_level = self
+            .level_hashes
+            .insert(name.clone(), hash)
+            .is_none_or(|previous| previous == hash);
+        let times = match records {
+            Records::Standard => &mut self.best_times,
+            Records::Challenges => &mut self.challenge_times,
+            Records::ReducedSpeed => &mut self.reduced_speed_times,
+        };
+        let best = times.entry(name).or_insert(time);
+        *best = if same_level { best.min(time) } else { time };
+    }
+}
+
+fn record_death(
+    mut save: ResMut<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    save.stats.deaths += 1;
+    if let WorldType::Level { index } = world.world_type {
+        let name = world
+            .variant
+            .level_name(&levels.levels[index].display_name());
+        save.record_failure(name);
+    }
+}
+
+impl SaveData {
+    // One token is earned per level, the SKIP_TOKEN_ATTEMPTS'th time it's failed
+    fn record_failure(&mut self, name: String) {
+        let attempts = self.failed_attempts.entry(name).or_default();
+        *attempts += 1;
+        if *attempts == SKIP_TOKEN_ATTEMPTS {
+            self.skip_tokens += 1;
+        }
+    }
+
+    // Spend a token to mark a level complete, without a time. Levels that are already
+    // complete don't take one.
+    pub fn skip_level(&mut self, name: String) -> bool {
+        if self.skip_tokens == 0 || self.is_completed(&name) {
+            return false;
+        }
+        self.skip_tokens -= 1;
+        self.skipped_levels.insert(name);
+        true
+    }
+
+    // Beaten or skipped
+    pub fn is_completed(&self, name: &str) -> bool {
+        self.best_times.contains_key(name) || self.skipped_levels.contains(name)
+    }
+}
+
+fn record_ability_uses(
+    mut save: ResMut<SaveData>,
+    controls: Res<Controls>,
+    upgrades: Res<UpgradeTracker>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    if upgrades.was_upgrade_activated(&controls, Upgrade::Teleport) {
+        save.stats.teleports += 1;
+    }
+    if controls.slow_motion.just_pressed {
+        save.stats.slow_motions += 1;
+    }
+}
+
+fn reset_dodges(mut dodges: ResMut<RunDodges>) {
+    *dodges = RunDodges::default();
+}
+
+fn count_dodges(mut dodges: ResMut<RunDodges>, mut despawned: EventReader<EnemyDespawned>) {
+    for event in despawned.iter() {
+        match event.projectile {
+            Projectile::Laser { .. } => dodges.lasers += 1,
+            Projectile::Missile => dodges.missiles += 1,
+        }
+    }
+}
+
+fn record_dodges(
+    mut save: ResMut<SaveData>,
+    mut dodges: ResMut<RunDodges>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    let dodges = std::mem::take(&mut *dodges);
+    if playback.is_none() {
+        save.stats.lasers_dodged += dodges.lasers;
+        save.stats.missiles_dodged += dodges.missiles;
+    }
+}
+
+// Nothing is written until there's a profile to write it to, and switching profiles
+// replaces the save without writing it back
+fn write_save(save: Res<SaveData>, profile: Option<Res<Profile>>) {
+    if let Some(profile) = profile {
+        if save.is_changed() && !save.is_added() {
+            save.write(&profile);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Variant;
+    use bevy::app::Events;
+
+    #[test]
+    fn best_times_are_kept_per_layout() {
+        let mut save = SaveData::default();
+        save.best_times.insert("Level 0".into(), 5.0);
+        // A time from before level hashes is kept
+        save.record_time("Level 0".into(), Records::Standard, 1, 6.0);
+        assert_eq!(save.best_times["Level 0"], 5.0);
+        save.record_time("Level 0".into(), Records::Standard, 1, 4.0);
+        assert_eq!(save.best_times["Level 0"], 4.0);
+
+        // An edited copy of the level starts again
+        save.record_time("Level 0".into(), Records::Standard, 2, 9.0);
+        assert_eq!(save.best_times["Level 0"], 9.0);
+        assert_eq!(save.level_hashes["Level 0"], 2);
+
+        save.record_time("Level 0 [Fog of war]".into(), Records::Challenges, 1, 7.0);
+        assert_eq!(save.challenge_times["Level 0 [Fog of war]"], 7.0);
+        save.record_time("Level 0 [70% speed]".into(), Records::ReducedSpeed, 1, 3.0);
+        assert_eq!(save.reduced_speed_times["Level 0 [70% speed]"], 3.0);
+        assert_eq!(save.best_times.len(), 1);
+    }
+
+    #[test]
+    fn slowed_down_runs_are_kept_apart() {
+        let mut levels = LevelRegistry::default();
+        let level = levels.load_cached(0, Variant::Original, false).unwrap();
+        let name = format!("{} [70% speed]", levels.levels[0].display_name());
+        let mut save = SaveData::default();
+        save.settings.game_speed = 70;
+
+        let mut world = World::new();
+        world.insert_resource(save);
+        world.insert_resource(GameTimer::default());
+        world.insert_resource(level);
+        world.insert_resource(levels);
+        world.insert_resource(ActiveChallenges::default());
+        world.insert_resource(ChallengeRun::default());
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(record_victory);
+        stage.run(&mut world);
+
+        let save = world.get_resource::<SaveData>().unwrap();
+        assert!(save.reduced_speed_times.contains_key(&name));
+        assert!(save.best_times.is_empty());
+        assert!(save.challenge_times.is_empty());
+    }
+
+    #[test]
+    fn failing_a_level_earns_one_skip_token() {
+        let mut save = SaveData::default();
+        for _ in 0..SKIP_TOKEN_ATTEMPTS * 2 {
+            save.record_failure("Level 1".into());
+        }
+        assert_eq!(save.skip_tokens, 1);
+        assert!(!save.is_completed("Level 1"));
+
+        assert!(save.skip_level("Level 1".into()));
+        assert!(save.is_completed("Level 1"));
+        assert!(!save.best_times.contains_key("Level 1"));
+        assert!(!save.skip_level("Level 2".into()));
+
+        // Beaten levels don't need skipping
+        save.skip_tokens = 1;
+        save.best_times.insert("Level 0".into(), 5.0);
+        assert!(!save.skip_level("Level 0".into()));
+        assert_eq!(save.skip_tokens, 1);
+    }
+
+    #[test]
+    fn dodges_are_counted_by_projectile() {
+        let mut world = World::new();
+        world.insert_resource(RunDodges::default());
+        world.insert_resource(Events::<EnemyDespawned>::default());
+        let mut events = world.get_resource_mut::<Events<EnemyDespawned>>().unwrap();
+        for projectile in [
+            Projectile::Missile,
+            Projectile::Laser {
+                angle: 0.0,
+                blocked: true,
+            },
+            Projectile::Laser {
+                angle: 1.0,
+                blocked: false,
+            },
+        ] {
+            events.send(EnemyDespawned { projectile });
+        }
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(count_dodges);
+        stage.run(&mut world);
+
+        let dodges = world.get_resource::<RunDodges>().unwrap();
+        assert_eq!((dodges.lasers, dodges.missiles), (2, 1));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    camera::MainCamera,
+    enemy::{Projectile, Spawning},
+    player::{Player, Shield},
+    playing,
+    render_layer::RenderLayer,
+    save::SaveData,
+    upgrades::{Upgrade, UpgradeTracker},
+    AppState, GameState,
+};
+use bevy::{
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        render_asset::{PrepareAssetError, RenderAsset},
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer,
+            BufferBindingType, BufferInitDescriptor, BufferSize, BufferUsages, ShaderStages,
+        },
+        renderer::RenderDevice,
+    },
+    sprite::{Material2d, Material2dPipeline, Material2dPlugin, MaterialMesh2dBundle},
+};
+
+// Full screen effects drawn over the game: a vignette when the player is one hit
+// from death, a colour fringe on near misses and a washed out screen while paused
+pub struct ScreenEffectsPlugin;
+
+impl Plugin for ScreenEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<ScreenEffectsMaterial>::default())
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(spawn_screen_effects))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(detect_near_misses),
+            )
+            // Keeps running while paused and dead so the effects can ease out
+            .add_system(update_screen_effects);
+    }
+}
+
+// Projectiles passing closer than this to the player count as near misses
+const NEAR_MISS_DISTANCE: f32 = Player::SIZE * 1.5;
+// How quickly effects ease towards their target, per second
+const EFFECT_EASING: f32 = 4.0;
+const ABERRATION_DECAY: f32 = 2.5;
+
+#[derive(Component)]
+struct ScreenEffects;
+
+// Set on projectiles once they've triggered a near miss
+#[derive(Component)]
+struct NearMissed;
+
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "a4b0f3e1-2f7c-4d5a-8c39-5e6d1b7f0c24"]
+pub struct ScreenEffectsMaterial {
+    vignette: f32,
+    aberration: f32,
+    desaturation: f32,
+    time: f32,
+}
+
+impl ScreenEffectsMaterial {
+    // Matches ScreenEffects in screen_effects.wgsl
+    const UNIFORM_SIZE: u64 = 16;
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        [self.vignette, self.aberration, self.desaturation, self.time]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect()
+    }
+}
+
+pub struct GpuScreenEffectsMaterial {
+    _buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for ScreenEffectsMaterial {
+    type ExtractedAsset = ScreenEffectsMaterial;
+    type PreparedAsset = GpuScreenEffectsMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<Material2dPipeline<ScreenEffectsMaterial>>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        material: Self::ExtractedAsset,
+        (render_device, pipeline): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("screen_effects_uniform_buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: &material.uniform_bytes(),
+        });
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("screen_effects_bind_group"),
+            layout: &pipeline.material2d_layout,
+        });
+
+        Ok(GpuScreenEffectsMaterial {
+            _buffer: buffer,
+            bind_group,
+        })
+    }
+}
+
+impl Material2d for ScreenEffectsMaterial {
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/screen_effects.wgsl"))
+    }
+
+    fn bind_group(material: &GpuScreenEffectsMaterial) -> &BindGroup {
+        &material.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(ScreenEffectsMaterial::UNIFORM_SIZE),
+                },
+                count: None,
+            }],
+            label: Some("screen_effects_layout"),
+        })
+    }
+}
+
+fn spawn_screen_effects(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ScreenEffectsMaterial>>,
+) {
+    if !save.settings.post_processing || save.settings.low_spec {
+        return;
+    }
+
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            // Unit quad, stretched over the screen every frame
+            mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE))).into(),
+            material: materials.add(ScreenEffectsMaterial::default()),
+            ..MaterialMesh2dBundle::default()
+        })
+        .insert(RenderLayer::PostProcessing)
+        .insert(ScreenEffects);
+}
+
+fn detect_near_misses(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ScreenEffectsMaterial>>,
+    effects: Query<&Handle<ScreenEffectsMaterial>>,
+    player: Query<&GlobalTransform, With<Player>>,
+    projectiles: Query<
+        (Entity, &GlobalTransform),
+        (With<Projectile>, Without<NearMissed>, Without<Spawning>),
+    >,
+) {
+    let material = match effects.get_single() {
+        Ok(handle) => materials.get_mut(handle),
+        Err(_) => return,
+    };
+    let (material, player) = match (material, player.get_single()) {
+        (Some(material), Ok(player)) => (material, player),
+        _ => return,
+    };
+
+    for (entity, transform) in projectiles.iter() {
+        let distance = transform
+            .translation
+            .truncate()
+            .distance(player.translation.truncate());
+        if distance < NEAR_MISS_DISTANCE {
+            material.aberration = 1.0;
+            commands.entity(entity).insert(NearMissed);
+        }
+    }
+}
+
+fn update_screen_effects(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    state: Res<State<GameState>>,
+    upgrades: Res<UpgradeTracker>,
+    mut materials: ResMut<Assets<ScreenEffectsMaterial>>,
+    mut effects: Query<(&Handle<ScreenEffectsMaterial>, &mut Transform), With<ScreenEffects>>,
+    camera: Query<
+        (&Transform, &OrthographicProjection),
+        (With<MainCamera>, Without<ScreenEffects>),
+    >,
+    player: Query<Option<&Shield>, With<Player>>,
+) {
+    let (handle, mut transform) = match effects.get_single_mut() {
+        Ok(effects) => effects,
+        Err(_) => return,
+    };
+
+    // Cover exactly what the camera sees
+    if let (Ok((camera, projection)), Some(window)) = (camera.get_single(), windows.get_primary()) {
+        transform.translation.x = camera.translation.x;
+        transform.translation.y = camera.translation.y;
+        transform.scale =
+            (Vec2::new(window.width(), window.height()) * projection.scale).extend(1.0);
+    }
+
+    if let Some(material) = materials.get_mut(handle) {
+        let delta = time.delta_seconds();
+        let ease =
+            |value: f32, target: f32| value + (target - value) * (EFFECT_EASING * delta).min(1.0);
+
+        // The player is a hit from death once the shield upgrade is used up
+        let last_hit =
+            upgrades.has_upgrade(Upgrade::Shield) && matches!(player.get_single(), Ok(None));
+        let paused = *state.current() == GameState::Paused;
+
+        material.vignette = ease(material.vignette, if last_hit { 1.0 } else { 0.0 });
+        material.desaturation = ease(material.desaturation, if paused { 1.0 } else { 0.0 });
+        material.aberration = (material.aberration - ABERRATION_DECAY * delta).max(0.0);
+        material.time = time.seconds_since_startup() as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_matches_shader_layout() {
+        let material = ScreenEffectsMaterial {
+            desaturation: 0.5,
+            ..ScreenEffectsMaterial::default()
+        };
+        let bytes = material.uniform_bytes();
+        assert_eq!(bytes.len() as u64, ScreenEffectsMaterial::UNIFORM_SIZE);
+        assert_eq!(f32::from_ne_bytes(bytes[8..12].try_into().unwrap()), 0.5);
+    }
+}
@@ -0,0 +1,549 @@
+use crate::{
+    camera::CameraOverride,
+    change_state,
+    clock::GameClock,
+    enemy::{Projectile, SpawnEnemies, SpawnEnemy},
+    player::Player,
+    playing,
+    ui::GameFont,
+    world::{GameWorld, OpenWalls, Wall, WorldOrigin},
+    AppState, GameState,
+};
+use bevy::prelude::*;
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, AST, FLOAT, INT};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+// Seconds that script text stays on screen
+const TEXT_DURATION: f32 = 3.0;
+// Steps a script can take in one go before it's stopped, so a stuck loop can't hang the game
+const MAX_OPERATIONS: u64 = 100_000;
+
+// Runs the Rhai scripts attached to pack levels. Scripts can only act on the game through
+// LevelCommand events, so adding a command here is the only way to widen what they can do.
+pub struct ScriptPlugin;
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelCommand>()
+            .init_resource::<Dialogue>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(load_script))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(fire_triggers)
+                    .with_system(run_level_commands.before(SpawnEnemies))
+                    .with_system(toggle_walls)
+                    .with_system(expire_script_text)
+                    .with_system(start_dialogue),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Dialogue).with_system(show_dialogue))
+            .add_system_set(SystemSet::on_update(GameState::Dialogue).with_system(advance_dialogue))
+            .add_system_set(SystemSet::on_exit(GameState::Dialogue).with_system(hide_dialogue));
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Trigger {
+    // When the level starts
+    Start,
+    // After the given number of seconds
+    Time(f32),
+    // Each time the player enters the tile at (x, y)
+    Tile((usize, usize)),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelCommand {
+    ShowText(String),
+    // Stop the game until the player dismisses the message
+    Dialogue(String),
+    // Pan the camera to a tile for the given number of seconds
+    MoveCamera { tile: (usize, usize), seconds: f32 },
+    SpawnMissile((usize, usize)),
+    ToggleWalls,
+}
+
+// What the script has asked for since it was last run, through the functions given to it
+#[derive(Default)]
+struct Calls {
+    triggers: Vec<(Trigger, FnPtr)>,
+    commands: Vec<LevelCommand>,
+}
+
+// The current level's script, with the triggers it has set up
+struct Script {
+    engine: Engine,
+    ast: AST,
+    calls: Arc<Mutex<Calls>>,
+    triggers: Vec<(Trigger, FnPtr)>,
+    elapsed: f32,
+    // Tile the player was on last frame, so tile triggers fire on the way in
+    player_tile: Option<(usize, usize)>,
+}
+
+impl Script {
+    // Compile a Rhai script and run it once, which is when it sets up its triggers:
+    //   on_start(|| ...), after(<seconds>, || ...), on_tile(<x>, <y>, || ...)
+    // Triggers can then call:
+    //   text(<message>), dialogue(<message>), camera(<x>, <y>, <seconds>), missile(<x>, <y>),
+    //   walls()
+    // Tiles are given as in the level file, and follow the layout if it's been transformed.
+    // Nothing else in the game is reachable, and runaway scripts are cut off.
+    fn new(source: &str, world: &Arc<GameWorld>) -> Result<Self, String> {
+        let calls = Arc::new(Mutex::new(Calls::default()));
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1000)
+            .set_max_array_size(1000)
+            .set_max_map_size(1000)
+            .on_print(|text| info!("Level script: {text}"))
+            .on_debug(|text, _, _| debug!("Level script: {text}"));
+
+        // Whole seconds can be written without a decimal point
+        let seconds = |value: Dynamic| -> Result<f32, Box<EvalAltResult>> {
+            match value
+                .as_float()
+                .or_else(|_| value.as_int().map(|value| value as FLOAT))
+            {
+                Ok(value) if value >= 0.0 => Ok(value as f32),
+                _ => Err(format!("invalid number of seconds {value}").into()),
+            }
+        };
+        let tile = {
+            let world = world.clone();
+            move |x: INT, y: INT| -> Result<(usize, usize), Box<EvalAltResult>> {
+                match (usize::try_from(x), usize::try_from(y)) {
+                    (Ok(x), Ok(y)) => Ok(world.map_tile((x, y))),
+                    _ => Err(format!("invalid tile {x}, {y}").into()),
+                }
+            }
+        };
+        let trigger = |calls: &Arc<Mutex<Calls>>| {
+            let calls = calls.clone();
+            move |trigger, callback| calls.lock().unwrap().triggers.push((trigger, callback))
+        };
+        let command = |calls: &Arc<Mutex<Calls>>| {
+            let calls = calls.clone();
+            move |command| calls.lock().unwrap().commands.push(command)
+        };
+
+        let on_start = trigger(&calls);
+        engine.register_fn("on_start", move |callback: FnPtr| {
+            on_start(Trigger::Start, callback)
+        });
+        let after = trigger(&calls);
+        engine.register_fn("after", move |at: Dynamic, callback: FnPtr| {
+            after(Trigger::Time(seconds(at)?), callback);
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let (on_tile, tile_at) = (trigger(&calls), tile.clone());
+        engine.register_fn("on_tile", move |x: INT, y: INT, callback: FnPtr| {
+            on_tile(Trigger::Tile(tile_at(x, y)?), callback);
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+
+        let text = command(&calls);
+        engine.register_fn("text", move |message: &str| {
+            text(LevelCommand::ShowText(message.into()))
+        });
+        let dialogue = command(&calls);
+        engine.register_fn("dialogue", move |message: &str| {
+            dialogue(LevelCommand::Dialogue(message.into()))
+        });
+        let (camera, tile_at) = (command(&calls), tile.clone());
+        engine.register_fn("camera", move |x: INT, y: INT, duration: Dynamic| {
+            camera(LevelCommand::MoveCamera {
+                tile: tile_at(x, y)?,
+                seconds: seconds(duration)?,
+            });
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let missile = command(&calls);
+        engine.register_fn("missile", move |x: INT, y: INT| {
+            missile(LevelCommand::SpawnMissile(tile(x, y)?));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+        let walls = command(&calls);
+        engine.register_fn("walls", move || walls(LevelCommand::ToggleWalls));
+
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        engine.run_ast(&ast).map_err(|err| err.to_string())?;
+        // Commands given outside a trigger are left for the first frame
+        let triggers = std::mem::take(&mut calls.lock().unwrap().triggers);
+        Ok(Self {
+            engine,
+            ast,
+            calls,
+            triggers,
+            elapsed: 0.0,
+            player_tile: None,
+        })
+    }
+
+    // Move the script's clock on, and run every trigger that fires. Returns the commands
+    // they gave, in order.
+    fn advance(&mut self, seconds: f32, player_tile: Option<(usize, usize)>) -> Vec<LevelCommand> {
+        self.elapsed += seconds;
+        let entered = player_tile.filter(|tile| self.player_tile != Some(*tile));
+        self.player_tile = player_tile;
+
+        let mut fired = Vec::new();
+        let elapsed = self.elapsed;
+        // Start and timed triggers fire once, tile triggers every time
+        self.triggers.retain(|(trigger, callback)| match trigger {
+            Trigger::Start => {
+                fired.push(callback.clone());
+                false
+            }
+            Trigger::Time(at) if elapsed >= *at => {
+                fired.push(callback.clone());
+                false
+            }
+            Trigger::Time(_) => true,
+            Trigger::Tile(tile) => {
+                if entered == Some(*tile) {
+                    fired.push(callback.clone());
+                }
+                true
+            }
+        });
+        for callback in fired {
+            if let Err(err) = callback.call::<Dynamic>(&self.engine, &self.ast, ()) {
+                warn!("Level script trigger failed: {err}");
+            }
+        }
+
+        // Triggers can set up more triggers
+        let mut calls = self.calls.lock().unwrap();
+        self.triggers.append(&mut calls.triggers);
+        std::mem::take(&mut calls.commands)
+    }
+}
+
+#[derive(Component)]
+struct ScriptText(Timer);
+
+// Dialogue lines waiting to be shown, in order
+#[derive(Default)]
+struct Dialogue(VecDeque<String>);
+
+#[derive(Component)]
+struct DialogueText;
+
+fn load_script(mut commands: Commands, world: Res<Arc<GameWorld>>, mut dialogue: ResMut<Dialogue>) {
+    commands.remove_resource::<Script>();
+    commands.remove_resource::<CameraOverride>();
+    dialogue.0.clear();
+
+    let script = match world
+        .script
+        .as_deref()
+        .map(|source| Script::new(source, &world))
+    {
+        Some(Ok(script)) => script,
+        Some(Err(err)) => {
+            warn!("Ignoring level script: {err}");
+            return;
+        }
+        None => return,
+    };
+    commands.insert_resource(script);
+}
+
+fn fire_triggers(
+    clock: Res<GameClock>,
+    script: Option<ResMut<Script>>,
+    origin: Res<WorldOrigin>,
+    player: Query<&Transform, With<Player>>,
+    mut level_commands: EventWriter<LevelCommand>,
+) {
+    let mut script = match script {
+        Some(script) => script,
+        None => return,
+    };
+    let player_tile = player
+        .get_single()
+        .ok()
+        .and_then(|transform| origin.world_to_tile(transform.translation.truncate()));
+    for command in script.advance(clock.delta_seconds(), player_tile) {
+        level_commands.send(command);
+    }
+}
+
+fn run_level_commands(
+    mut commands: Commands,
+    mut level_commands: EventReader<LevelCommand>,
+    mut launches: EventWriter<SpawnEnemy>,
+    font: Res<GameFont>,
+    mut dialogue: ResMut<Dialogue>,
+    origin: Res<WorldOrigin>,
+) {
+    for command in level_commands.iter() {
+        match command {
+            LevelCommand::ShowText(message) => {
+                commands
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: Rect {
+                                top: Val::Percent(15.0),
+                                left: Val::Percent(30.0),
+                                ..Rect::default()
+                            },
+                            ..Style::default()
+                        },
+                        text: Text::with_section(
+                            message,
+                            TextStyle {
+                                font: font.get_handle(),
+                                font_size: 40.0,
+                                ..TextStyle::default()
+                            },
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    })
+                    .insert(ScriptText(Timer::from_seconds(TEXT_DURATION, false)));
+            }
+            LevelCommand::Dialogue(message) => dialogue.0.push_back(message.clone()),
+            LevelCommand::MoveCamera { tile, seconds } => {
+                commands.insert_resource(CameraOverride {
+                    position: origin.tile_to_world(*tile),
+                    timer: Timer::from_seconds(*seconds, false),
+                });
+            }
+            LevelCommand::SpawnMissile(tile) => {
+                launches.send(SpawnEnemy {
+                    projectile: Projectile::Missile,
+                    position: origin.tile_to_world(*tile),
+                });
+            }
+            // Left to toggle_walls
+            LevelCommand::ToggleWalls => {}
+        }
+    }
+}
+
+// Walls stop everything only while they're up, which they're drawn as
+fn toggle_walls(
+    mut level_commands: EventReader<LevelCommand>,
+    mut open_walls: ResMut<OpenWalls>,
+    mut walls: Query<&mut Visibility, With<Wall>>,
+) {
+    for command in level_commands.iter() {
+        if *command == LevelCommand::ToggleWalls {
+            open_walls.0 = !open_walls.0;
+            for mut visibility in walls.iter_mut() {
+                visibility.is_visible = !open_walls.0;
+            }
+        }
+    }
+}
+
+fn expire_script_text(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut texts: Query<(Entity, &mut ScriptText)>,
+) {
+    for (entity, mut text) in texts.iter_mut() {
+        if text.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn start_dialogue(dialogue: Res<Dialogue>, mut state: ResMut<State<GameState>>) {
+    if !dialogue.0.is_empty() {
+        change_state(&mut state, GameState::Dialogue);
+    }
+}
+
+fn show_dialogue(mut commands: Commands, font: Res<GameFont>, dialogue: Res<Dialogue>) {
+    let style = |size| TextStyle {
+        font: font.get_handle(),
+        font_size: size,
+        ..TextStyle::default()
+    };
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(10.0),
+                    left: Val::Percent(20.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: dialogue.0.front().cloned().unwrap_or_default(),
+                        style: style(40.0),
+                    },
+                    TextSection {
+                        value: "\n[Enter]".to_string(),
+                        style: style(25.0),
+                    },
+                ],
+                alignment: TextAlignment::default(),
+            },
+            ..TextBundle::default()
+        })
+        .insert(DialogueText);
+}
+
+// Step through the queued lines, then go back to playing
+fn advance_dialogue(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut dialogue: ResMut<Dialogue>,
+    mut state: ResMut<State<GameState>>,
+    mut text: Query<&mut Text, With<DialogueText>>,
+) {
+    if !keys.clear_just_pressed(KeyCode::Return) {
+        return;
+    }
+    dialogue.0.pop_front();
+    match dialogue.0.front() {
+        Some(line) => {
+            if let Ok(mut text) = text.get_single_mut() {
+                text.sections[0].value = line.clone();
+            }
+        }
+        None => change_state(&mut state, GameState::Playing),
+    }
+}
+
+fn hide_dialogue(mut commands: Commands, text: Query<Entity, With<DialogueText>>) {
+    for entity in text.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        movement::{self, WallCollider},
+        world::{Tile, WorldType},
+    };
+    use bevy::app::Events;
+
+    #[test]
+    fn triggers_run_the_script() {
+        let level = Arc::new(GameWorld::parse("*\tG\n", WorldType::Endless).unwrap());
+        let mut script = Script::new(
+            r#"
+                let entered = 0;
+                on_start(|| text("Watch out!"));
+                after(2.5, || walls());
+                on_tile(3, 4, || {
+                    entered += 1;
+                    if entered == 2 {
+                        missile(5, 5);
+                    } else {
+                        camera(10, 2, 1.5);
+                    }
+                });
+                after(1, || dialogue("Hello"));
+            "#,
+            &level,
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.advance(0.0, None),
+            [LevelCommand::ShowText("Watch out!".into())]
+        );
+        assert_eq!(
+            script.advance(1.0, None),
+            [LevelCommand::Dialogue("Hello".into())]
+        );
+        assert_eq!(
+            script.advance(2.0, Some((3, 4))),
+            [
+                LevelCommand::ToggleWalls,
+                LevelCommand::MoveCamera {
+                    tile: (10, 2),
+                    seconds: 1.5
+                },
+            ]
+        );
+        // Tile triggers fire on the way in, not while the player stays there
+        assert_eq!(script.advance(0.1, Some((3, 4))), []);
+        assert_eq!(script.advance(0.1, Some((0, 0))), []);
+        assert_eq!(
+            script.advance(0.1, Some((3, 4))),
+            [LevelCommand::SpawnMissile((5, 5))]
+        );
+    }
+
+    #[test]
+    fn scripts_are_sandboxed() {
+        let level = Arc::new(GameWorld::parse("*\tG\n", WorldType::Endless).unwrap());
+        assert!(Script::new("on_start(|| dance());", &level).is_ok());
+        assert!(Script::new("on_tile(-1, 0, || walls());", &level).is_err());
+        assert!(Script::new("after(-1, || walls());", &level).is_err());
+        assert!(Script::new("loop {}", &level).is_err());
+        assert!(Script::new("open(\"save.ron\")", &level).is_err());
+
+        // A failing trigger doesn't take the rest down with it
+        let mut script =
+            Script::new("on_start(|| dance()); on_start(|| walls());", &level).unwrap();
+        assert_eq!(script.advance(0.0, None), [LevelCommand::ToggleWalls]);
+    }
+
+    #[test]
+    fn toggled_walls_stop_blocking() {
+        let level = GameWorld::parse("*\t#\n", WorldType::Endless).unwrap();
+        let mut world = World::new();
+        world.insert_resource(OpenWalls::default());
+        world.insert_resource(Events::<LevelCommand>::default());
+        let wall = world
+            .spawn()
+            .insert(Wall)
+            .insert(Visibility::default())
+            .id();
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(toggle_walls);
+
+        // Flies from the start tile towards the wall next to it
+        let fly = |world: &World| {
+            let mut transform = Transform::default();
+            let collider = WallCollider {
+                size: Vec2::splat(Tile::SIZE),
+            };
+            let open_walls = world.get_resource::<OpenWalls>().unwrap();
+            movement::move_entity(
+                &level,
+                open_walls,
+                &mut transform,
+                Some(&collider),
+                Vec2::X * Tile::SIZE,
+            );
+            transform.translation.x
+        };
+        assert_eq!(fly(&world), 0.0);
+
+        let mut toggle = |world: &mut World| {
+            world
+                .get_resource_mut::<Events<LevelCommand>>()
+                .unwrap()
+                .send(LevelCommand::ToggleWalls);
+            stage.run(world);
+        };
+        toggle(&mut world);
+        assert_eq!(fly(&world), Tile::SIZE);
+        assert!(!world.get::<Visibility>(wall).unwrap().is_visible);
+
+        toggle(&mut world);
+        assert_eq!(fly(&world), 0.0);
+        assert!(world.get::<Visibility>(wall).unwrap().is_visible);
+    }
+}
@@ -0,0 +1,95 @@
+// Timeline for cutscenes: a list of steps played one after another for set lengths of
+// time. Each step is a value the cutscene's own systems act on, the sequencer only keeps
+// time and says which step is playing.
+pub struct Sequencer<S> {
+    steps: Vec<(S, f32)>,
+    elapsed: f32,
+    // Steps that have started so far
+    started: usize,
+}
+
+impl<S: Copy> Sequencer<S> {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            elapsed: 0.0,
+            started: 0,
+        }
+    }
+
+    pub fn then(mut self, step: S, seconds: f32) -> Self {
+        self.steps.push((step, seconds));
+        self
+    }
+
+    // Move time on, returning the steps that started along the way in order, including
+    // any that were skipped over entirely by a long frame
+    pub fn advance(&mut self, delta: f32) -> Vec<S> {
+        self.elapsed += delta;
+        let mut start = 0.0;
+        let mut started = Vec::new();
+        for (index, &(step, seconds)) in self.steps.iter().enumerate() {
+            if index >= self.started && start <= self.elapsed {
+                started.push(step);
+                self.started = index + 1;
+            }
+            start += seconds;
+        }
+        started
+    }
+
+    // The step playing and how far through it is, from 0 to 1, None once all have played
+    pub fn current(&self) -> Option<(S, f32)> {
+        let mut start = 0.0;
+        for &(step, seconds) in &self.steps {
+            if self.elapsed < start + seconds {
+                let progress = if seconds > 0.0 {
+                    (self.elapsed - start) / seconds
+                } else {
+                    1.0
+                };
+                return Some((step, progress));
+            }
+            start += seconds;
+        }
+        None
+    }
+}
+
+impl<S: Copy> Default for Sequencer<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Step {
+        First,
+        Second,
+        Third,
+    }
+
+    #[test]
+    fn steps_play_in_order() {
+        let mut sequencer = Sequencer::new()
+            .then(Step::First, 1.0)
+            .then(Step::Second, 0.5)
+            .then(Step::Third, 2.0);
+
+        assert_eq!(sequencer.advance(0.5), vec![Step::First]);
+        assert_eq!(sequencer.current(), Some((Step::First, 0.5)));
+        assert_eq!(sequencer.advance(0.25), vec![]);
+
+        // A long frame skips the second step, which still gets to start
+        assert_eq!(sequencer.advance(1.0), vec![Step::Second, Step::Third]);
+        assert_eq!(sequencer.current(), Some((Step::Third, 0.125)));
+
+        sequencer.advance(5.0);
+        assert_eq!(sequencer.current(), None);
+        assert_eq!(sequencer.advance(1.0), vec![]);
+    }
+}
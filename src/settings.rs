@@ -0,0 +1,773 @@
+use crate::{
+    change_state,
+    display::Monitors,
+    hud::HudSettings,
+    input_map::{Action, InputMap},
+    save::SaveData,
+    telemetry::Telemetry,
+    ui::{spawn_back_button, GameFont, ACTIVE_BUTTON_COLOR, NORMAL_BUTTON_COLOR},
+    AppState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Settings).with_system(create_settings))
+            .add_system_set(
+                SystemSet::on_update(AppState::Settings)
+                    .with_system(manage_setting_buttons)
+                    .with_system(manage_binding_buttons)
+                    .with_system(manage_delete_telemetry_button)
+                    .with_system(manage_introduction_button),
+            );
+    }
+}
+
+// Options that are kept in the save file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Screen effects like the vignette, which can be slow on low-end machines
+    pub post_processing: bool,
+    // Millisecond timer with splits at checkpoints
+    pub speedrun_timer: bool,
+    // Time runs by the wall clock, including pauses, instead of in-game time
+    pub real_time: bool,
+    // Show the controls being pressed, for streaming
+    pub input_display: bool,
+    // Record anonymous attempt counts, deaths and times per level, off unless opted into
+    pub telemetry: bool,
+    // Plain http:// URL collected telemetry is posted to on launch, only set by hand
+    pub telemetry_endpoint: Option<String>,
+    // Plain http:// URL a newer changelog is fetched from for What's new, only set by hand
+    pub news_url: Option<String>,
+    // Plain http:// URL of a server finished runs are shared with and record ghosts come
+    // from, only set by hand
+    pub ghost_server: Option<String>,
+    pub frame_rate_cap: FrameRateCap,
+    pub vsync: bool,
+    // Skip effects and detail that slow down old machines
+    pub low_spec: bool,
+    pub control_scheme: ControlScheme,
+    // How much nearer the cursor has to be to the center for full speed, one of
+    // SENSITIVITY_STEPS
+    pub mouse_sensitivity: f32,
+    // Name of the monitor to open on, or None to let the system decide
+    pub monitor: Option<String>,
+    // Monitors the game runs fullscreen on, with "" standing for letting the system decide
+    pub fullscreen_monitors: Vec<String>,
+    // Race the developers' ghost on built-in levels, toggled in level select
+    pub dev_ghost: bool,
+    pub input_map: InputMap,
+    // Toggled by hotkeys, wherever the game is
+    pub muted: bool,
+    pub fps_overlay: bool,
+    // Seconds without input before a level pauses itself, one of IDLE_PAUSE_STEPS, with 0
+    // for never
+    pub idle_pause_seconds: u32,
+    // Pause a level when the window loses focus or is minimized, like when tabbing out,
+    // counting back in when it's focused again
+    pub pause_when_unfocused: bool,
+    pub background_audio: BackgroundAudio,
+    // Percent of full speed levels are played at, one of GAME_SPEED_STEPS. Slower runs are
+    // recorded apart from full speed ones.
+    pub game_speed: u32,
+    pub enemy_visibility: EnemyVisibility,
+    pub hud: HudSettings,
+}
+
+// Frames per second the game is limited to, to save battery
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Serialize, Deserialize)]
+pub enum FrameRateCap {
+    Thirty,
+    Sixty,
+    OneHundredTwenty,
+    Uncapped,
+}
+
+impl FrameRateCap {
+    pub fn frames_per_second(self) -> Option<u32> {
+        match self {
+            FrameRateCap::Thirty => Some(30),
+            FrameRateCap::Sixty => Some(60),
+            FrameRateCap::OneHundredTwenty => Some(120),
+            FrameRateCap::Uncapped => None,
+        }
+    }
+
+    fn next(self) -> Self {
+        FrameRateCap::iter()
+            .cycle()
+            .skip_while(|cap| *cap != self)
+            .nth(1)
+            .unwrap()
+    }
+}
+
+pub const SENSITIVITY_STEPS: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+// The sensitivity after `current`, back to the lowest after the highest
+fn next_sensitivity(current: f32) -> f32 {
+    SENSITIVITY_STEPS
+        .into_iter()
+        .find(|step| *step > current)
+        .unwrap_or(SENSITIVITY_STEPS[0])
+}
+
+pub const IDLE_PAUSE_STEPS: [u32; 4] = [0, 15, 30, 60];
+
+// The idle time after `current`, back to never after the longest
+fn next_idle_pause(current: u32) -> u32 {
+    IDLE_PAUSE_STEPS
+        .into_iter()
+        .find(|step| *step > current)
+        .unwrap_or(IDLE_PAUSE_STEPS[0])
+}
+
+pub const GAME_SPEED_STEPS: [u32; 4] = [70, 80, 90, 100];
+
+// The game speed after `current`, back to the slowest after full speed
+fn next_game_speed(current: u32) -> u32 {
+    GAME_SPEED_STEPS
+        .into_iter()
+        .find(|step| *step > current)
+        .unwrap_or(GAME_SPEED_STEPS[0])
+}
+
+// How the bee is steered
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Serialize, Deserialize)]
+pub enum ControlScheme {
+    // Fly towards the cursor, with upgrades on the mouse buttons
+    Mouse,
+    // WASD or the arrow keys, with upgrades on Q and E unless they've been rebound
+    Keyboard,
+}
+
+impl ControlScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            ControlScheme::Mouse => "Mouse",
+            ControlScheme::Keyboard => "Keyboard",
+        }
+    }
+
+    fn next(self) -> Self {
+        ControlScheme::iter()
+            .cycle()
+            .skip_while(|scheme| *scheme != self)
+            .nth(1)
+            .unwrap()
+    }
+}
+
+// How loud the game is while its window isn't focused
+#[derive(Debug, Clone, Copy, Default, PartialEq, EnumIter, Serialize, Deserialize)]
+pub enum BackgroundAudio {
+    Full,
+    #[default]
+    Quiet,
+    Muted,
+}
+
+impl BackgroundAudio {
+    pub fn volume(self) -> f32 {
+        match self {
+            BackgroundAudio::Full => 1.0,
+            BackgroundAudio::Quiet => 0.25,
+            BackgroundAudio::Muted => 0.0,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            BackgroundAudio::Full => "Full",
+            BackgroundAudio::Quiet => "Quiet",
+            BackgroundAudio::Muted => "Muted",
+        }
+    }
+
+    fn next(self) -> Self {
+        BackgroundAudio::iter()
+            .cycle()
+            .skip_while(|audio| *audio != self)
+            .nth(1)
+            .unwrap()
+    }
+}
+
+// How projectiles are drawn, for players who have trouble picking them out of the level
+#[derive(Debug, Clone, Copy, Default, PartialEq, EnumIter, Serialize, Deserialize)]
+pub enum EnemyVisibility {
+    #[default]
+    Normal,
+    // A bold outline for as long as they last, with bigger launch telegraphs
+    Outlined,
+    // Outlined and filled in with one bright color instead of their art
+    Solid,
+}
+
+impl EnemyVisibility {
+    fn name(self) -> &'static str {
+        match self {
+            EnemyVisibility::Normal => "Normal",
+            EnemyVisibility::Outlined => "Outlined",
+            EnemyVisibility::Solid => "Solid",
+        }
+    }
+
+    fn next(self) -> Self {
+        EnemyVisibility::iter()
+            .cycle()
+            .skip_while(|visibility| *visibility != self)
+            .nth(1)
+            .unwrap()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            post_processing: true,
+            speedrun_timer: false,
+            real_time: false,
+            input_display: false,
+            telemetry: false,
+            telemetry_endpoint: None,
+            news_url: None,
+            ghost_server: None,
+            frame_rate_cap: FrameRateCap::Uncapped,
+            vsync: true,
+            low_spec: false,
+            control_scheme: ControlScheme::Mouse,
+            mouse_sensitivity: 1.0,
+            monitor: None,
+            fullscreen_monitors: Vec::new(),
+            dev_ghost: false,
+            input_map: InputMap::default(),
+            muted: false,
+            fps_overlay: false,
+            idle_pause_seconds: 30,
+            pause_when_unfocused: true,
+            background_audio: BackgroundAudio::default(),
+            game_speed: 100,
+            enemy_visibility: EnemyVisibility::default(),
+            hud: HudSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    // Game time passed per second of real time, kept within the steps for hand-edited saves
+    pub fn game_speed_scale(&self) -> f32 {
+        let speed = self.game_speed.clamp(
+            GAME_SPEED_STEPS[0],
+            GAME_SPEED_STEPS[GAME_SPEED_STEPS.len() - 1],
+        );
+        speed as f32 / 100.0
+    }
+
+    fn monitor_key(&self) -> &str {
+        self.monitor.as_deref().unwrap_or_default()
+    }
+
+    // Whether the game is fullscreen on the chosen monitor
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen_monitors
+            .iter()
+            .any(|monitor| monitor == self.monitor_key())
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        let key = self.monitor_key().to_string();
+        self.fullscreen_monitors.retain(|monitor| *monitor != key);
+        if fullscreen {
+            self.fullscreen_monitors.push(key);
+        }
+    }
+}
+
+// A toggle shown on the settings screen
+#[derive(Debug, EnumIter, Clone, Copy)]
+enum Setting {
+    PostProcessing,
+    SpeedrunTimer,
+    RealTime,
+    InputDisplay,
+    Telemetry,
+    FrameRateCap,
+    Vsync,
+    LowSpec,
+    ControlScheme,
+    MouseSensitivity,
+    Monitor,
+    Fullscreen,
+    IdlePause,
+    FocusPause,
+    BackgroundAudio,
+    GameSpeed,
+    EnemyVisibility,
+    HudScale,
+    TimerCorner,
+    ScoreCorner,
+    PowerUpCorner,
+    MinimalHud,
+}
+
+impl Setting {
+    fn name(self) -> &'static str {
+        match self {
+            Setting::PostProcessing => "Screen effects",
+            Setting::SpeedrunTimer => "Speedrun timer",
+            Setting::RealTime => "Time pauses",
+            Setting::InputDisplay => "Input display",
+            Setting::Telemetry => "Collect balance data",
+            Setting::FrameRateCap => "Frame rate cap",
+            Setting::Vsync => "Vsync",
+            Setting::LowSpec => "Low-spec mode",
+            Setting::ControlScheme => "Controls",
+            Setting::MouseSensitivity => "Mouse sensitivity",
+            Setting::Monitor => "Monitor",
+            Setting::Fullscreen => "Fullscreen",
+            Setting::IdlePause => "Pause when idle",
+            Setting::FocusPause => "Pause when unfocused",
+            Setting::BackgroundAudio => "Sound in background",
+            Setting::GameSpeed => "Game speed",
+            Setting::EnemyVisibility => "Enemy visibility",
+            Setting::HudScale => "HUD scale",
+            Setting::TimerCorner => "Timer",
+            Setting::ScoreCorner => "Wall hug score",
+            Setting::PowerUpCorner => "Power-ups",
+            Setting::MinimalHud => "Minimal HUD",
+        }
+    }
+
+    fn is_enabled(self, settings: &Settings) -> bool {
+        match self {
+            Setting::PostProcessing => settings.post_processing,
+            Setting::SpeedrunTimer => settings.speedrun_timer,
+            Setting::RealTime => settings.real_time,
+            Setting::InputDisplay => settings.input_display,
+            Setting::Telemetry => settings.telemetry,
+            Setting::FrameRateCap => settings.frame_rate_cap != FrameRateCap::Uncapped,
+            Setting::Vsync => settings.vsync,
+            Setting::LowSpec => settings.low_spec,
+            Setting::ControlScheme => settings.control_scheme != ControlScheme::Mouse,
+            Setting::MouseSensitivity => settings.mouse_sensitivity != 1.0,
+            Setting::Monitor => settings.monitor.is_some(),
+            Setting::Fullscreen => settings.fullscreen(),
+            Setting::IdlePause => settings.idle_pause_seconds != 0,
+            Setting::FocusPause => settings.pause_when_unfocused,
+            Setting::BackgroundAudio => settings.background_audio != BackgroundAudio::Full,
+            Setting::GameSpeed => settings.game_speed != 100,
+            Setting::EnemyVisibility => settings.enemy_visibility != EnemyVisibility::Normal,
+            Setting::HudScale => settings.hud.scale != 1.0,
+            Setting::TimerCorner => settings.hud.timer != HudSettings::default().timer,
+            Setting::ScoreCorner => settings.hud.score != HudSettings::default().score,
+            Setting::PowerUpCorner => settings.hud.power_ups != HudSettings::default().power_ups,
+            Setting::MinimalHud => settings.hud.minimal,
+        }
+    }
+
+    // `monitors` are the ones to choose between for Setting::Monitor
+    fn toggle(self, settings: &mut Settings, monitors: &Monitors) {
+        match self {
+            Setting::PostProcessing => settings.post_processing ^= true,
+            Setting::SpeedrunTimer => settings.speedrun_timer ^= true,
+            Setting::RealTime => settings.real_time ^= true,
+            Setting::InputDisplay => settings.input_display ^= true,
+            Setting::Telemetry => settings.telemetry ^= true,
+            Setting::FrameRateCap => settings.frame_rate_cap = settings.frame_rate_cap.next(),
+            Setting::Vsync => settings.vsync ^= true,
+            Setting::LowSpec => settings.low_spec ^= true,
+            Setting::ControlScheme => settings.control_scheme = settings.control_scheme.next(),
+            Setting::MouseSensitivity => {
+                settings.mouse_sensitivity = next_sensitivity(settings.mouse_sensitivity)
+            }
+            Setting::Monitor => settings.monitor = monitors.next(settings.monitor.as_deref()),
+            Setting::Fullscreen => settings.set_fullscreen(!settings.fullscreen()),
+            Setting::IdlePause => {
+                settings.idle_pause_seconds = next_idle_pause(settings.idle_pause_seconds)
+            }
+            Setting::FocusPause => settings.pause_when_unfocused ^= true,
+            Setting::BackgroundAudio => {
+                settings.background_audio = settings.background_audio.next()
+            }
+            Setting::GameSpeed => settings.game_speed = next_game_speed(settings.game_speed),
+            Setting::EnemyVisibility => {
+                settings.enemy_visibility = settings.enemy_visibility.next()
+            }
+            Setting::HudScale => settings.hud.next_scale(),
+            Setting::TimerCorner => settings.hud.timer = settings.hud.timer.next(),
+            Setting::ScoreCorner => settings.hud.score = settings.hud.score.next(),
+            Setting::PowerUpCorner => settings.hud.power_ups = settings.hud.power_ups.next(),
+            Setting::MinimalHud => settings.hud.minimal ^= true,
+        }
+    }
+
+    fn label(self, settings: &Settings) -> String {
+        let value = match (self, settings.frame_rate_cap.frames_per_second()) {
+            (Setting::FrameRateCap, Some(fps)) => fps.to_string(),
+            (Setting::ControlScheme, _) => settings.control_scheme.name().into(),
+            (Setting::MouseSensitivity, _) => format!("{}x", settings.mouse_sensitivity),
+            (Setting::Monitor, _) => settings.monitor.as_deref().unwrap_or("Automatic").into(),
+            (Setting::BackgroundAudio, _) => settings.background_audio.name().into(),
+            (Setting::GameSpeed, _) => format!("{}%", settings.game_speed),
+            (Setting::EnemyVisibility, _) => settings.enemy_visibility.name().into(),
+            (Setting::HudScale, _) => format!("{}x", settings.hud.scale),
+            (Setting::TimerCorner, _) => settings.hud.timer.name().into(),
+            (Setting::ScoreCorner, _) => settings.hud.score.name().into(),
+            (Setting::PowerUpCorner, _) => settings.hud.power_ups.name().into(),
+            (Setting::IdlePause, _) if self.is_enabled(settings) => {
+                format!("{}s", settings.idle_pause_seconds)
+            }
+            _ if self.is_enabled(settings) => "On".into(),
+            _ => "Off".into(),
+        };
+        format!("{}: {value}", self.name())
+    }
+}
+
+#[derive(Component)]
+pub struct SettingButton(Setting);
+
+// Rebinds an action's key, with the next key pressed after clicking it
+#[derive(Component)]
+struct BindingButton(Action);
+
+#[derive(Component)]
+struct DeleteTelemetryButton;
+
+#[derive(Component)]
+struct IntroductionButton;
+
+fn create_settings(mut commands: Commands, font: Res<GameFont>, save: Res<SaveData>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    spawn_back_button(&mut commands, font.get_handle());
+
+    let text_style = |size| TextStyle {
+        font: font.get_handle(),
+        font_size: size,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                // Into a second column when there are more settings than fit on screen
+                flex_wrap: FlexWrap::Wrap,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_content: AlignContent::Center,
+                padding: Rect {
+                    top: Val::Px(90.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Percent(2.0),
+                        ..Rect::default()
+                    },
+                    ..Style::default()
+                },
+                text: Text::with_section(
+                    "Settings",
+                    TextStyle {
+                        color: Color::WHITE,
+                        ..text_style(70.0)
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+
+            for setting in Setting::iter() {
+                let enabled = setting.is_enabled(&save.settings);
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(400.0), Val::Px(46.0)),
+                            margin: Rect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        color: if enabled {
+                            ACTIVE_BUTTON_COLOR
+                        } else {
+                            NORMAL_BUTTON_COLOR
+                        }
+                        .into(),
+                        ..ButtonBundle::default()
+                    })
+                    .insert(SettingButton(setting))
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                setting.label(&save.settings),
+                                text_style(40.0),
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        });
+                    });
+            }
+
+            for action in Action::iter() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(400.0), Val::Px(46.0)),
+                            margin: Rect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        ..ButtonBundle::default()
+                    })
+                    .insert(BindingButton(action))
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                binding_label(action, &save.settings.input_map),
+                                text_style(40.0),
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        });
+                    });
+            }
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(400.0), Val::Px(46.0)),
+                        margin: Rect::all(Val::Px(5.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(DeleteTelemetryButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Delete balance data",
+                            text_style(40.0),
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    });
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(400.0), Val::Px(46.0)),
+                        margin: Rect::all(Val::Px(5.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(IntroductionButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Replay introduction",
+                            text_style(40.0),
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    });
+                });
+        });
+}
+
+fn binding_label(action: Action, input_map: &InputMap) -> String {
+    format!("{}: {:?}", action.name(), input_map.key(action))
+}
+
+// Clicking a binding waits for a key, and Escape or clicking again leaves it as it was
+fn manage_binding_buttons(
+    mut save: ResMut<SaveData>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut waiting: Local<Option<Action>>,
+    interaction: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+    buttons: Query<(&BindingButton, &Children)>,
+    mut text: Query<&mut Text>,
+) {
+    let before = *waiting;
+    for (interaction, button) in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            *waiting = (*waiting != Some(button.0)).then_some(button.0);
+        }
+    }
+    if let Some(action) = *waiting {
+        if keys.clear_just_pressed(KeyCode::Escape) {
+            *waiting = None;
+        } else if let Some(key) = keys.get_just_pressed().next().copied() {
+            // Reserved keys are ignored, and it carries on waiting for another
+            if save.settings.input_map.bind(action, key) {
+                *waiting = None;
+            }
+        }
+    }
+    if *waiting == before && !save.is_changed() {
+        return;
+    }
+    for (button, children) in buttons.iter() {
+        if let Ok(mut text) = text.get_mut(children[0]) {
+            text.sections[0].value = if *waiting == Some(button.0) {
+                format!("{}: press a key", button.0.name())
+            } else {
+                binding_label(button.0, &save.settings.input_map)
+            };
+        }
+    }
+}
+
+fn manage_delete_telemetry_button(
+    mut telemetry: ResMut<Telemetry>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<DeleteTelemetryButton>)>,
+) {
+    for interaction in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            telemetry.delete();
+        }
+    }
+}
+
+fn manage_introduction_button(
+    mut state: ResMut<State<AppState>>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<IntroductionButton>)>,
+) {
+    for interaction in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            change_state(&mut state, AppState::NameEntry);
+        }
+    }
+}
+
+fn manage_setting_buttons(
+    mut save: ResMut<SaveData>,
+    monitors: Res<Monitors>,
+    mut buttons: Query<
+        (&Interaction, &mut UiColor, &Children, &SettingButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut text: Query<&mut Text>,
+) {
+    for (interaction, mut color, children, button) in buttons.iter_mut() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+
+        button.0.toggle(&mut save.settings, &monitors);
+
+        // Enabled settings stay highlighted
+        *color = if button.0.is_enabled(&save.settings) {
+            ACTIVE_BUTTON_COLOR
+        } else {
+            NORMAL_BUTTON_COLOR
+        }
+        .into();
+        if let Ok(mut text) = text.get_mut(children[0]) {
+            text.sections[0].value = button.0.label(&save.settings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rate_caps_cycle() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            Setting::FrameRateCap.label(&settings),
+            "Frame rate cap: Off"
+        );
+        Setting::FrameRateCap.toggle(&mut settings, &Monitors::default());
+        assert_eq!(Setting::FrameRateCap.label(&settings), "Frame rate cap: 30");
+        for _ in 0..3 {
+            Setting::FrameRateCap.toggle(&mut settings, &Monitors::default());
+        }
+        assert_eq!(settings.frame_rate_cap, FrameRateCap::Uncapped);
+    }
+
+    #[test]
+    fn sensitivity_steps_wrap_around() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            Setting::MouseSensitivity.label(&settings),
+            "Mouse sensitivity: 1x"
+        );
+        Setting::MouseSensitivity.toggle(&mut settings, &Monitors::default());
+        assert_eq!(
+            Setting::MouseSensitivity.label(&settings),
+            "Mouse sensitivity: 1.25x"
+        );
+        for _ in 0..3 {
+            Setting::MouseSensitivity.toggle(&mut settings, &Monitors::default());
+        }
+        assert_eq!(settings.mouse_sensitivity, 0.5);
+        // Values from elsewhere, like a hand-edited save, step to the next one up
+        assert_eq!(next_sensitivity(1.1), 1.25);
+    }
+
+    #[test]
+    fn fullscreen_is_remembered_per_monitor() {
+        let mut settings = Settings::default();
+        settings.set_fullscreen(true);
+        settings.monitor = Some("Right".into());
+        assert!(!settings.fullscreen());
+        settings.set_fullscreen(true);
+        settings.set_fullscreen(false);
+        assert!(!settings.fullscreen());
+        settings.monitor = None;
+        assert!(settings.fullscreen());
+        assert_eq!(Setting::Monitor.label(&settings), "Monitor: Automatic");
+    }
+
+    #[test]
+    fn idle_pause_steps_wrap_around() {
+        let mut settings = Settings::default();
+        assert_eq!(Setting::IdlePause.label(&settings), "Pause when idle: 30s");
+        Setting::IdlePause.toggle(&mut settings, &Monitors::default());
+        assert_eq!(Setting::IdlePause.label(&settings), "Pause when idle: 60s");
+        Setting::IdlePause.toggle(&mut settings, &Monitors::default());
+        assert_eq!(Setting::IdlePause.label(&settings), "Pause when idle: Off");
+        Setting::IdlePause.toggle(&mut settings, &Monitors::default());
+        assert_eq!(settings.idle_pause_seconds, 15);
+    }
+
+    #[test]
+    fn game_speed_steps_wrap_around() {
+        let mut settings = Settings::default();
+        assert_eq!(Setting::GameSpeed.label(&settings), "Game speed: 100%");
+        Setting::GameSpeed.toggle(&mut settings, &Monitors::default());
+        assert_eq!(Setting::GameSpeed.label(&settings), "Game speed: 70%");
+        assert_eq!(settings.game_speed_scale(), 0.7);
+        settings.game_speed = 20;
+        assert_eq!(settings.game_speed_scale(), 0.7);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    animation::{AnimationState, Motion},
+    clock::GameClock,
+    enemy::Spawning,
+    save::SaveData,
+};
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+// Soft shadows under things that fly, offset further the higher they are, to give the
+// flat scene some depth
+pub struct ShadowPlugin;
+
+impl Plugin for ShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowImage>()
+            .add_system(add_shadows)
+            .add_system(lift_spawning_projectiles)
+            .add_system(lift_fast_flyers)
+            .add_system(update_shadows);
+    }
+}
+
+// Which way shadows fall, in pixels per unit of height
+const SHADOW_DIRECTION: Vec2 = bevy::math::const_vec2!([0.4, -0.8]);
+const SHADOW_ALPHA: f32 = 0.35;
+// Height at which a shadow has spread to twice its size and half its darkness
+const SHADOW_FALLOFF: f32 = 40.0;
+// Projectiles drop in from this high while they spawn
+const SPAWN_LIFT: f32 = 24.0;
+// Sprites flying fast rise this much higher
+const FAST_LIFT: f32 = 8.0;
+// How quickly height follows its target, per second
+const LIFT_RATE: f32 = 10.0;
+const SHADOW_IMAGE_SIZE: (u32, u32) = (32, 16);
+
+// Casts a shadow the size of its footprint from `height` pixels up
+#[derive(Component)]
+pub struct Shadow {
+    size: Vec2,
+    height: f32,
+    // Extra height on top, for spawning in and flying fast
+    lift: f32,
+}
+
+impl Shadow {
+    pub fn new(size: Vec2, height: f32) -> Self {
+        Self {
+            size,
+            height,
+            lift: 0.0,
+        }
+    }
+
+    fn total_height(&self) -> f32 {
+        self.height + self.lift
+    }
+}
+
+#[derive(Component)]
+struct ShadowSprite;
+
+// A blurred black ellipse, stretched to fit each shadow
+struct ShadowImage(Handle<Image>);
+
+impl FromWorld for ShadowImage {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.get_resource_mut::<Assets<Image>>().unwrap();
+        Self(images.add(shadow_image()))
+    }
+}
+
+fn shadow_image() -> Image {
+    let (width, height) = SHADOW_IMAGE_SIZE;
+    let data = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            // Distance from the middle, 1 at the edge of the ellipse
+            let dx = (x as f32 + 0.5) / (width as f32 / 2.0) - 1.0;
+            let dy = (y as f32 + 0.5) / (height as f32 / 2.0) - 1.0;
+            [0, 0, 0, (shadow_alpha(dx.hypot(dy)) * 255.0) as u8]
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+// Solid in the middle, fading out smoothly to the edge
+fn shadow_alpha(distance: f32) -> f32 {
+    let t = (1.0 - distance).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn add_shadows(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    image: Res<ShadowImage>,
+    casters: Query<Entity, Added<Shadow>>,
+) {
+    if save.settings.low_spec {
+        return;
+    }
+    for entity in casters.iter() {
+        let shadow = commands
+            .spawn_bundle(SpriteBundle {
+                texture: image.0.clone(),
+                // Just under the sprite casting it
+                transform: Transform::from_xyz(0.0, 0.0, -0.01),
+                ..SpriteBundle::default()
+            })
+            .insert(ShadowSprite)
+            .id();
+        commands.entity(entity).add_child(shadow);
+    }
+}
+
+// Projectiles drop into place as they grow in
+fn lift_spawning_projectiles(mut projectiles: Query<(&mut Shadow, &Spawning)>) {
+    for (mut shadow, spawning) in projectiles.iter_mut() {
+        shadow.lift = SPAWN_LIFT * (1.0 - spawning.progress());
+    }
+}
+
+fn lift_fast_flyers(
+    clock: Res<GameClock>,
+    mut flyers: Query<(&mut Shadow, &AnimationState), Without<Spawning>>,
+) {
+    let rate = (LIFT_RATE * clock.delta_seconds()).min(1.0);
+    for (mut shadow, animation) in flyers.iter_mut() {
+        let target = match animation.motion() {
+            Motion::FastFlying => FAST_LIFT,
+            _ => 0.0,
+        };
+        shadow.lift += (target - shadow.lift) * rate;
+    }
+}
+
+fn update_shadows(
+    casters: Query<(&Transform, &Shadow), Without<ShadowSprite>>,
+    mut shadows: Query<(&Parent, &mut Transform, &mut Sprite), With<ShadowSprite>>,
+) {
+    for (parent, mut transform, mut sprite) in shadows.iter_mut() {
+        let (caster, shadow) = match casters.get(parent.0) {
+            Ok(caster) => caster,
+            Err(_) => continue,
+        };
+        let height = shadow.total_height();
+        // Shadows fall the same way however the sprite casting them is turned
+        let unrotate = caster.rotation.inverse();
+        let offset = unrotate * (SHADOW_DIRECTION * height).extend(0.0);
+        transform.translation = offset.truncate().extend(transform.translation.z);
+        transform.rotation = unrotate;
+
+        let spread = 1.0 + height / SHADOW_FALLOFF;
+        sprite.custom_size = Some(shadow.size * spread);
+        sprite.color = Color::rgba(0.0, 0.0, 0.0, SHADOW_ALPHA / spread);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shadows_fade_to_the_edge() {
+        assert_eq!(shadow_alpha(0.0), 1.0);
+        assert_eq!(shadow_alpha(1.0), 0.0);
+        assert_eq!(shadow_alpha(1.5), 0.0);
+        assert!(shadow_alpha(0.25) > shadow_alpha(0.75));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{save::SaveData, settings::Settings};
+use bevy::{
+    audio::{play_queued_audio_system, AudioOutput, Decodable},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+// Sound effects are synthesized rather than loaded, played through Audio<Tone>
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Tone>()
+            .init_non_send_resource::<AudioOutput<Tone>>()
+            .init_resource::<Audio<Tone>>()
+            .init_resource::<Sounds>()
+            .add_system(apply_volume)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                play_queued_audio_system::<Tone>.exclusive_system(),
+            );
+    }
+}
+
+const SAMPLE_RATE: u32 = 44100;
+
+// Read by tones as they play on the audio thread, so muting or ducking changes ones already
+// playing. The bits of the f32 every tone's volume is scaled by.
+static VOLUME: AtomicU32 = AtomicU32::new(1.0f32.to_bits());
+
+fn volume(settings: &Settings, focused: bool) -> f32 {
+    match (settings.muted, focused) {
+        (true, _) => 0.0,
+        (false, true) => 1.0,
+        (false, false) => settings.background_audio.volume(),
+    }
+}
+
+fn apply_volume(save: Res<SaveData>, windows: Res<Windows>) {
+    let focused = windows.get_primary().is_none_or(Window::is_focused);
+    VOLUME.store(volume(&save.settings, focused).to_bits(), Ordering::Relaxed);
+}
+
+// Sine sweep between two pitches
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "b3e4f0a2-8d1c-4f6e-9a57-2c8e1d3b7f40"]
+pub struct Tone {
+    from_hz: f32,
+    to_hz: f32,
+    seconds: f32,
+    volume: f32,
+}
+
+impl Tone {
+    pub fn seconds(&self) -> f32 {
+        self.seconds
+    }
+}
+
+pub struct ToneDecoder {
+    tone: Tone,
+    sample: u32,
+    phase: f32,
+}
+
+impl Iterator for ToneDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let t = self.sample as f32 / SAMPLE_RATE as f32;
+        if t >= self.tone.seconds {
+            return None;
+        }
+        self.sample += 1;
+
+        let progress = t / self.tone.seconds;
+        let frequency = self.tone.from_hz + (self.tone.to_hz - self.tone.from_hz) * progress;
+        self.phase = (self.phase + frequency / SAMPLE_RATE as f32).fract();
+        // Quick fade in and out so the tone doesn't click
+        let envelope = (progress * 20.0).min(1.0).min((1.0 - progress) * 20.0);
+        let volume = self.tone.volume * f32::from_bits(VOLUME.load(Ordering::Relaxed));
+        Some((self.phase * std::f32::consts::TAU).sin() * envelope * volume)
+    }
+}
+
+impl rodio::Source for ToneDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.tone.seconds))
+    }
+}
+
+impl Decodable for Tone {
+    type Decoder = ToneDecoder;
+    type DecoderItem = f32;
+
+    fn decoder(&self) -> ToneDecoder {
+        ToneDecoder {
+            tone: self.clone(),
+            sample: 0,
+            phase: 0.0,
+        }
+    }
+}
+
+pub struct Sounds {
+    // Rises over the last moments before a missile launches
+    pub missile_warning: Handle<Tone>,
+    // Short drop in pitch as a missile leaves its spawner
+    pub missile_launch: Handle<Tone>,
+    // Falls away as the player goes down
+    pub player_hit: Handle<Tone>,
+    // Notes played one after another on reaching the goal
+    pub fanfare: [Handle<Tone>; 3],
+    // Soft thud on flying into a wall
+    pub wall_bump: Handle<Tone>,
+}
+
+impl FromWorld for Sounds {
+    fn from_world(world: &mut World) -> Self {
+        let mut tones = world.get_resource_mut::<Assets<Tone>>().unwrap();
+        Self {
+            missile_warning: tones.add(Tone {
+                from_hz: 440.0,
+                to_hz: 880.0,
+                seconds: 0.5,
+                volume: 0.15,
+            }),
+            missile_launch: tones.add(Tone {
+                from_hz: 320.0,
+                to_hz: 160.0,
+                seconds: 0.15,
+                volume: 0.1,
+            }),
+            player_hit: tones.add(Tone {
+                from_hz: 660.0,
+                to_hz: 110.0,
+                seconds: 0.8,
+                volume: 0.2,
+            }),
+            fanfare: [
+                (523.0, 523.0, 0.12),
+                (659.0, 659.0, 0.12),
+                (784.0, 1047.0, 0.4),
+            ]
+            .map(|(from_hz, to_hz, seconds)| {
+                tones.add(Tone {
+                    from_hz,
+                    to_hz,
+                    seconds,
+                    volume: 0.15,
+                })
+            }),
+            wall_bump: tones.add(Tone {
+                from_hz: 180.0,
+                to_hz: 90.0,
+                seconds: 0.08,
+                volume: 0.08,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::BackgroundAudio;
+
+    #[test]
+    fn tones_last_as_long_as_asked() {
+        let tone = Tone {
+            from_hz: 100.0,
+            to_hz: 200.0,
+            seconds: 0.25,
+            volume: 0.5,
+        };
+        let samples: Vec<f32> = tone.decoder().collect();
+        assert_eq!(samples.len(), (SAMPLE_RATE / 4) as usize);
+        assert!(samples.iter().all(|sample| sample.abs() <= 0.5));
+    }
+
+    #[test]
+    fn sound_is_ducked_in_the_background() {
+        let mut settings = Settings::default();
+        assert_eq!(volume(&settings, true), 1.0);
+        assert_eq!(
+            volume(&settings, false),
+            BackgroundAudio::default().volume()
+        );
+        settings.background_audio = BackgroundAudio::Muted;
+        assert_eq!(volume(&settings, false), 0.0);
+        settings.background_audio = BackgroundAudio::Full;
+        assert_eq!(volume(&settings, false), 1.0);
+        settings.muted = true;
+        assert_eq!(volume(&settings, true), 0.0);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{clock::GameClock, playing};
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+// Directional sheets have one frame per compass point, starting facing right and going
+// anticlockwise
+pub const DIRECTIONS: usize = 8;
+// Radians per second a barrel turns to follow its spawner's aim
+const BARREL_TURN_SPEED: f32 = 1.5 * PI;
+
+// Art for spawners that aim. Turning a whole sprite drawn square to the grid looks wrong at
+// anything but right angles, so levels can draw them from a sheet of directions instead,
+// with a barrel on top that turns smoothly.
+pub struct SpawnerArtPlugin;
+
+impl Plugin for SpawnerArtPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(playing)
+                .with_system(turn_barrels),
+        );
+    }
+}
+
+// How a kind of spawner is drawn, set per level with `@spawner_art\t<kind>\t<option>...`.
+// Without it the whole sprite is turned to face the spawner's angle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpawnerArt {
+    // A sheet of DIRECTIONS frames, drawn unturned on the one nearest the spawner's angle
+    pub directions: Option<String>,
+    // Drawn over the base and turned to wherever the spawner's aiming, pointing up unturned
+    pub barrel: Option<String>,
+}
+
+impl SpawnerArt {
+    // Options are `directions:<sprite>` and `barrel:<sprite>`, separated by tabs
+    pub fn parse(options: &str) -> Result<Self, String> {
+        let mut art = Self::default();
+        for option in options.split('\t') {
+            match option.split_once(':') {
+                Some(("directions", sprite)) if !sprite.is_empty() => {
+                    art.directions = Some(sprite.to_string());
+                }
+                Some(("barrel", sprite)) if !sprite.is_empty() => {
+                    art.barrel = Some(sprite.to_string());
+                }
+                _ => return Err(format!("invalid spawner art option {option:?}")),
+            }
+        }
+        Ok(art)
+    }
+
+    pub fn serialize(&self) -> String {
+        let directions = self
+            .directions
+            .iter()
+            .map(|sprite| format!("directions:{sprite}"));
+        let barrel = self.barrel.iter().map(|sprite| format!("barrel:{sprite}"));
+        directions.chain(barrel).collect::<Vec<_>>().join("\t")
+    }
+
+    // Whether the base sprite is turned to face the spawner's angle
+    pub fn turns_base(&self) -> bool {
+        self.directions.is_none() && self.barrel.is_none()
+    }
+}
+
+// Frame of a directional sheet facing closest to `angle`
+pub fn direction_frame(angle: f32) -> usize {
+    (angle.rem_euclid(TAU) / (TAU / DIRECTIONS as f32)).round() as usize % DIRECTIONS
+}
+
+// Where a spawner with a barrel is aiming, which patterns turn between shots
+#[derive(Component)]
+pub struct Aim(pub f32);
+
+#[derive(Component)]
+struct Barrel {
+    angle: f32,
+}
+
+// Put a barrel on a spawner, already facing its aim
+pub fn attach_barrel(spawner: &mut ChildBuilder, texture: Handle<Image>, size: Vec2, angle: f32) {
+    spawner
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(size),
+                ..Sprite::default()
+            },
+            texture,
+            transform: Transform {
+                // Just above the base
+                translation: Vec3::new(0.0, 0.0, 0.1),
+                rotation: barrel_rotation(angle),
+                ..Transform::default()
+            },
+            ..SpriteBundle::default()
+        })
+        .insert(Barrel { angle });
+}
+
+fn barrel_rotation(angle: f32) -> Quat {
+    Quat::from_rotation_z(angle - PI / 2.0)
+}
+
+// The shortest turn from one angle to another, from -PI to PI
+fn turn_between(from: f32, to: f32) -> f32 {
+    (to - from + PI).rem_euclid(TAU) - PI
+}
+
+fn turn_barrels(
+    clock: Res<GameClock>,
+    aims: Query<&Aim>,
+    mut barrels: Query<(&Parent, &mut Barrel, &mut Transform)>,
+) {
+    let step = BARREL_TURN_SPEED * clock.delta_seconds();
+    for (parent, mut barrel, mut transform) in barrels.iter_mut() {
+        if let Ok(aim) = aims.get(parent.0) {
+            let turn = turn_between(barrel.angle, aim.0).clamp(-step, step);
+            if turn != 0.0 {
+                barrel.angle += turn;
+                transform.rotation = barrel_rotation(barrel.angle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn angles_pick_the_nearest_direction() {
+        assert_eq!(direction_frame(0.0), 0);
+        assert_eq!(direction_frame(PI / 2.0), 2);
+        assert_eq!(direction_frame(PI / 8.0 - 0.01), 0);
+        assert_eq!(direction_frame(PI / 8.0 + 0.01), 1);
+        assert_eq!(direction_frame(-PI / 4.0), 7);
+        assert_eq!(direction_frame(TAU - 0.01), 0);
+        assert_eq!(direction_frame(5.0 * TAU + PI), 4);
+    }
+
+    #[test]
+    fn barrels_turn_the_short_way() {
+        assert!((turn_between(0.1, TAU - 0.1) + 0.2).abs() < 1e-5);
+        assert!((turn_between(TAU - 0.1, 0.1) - 0.2).abs() < 1e-5);
+        assert!((turn_between(0.0, PI / 2.0) - PI / 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn options_round_trip() {
+        let art = SpawnerArt::parse("directions:turret.png\tbarrel:barrel.png").unwrap();
+        assert_eq!(art.directions.as_deref(), Some("turret.png"));
+        assert_eq!(art.barrel.as_deref(), Some("barrel.png"));
+        assert!(!art.turns_base());
+        assert_eq!(SpawnerArt::parse(&art.serialize()), Ok(art));
+        assert!(SpawnerArt::parse("barrel:barrel.png").is_ok());
+        assert!(SpawnerArt::parse("").is_err());
+        assert!(SpawnerArt::parse("barrel:").is_err());
+        assert!(SpawnerArt::parse("spin:fast").is_err());
+    }
+}
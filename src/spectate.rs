@@ -0,0 +1,144 @@
+use crate::{
+    camera::CameraOverride,
+    enemy::{Enemy, Projectile},
+    playing,
+    ui::GameFont,
+    AppState,
+};
+use bevy::prelude::*;
+
+// Debug camera that rides along with an enemy instead of the player, to watch pursuit up
+// close while tuning it. F2 moves on to the next enemy, and past the last back to the bee.
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Spectating>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(create_spectate_text))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(cycle_spectated)
+                    .with_system(follow_spectated),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Game).with_system(stop_spectating));
+    }
+}
+
+const CYCLE_KEY: KeyCode = KeyCode::F2;
+// Renewed every frame, so the camera goes back to the player soon after the enemy is gone
+const OVERRIDE_SECONDS: f32 = 0.1;
+
+#[derive(Default)]
+struct Spectating(Option<Entity>);
+
+#[derive(Component)]
+struct SpectateText;
+
+// The enemy after `current` in a stable order, or None after the last one
+fn next_enemy(enemies: &mut [Entity], current: Option<Entity>) -> Option<Entity> {
+    enemies.sort();
+    match current {
+        None => enemies.first().copied(),
+        Some(current) => enemies.iter().copied().find(|enemy| *enemy > current),
+    }
+}
+
+fn create_spectate_text(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(12.0),
+                    left: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 25.0,
+                    color: Color::rgb(1.0, 0.6, 0.6),
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(SpectateText);
+}
+
+fn cycle_spectated(
+    keys: Res<Input<KeyCode>>,
+    mut spectating: ResMut<Spectating>,
+    enemies: Query<Entity, With<Enemy>>,
+) {
+    if keys.just_pressed(CYCLE_KEY) {
+        let mut enemies: Vec<_> = enemies.iter().collect();
+        spectating.0 = next_enemy(&mut enemies, spectating.0);
+    }
+}
+
+fn follow_spectated(
+    mut commands: Commands,
+    mut spectating: ResMut<Spectating>,
+    enemies: Query<(&Transform, &Projectile), With<Enemy>>,
+    mut text: Query<&mut Text, With<SpectateText>>,
+) {
+    let label = match spectating.0.map(|entity| enemies.get(entity)) {
+        Some(Ok((transform, projectile))) => {
+            commands.insert_resource(CameraOverride {
+                position: transform.translation.truncate(),
+                timer: Timer::from_seconds(OVERRIDE_SECONDS, false),
+            });
+            let kind = match projectile {
+                Projectile::Missile => "missile",
+                Projectile::Laser { .. } => "laser",
+            };
+            format!(
+                "Spectating {kind} {:?} at {:.0}, {:.0} [F2]",
+                spectating.0.unwrap(),
+                transform.translation.x,
+                transform.translation.y
+            )
+        }
+        // Gone, so back to the player
+        Some(Err(_)) => {
+            spectating.0 = None;
+            String::new()
+        }
+        None => String::new(),
+    };
+    if let Ok(mut text) = text.get_single_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label;
+        }
+    }
+}
+
+fn stop_spectating(mut spectating: ResMut<Spectating>) {
+    spectating.0 = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemies_are_cycled_in_order() {
+        let (a, b, c) = (
+            Entity::from_raw(3),
+            Entity::from_raw(7),
+            Entity::from_raw(9),
+        );
+        let mut enemies = vec![c, a, b];
+        assert_eq!(next_enemy(&mut enemies, None), Some(a));
+        assert_eq!(next_enemy(&mut enemies, Some(a)), Some(b));
+        assert_eq!(next_enemy(&mut enemies, Some(c)), None);
+        // Carries on from where a despawned enemy was
+        assert_eq!(next_enemy(&mut enemies, Some(Entity::from_raw(8))), Some(c));
+        assert_eq!(next_enemy(&mut [], None), None);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    challenges::{ActiveChallenges, Challenge},
+    clock::GameClock,
+    collision::CollisionShape,
+    controls::Controls,
+    hud::{HudElement, HudItem},
+    player::Player,
+    playing,
+    util::ReflectResource,
+    world::GameWorld,
+    AppState,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+
+// Realistic flight: flying flat out wears the bee out, and it gets its breath back by
+// slowing down or resting on flowers. On for levels marked @stamina, or everywhere with
+// the challenge.
+pub struct StaminaPlugin;
+
+impl Plugin for StaminaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stamina>()
+            .register_type::<Stamina>()
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(start_stamina))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(update_stamina)
+                    .with_system(update_stamina_bar),
+            );
+    }
+}
+
+// Flying faster than this uses stamina, and slower gets it back
+const CRUISING_THROTTLE: f32 = 0.6;
+// Seconds at full throttle to go from rested to worn out
+const DRAIN_SECONDS: f32 = 4.0;
+// Seconds hovering in place to go from worn out to rested
+const RECOVERY_SECONDS: f32 = 6.0;
+// Seconds on a flower to go from worn out to rested, on top of any other recovery
+const FLOWER_RECOVERY_SECONDS: f32 = 1.5;
+// A worn out bee can only go this fast, which is slow enough to recover
+const EXHAUSTED_THROTTLE: f32 = 0.4;
+// Stamina a worn out bee needs back before it can speed up again
+const RECOVERED: f32 = 0.3;
+
+const BAR_COLOR: Color = Color::rgb(0.98, 0.82, 0.48);
+const EXHAUSTED_BAR_COLOR: Color = Color::rgb(0.8, 0.3, 0.3);
+
+// Stands still, restoring stamina to a bee hovering over it
+#[derive(Component)]
+pub struct Flower;
+
+impl Flower {
+    pub const SIZE: f32 = 16.0;
+}
+
+#[derive(Debug, Reflect)]
+#[reflect(Resource)]
+pub struct Stamina {
+    pub enabled: bool,
+    // From 0 when worn out to 1 when rested
+    level: f32,
+    exhausted: bool,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: 1.0,
+            exhausted: false,
+        }
+    }
+}
+
+impl Stamina {
+    // Use or recover stamina for flying at a throttle, already limited by `throttle`
+    fn update(&mut self, throttle: f32, seconds: f32, on_flower: bool) {
+        if !self.enabled {
+            return;
+        }
+        let rate = if throttle > CRUISING_THROTTLE {
+            -(throttle - CRUISING_THROTTLE) / (1.0 - CRUISING_THROTTLE) / DRAIN_SECONDS
+        } else {
+            (CRUISING_THROTTLE - throttle) / CRUISING_THROTTLE / RECOVERY_SECONDS
+        };
+        let flower_rate = if on_flower {
+            1.0 / FLOWER_RECOVERY_SECONDS
+        } else {
+            0.0
+        };
+        self.level = (self.level + (rate + flower_rate) * seconds).clamp(0.0, 1.0);
+
+        if self.level == 0.0 {
+            self.exhausted = true;
+        } else if self.level >= RECOVERED {
+            self.exhausted = false;
+        }
+    }
+
+    // The throttle the bee can manage right now
+    pub fn throttle(&self, throttle: f32) -> f32 {
+        if self.enabled && self.exhausted {
+            throttle.min(EXHAUSTED_THROTTLE)
+        } else {
+            throttle
+        }
+    }
+}
+
+// Every run starts rested, with the bar shown if stamina counts
+fn start_stamina(
+    mut commands: Commands,
+    mut stamina: ResMut<Stamina>,
+    world: Res<Arc<GameWorld>>,
+    challenges: Res<ActiveChallenges>,
+) {
+    *stamina = Stamina {
+        enabled: world.metadata.stamina || challenges.is_active(Challenge::RealisticFlight),
+        ..Stamina::default()
+    };
+    if stamina.enabled {
+        spawn_stamina_bar(&mut commands);
+    }
+}
+
+fn update_stamina(
+    clock: Res<GameClock>,
+    controls: Res<Controls>,
+    mut stamina: ResMut<Stamina>,
+    player: Query<&CollisionShape, With<Player>>,
+    flowers: Query<&CollisionShape, With<Flower>>,
+) {
+    let player = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let on_flower = flowers.iter().any(|flower| player.is_collided_with(flower));
+    let throttle = match controls.aim {
+        Some(_) => stamina.throttle(controls.throttle),
+        None => 0.0,
+    };
+    stamina.update(throttle, clock.delta_seconds(), on_flower);
+}
+
+#[derive(Component)]
+struct StaminaBar;
+
+fn spawn_stamina_bar(commands: &mut Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(2.0),
+                    left: Val::Percent(35.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Percent(30.0), Val::Px(16.0)),
+                padding: Rect::all(Val::Px(3.0)),
+                ..Style::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..NodeBundle::default()
+        })
+        .insert(HudElement(HudItem::Stamina))
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..Style::default()
+                    },
+                    color: BAR_COLOR.into(),
+                    ..NodeBundle::default()
+                })
+                .insert(StaminaBar);
+        });
+}
+
+fn update_stamina_bar(
+    stamina: Res<Stamina>,
+    mut bar: Query<(&mut Style, &mut UiColor), With<StaminaBar>>,
+) {
+    if let Ok((mut style, mut color)) = bar.get_single_mut() {
+        style.size.width = Val::Percent(stamina.level * 100.0);
+        *color = if stamina.exhausted {
+            EXHAUSTED_BAR_COLOR
+        } else {
+            BAR_COLOR
+        }
+        .into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamina_wears_out_and_comes_back() {
+        let mut stamina = Stamina {
+            enabled: true,
+            ..Stamina::default()
+        };
+        // Cruising is free
+        stamina.update(CRUISING_THROTTLE, 10.0, false);
+        assert_eq!(stamina.level, 1.0);
+
+        stamina.update(1.0, DRAIN_SECONDS / 2.0, false);
+        assert!((stamina.level - 0.5).abs() < 1e-5);
+        stamina.update(1.0, DRAIN_SECONDS, false);
+        assert_eq!(stamina.level, 0.0);
+        assert_eq!(stamina.throttle(1.0), EXHAUSTED_THROTTLE);
+
+        // Flying as fast as a worn out bee can still recovers, slowly
+        stamina.update(stamina.throttle(1.0), 1.0, false);
+        assert!(stamina.level > 0.0 && stamina.exhausted);
+        stamina.update(0.0, 0.5, true);
+        assert!(!stamina.exhausted);
+        assert_eq!(stamina.throttle(1.0), 1.0);
+    }
+
+    #[test]
+    fn stamina_only_counts_when_enabled() {
+        let mut stamina = Stamina::default();
+        stamina.update(1.0, 100.0, false);
+        assert_eq!(stamina.level, 1.0);
+        assert_eq!(stamina.throttle(1.0), 1.0);
+    }
+}
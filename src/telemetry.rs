@@ -0,0 +1,284 @@
+use crate::{
+    game_overlay::GameTimer,
+    http,
+    pack::LevelRegistry,
+    player::Player,
+    replay::ReplayPlayback,
+    save::SaveData,
+    world::{GameWorld, WorldOrigin, WorldType},
+    AppState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+// Balance data is only collected with the setting on, and stays on this machine unless
+// an upload endpoint is set in the save file
+const TELEMETRY_FILE: &str = "telemetry.ron";
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Telemetry::load())
+            .add_system_set(SystemSet::on_enter(AppState::Menu).with_system(upload_telemetry))
+            .add_system(receive_upload)
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(record_attempt))
+            .add_system_set(SystemSet::on_enter(AppState::Death).with_system(record_death))
+            .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(record_completion))
+            .add_system_to_stage(CoreStage::Last, write_telemetry);
+    }
+}
+
+// Anonymous per-level statistics, with nothing identifying the player
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Telemetry {
+    // Keyed by level name, including the variant, and the hash of the level's layout so
+    // stats from edited copies of a level are kept apart
+    levels: BTreeMap<String, LevelStats>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct LevelStats {
+    attempts: u32,
+    // Tiles the player died on
+    deaths: Vec<(usize, usize)>,
+    // Completion times in seconds
+    completions: Vec<f32>,
+}
+
+impl Telemetry {
+    fn load() -> Self {
+        match fs::read_to_string(TELEMETRY_FILE) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Ignoring unreadable telemetry file: {err}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn write(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                fs::write(TELEMETRY_FILE, contents).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("Failed to write telemetry file: {err}");
+        }
+    }
+
+    // Forget what was in `sent`, keeping anything collected since. Stats are only ever
+    // added to, so what was sent is at the start of each list.
+    fn forget(&mut self, sent: &Telemetry) {
+        for (name, sent) in &sent.levels {
+            if let Some(stats) = self.levels.get_mut(name) {
+                stats.attempts = stats.attempts.saturating_sub(sent.attempts);
+                stats
+                    .deaths
+                    .drain(..sent.deaths.len().min(stats.deaths.len()));
+                stats
+                    .completions
+                    .drain(..sent.completions.len().min(stats.completions.len()));
+            }
+        }
+        self.levels
+            .retain(|_, stats| *stats != LevelStats::default());
+    }
+
+    // Forget everything collected so far, on this machine
+    pub fn delete(&mut self) {
+        self.levels.clear();
+        if let Err(err) = fs::remove_file(TELEMETRY_FILE) {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Failed to delete telemetry file: {err}");
+            }
+        }
+    }
+}
+
+// Name to file the current run under, if it's on a level and should be recorded
+fn run_name(
+    save: &SaveData,
+    world: &GameWorld,
+    levels: &LevelRegistry,
+    playback: Option<Res<ReplayPlayback>>,
+) -> Option<String> {
+    match world.world_type {
+        WorldType::Level { index } if save.settings.telemetry && playback.is_none() => {
+            let name = world
+                .variant
+                .level_name(&levels.levels[index].display_name());
+            // Slowed down runs would skew the balance data for the level at full speed
+            let speed = match save.settings.game_speed {
+                speed if speed < 100 => format!(" [{speed}% speed]"),
+                _ => String::new(),
+            };
+            Some(format!("{name}{speed} #{:016x}", world.hash()))
+        }
+        _ => None,
+    }
+}
+
+fn record_attempt(
+    mut telemetry: ResMut<Telemetry>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if let Some(name) = run_name(&save, &world, &levels, playback) {
+        telemetry.levels.entry(name).or_default().attempts += 1;
+    }
+}
+
+fn record_death(
+    mut telemetry: ResMut<Telemetry>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    origin: Res<WorldOrigin>,
+    levels: Res<LevelRegistry>,
+    playback: Option<Res<ReplayPlayback>>,
+    player: Query<&Transform, With<Player>>,
+) {
+    let name = match run_name(&save, &world, &levels, playback) {
+        Some(name) => name,
+        None => return,
+    };
+    // Deaths outside the layout aren't placed anywhere useful
+    let tile = player
+        .get_single()
+        .ok()
+        .and_then(|transform| origin.world_to_tile(transform.translation.truncate()));
+    if let Some(tile) = tile {
+        telemetry.levels.entry(name).or_default().deaths.push(tile);
+    }
+}
+
+fn record_completion(
+    mut telemetry: ResMut<Telemetry>,
+    save: Res<SaveData>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    playback: Option<Res<ReplayPlayback>>,
+    timer: Res<GameTimer>,
+) {
+    if let Some(name) = run_name(&save, &world, &levels, playback) {
+        let stats = telemetry.levels.entry(name).or_default();
+        stats.completions.push(timer.elapsed());
+    }
+}
+
+fn write_telemetry(telemetry: Res<Telemetry>) {
+    if telemetry.is_changed() && !telemetry.is_added() && !telemetry.levels.is_empty() {
+        telemetry.write();
+    }
+}
+
+// Telemetry on its way to the upload endpoint, kept to be forgotten once it's arrived
+struct PendingUpload {
+    sent: Telemetry,
+    result: Mutex<Receiver<io::Result<()>>>,
+}
+
+// Send what was collected in earlier sessions, in the background so the menu doesn't wait
+// on the network. The setting and endpoint are saved with a profile, so this waits for the
+// menu, when one has been picked. At most one upload is made each session.
+fn upload_telemetry(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    telemetry: Res<Telemetry>,
+    mut uploaded: Local<bool>,
+) {
+    let endpoint = match &save.settings.telemetry_endpoint {
+        Some(endpoint) if save.settings.telemetry && !telemetry.levels.is_empty() && !*uploaded => {
+            endpoint.clone()
+        }
+        _ => return,
+    };
+    let body = match ron::to_string(&*telemetry) {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Failed to serialize telemetry: {err}");
+            return;
+        }
+    };
+    *uploaded = true;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = http::post(&endpoint, &body);
+        if let Err(err) = &result {
+            warn!("Failed to upload telemetry to {endpoint}: {err}");
+        }
+        let _ = sender.send(result);
+    });
+    commands.insert_resource(PendingUpload {
+        sent: telemetry.clone(),
+        result: Mutex::new(receiver),
+    });
+}
+
+// Start afresh once an upload has gone through, so nothing is sent twice
+fn receive_upload(
+    mut commands: Commands,
+    pending: Option<Res<PendingUpload>>,
+    mut telemetry: ResMut<Telemetry>,
+) {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    let result = match pending.result.lock().unwrap().try_recv() {
+        Ok(result) => result,
+        Err(_) => return,
+    };
+    commands.remove_resource::<PendingUpload>();
+    if result.is_ok() {
+        telemetry.forget(&pending.sent);
+        if telemetry.levels.is_empty() {
+            telemetry.delete();
+        } else {
+            telemetry.write();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploads_forget_only_what_was_sent() {
+        let stats = |attempts, deaths: &[(usize, usize)], completions: &[f32]| LevelStats {
+            attempts,
+            deaths: deaths.to_vec(),
+            completions: completions.to_vec(),
+        };
+        let mut sent = Telemetry::default();
+        sent.levels.insert("a".into(), stats(2, &[(1, 1)], &[10.0]));
+        sent.levels.insert("b".into(), stats(1, &[], &[12.0]));
+
+        // Collected while the upload was on its way
+        let mut telemetry = sent.clone();
+        telemetry.levels.get_mut("a").unwrap().attempts += 1;
+        telemetry.levels.get_mut("a").unwrap().deaths.push((2, 3));
+        telemetry.levels.insert("c".into(), stats(1, &[], &[]));
+
+        telemetry.forget(&sent);
+        let mut expected = Telemetry::default();
+        expected.levels.insert("a".into(), stats(1, &[(2, 3)], &[]));
+        expected.levels.insert("c".into(), stats(1, &[], &[]));
+        assert_eq!(telemetry, expected);
+    }
+}
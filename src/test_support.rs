@@ -0,0 +1,136 @@
+use crate::{add_game, pack::LevelRegistry, world::Variant, AppState};
+use bevy::{
+    asset::AssetPlugin, core::CorePlugin, input::InputPlugin, prelude::*, utils::HashSet,
+    window::WindowPlugin,
+};
+
+// The game without a window or GPU, starting in `state`, for tests that drive it frame by
+// frame
+pub fn headless_app(state: AppState) -> App {
+    let mut app = App::new();
+    app.add_plugin(CorePlugin)
+        .add_plugin(TransformPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(WindowPlugin::default())
+        .add_plugin(AssetPlugin)
+        // Assets that Bevy's rendering, text and audio plugins would otherwise add
+        .add_asset::<Mesh>()
+        .add_asset::<Image>()
+        .add_asset::<Font>()
+        .add_asset::<TextureAtlas>()
+        .add_asset::<ColorMaterial>()
+        .add_asset::<AudioSource>()
+        .add_state(state);
+    add_game(&mut app);
+    app
+}
+
+// Run the schedule `frames` times
+pub fn advance(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+// Queue a state change and run a frame for it to happen
+pub fn go_to(app: &mut App, state: AppState) {
+    app.world
+        .get_resource_mut::<State<AppState>>()
+        .unwrap()
+        .set(state.clone())
+        .unwrap();
+    app.update();
+    assert_eq!(current_state(app), state);
+}
+
+pub fn current_state(app: &App) -> AppState {
+    app.world
+        .get_resource::<State<AppState>>()
+        .unwrap()
+        .current()
+        .clone()
+}
+
+// How many entities have component `T`
+pub fn count<T: Component>(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<T>>()
+        .iter(&app.world)
+        .count()
+}
+
+// Every entity that exists right now
+pub fn entities(app: &mut App) -> HashSet<Entity> {
+    app.world.query::<Entity>().iter(&app.world).collect()
+}
+
+// Load a built-in level the way level select does, ready for going to AppState::Game
+pub fn load_level(app: &mut App, index: usize) {
+    let world = app
+        .world
+        .get_resource_mut::<LevelRegistry>()
+        .unwrap()
+        .load_cached(index, Variant::Original, false)
+        .unwrap();
+    app.world.insert_resource(world);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Player;
+
+    // Whether everything in `before` has been despawned
+    fn all_despawned(app: &mut App, before: &HashSet<Entity>) -> bool {
+        entities(app).is_disjoint(before)
+    }
+
+    #[test]
+    fn menus_clean_up_after_themselves() {
+        let mut app = headless_app(AppState::Menu);
+        advance(&mut app, 2);
+        let menu = entities(&mut app);
+        assert!(!menu.is_empty());
+
+        go_to(&mut app, AppState::LevelSelect);
+        advance(&mut app, 2);
+        assert!(all_despawned(&mut app, &menu));
+        let level_select = entities(&mut app);
+
+        load_level(&mut app, 0);
+        go_to(&mut app, AppState::Game);
+        advance(&mut app, 2);
+        assert!(all_despawned(&mut app, &level_select));
+        assert_eq!(count::<Player>(&mut app), 1);
+    }
+
+    #[test]
+    fn retrying_starts_the_level_afresh() {
+        let mut app = headless_app(AppState::LevelSelect);
+        load_level(&mut app, 0);
+        go_to(&mut app, AppState::Game);
+        advance(&mut app, 5);
+        let run = entities(&mut app);
+
+        // The level stays in the background while dying and choosing to retry
+        go_to(&mut app, AppState::Death);
+        advance(&mut app, 2);
+        assert_eq!(count::<Player>(&mut app), 1);
+        go_to(&mut app, AppState::Retry);
+        advance(&mut app, 2);
+
+        go_to(&mut app, AppState::Game);
+        advance(&mut app, 2);
+        assert!(all_despawned(&mut app, &run));
+        assert_eq!(count::<Player>(&mut app), 1);
+
+        // And again from the start of that run
+        let run = entities(&mut app);
+        go_to(&mut app, AppState::Death);
+        go_to(&mut app, AppState::Retry);
+        go_to(&mut app, AppState::Game);
+        advance(&mut app, 2);
+        assert!(all_despawned(&mut app, &run));
+        assert_eq!(count::<Player>(&mut app), 1);
+    }
+}
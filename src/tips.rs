@@ -0,0 +1,305 @@
+use crate::{atlas::asset_exists, save::SaveData, ui::GameFont, world::LoadingText, AppState};
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use rand::random;
+use serde::Deserialize;
+
+// Gameplay tips shown while a level loads and after dying. They're read from
+// tips/<language>.tips, and the ones about things the player hasn't tried yet come up more.
+pub struct TipsPlugin;
+
+impl Plugin for TipsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TipList>()
+            .init_asset_loader::<TipListLoader>()
+            .add_startup_system(load_tips)
+            .add_system_set(SystemSet::on_enter(AppState::Death).with_system(spawn_death_tip))
+            .add_system(spawn_loading_tip)
+            .add_system(despawn_loading_tip)
+            .add_system(rotate_tips);
+    }
+}
+
+const DEFAULT_LANGUAGE: &str = "en";
+// How long each tip stays up before the next
+const TIP_SECONDS: f32 = 6.0;
+
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "4e8b1f27-95c3-4d0a-8a6e-2f7c3b9d1e45"]
+pub struct TipList {
+    tips: Vec<Tip>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tip {
+    text: String,
+    #[serde(default = "default_weight")]
+    weight: f32,
+    // Only shown while this holds
+    #[serde(default)]
+    when: Option<Condition>,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum Condition {
+    Below(Stat, u32),
+    AtLeast(Stat, u32),
+}
+
+impl Condition {
+    fn holds(self, save: &SaveData) -> bool {
+        match self {
+            Condition::Below(stat, count) => stat.of(save) < count,
+            Condition::AtLeast(stat, count) => stat.of(save) >= count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum Stat {
+    Deaths,
+    Teleports,
+    SlowMotions,
+    LevelsBeaten,
+}
+
+impl Stat {
+    fn of(self, save: &SaveData) -> u32 {
+        match self {
+            Stat::Deaths => save.stats.deaths,
+            Stat::Teleports => save.stats.teleports,
+            Stat::SlowMotions => save.stats.slow_motions,
+            Stat::LevelsBeaten => save.best_times.len() as u32,
+        }
+    }
+}
+
+impl TipList {
+    // Pick a tip at random by weight, out of those that apply, trying not to repeat the one
+    // already showing. `roll` is in [0, 1).
+    fn choose(&self, save: &SaveData, roll: f32, showing: Option<usize>) -> Option<usize> {
+        let applies = |&(index, tip): &(usize, &Tip)| {
+            Some(index) != showing && tip.when.is_none_or(|when| when.holds(save))
+        };
+        let candidates: Vec<_> = self.tips.iter().enumerate().filter(applies).collect();
+        if candidates.is_empty() {
+            return showing;
+        }
+        let total: f32 = candidates.iter().map(|(_, tip)| tip.weight.max(0.0)).sum();
+        let mut remaining = roll * total;
+        for &(index, tip) in &candidates {
+            remaining -= tip.weight.max(0.0);
+            if remaining < 0.0 {
+                return Some(index);
+            }
+        }
+        candidates.last().map(|&(index, _)| index)
+    }
+}
+
+#[derive(Default)]
+struct TipListLoader;
+
+impl AssetLoader for TipListLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let tips: TipList = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(tips));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tips"]
+    }
+}
+
+struct Tips(Handle<TipList>);
+
+// The language to show tips in, from the usual locale variables, e.g. "fr_FR.UTF-8" to "fr"
+fn language() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| language_of(&value))
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+fn language_of(locale: &str) -> String {
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    // The "C" and "POSIX" locales don't name a language
+    if language.is_empty() || language == "C" || language == "POSIX" {
+        DEFAULT_LANGUAGE.to_string()
+    } else {
+        language.to_lowercase()
+    }
+}
+
+fn load_tips(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let path = format!("tips/{}.tips", language());
+    let path = if asset_exists(&path) {
+        path
+    } else {
+        format!("tips/{DEFAULT_LANGUAGE}.tips")
+    };
+    commands.insert_resource(Tips(asset_server.load(&path)));
+}
+
+#[derive(Component)]
+struct TipText {
+    timer: Timer,
+    showing: Option<usize>,
+}
+
+// Goes away with the loading text
+#[derive(Component)]
+struct LoadingTip;
+
+fn spawn_tip(commands: &mut Commands, font: &GameFont, position: Rect<Val>) -> Entity {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position,
+                max_size: Size::new(Val::Percent(90.0), Val::Auto),
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 30.0,
+                    color: Color::rgb(0.9, 0.9, 0.8),
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(TipText {
+            timer: Timer::from_seconds(TIP_SECONDS, true),
+            showing: None,
+        })
+        .id()
+}
+
+// Stays up through the retry menu, which is cleared away with the rest of the run
+fn spawn_death_tip(mut commands: Commands, font: Res<GameFont>) {
+    spawn_tip(
+        &mut commands,
+        &font,
+        Rect {
+            bottom: Val::Percent(8.0),
+            left: Val::Percent(5.0),
+            ..Rect::default()
+        },
+    );
+}
+
+fn spawn_loading_tip(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    loading_text: Query<(), Added<LoadingText>>,
+) {
+    if loading_text.is_empty() {
+        return;
+    }
+    let tip = spawn_tip(
+        &mut commands,
+        &font,
+        Rect {
+            bottom: Val::Percent(8.0),
+            left: Val::Percent(2.0),
+            ..Rect::default()
+        },
+    );
+    commands.entity(tip).insert(LoadingTip);
+}
+
+fn despawn_loading_tip(
+    mut commands: Commands,
+    loading_text: Query<(), With<LoadingText>>,
+    tips: Query<Entity, With<LoadingTip>>,
+) {
+    if !loading_text.is_empty() {
+        return;
+    }
+    for tip in tips.iter() {
+        commands.entity(tip).despawn_recursive();
+    }
+}
+
+fn rotate_tips(
+    time: Res<Time>,
+    save: Res<SaveData>,
+    tips: Res<Tips>,
+    tip_lists: Res<Assets<TipList>>,
+    mut texts: Query<(&mut TipText, &mut Text)>,
+) {
+    let tip_list = match tip_lists.get(&tips.0) {
+        Some(tip_list) => tip_list,
+        None => return,
+    };
+    for (mut tip, mut text) in texts.iter_mut() {
+        let finished = tip.timer.tick(time.delta()).just_finished();
+        if tip.showing.is_some() && !finished {
+            continue;
+        }
+        tip.showing = tip_list.choose(&save, random(), tip.showing);
+        if let Some(index) = tip.showing {
+            text.sections[0].value = tip_list.tips[index].text.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tip_list() -> TipList {
+        ron::from_str(
+            "(tips: [
+                (text: \"Hold the throttle to fly faster.\"),
+                (text: \"Click to teleport.\", weight: 3.0, when: Some(Below(Teleports, 1))),
+                (text: \"Take a break?\", when: Some(AtLeast(Deaths, 50))),
+            ])",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tips_follow_player_stats() {
+        let tips = tip_list();
+        let mut save = SaveData::default();
+        // The teleport tip is three times as likely as the general one, and the
+        // break tip can't come up yet
+        assert_eq!(tips.choose(&save, 0.2, None), Some(0));
+        assert_eq!(tips.choose(&save, 0.3, None), Some(1));
+        assert_eq!(tips.choose(&save, 0.99, None), Some(1));
+
+        save.stats.teleports = 1;
+        save.stats.deaths = 50;
+        assert_eq!(tips.choose(&save, 0.99, None), Some(2));
+        // The tip showing isn't picked again while there's another
+        assert_eq!(tips.choose(&save, 0.0, Some(0)), Some(2));
+    }
+
+    #[test]
+    fn languages_come_from_locales() {
+        assert_eq!(language_of("fr_FR.UTF-8"), "fr");
+        assert_eq!(language_of("de"), "de");
+        assert_eq!(language_of("C.UTF-8"), "en");
+        assert_eq!(language_of(""), "en");
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    clock::GameClock,
+    enemy::{EnemySpawned, Projectile, SpawnEnemies},
+    render_layer::RenderLayer,
+    save::SaveData,
+    AppState,
+};
+use bevy::{
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::Indices,
+        render_asset::{PrepareAssetError, RenderAsset},
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer,
+            BufferBindingType, BufferInitDescriptor, BufferSize, BufferUsages, PrimitiveTopology,
+            ShaderStages,
+        },
+        renderer::RenderDevice,
+    },
+    sprite::{
+        Material2d, Material2dPipeline, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle,
+    },
+};
+use std::collections::VecDeque;
+
+// Motion trails behind projectiles. Every trail of a kind is packed into one mesh that's
+// rebuilt each frame, so hundreds of projectiles cost one draw call per kind rather than
+// an entity per trail segment.
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<TrailMaterial>::default())
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(spawn_trail_meshes))
+            .add_system_set(
+                SystemSet::on_update(AppState::Game)
+                    .with_system(add_trails.after(SpawnEnemies))
+                    .with_system(record_trails)
+                    .with_system(build_trail_meshes),
+            );
+    }
+}
+
+// How long a point stays in a trail
+const TRAIL_SECONDS: f32 = 0.25;
+// Points closer together than this are merged, so slow projectiles don't pile them up
+const MIN_SEGMENT: f32 = 4.0;
+const MAX_POINTS: usize = 16;
+const MISSILE_TRAIL: (f32, Color) = (8.0, Color::rgba(1.0, 0.65, 0.25, 0.45));
+const LASER_TRAIL: (f32, Color) = (4.0, Color::rgba(1.0, 0.3, 0.3, 0.6));
+
+#[derive(Clone, Copy, PartialEq)]
+enum TrailKind {
+    Missile,
+    Laser,
+}
+
+impl TrailKind {
+    fn of(projectile: &Projectile) -> Self {
+        match projectile {
+            Projectile::Missile => Self::Missile,
+            Projectile::Laser { .. } => Self::Laser,
+        }
+    }
+
+    // Width at the projectile, and color
+    fn style(self) -> (f32, Color) {
+        match self {
+            Self::Missile => MISSILE_TRAIL,
+            Self::Laser => LASER_TRAIL,
+        }
+    }
+}
+
+// Where a projectile has been recently, as (position, age), newest last
+#[derive(Component, Default)]
+struct ProjectileTrail(VecDeque<(Vec2, f32)>);
+
+// The mesh all trails of one kind are drawn into
+#[derive(Component)]
+struct TrailMesh(TrailKind);
+
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "b2f0c7e1-8d4a-4c69-9a53-3e7d1f6a0c28"]
+pub struct TrailMaterial {
+    color: Color,
+}
+
+impl TrailMaterial {
+    // Matches the std140 layout of TrailMaterial in trail.wgsl
+    const UNIFORM_SIZE: u64 = 16;
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        self.color
+            .as_linear_rgba_f32()
+            .into_iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect()
+    }
+}
+
+pub struct GpuTrailMaterial {
+    _buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for TrailMaterial {
+    type ExtractedAsset = TrailMaterial;
+    type PreparedAsset = GpuTrailMaterial;
+    type Param = (SRes<RenderDevice>, SRes<Material2dPipeline<TrailMaterial>>);
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        material: Self::ExtractedAsset,
+        (render_device, pipeline): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("trail_material_uniform_buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: &material.uniform_bytes(),
+        });
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("trail_material_bind_group"),
+            layout: &pipeline.material2d_layout,
+        });
+
+        Ok(GpuTrailMaterial {
+            _buffer: buffer,
+            bind_group,
+        })
+    }
+}
+
+impl Material2d for TrailMaterial {
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/trail.wgsl"))
+    }
+
+    fn bind_group(material: &GpuTrailMaterial) -> &BindGroup {
+        &material.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(TrailMaterial::UNIFORM_SIZE),
+                },
+                count: None,
+            }],
+            label: Some("trail_material_layout"),
+        })
+    }
+}
+
+// Vertex data for a batch of trails
+#[derive(Default)]
+struct TrailVertices {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl TrailVertices {
+    // Add a strip through the points, oldest first, tapering from `width` at the newest
+    // point down to nothing at the oldest
+    fn push_strip(&mut self, points: &[Vec2], width: f32) {
+        if points.len() < 2 {
+            return;
+        }
+        let last = points.len() - 1;
+        for (i, &point) in points.iter().enumerate() {
+            let direction =
+                (points[(i + 1).min(last)] - points[i.saturating_sub(1)]).normalize_or_zero();
+            let progress = i as f32 / last as f32;
+            let side = direction.perp() * width * progress / 2.0;
+            for (edge, offset) in [(0.0, side), (1.0, -side)] {
+                self.positions.push((point + offset).extend(0.0).to_array());
+                self.uvs.push([progress, edge]);
+            }
+        }
+        let first = (self.positions.len() - points.len() * 2) as u32;
+        for i in 0..last as u32 {
+            let corner = first + i * 2;
+            self.indices.extend([
+                corner,
+                corner + 1,
+                corner + 2,
+                corner + 1,
+                corner + 3,
+                corner + 2,
+            ]);
+        }
+    }
+
+    fn write_to(self, mesh: &mut Mesh) {
+        let normals = vec![[0.0, 0.0, 1.0]; self.positions.len()];
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+    }
+}
+
+fn spawn_trail_meshes(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TrailMaterial>>,
+) {
+    if save.settings.low_spec {
+        return;
+    }
+    for kind in [TrailKind::Missile, TrailKind::Laser] {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        TrailVertices::default().write_to(&mut mesh);
+        let (_, color) = kind.style();
+        commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: meshes.add(mesh).into(),
+                material: materials.add(TrailMaterial { color }),
+                ..MaterialMesh2dBundle::default()
+            })
+            .insert(TrailMesh(kind))
+            .insert(RenderLayer::Trails);
+    }
+}
+
+fn add_trails(mut commands: Commands, save: Res<SaveData>, mut spawned: EventReader<EnemySpawned>) {
+    if save.settings.low_spec {
+        return;
+    }
+    for event in spawned.iter() {
+        commands
+            .entity(event.entity)
+            .insert(ProjectileTrail::default());
+    }
+}
+
+fn record_trails(clock: Res<GameClock>, mut trails: Query<(&Transform, &mut ProjectileTrail)>) {
+    let delta = clock.delta_seconds();
+    for (transform, mut trail) in trails.iter_mut() {
+        for (_, age) in trail.0.iter_mut() {
+            *age += delta;
+        }
+        while trail.0.front().is_some_and(|(_, age)| *age > TRAIL_SECONDS) {
+            trail.0.pop_front();
+        }
+
+        let position = transform.translation.truncate();
+        let moved = trail
+            .0
+            .back()
+            .is_none_or(|(last, _)| last.distance(position) >= MIN_SEGMENT);
+        if moved {
+            trail.0.push_back((position, 0.0));
+            if trail.0.len() > MAX_POINTS {
+                trail.0.pop_front();
+            }
+        }
+    }
+}
+
+fn build_trail_meshes(
+    mut meshes: ResMut<Assets<Mesh>>,
+    trail_meshes: Query<(&TrailMesh, &Mesh2dHandle)>,
+    trails: Query<(&Projectile, &Transform, &ProjectileTrail)>,
+) {
+    for (trail_mesh, handle) in trail_meshes.iter() {
+        let (width, _) = trail_mesh.0.style();
+        let mut vertices = TrailVertices::default();
+        let mut points = Vec::with_capacity(MAX_POINTS + 1);
+        for (projectile, transform, trail) in trails.iter() {
+            if TrailKind::of(projectile) != trail_mesh.0 {
+                continue;
+            }
+            // Always reaches right up to the projectile
+            points.clear();
+            points.extend(trail.0.iter().map(|(point, _)| *point));
+            points.push(transform.translation.truncate());
+            vertices.push_strip(&points, width * transform.scale.x);
+        }
+        if let Some(mesh) = meshes.get_mut(&handle.0) {
+            vertices.write_to(mesh);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_taper_towards_the_tail() {
+        let mut vertices = TrailVertices::default();
+        let points = [Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(20.0, 0.0)];
+        vertices.push_strip(&points, 8.0);
+        vertices.push_strip(&points[..1], 8.0);
+        vertices.push_strip(&points, 8.0);
+
+        // Two vertices per point and two triangles per segment, for both strips
+        assert_eq!(vertices.positions.len(), 12);
+        assert_eq!(vertices.indices.len(), 24);
+        assert!(vertices.indices[12..].iter().all(|&index| index >= 6));
+        // Nothing wide at the tail, full width at the head
+        assert_eq!(vertices.positions[0], vertices.positions[1]);
+        assert_eq!(vertices.positions[4], [20.0, 4.0, 0.0]);
+        assert_eq!(vertices.positions[5], [20.0, -4.0, 0.0]);
+        assert_eq!(vertices.uvs[4], [1.0, 0.0]);
+    }
+}
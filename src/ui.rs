@@ -1,11 +1,22 @@
 use crate::{
+    attract::AttractPlugin,
+    challenges::ChallengeButton,
+    change_state,
+    countdown::CountdownPlugin,
+    credits::CreditsPlugin,
     game_overlay::GameOverlayPlugin,
     help::HelpPlugin,
+    input_display::InputDisplayPlugin,
     level_select::LevelSelectPlugin,
     menu::MenuPlugin,
+    news::NewsPlugin,
+    onboarding::OnboardingPlugin,
+    profile::ProfilePlugin,
     retry::RetryPlugin,
+    settings::{SettingButton, SettingsPlugin},
     upgrade_select::{UpgradeButton, UpgradeSelectPlugin},
     victory::VictoryPlugin,
+    wardrobe::WardrobePlugin,
     AppState,
 };
 use bevy::{app::PluginGroupBuilder, prelude::*};
@@ -35,12 +46,20 @@ impl FromWorld for GameFont {
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<GameFont>()
+            .add_system_set(
+                SystemSet::on_update(AppState::ProfileSelect).with_system(manage_button_colors),
+            )
             .add_system_set(SystemSet::on_update(AppState::Menu).with_system(manage_button_colors))
             .add_system_set(
                 SystemSet::on_update(AppState::UpgradeSelect)
                     .with_system(manage_button_colors)
                     .with_system(manage_back_button),
             )
+            .add_system_set(
+                SystemSet::on_update(AppState::Wardrobe)
+                    .with_system(manage_button_colors)
+                    .with_system(manage_back_button),
+            )
             .add_system_set(
                 SystemSet::on_update(AppState::LevelSelect)
                     .with_system(manage_button_colors)
@@ -51,6 +70,22 @@ impl Plugin for UiPlugin {
                     .with_system(manage_button_colors)
                     .with_system(manage_back_button),
             )
+            .add_system_set(
+                SystemSet::on_update(AppState::Settings)
+                    .with_system(manage_button_colors)
+                    .with_system(manage_back_button),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Credits)
+                    .with_system(manage_button_colors)
+                    .with_system(manage_back_button),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::NameEntry).with_system(manage_button_colors),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::ControlSelect).with_system(manage_button_colors),
+            )
             .add_system_set(SystemSet::on_update(AppState::Retry).with_system(manage_button_colors))
             .add_system_set(
                 SystemSet::on_update(AppState::Victory).with_system(manage_button_colors),
@@ -65,10 +100,19 @@ impl PluginGroup for UiPlugins {
         group
             .add(LevelSelectPlugin)
             .add(MenuPlugin)
+            .add(AttractPlugin)
+            .add(NewsPlugin)
             .add(UpgradeSelectPlugin)
+            .add(WardrobePlugin)
             .add(HelpPlugin)
+            .add(SettingsPlugin)
+            .add(CreditsPlugin)
+            .add(OnboardingPlugin)
+            .add(ProfilePlugin)
             .add(RetryPlugin)
             .add(GameOverlayPlugin)
+            .add(CountdownPlugin)
+            .add(InputDisplayPlugin)
             .add(VictoryPlugin)
             .add(UiPlugin);
     }
@@ -118,7 +162,7 @@ fn manage_back_button(
     for interaction in interaction.iter() {
         if matches!(interaction, Interaction::Clicked) {
             // Go back menu state when back button is clicked
-            state.set(AppState::Menu).unwrap();
+            change_state(&mut state, AppState::Menu);
         }
     }
 }
@@ -126,7 +170,13 @@ fn manage_back_button(
 fn manage_button_colors(
     mut interaction: Query<
         (&Interaction, &mut UiColor),
-        (Changed<Interaction>, With<Button>, Without<UpgradeButton>),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            Without<UpgradeButton>,
+            Without<ChallengeButton>,
+            Without<SettingButton>,
+        ),
     >,
 ) {
     for (interaction, mut color) in interaction.iter_mut() {
@@ -0,0 +1,154 @@
+use crate::{
+    despawn::DespawnQueue,
+    player::Shield,
+    playing,
+    save::SaveData,
+    upgrades::{Upgrade, UpgradeTracker},
+};
+use bevy::prelude::*;
+
+pub struct UpgradeEffectsPlugin;
+
+impl Plugin for UpgradeEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(playing)
+                .with_system(spawn_speed_trail)
+                .with_system(sync_shield_outline),
+        )
+        // Keep fading out trail images after the run ends
+        .add_system(fade_trail_images);
+    }
+}
+
+// Leaves fading copies of the sprite behind while moving
+#[derive(Component)]
+pub struct SpeedTrail(Timer);
+
+impl Default for SpeedTrail {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0.04, true))
+    }
+}
+
+#[derive(Component)]
+struct TrailImage(Timer);
+
+const TRAIL_ALPHA: f32 = 0.4;
+
+// Tinted, slightly larger copy of the parent sprite drawn behind it
+#[derive(Component)]
+struct ShieldOutline;
+
+// Attach the visuals for the active upgrades to the player
+// Shrink has no component of its own since the player is spawned scaled down
+pub fn add_upgrade_effects(commands: &mut Commands, player: Entity, upgrades: &UpgradeTracker) {
+    if upgrades.has_upgrade(Upgrade::DoubleSpeed) {
+        commands.entity(player).insert(SpeedTrail::default());
+    }
+    if upgrades.has_upgrade(Upgrade::Shield) {
+        commands
+            .entity(player)
+            .insert(Shield)
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(SpriteSheetBundle {
+                        sprite: TextureAtlasSprite {
+                            color: Color::rgba(0.6, 0.9, 1.0, 0.6),
+                            ..TextureAtlasSprite::default()
+                        },
+                        transform: Transform {
+                            translation: Vec3::new(0.0, 0.0, -0.05),
+                            scale: Vec3::splat(1.25),
+                            ..Transform::default()
+                        },
+                        ..SpriteSheetBundle::default()
+                    })
+                    .insert(ShieldOutline);
+            });
+    }
+}
+
+fn spawn_speed_trail(
+    mut commands: Commands,
+    time: Res<Time>,
+    save: Res<SaveData>,
+    mut trails: Query<(
+        &mut SpeedTrail,
+        &GlobalTransform,
+        ChangeTrackers<Transform>,
+        &TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+    )>,
+) {
+    if save.settings.low_spec {
+        return;
+    }
+    for (mut trail, transform, transform_tracker, sprite, atlas) in trails.iter_mut() {
+        if !trail.0.tick(time.delta()).just_finished() || !transform_tracker.is_changed() {
+            continue;
+        }
+
+        let mut transform = Transform::from(*transform);
+        transform.translation.z -= 0.1;
+        let mut color = sprite.color;
+        color.set_a(TRAIL_ALPHA);
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: sprite.index,
+                    color,
+                    ..TextureAtlasSprite::default()
+                },
+                texture_atlas: atlas.clone(),
+                transform,
+                ..SpriteSheetBundle::default()
+            })
+            .insert(TrailImage(Timer::from_seconds(0.25, false)));
+    }
+}
+
+fn fade_trail_images(
+    mut despawn: ResMut<DespawnQueue>,
+    time: Res<Time>,
+    mut images: Query<(Entity, &mut TrailImage, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut image, mut sprite) in images.iter_mut() {
+        if image.0.tick(time.delta()).finished() {
+            despawn.push(entity);
+        } else {
+            sprite.color.set_a(TRAIL_ALPHA * image.0.percent_left());
+        }
+    }
+}
+
+// Mirror the parent's animation frame, and drop the outline once the shield breaks
+fn sync_shield_outline(
+    mut despawn: ResMut<DespawnQueue>,
+    parents: Query<
+        (&TextureAtlasSprite, &Handle<TextureAtlas>, Option<&Shield>),
+        Without<ShieldOutline>,
+    >,
+    mut outlines: Query<
+        (
+            Entity,
+            &Parent,
+            &mut TextureAtlasSprite,
+            &mut Handle<TextureAtlas>,
+        ),
+        With<ShieldOutline>,
+    >,
+) {
+    for (entity, parent, mut sprite, mut atlas) in outlines.iter_mut() {
+        match parents.get(parent.0) {
+            Ok((parent_sprite, parent_atlas, Some(_))) => {
+                sprite.index = parent_sprite.index;
+                if *atlas != *parent_atlas {
+                    *atlas = parent_atlas.clone();
+                }
+            }
+            _ => despawn.push(entity),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    change_state,
     ui::{
         spawn_back_button, GameFont, ACTIVE_BUTTON_COLOR, INACTIVE_BUTTON_COLOR,
         NORMAL_BUTTON_COLOR,
@@ -19,6 +20,7 @@ impl Plugin for UpgradeSelectPlugin {
         .add_system_set(
             SystemSet::on_update(AppState::UpgradeSelect)
                 .with_system(manage_upgrade_buttons)
+                .with_system(manage_start_button)
                 .with_system(update_upgrades_selected_indicator),
         );
     }
@@ -31,12 +33,51 @@ pub struct UpgradeButton(Upgrade);
 #[derive(Component)]
 struct UpgradesSelectedIndicator;
 
+// Explains why the last clicked upgrade couldn't be added
+#[derive(Component)]
+struct LoadoutMessage;
+
+#[derive(Component)]
+struct StartButton;
+
 fn create_upgrade_select(mut commands: Commands, font: Res<GameFont>) {
     commands.spawn_bundle(UiCameraBundle::default());
 
     spawn_back_button(&mut commands, font.get_handle());
     create_upgrades_overlay(&mut commands, &font);
 
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(2.0),
+                    right: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Px(100.0), Val::Px(40.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(StartButton)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Start",
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 50.0,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        });
+
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -61,7 +102,7 @@ fn create_upgrade_select(mut commands: Commands, font: Res<GameFont>) {
                     ..Style::default()
                 },
                 text: Text::with_section(
-                    "Upgrade Select",
+                    "Loadout",
                     TextStyle {
                         font: font.get_handle(),
                         font_size: 70.0,
@@ -96,6 +137,29 @@ fn create_upgrade_select(mut commands: Commands, font: Res<GameFont>) {
                 })
                 .insert(UpgradesSelectedIndicator);
 
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Percent(18.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.3, 0.3),
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(LoadoutMessage);
+
             // Spawn level selector
             parent
                 .spawn_bundle(NodeBundle {
@@ -148,7 +212,7 @@ fn create_upgrade_select(mut commands: Commands, font: Res<GameFont>) {
                                             .with_children(|parent| {
                                                 parent.spawn_bundle(TextBundle {
                                                     text: Text::with_section(
-                                                        upgrade.to_string(),
+                                                        format!("{upgrade} ({})", upgrade.cost()),
                                                         TextStyle {
                                                             font: font.get_handle(),
                                                             font_size: 30.0,
@@ -175,6 +239,7 @@ fn manage_upgrade_buttons(
         (&Interaction, &mut UiColor, &UpgradeButton),
         (Changed<Interaction>, With<Button>),
     >,
+    mut message: Query<&mut Text, With<LoadoutMessage>>,
 ) {
     for (interaction, mut color, button) in interaction.iter_mut() {
         let has_upgrade = upgrades.has_upgrade(button.0);
@@ -189,10 +254,17 @@ fn manage_upgrade_buttons(
         .into();
 
         if matches!(interaction, Interaction::Clicked) {
-            if has_upgrade {
+            let result = if has_upgrade {
                 upgrades.unset_upgrade(button.0);
+                Ok(())
             } else {
-                upgrades.set_upgrade(button.0);
+                upgrades.set_upgrade(button.0)
+            };
+            if let Ok(mut message) = message.get_single_mut() {
+                message.sections[0].value = match result {
+                    Ok(()) => String::new(),
+                    Err(err) => err.to_string(),
+                };
             }
         }
     }
@@ -202,12 +274,25 @@ fn update_upgrades_selected_indicator(
     upgrades: Res<UpgradeTracker>,
     mut indicator: Query<&mut Text, With<UpgradesSelectedIndicator>>,
 ) {
-    let number = match (upgrades.primary, upgrades.secondary) {
-        (None, None) => "0",
-        (Some(_), Some(_)) => "2",
-        _ => "1",
+    let mut indicator = match indicator.get_single_mut() {
+        Ok(indicator) => indicator,
+        Err(_) => return,
     };
+    indicator.sections[0].value = format!(
+        "{}/2 upgrades, {}/{} points",
+        upgrades.upgrades().count(),
+        upgrades.cost(),
+        UpgradeTracker::BUDGET
+    );
+}
 
-    let mut indicator = indicator.single_mut();
-    indicator.sections[0].value = format!("{number}/2 upgrades selected");
+fn manage_start_button(
+    mut state: ResMut<State<AppState>>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<StartButton>)>,
+) {
+    for interaction in interaction.iter() {
+        if matches!(interaction, Interaction::Clicked) {
+            change_state(&mut state, AppState::Game);
+        }
+    }
 }
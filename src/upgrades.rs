@@ -1,6 +1,14 @@
-use crate::{ui::GameFont, AppState};
+use crate::{
+    controls::Controls,
+    hud::{HudElement, HudItem},
+    save::SaveData,
+    ui::GameFont,
+    util::ReflectResource,
+    AppState,
+};
 use bevy::prelude::*;
-use std::string::ToString;
+use serde::{Deserialize, Serialize};
+use std::{fmt, string::ToString};
 use strum_macros::{Display, EnumIter};
 
 const NO_UPGRADE_TEXT: &str = "EMPTY";
@@ -10,8 +18,14 @@ pub struct UpgradesPlugin;
 impl Plugin for UpgradesPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<UpgradeTracker>()
+            .register_type::<UpgradeTracker>()
+            // The loadout is only edited on the upgrade select screen. Runs with the no upgrades
+            // challenge clear it, so it's restored from the save when coming back.
+            .add_system_set(SystemSet::on_enter(AppState::UpgradeSelect).with_system(load_loadout))
             .add_system_set(
-                SystemSet::on_update(AppState::UpgradeSelect).with_system(update_upgrades_overlay),
+                SystemSet::on_update(AppState::UpgradeSelect)
+                    .with_system(update_upgrades_overlay)
+                    .with_system(persist_loadout),
             )
             .add_system_set(
                 SystemSet::on_update(AppState::Game).with_system(update_upgrades_overlay),
@@ -37,6 +51,7 @@ pub fn create_upgrades_overlay(commands: &mut Commands, font: &Res<GameFont>) {
             color: Color::NONE.into(),
             ..NodeBundle::default()
         })
+        .insert(HudElement(HudItem::Upgrades))
         .with_children(|parent| {
             let text_bundle = TextBundle {
                 text: Text::with_section(
@@ -89,30 +104,67 @@ pub fn create_upgrades_overlay(commands: &mut Commands, font: &Res<GameFont>) {
 
 fn update_upgrades_overlay(
     upgrades: Res<UpgradeTracker>,
-    mut overlay_texts: Query<&mut Text, With<UpgradeOverlay>>,
+    mut overlay_texts: Query<(&mut Text, &UpgradeOverlay)>,
 ) {
-    let mut overlay_texts: Vec<Mut<'_, Text>> = overlay_texts.iter_mut().collect();
-    assert_eq!(overlay_texts.len(), 2);
-
-    overlay_texts[0].sections[0].value = if let Some(upgrade) = upgrades.primary {
-        upgrade.to_string()
-    } else {
-        NO_UPGRADE_TEXT.into()
-    };
-
-    overlay_texts[1].sections[0].value = if let Some(upgrade) = upgrades.secondary {
-        upgrade.to_string()
-    } else {
-        NO_UPGRADE_TEXT.into()
-    };
+    for (mut text, overlay) in overlay_texts.iter_mut() {
+        let upgrade = match overlay.0 {
+            UpgradeSlot::Primary => upgrades.primary,
+            UpgradeSlot::Secondary => upgrades.secondary,
+        };
+
+        text.sections[0].value = if let Some(upgrade) = upgrade {
+            upgrade.to_string()
+        } else {
+            NO_UPGRADE_TEXT.into()
+        };
+    }
 }
 
-#[derive(Debug, Display, EnumIter, PartialEq, Copy, Clone)]
+#[derive(Debug, Display, EnumIter, PartialEq, Copy, Clone, Serialize, Deserialize, Reflect)]
+#[reflect_value(PartialEq)]
 pub enum Upgrade {
     DoubleSpeed,
     Shrink,
     Teleport,
     SlowEnemies,
+    Shield,
+}
+
+impl Upgrade {
+    // Points taken out of the loadout budget
+    pub fn cost(self) -> u32 {
+        match self {
+            Upgrade::DoubleSpeed => 2,
+            Upgrade::Shrink => 2,
+            Upgrade::Teleport => 3,
+            Upgrade::SlowEnemies => 2,
+            Upgrade::Shield => 1,
+        }
+    }
+
+    pub fn conflicts_with(self, other: Upgrade) -> bool {
+        matches!(
+            (self, other),
+            (Upgrade::Shrink, Upgrade::Shield) | (Upgrade::Shield, Upgrade::Shrink)
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoadoutError {
+    SlotsFull,
+    OverBudget,
+    Conflict(Upgrade, Upgrade),
+}
+
+impl fmt::Display for LoadoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadoutError::SlotsFull => write!(f, "No free upgrade slots"),
+            LoadoutError::OverBudget => write!(f, "Not enough points"),
+            LoadoutError::Conflict(a, b) => write!(f, "{a} can't be used with {b}"),
+        }
+    }
 }
 
 pub enum UpgradeSlot {
@@ -120,19 +172,30 @@ pub enum UpgradeSlot {
     Secondary,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Resource)]
 pub struct UpgradeTracker {
     pub primary: Option<Upgrade>,
     pub secondary: Option<Upgrade>,
 }
 
 impl UpgradeTracker {
-    pub fn set_upgrade(&mut self, upgrade: Upgrade) {
-        if self.primary == None {
-            self.primary = Some(upgrade);
-        } else if self.secondary == None {
-            self.secondary = Some(upgrade);
+    pub const BUDGET: u32 = 4;
+
+    // Add an upgrade to the first free slot, leaving the loadout untouched if
+    // the result wouldn't be valid
+    pub fn set_upgrade(&mut self, upgrade: Upgrade) -> Result<(), LoadoutError> {
+        let mut loadout = self.clone();
+        if loadout.primary.is_none() {
+            loadout.primary = Some(upgrade);
+        } else if loadout.secondary.is_none() {
+            loadout.secondary = Some(upgrade);
+        } else {
+            return Err(LoadoutError::SlotsFull);
         }
+        loadout.validate()?;
+        *self = loadout;
+        Ok(())
     }
 
     pub fn unset_upgrade(&mut self, upgrade: Upgrade) {
@@ -143,16 +206,72 @@ impl UpgradeTracker {
         }
     }
 
-    pub fn was_upgrade_activated(
-        &self,
-        button_input: Res<Input<MouseButton>>,
-        upgrade: Upgrade,
-    ) -> bool {
-        (self.primary == Some(upgrade) && button_input.just_pressed(MouseButton::Left))
-            || (self.secondary == Some(upgrade) && button_input.just_pressed(MouseButton::Right))
+    pub fn was_upgrade_activated(&self, controls: &Controls, upgrade: Upgrade) -> bool {
+        (self.primary == Some(upgrade) && controls.primary.just_pressed)
+            || (self.secondary == Some(upgrade) && controls.secondary.just_pressed)
     }
 
     pub fn has_upgrade(&self, upgrade: Upgrade) -> bool {
         self.primary == Some(upgrade) || self.secondary == Some(upgrade)
     }
+
+    pub fn upgrades(&self) -> impl Iterator<Item = Upgrade> {
+        self.primary.into_iter().chain(self.secondary)
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.upgrades().map(Upgrade::cost).sum()
+    }
+
+    pub fn validate(&self) -> Result<(), LoadoutError> {
+        if self.cost() > Self::BUDGET {
+            return Err(LoadoutError::OverBudget);
+        }
+        if let (Some(primary), Some(secondary)) = (self.primary, self.secondary) {
+            if primary.conflicts_with(secondary) {
+                return Err(LoadoutError::Conflict(secondary, primary));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Restore the last loadout, dropping it if it's no longer valid
+fn load_loadout(mut upgrades: ResMut<UpgradeTracker>, save: Res<SaveData>) {
+    match save.loadout.validate() {
+        Ok(()) => *upgrades = save.loadout.clone(),
+        Err(err) => warn!("Ignoring saved loadout: {err}"),
+    }
+}
+
+fn persist_loadout(upgrades: Res<UpgradeTracker>, mut save: ResMut<SaveData>) {
+    if upgrades.is_changed() && save.loadout != *upgrades {
+        save.loadout = upgrades.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_upgrades_are_rejected() {
+        let mut upgrades = UpgradeTracker::default();
+        assert_eq!(upgrades.set_upgrade(Upgrade::Shrink), Ok(()));
+        assert_eq!(
+            upgrades.set_upgrade(Upgrade::Shield),
+            Err(LoadoutError::Conflict(Upgrade::Shield, Upgrade::Shrink))
+        );
+        assert_eq!(
+            upgrades.set_upgrade(Upgrade::Teleport),
+            Err(LoadoutError::OverBudget)
+        );
+        assert_eq!(upgrades.set_upgrade(Upgrade::DoubleSpeed), Ok(()));
+        assert_eq!(
+            upgrades.set_upgrade(Upgrade::SlowEnemies),
+            Err(LoadoutError::SlotsFull)
+        );
+        assert_eq!(upgrades.upgrades().count(), 2);
+        assert_eq!(upgrades.cost(), UpgradeTracker::BUDGET);
+    }
 }
@@ -1,44 +1,73 @@
-use benimator::{AnimationMode, Play, SpriteSheetAnimation};
-use bevy::prelude::*;
+use crate::animation::{AnimationMode, AnimationProgress, Play, SpriteSheetAnimation};
+use bevy::{prelude::*, reflect::FromType};
 use std::{path::PathBuf, time::Duration};
 
 pub fn polar_to_cartesian(angle: f32, length: f32) -> Vec2 {
     Vec2::new(length * angle.cos(), length * angle.sin())
 }
 
+// Type data for finding resources through the type registry, which Bevy only has for
+// components. Added with #[reflect(Resource)] alongside #[derive(Reflect)].
+#[derive(Clone)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub struct ReflectResource {
+    reflect: fn(&World) -> Option<&dyn Reflect>,
+    apply: fn(&mut World, &dyn Reflect),
+}
+
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+impl ReflectResource {
+    pub fn reflect<'a>(&self, world: &'a World) -> Option<&'a dyn Reflect> {
+        (self.reflect)(world)
+    }
+
+    // Copy a changed value into the resource. Only this marks it as changed, so reading it
+    // every frame doesn't set off change detection.
+    pub fn apply(&self, world: &mut World, value: &dyn Reflect) {
+        (self.apply)(world, value);
+    }
+}
+
+impl<R: Reflect> FromType<R> for ReflectResource {
+    fn from_type() -> Self {
+        Self {
+            reflect: |world| {
+                world
+                    .get_resource::<R>()
+                    .map(|resource| resource as &dyn Reflect)
+            },
+            apply: |world, value| {
+                if let Some(mut resource) = world.get_resource_mut::<R>() {
+                    resource.apply(value);
+                }
+            },
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct AnimatedSprite {
     animation_handle: Handle<SpriteSheetAnimation>,
     play: Play,
+    progress: AnimationProgress,
+    timing: AnimationTiming,
 
     #[bundle]
     sprite_sheet_bundle: SpriteSheetBundle,
 }
 
-impl<'a> AnimatedSprite {
+impl AnimatedSprite {
     pub fn new(
         animations: &mut ResMut<Assets<SpriteSheetAnimation>>,
         textures: &mut ResMut<Assets<TextureAtlas>>,
         asset_server: &Res<AssetServer>,
         data: AnimatedSpriteData,
     ) -> Self {
-        let animation_handle = animations.add({
-            let sheet = SpriteSheetAnimation::from_range(0..=(data.frames - 1), data.delay);
-            match data.mode {
-                AnimationMode::Once => sheet.once(),
-                AnimationMode::Repeat => sheet.repeat(),
-                AnimationMode::PingPong => sheet.ping_pong(),
-                _ => unimplemented!(),
-            }
-        });
+        let timing = data.timing();
+        let animation_handle = animations.add(timing.animation(1));
 
         let sprite_sheet_bundle = SpriteSheetBundle {
-            texture_atlas: textures.add(TextureAtlas::from_grid(
-                asset_server.load(data.path),
-                data.size,
-                data.frames,
-                1,
-            )),
+            texture_atlas: data.atlas(textures, asset_server),
             transform: data.transform,
             ..SpriteSheetBundle::default()
         };
@@ -46,25 +75,72 @@ impl<'a> AnimatedSprite {
         Self {
             animation_handle,
             play: Play,
+            progress: AnimationProgress::default(),
+            timing,
             sprite_sheet_bundle,
         }
     }
 }
 
+// What an AnimatedSprite's animation was made from, so it can be remade at another speed
+#[derive(Component, Clone, Copy)]
+pub struct AnimationTiming {
+    frames: usize,
+    first_frame: usize,
+    delay: Duration,
+    mode: AnimationMode,
+}
+
+impl AnimationTiming {
+    // The animation with each frame held `slowdown` times as long
+    pub fn animation(&self, slowdown: u32) -> SpriteSheetAnimation {
+        let frames = (0..self.frames).map(|frame| (frame + self.first_frame) % self.frames);
+        SpriteSheetAnimation::new(frames, self.delay * slowdown, self.mode)
+    }
+}
+
 pub struct AnimatedSpriteData {
     pub path: PathBuf,
     pub frames: usize,
+    // Frame the animation starts on, so neighbors can play out of step
+    pub first_frame: usize,
     pub size: Vec2,
     pub transform: Transform,
     pub delay: Duration,
     pub mode: AnimationMode,
 }
 
+impl AnimatedSpriteData {
+    pub fn timing(&self) -> AnimationTiming {
+        AnimationTiming {
+            frames: self.frames,
+            first_frame: self.first_frame,
+            delay: self.delay,
+            mode: self.mode,
+        }
+    }
+
+    // The sprite sheet, with the frames side by side in a single row
+    pub fn atlas(
+        &self,
+        textures: &mut Assets<TextureAtlas>,
+        asset_server: &AssetServer,
+    ) -> Handle<TextureAtlas> {
+        textures.add(TextureAtlas::from_grid(
+            asset_server.load(self.path.clone()),
+            self.size,
+            self.frames,
+            1,
+        ))
+    }
+}
+
 impl Default for AnimatedSpriteData {
     fn default() -> Self {
         Self {
             path: PathBuf::default(),
             frames: usize::default(),
+            first_frame: 0,
             size: Vec2::default(),
             transform: Transform::default(),
             delay: Duration::from_millis(100),
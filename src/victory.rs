@@ -1,21 +1,261 @@
-use crate::{ui::GameFont, AppState};
+use crate::{
+    challenges::ActiveChallenges,
+    change_state,
+    cutscene::{play_cutscene, Action, ActiveCutscene, Actor, Cutscene, CutsceneEvent, Place},
+    game_overlay::{format_time, GameTimer},
+    ghost::{self, Medal},
+    pack::LevelRegistry,
+    player::Player,
+    profile::Profile,
+    render_layer::RenderLayer,
+    run_log::RunLog,
+    save::SaveData,
+    sound::{Sounds, Tone},
+    ui::GameFont,
+    world::{GameWorld, Goal},
+    AppState,
+};
 use bevy::prelude::*;
+use rand::random;
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    sync::Arc,
+};
 
 pub struct VictoryPlugin;
 
 impl Plugin for VictoryPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::Victory).with_system(create_victory_menu))
-            .add_system_set(
-                SystemSet::on_update(AppState::Victory).with_system(manage_menu_button),
-            );
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Victory)
+                .with_system(create_victory_menu)
+                .with_system(start_celebration),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Victory)
+                .with_system(manage_menu_button)
+                .with_system(manage_export_button)
+                .with_system(celebrate)
+                .with_system(update_confetti),
+        );
     }
 }
 
+const FLY_IN_SECONDS: f32 = 0.6;
+const TALLY_SECONDS: f32 = 1.2;
+const CONFETTI: usize = 40;
+const CONFETTI_SIZE: Vec2 = bevy::math::const_vec2!([3.0, 6.0]);
+const CONFETTI_SPEED: (f32, f32) = (150.0, 350.0);
+// Radians either side of straight up the confetti is thrown
+const CONFETTI_SPREAD: f32 = 1.0;
+const CONFETTI_GRAVITY: f32 = 400.0;
+const CONFETTI_SECONDS: f32 = 1.5;
+const CONFETTI_COLORS: [Color; 4] = [
+    Color::rgb(1.0, 0.8, 0.1),
+    Color::rgb(0.3, 0.7, 1.0),
+    Color::rgb(1.0, 0.4, 0.6),
+    Color::rgb(0.5, 0.9, 0.4),
+];
+
+// Step names in the celebration cutscene. The fanfare plays note by note, with confetti
+// on the first, then the time counts up to the final time.
+const NOTE_STEP: &str = "note";
+const TALLY_STEP: &str = "tally";
+
+struct Celebration {
+    goal: Vec2,
+    time: f32,
+    notes_played: usize,
+}
+
+#[derive(Component)]
+struct TallyText;
+
+#[derive(Component)]
+struct Confetti {
+    velocity: Vec2,
+    spin: f32,
+    age: f32,
+}
+
 #[derive(Component)]
 struct MenuButton;
 
-fn create_victory_menu(mut commands: Commands, font: Res<GameFont>) {
+#[derive(Component)]
+struct ExportButton;
+
+fn start_celebration(
+    mut commands: Commands,
+    timer: Option<Res<GameTimer>>,
+    save: Res<SaveData>,
+    sounds: Res<Sounds>,
+    tones: Res<Assets<Tone>>,
+    goal: Query<&Transform, With<Goal>>,
+    player: Query<&Transform, (With<Player>, Without<Goal>)>,
+) {
+    // The bee flies into the hive, shrinking away as it gets there
+    let mut cutscene = Cutscene::new().then(Action::Move {
+        actor: Actor::Player,
+        to: Place::Goal,
+        scale: Some(0.0),
+        seconds: FLY_IN_SECONDS,
+    });
+    // Each note follows on from the last without a gap
+    for tone in &sounds.fanfare {
+        cutscene = cutscene.then(Action::Step {
+            name: NOTE_STEP.to_string(),
+            seconds: tones.get(tone).map_or(0.0, Tone::seconds),
+        });
+    }
+    let cutscene = cutscene.then(Action::Step {
+        name: TALLY_STEP.to_string(),
+        seconds: TALLY_SECONDS,
+    });
+    play_cutscene(&mut commands, cutscene);
+
+    // The goal reached, if the level has more than one
+    let player = player
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    let goal = goal
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .min_by(|a, b| {
+            a.distance_squared(player)
+                .total_cmp(&b.distance_squared(player))
+        });
+    commands.insert_resource(Celebration {
+        goal: goal.unwrap_or(Vec2::ZERO),
+        time: timer.map_or(0.0, |timer| timer.display_time(save.settings.real_time)),
+        notes_played: 0,
+    });
+}
+
+fn celebrate(
+    mut commands: Commands,
+    celebration: Option<ResMut<Celebration>>,
+    cutscene: Option<Res<ActiveCutscene>>,
+    mut events: EventReader<CutsceneEvent>,
+    audio: Res<Audio<Tone>>,
+    sounds: Res<Sounds>,
+    mut tally: Query<&mut Text, With<TallyText>>,
+) {
+    let mut celebration = match celebration {
+        Some(celebration) => celebration,
+        None => return,
+    };
+    let mut finished = false;
+    for event in events.iter() {
+        match event {
+            CutsceneEvent::StepStarted(name) if name == NOTE_STEP => {
+                let note = celebration.notes_played;
+                if let Some(tone) = sounds.fanfare.get(note) {
+                    audio.play(tone.clone());
+                }
+                if note == 0 {
+                    spawn_confetti(&mut commands, celebration.goal);
+                }
+                celebration.notes_played += 1;
+            }
+            CutsceneEvent::StepStarted(_) => {}
+            CutsceneEvent::Finished => finished = true,
+        }
+    }
+
+    let shown = if finished {
+        celebration.time
+    } else {
+        cutscene
+            .and_then(|cutscene| cutscene.step_progress(TALLY_STEP))
+            .map_or(0.0, |progress| celebration.time * progress)
+    };
+    if let Ok(mut text) = tally.get_single_mut() {
+        text.sections[0].value = format_time(shown);
+    }
+    if finished {
+        commands.remove_resource::<Celebration>();
+    }
+}
+
+fn spawn_confetti(commands: &mut Commands, position: Vec2) {
+    for i in 0..CONFETTI {
+        let angle = FRAC_PI_2 + (random::<f32>() * 2.0 - 1.0) * CONFETTI_SPREAD;
+        let speed = CONFETTI_SPEED.0 + random::<f32>() * (CONFETTI_SPEED.1 - CONFETTI_SPEED.0);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: CONFETTI_COLORS[i % CONFETTI_COLORS.len()],
+                    custom_size: Some(CONFETTI_SIZE),
+                    ..Sprite::default()
+                },
+                transform: Transform {
+                    translation: position.extend(0.0),
+                    rotation: Quat::from_rotation_z(random::<f32>() * TAU),
+                    ..Transform::default()
+                },
+                ..SpriteBundle::default()
+            })
+            .insert(RenderLayer::Particles)
+            .insert(Confetti {
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                spin: (random::<f32>() * 2.0 - 1.0) * TAU,
+                age: 0.0,
+            });
+    }
+}
+
+fn update_confetti(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut confetti: Query<(Entity, &mut Transform, &mut Sprite, &mut Confetti)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut transform, mut sprite, mut piece) in confetti.iter_mut() {
+        piece.age += delta;
+        if piece.age >= CONFETTI_SECONDS {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        piece.velocity.y -= CONFETTI_GRAVITY * delta;
+        transform.translation += (piece.velocity * delta).extend(0.0);
+        transform.rotate(Quat::from_rotation_z(piece.spin * delta));
+        sprite.color.set_a(1.0 - piece.age / CONFETTI_SECONDS);
+    }
+}
+
+// The medal earned against the developers' time, with that time. Challenges make medals
+// easier to earn, taking the reward multiplier off the time.
+fn medal_text(time: f32, dev_time: f32, reward: f32) -> (String, Color) {
+    match Medal::for_time(time / reward, dev_time) {
+        Some(medal) => (
+            format!(
+                "{} medal - dev time {}",
+                medal.name(),
+                format_time(dev_time)
+            ),
+            medal.color(),
+        ),
+        None => (format!("Dev time {}", format_time(dev_time)), Color::WHITE),
+    }
+}
+
+fn create_victory_menu(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    timer: Option<Res<GameTimer>>,
+    world: Res<Arc<GameWorld>>,
+    levels: Res<LevelRegistry>,
+    challenges: Res<ActiveChallenges>,
+) {
+    // Measured in game time, the same as best times
+    let medal = match (timer, ghost::dev_time(&world, &levels)) {
+        (Some(timer), Some(dev_time)) => Some(medal_text(
+            timer.elapsed(),
+            dev_time,
+            challenges.reward_multiplier(),
+        )),
+        _ => None,
+    };
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -49,12 +289,58 @@ fn create_victory_menu(mut commands: Commands, font: Res<GameFont>) {
                 ..TextBundle::default()
             });
 
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Percent(37.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    text: Text::with_section(
+                        format_time(0.0),
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 50.0,
+                            ..TextStyle::default()
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                })
+                .insert(TallyText);
+
+            if let Some((medal, color)) = medal {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Percent(44.0),
+                            ..Rect::default()
+                        },
+                        ..Style::default()
+                    },
+                    text: Text::with_section(
+                        medal,
+                        TextStyle {
+                            font: font.get_handle(),
+                            font_size: 35.0,
+                            color,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                });
+            }
+
             parent
                 .spawn_bundle(ButtonBundle {
                     style: Style {
                         position_type: PositionType::Absolute,
                         position: Rect {
-                            top: Val::Percent(40.0),
+                            top: Val::Percent(50.0),
                             ..Rect::default()
                         },
                         size: Size::new(Val::Px(300.0), Val::Px(65.0)),
@@ -79,16 +365,78 @@ fn create_victory_menu(mut commands: Commands, font: Res<GameFont>) {
                         ..TextBundle::default()
                     });
                 });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Percent(60.0),
+                            ..Rect::default()
+                        },
+                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(ExportButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Export run",
+                            TextStyle {
+                                font: font.get_handle(),
+                                font_size: 40.0,
+                                color: Color::BLACK,
+                            },
+                            TextAlignment::default(),
+                        ),
+                        ..TextBundle::default()
+                    });
+                });
         });
 }
 
+// Save the run's samples and events for graphing, saying where they went on the button
+fn manage_export_button(
+    log: Res<RunLog>,
+    profile: Option<Res<Profile>>,
+    interaction: Query<(&Interaction, &Children), (Changed<Interaction>, With<ExportButton>)>,
+    mut text: Query<&mut Text>,
+) {
+    for (interaction, children) in interaction.iter() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        let result = match &profile {
+            Some(profile) => log.export(profile),
+            None => continue,
+        };
+        let label = match result {
+            Ok(path) => {
+                info!("Exported run to {}", path.display());
+                "Exported"
+            }
+            Err(err) => {
+                warn!("Failed to export run: {err}");
+                "Export failed"
+            }
+        };
+        if let Ok(mut text) = text.get_mut(children[0]) {
+            text.sections[0].value = label.to_string();
+        }
+    }
+}
+
 fn manage_menu_button(
     mut state: ResMut<State<AppState>>,
     interaction: Query<&Interaction, (Changed<Interaction>, With<Button>, With<MenuButton>)>,
 ) {
     for interaction in interaction.iter() {
         if matches!(interaction, Interaction::Clicked) {
-            state.set(AppState::Menu).unwrap();
+            change_state(&mut state, AppState::Menu);
         }
     }
 }
@@ -0,0 +1,130 @@
+use crate::{
+    clock::GameClock,
+    player::Player,
+    playing,
+    sound::{Sounds, Tone},
+    GameState,
+};
+use bevy::prelude::*;
+
+// Feedback for flying into walls: the bee's sprite squashes against the wall and a soft bump
+// plays. Only the sprite changes, the collision box stays the same size.
+pub struct WallContactPlugin;
+
+impl Plugin for WallContactPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(playing)
+                .with_system(squash_against_walls)
+                .with_system(bump_walls),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::Dying).with_system(unsquash));
+    }
+}
+
+// How much of the sprite's size is squashed away while pressing into a wall
+const MAX_SQUASH: f32 = 0.2;
+// Squash gained or lost per second, so it eases in and out
+const SQUASH_RATE: f32 = 2.0;
+// Seconds between bump sounds, so sliding along a bumpy wall doesn't rattle
+const BUMP_COOLDOWN: f32 = 0.4;
+
+// Set each frame the player moves, to the normal of the wall being pressed into
+#[derive(Component)]
+pub struct WallContact {
+    pub normal: Option<Vec2>,
+    // Normal the sprite is squashed along, kept while the squash eases out
+    squashed_along: Vec2,
+    squash: f32,
+    pressing: bool,
+    cooldown: Timer,
+}
+
+impl Default for WallContact {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(BUMP_COOLDOWN, false);
+        cooldown.tick(cooldown.duration());
+        Self {
+            normal: None,
+            squashed_along: Vec2::X,
+            squash: 0.0,
+            pressing: false,
+            cooldown,
+        }
+    }
+}
+
+// How much to scale the sprite along its own x and y for `squash` against a wall with the
+// given normal in sprite space. Squashed along the normal and stretched across it.
+fn squash_scale(normal: Vec2, squash: f32) -> Vec2 {
+    let along = normal * normal;
+    Vec2::ONE - along * squash + (Vec2::ONE - along) * squash / 2.0
+}
+
+fn squash_against_walls(
+    clock: Res<GameClock>,
+    mut player: Query<(&mut WallContact, &mut TextureAtlasSprite, &Transform), With<Player>>,
+) {
+    let (mut contact, mut sprite, transform) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let target = match contact.normal {
+        Some(normal) => {
+            contact.squashed_along = normal;
+            MAX_SQUASH
+        }
+        None => 0.0,
+    };
+    let step = SQUASH_RATE * MAX_SQUASH * clock.delta_seconds();
+    contact.squash += (target - contact.squash).clamp(-step, step);
+
+    sprite.custom_size = if contact.squash > 0.0 {
+        let normal = transform.rotation.inverse() * contact.squashed_along.extend(0.0);
+        Some(Vec2::splat(Player::SIZE) * squash_scale(normal.truncate(), contact.squash))
+    } else {
+        None
+    };
+}
+
+fn bump_walls(
+    clock: Res<GameClock>,
+    audio: Res<Audio<Tone>>,
+    sounds: Res<Sounds>,
+    mut player: Query<&mut WallContact, With<Player>>,
+) {
+    let mut contact = match player.get_single_mut() {
+        Ok(contact) => contact,
+        Err(_) => return,
+    };
+    contact.cooldown.tick(clock.delta());
+    let pressing = contact.normal.is_some();
+    if pressing && !contact.pressing && contact.cooldown.finished() {
+        audio.play(sounds.wall_bump.clone());
+        contact.cooldown.reset();
+    }
+    contact.pressing = pressing;
+}
+
+// The death animation plays at its usual size
+fn unsquash(mut player: Query<(&mut WallContact, &mut TextureAtlasSprite), With<Player>>) {
+    if let Ok((mut contact, mut sprite)) = player.get_single_mut() {
+        contact.squash = 0.0;
+        sprite.custom_size = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprites_squash_along_the_normal() {
+        assert_eq!(squash_scale(Vec2::X, 0.0), Vec2::ONE);
+        assert_eq!(squash_scale(Vec2::X, 0.2), Vec2::new(0.8, 1.1));
+        assert_eq!(squash_scale(-Vec2::Y, 0.2), Vec2::new(1.1, 0.8));
+        let diagonal = squash_scale(Vec2::ONE.normalize(), 0.2);
+        assert!(diagonal.abs_diff_eq(Vec2::splat(0.95), 1e-6));
+    }
+}
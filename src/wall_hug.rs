@@ -0,0 +1,216 @@
+use crate::{
+    challenges::ActiveChallenges,
+    clock::GameClock,
+    hud::{HudElement, HudItem},
+    movement::WallCollider,
+    player::Player,
+    playing,
+    ui::GameFont,
+    wall_contact::WallContact,
+    world::{GameWorld, OpenWalls},
+    AppState,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+
+// Trick scoring for flying close along walls without touching them. Keeping it up builds a
+// multiplier, and touching a wall or straying from it for too long breaks the chain.
+pub struct WallHugPlugin;
+
+impl Plugin for WallHugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WallHug>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Game)
+                    .with_system(reset_wall_hug)
+                    .with_system(create_wall_hug_text),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(hug_walls)
+                    .with_system(update_wall_hug_text),
+            );
+    }
+}
+
+// Largest gap to a wall that counts as hugging it
+const HUG_DISTANCE: f32 = 12.0;
+// Seconds of hugging for each step up in multiplier
+const SECONDS_PER_MULTIPLIER: f32 = 1.0;
+const MAX_MULTIPLIER: u32 = 8;
+// Seconds away from walls before the chain breaks, so gaps between walls can be crossed
+const GRACE_SECONDS: f32 = 0.5;
+// Points for each second of hugging, times the multiplier
+const POINTS_PER_SECOND: f32 = 10.0;
+
+#[derive(Debug)]
+pub struct WallHug {
+    // Seconds of hugging in the current chain
+    chain: f32,
+    // Seconds since a wall was last close, while a chain is going
+    away: f32,
+    pub points: f32,
+    // Reward multiplier for the challenges the run is played with
+    reward: f32,
+}
+
+impl Default for WallHug {
+    fn default() -> Self {
+        Self {
+            chain: 0.0,
+            away: 0.0,
+            points: 0.0,
+            reward: 1.0,
+        }
+    }
+}
+
+impl WallHug {
+    pub fn multiplier(&self) -> u32 {
+        (1 + (self.chain / SECONDS_PER_MULTIPLIER) as u32).min(MAX_MULTIPLIER)
+    }
+
+    // Carry on for `seconds` with the nearest wall `gap` away, if one's close enough to matter
+    fn update(&mut self, gap: Option<f32>, touching: bool, seconds: f32) {
+        match gap {
+            _ if touching => self.break_chain(),
+            Some(gap) if gap > 0.0 && gap <= HUG_DISTANCE => {
+                self.points += POINTS_PER_SECOND * self.multiplier() as f32 * self.reward * seconds;
+                self.chain += seconds;
+                self.away = 0.0;
+            }
+            Some(0.0) => self.break_chain(),
+            _ if self.chain > 0.0 => {
+                self.away += seconds;
+                if self.away > GRACE_SECONDS {
+                    self.break_chain();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn break_chain(&mut self) {
+        self.chain = 0.0;
+        self.away = 0.0;
+    }
+}
+
+#[derive(Component)]
+struct WallHugText;
+
+fn reset_wall_hug(challenges: Res<ActiveChallenges>, mut hug: ResMut<WallHug>) {
+    *hug = WallHug {
+        reward: challenges.reward_multiplier(),
+        ..WallHug::default()
+    };
+}
+
+fn create_wall_hug_text(mut commands: Commands, font: Res<GameFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(2.0),
+                    right: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 30.0,
+                    color: Color::rgb(1.0, 0.85, 0.3),
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(WallHugText)
+        .insert(HudElement(HudItem::Score));
+}
+
+fn hug_walls(
+    clock: Res<GameClock>,
+    world: Res<Arc<GameWorld>>,
+    open_walls: Res<OpenWalls>,
+    mut hug: ResMut<WallHug>,
+    player: Query<(&Transform, &WallCollider, &WallContact), With<Player>>,
+) {
+    if let Ok((transform, collider, contact)) = player.get_single() {
+        let half_size = collider.size / 2.0 * transform.scale.truncate().abs();
+        // Open walls are nothing to hug
+        let gap = if open_walls.0 {
+            None
+        } else {
+            world.distance_to_wall(transform.translation.truncate(), half_size, HUG_DISTANCE)
+        };
+        hug.update(gap, contact.normal.is_some(), clock.delta_seconds());
+    }
+}
+
+fn update_wall_hug_text(hug: Res<WallHug>, mut text: Query<&mut Text, With<WallHugText>>) {
+    if !hug.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = text.get_single_mut() {
+        // Hidden until the first points are scored
+        text.sections[0].value = if hug.points > 0.0 {
+            format!("Wall hug x{} {:.0}", hug.multiplier(), hug.points)
+        } else {
+            String::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hugging_builds_a_multiplier() {
+        let mut hug = WallHug::default();
+        assert_eq!(hug.multiplier(), 1);
+        for _ in 0..10 {
+            hug.update(Some(5.0), false, 0.25);
+        }
+        assert_eq!(hug.multiplier(), 3);
+        assert_eq!(hug.points, 10.0 * (1.0 + 2.0 + 0.5 * 3.0));
+
+        // Crossing a short gap keeps the chain going
+        hug.update(None, false, 0.4);
+        hug.update(Some(5.0), false, 0.0);
+        assert_eq!(hug.multiplier(), 3);
+        hug.update(None, false, 0.6);
+        assert_eq!(hug.multiplier(), 1);
+    }
+
+    #[test]
+    fn touching_a_wall_breaks_the_chain() {
+        let mut hug = WallHug::default();
+        hug.update(Some(5.0), false, 2.0);
+        assert_eq!(hug.multiplier(), 3);
+        hug.update(Some(0.0), false, 0.1);
+        assert_eq!(hug.multiplier(), 1);
+
+        hug.update(Some(5.0), false, 2.0);
+        let points = hug.points;
+        hug.update(Some(5.0), true, 0.1);
+        assert_eq!(hug.multiplier(), 1);
+        assert_eq!(hug.points, points);
+    }
+
+    #[test]
+    fn challenges_scale_the_points() {
+        let mut hug = WallHug {
+            reward: 1.5,
+            ..WallHug::default()
+        };
+        hug.update(Some(5.0), false, 1.0);
+        assert_eq!(hug.points, 15.0);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    animation::SpriteSheetAnimation,
+    cosmetics::{self, AccessoryRegistry, AttachmentPoint, SkinRegistry, Unlock},
+    player::Player,
+    save::SaveData,
+    ui::{spawn_back_button, GameFont},
+    util::{AnimatedSprite, AnimatedSpriteData},
+    AppState,
+};
+use bevy::prelude::*;
+
+pub struct WardrobePlugin;
+
+impl Plugin for WardrobePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Wardrobe).with_system(create_wardrobe))
+            .add_system_set(
+                SystemSet::on_update(AppState::Wardrobe)
+                    .with_system(manage_wardrobe_buttons)
+                    .with_system(update_preview),
+            );
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+enum WardrobeButton {
+    Skin(usize),
+    // None takes the accessory off
+    Accessory(Option<usize>),
+}
+
+#[derive(Component)]
+struct SkinPreview;
+
+fn create_wardrobe(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    skins: Res<SkinRegistry>,
+    accessories: Res<AccessoryRegistry>,
+    save: Res<SaveData>,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    spawn_back_button(&mut commands, font.get_handle());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::FlexEnd,
+                ..Style::default()
+            },
+            color: Color::NONE.into(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            const SKIN_SELECT_HEIGHT: f32 = 70.0;
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Percent(2.0),
+                        ..Rect::default()
+                    },
+                    ..Style::default()
+                },
+                text: Text::with_section(
+                    "Wardrobe",
+                    TextStyle {
+                        font: font.get_handle(),
+                        font_size: 70.0,
+                        ..TextStyle::default()
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+
+            // Spawn skin selector on the left, leaving room for the preview
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(40.0), Val::Percent(SKIN_SELECT_HEIGHT)),
+                        margin: Rect {
+                            left: Val::Percent(10.0),
+                            right: Val::Auto,
+                            top: Val::Auto,
+                            bottom: Val::Auto,
+                        },
+                        flex_direction: FlexDirection::ColumnReverse,
+                        ..Style::default()
+                    },
+                    color: Color::GRAY.into(),
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    let skin_buttons = skins.skins.iter().enumerate().map(|(index, skin)| {
+                        let label = item_label(&skin.name, &skin.unlock, &save);
+                        (WardrobeButton::Skin(index), label)
+                    });
+                    let accessory_buttons =
+                        accessories
+                            .accessories
+                            .iter()
+                            .enumerate()
+                            .map(|(index, accessory)| {
+                                let label = item_label(accessory.name, &accessory.unlock, &save);
+                                (WardrobeButton::Accessory(Some(index)), label)
+                            });
+                    let buttons: Vec<_> = skin_buttons
+                        .chain([(WardrobeButton::Accessory(None), "No accessory".into())])
+                        .chain(accessory_buttons)
+                        .collect();
+                    let button_height = SKIN_SELECT_HEIGHT / buttons.len() as f32;
+
+                    for (button, label) in buttons {
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        Val::Percent(90.0),
+                                        Val::Percent(button_height),
+                                    ),
+                                    margin: Rect::all(Val::Auto),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Style::default()
+                                },
+                                ..ButtonBundle::default()
+                            })
+                            .insert(button)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        label,
+                                        TextStyle {
+                                            font: font.get_handle(),
+                                            font_size: 24.0,
+                                            color: Color::BLACK,
+                                        },
+                                        TextAlignment::default(),
+                                    ),
+                                    ..TextBundle::default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+// Locked items show how to unlock them
+fn item_label(name: &str, unlock: &Unlock, save: &SaveData) -> String {
+    if unlock.is_met(save) {
+        name.to_string()
+    } else {
+        format!("{} - {}", name, unlock.description())
+    }
+}
+
+fn manage_wardrobe_buttons(
+    mut save: ResMut<SaveData>,
+    skins: Res<SkinRegistry>,
+    accessories: Res<AccessoryRegistry>,
+    interaction: Query<(&Interaction, &WardrobeButton), (Changed<Interaction>, With<Button>)>,
+) {
+    for (interaction, button) in interaction.iter() {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        // Locked items can't be selected
+        match *button {
+            WardrobeButton::Skin(index) => {
+                let skin = &skins.skins[index];
+                if skin.unlock.is_met(&save) {
+                    save.skin = Some(skin.name.clone());
+                }
+            }
+            WardrobeButton::Accessory(Some(index)) => {
+                let accessory = &accessories.accessories[index];
+                if accessory.unlock.is_met(&save) {
+                    save.accessory = Some(accessory.name.to_string());
+                }
+            }
+            WardrobeButton::Accessory(None) => save.accessory = None,
+        }
+    }
+}
+
+// Show the selected skin and accessory, respawning the preview whenever the
+// selection changes
+fn update_preview(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    skins: Res<SkinRegistry>,
+    accessories: Res<AccessoryRegistry>,
+    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
+    mut textures: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+    state: Res<State<AppState>>,
+    preview: Query<Entity, With<SkinPreview>>,
+) {
+    if !save.is_changed() && !state.is_changed() && !preview.is_empty() {
+        return;
+    }
+
+    for entity in preview.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let preview = commands
+        .spawn_bundle(AnimatedSprite::new(
+            &mut animations,
+            &mut textures,
+            &asset_server,
+            AnimatedSpriteData {
+                path: skins.selected(&save).path.clone().into(),
+                frames: 6,
+                size: Vec2::splat(Player::SIZE),
+                transform: Transform {
+                    translation: Vec3::new(250.0, 0.0, 0.0),
+                    scale: Vec3::splat(6.0),
+                    ..Transform::default()
+                },
+                ..AnimatedSpriteData::default()
+            },
+        ))
+        .insert(AttachmentPoint(Player::HEAD))
+        .insert(SkinPreview)
+        .id();
+    if let Some(accessory) = accessories.selected(&save) {
+        cosmetics::attach_accessory(&mut commands, preview, accessory, &asset_server);
+    }
+}
@@ -1,113 +1,1142 @@
 use crate::{
-    camera,
-    enemy::{Enemy, Projectile},
-    player,
+    animation::SpriteSheetAnimation,
+    camera::{self, MainCamera},
+    clock::GameClock,
+    collision::CollisionShape,
+    cosmetics::{self, AccessoryRegistry, SkinRegistry},
+    enemy::{Enemy, Projectile, SpawnEnemies, SpawnEnemy, PROJECTILE_RANGE},
+    pathfinding,
+    pattern::{Pattern, PatternRunner},
+    player::{self, Player},
+    playing,
+    powerups::{Pickup, PowerUp},
+    render_layer::RenderLayer,
+    save::SaveData,
+    shadow::Shadow,
+    sound::{Sounds, Tone},
+    spawner_art::{self, Aim, SpawnerArt},
+    stamina::Flower,
+    ui::GameFont,
     upgrades::UpgradeTracker,
-    util::{AnimatedSprite, AnimatedSpriteData},
+    util::{polar_to_cartesian, AnimatedSprite, AnimatedSpriteData},
     AppState,
 };
-use benimator::SpriteSheetAnimation;
 use bevy::prelude::*;
-use impacted::CollisionShape;
+use image::{Rgb, RgbImage};
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     f32::consts::PI,
-    fs::File,
-    io::{self, BufRead, BufReader},
-    path::{Path, PathBuf},
+    io,
+    path::PathBuf,
+    sync::Arc,
 };
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
 pub enum WorldType {
-    Level { index: usize, },
+    Level {
+        index: usize,
+    },
+    #[allow(dead_code)]
     Endless,
 }
 
 #[derive(Component, Clone, Debug)]
 struct Spawner {
     projectile: Projectile,
-    timer: Timer,
+    cooldown: f32,
+    // SpawnerClock time the spawner fires next
+    next_fire: f64,
+    // Whether the warning tone has played for the next shot
+    warned: bool,
+    // Spawners with a group or pattern fire when that says, ignoring next_fire
+    control: Option<FireControl>,
 }
 
 impl Spawner {
     // Create spawner given a projectile enemy
-    fn new(projectile: Projectile) -> Self {
+    fn new(projectile: Projectile, control: Option<FireControl>) -> Self {
         let cooldown = match projectile {
             Projectile::Missile => Projectile::MISSILE_COOLDOWN,
             Projectile::Laser { .. } => Projectile::LASER_COOLDOWN,
         };
         Self {
             projectile,
-            timer: Timer::from_seconds(cooldown, true),
+            cooldown,
+            next_fire: cooldown as f64,
+            warned: false,
+            control,
         }
     }
+
+    // How far through the warning before the next missile launch, from 0 to 1
+    fn warning(&self, now: f64) -> Option<f32> {
+        let remaining = (self.next_fire - now) as f32;
+        match self.projectile {
+            Projectile::Missile if remaining > 0.0 && remaining <= WARNING_SECONDS => {
+                Some(1.0 - remaining / WARNING_SECONDS)
+            }
+            _ => None,
+        }
+    }
+
+    // Whether the spawner fires at the given time.
+    // Spawners that were asleep out of range fire once and restart their cooldown,
+    // rather than catching up on every shot they missed.
+    fn fire(&mut self, now: f64) -> bool {
+        if now < self.next_fire {
+            return false;
+        }
+        self.next_fire += self.cooldown as f64;
+        self.warned = false;
+        if self.next_fire <= now {
+            self.next_fire = now + self.cooldown as f64;
+        }
+        true
+    }
 }
 
-#[derive(Debug)]
-enum Tile {
+#[derive(Debug, PartialEq)]
+pub enum Tile {
     Wall,
-    Spawner(Spawner),
+    // Spawner components are only created when the level is spawned
+    Spawner(Projectile, Option<FireControl>),
     Trap,
     Goal,
+    PowerUp(PowerUp),
+    // Marks a split for the speedrun timer, without affecting play
+    Checkpoint,
+    // Restores stamina while the bee hovers over it
+    Flower,
 }
 
 impl Tile {
-    const SIZE: f32 = 24.0;
+    pub const SIZE: f32 = 24.0;
+}
+
+// What decides when a spawner fires, instead of its own cooldown
+#[derive(Clone, Debug, PartialEq)]
+pub enum FireControl {
+    Group(GroupSlot),
+    // Name of a pattern from the level metadata
+    Pattern(String),
+}
+
+// A spawner's place in a group, numbered in the order members appear in the level file
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupSlot {
+    pub group: String,
+    pub index: usize,
 }
 
 #[derive(Component)]
 pub struct Goal;
 
+// Checkpoints are numbered in the order they appear in the layout
+#[derive(Component)]
+pub struct Checkpoint(pub usize);
+
+impl Checkpoint {
+    pub const COLOR: Color = Color::rgba(0.5, 0.8, 0.8, 0.3);
+    pub const REACHED_COLOR: Color = Color::rgba(0.5, 0.8, 0.8, 0.08);
+}
+
+#[derive(Component)]
+pub struct Wall;
+
+// Set while a level script has taken the walls down, letting everything fly through them
+#[derive(Default)]
+pub struct OpenWalls(pub bool);
+
+// Marks every entity spawned from the level layout
+#[derive(Component)]
+pub struct WorldTile;
+
+// World position of the layout's top-left tile, which the rest are laid out from going
+// right and down. (0, 0) unless the level is centered on the origin.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WorldOrigin(pub Vec2);
+
+impl WorldOrigin {
+    // Convert layout coordinates (x, y) into a world position
+    pub fn tile_to_world(self, coordinates: (usize, usize)) -> Vec2 {
+        self.0 + Vec2::new(coordinates.0 as f32, -(coordinates.1 as f32)) * Tile::SIZE
+    }
+
+    // Convert a world position into the layout coordinates (x, y) of the tile containing it
+    pub fn world_to_tile(self, position: Vec2) -> Option<(usize, usize)> {
+        let position = position - self.0;
+        let (x, y) = (
+            (position.x / Tile::SIZE).round(),
+            (-position.y / Tile::SIZE).round(),
+        );
+        (x >= 0.0 && y >= 0.0).then_some((x as usize, y as usize))
+    }
+}
+
+// Per-level settings, written as `@<key>\t<value>` lines in the level file
+#[derive(Debug, Default, PartialEq)]
+pub struct LevelMetadata {
+    // Radius the player can see within, if the level is shrouded in fog
+    pub fog: Option<f32>,
+    // Keep the player inside the layout instead of letting them fly off into the void
+    pub boundary: bool,
+    // What happens to a player who strays too far outside the level
+    pub out_of_bounds: Option<OutOfBounds>,
+    // Spawner groups by ID, which fire together on a shared schedule
+    pub groups: BTreeMap<String, SpawnerGroup>,
+    // Bullet patterns by name
+    pub patterns: BTreeMap<String, Pattern>,
+    // Asset path of a cutscene to play before the countdown
+    pub intro: Option<String>,
+    // Flying flat out wears the bee out, as in the realistic flight challenge
+    pub stamina: bool,
+    // Lay the level out around (0, 0) rather than from its top-left corner
+    pub centered: bool,
+    // How each kind of spawner is drawn, by the name it's given in `@spawner_art`
+    pub spawner_art: BTreeMap<String, SpawnerArt>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnerGroup {
+    pub pattern: FirePattern,
+    // Seconds between volleys, defaulting to the slowest member's cooldown
+    pub period: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FirePattern {
+    // Every member fires at once
+    Simultaneous,
+    // Members take turns, one per volley
+    Alternate,
+    // Every member fires each volley, each one this many seconds after the last
+    Sequential { offset: f32 },
+}
+
+impl FirePattern {
+    fn parse(source: &str) -> Option<Self> {
+        match source.split_once(':') {
+            None if source == "simultaneous" => Some(FirePattern::Simultaneous),
+            None if source == "alternate" => Some(FirePattern::Alternate),
+            Some(("sequential", offset)) => offset
+                .parse::<f32>()
+                .ok()
+                .filter(|offset| offset.is_finite() && *offset >= 0.0)
+                .map(|offset| FirePattern::Sequential { offset }),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> String {
+        match self {
+            FirePattern::Simultaneous => "simultaneous".into(),
+            FirePattern::Alternate => "alternate".into(),
+            FirePattern::Sequential { offset } => format!("sequential:{offset}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutOfBounds {
+    // Put the player back at the start
+    Respawn,
+    Death,
+}
+
+impl LevelMetadata {
+    pub const DEFAULT_FOG_RADIUS: f32 = 100.0;
+
+    fn parse_entry(&mut self, entry: &str) -> Result<(), String> {
+        let (key, value) = entry.split_once('\t').unwrap_or((entry, ""));
+        match key {
+            "fog" if value.is_empty() => self.fog = Some(Self::DEFAULT_FOG_RADIUS),
+            "fog" => {
+                let radius = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|radius| radius.is_finite() && *radius > 0.0)
+                    .ok_or_else(|| format!("invalid fog radius {value:?}"))?;
+                self.fog = Some(radius);
+            }
+            "boundary" if value.is_empty() => self.boundary = true,
+            "out_of_bounds" => {
+                self.out_of_bounds = Some(match value {
+                    "respawn" => OutOfBounds::Respawn,
+                    "death" => OutOfBounds::Death,
+                    _ => return Err(format!("invalid out of bounds behavior {value:?}")),
+                });
+            }
+            // @group\t<id>\t<pattern>[\t<period>]
+            "group" => {
+                let mut fields = value.split('\t');
+                let id = fields
+                    .next()
+                    .filter(|id| !id.is_empty())
+                    .ok_or("missing spawner group ID")?;
+                let pattern = fields.next().unwrap_or_default();
+                let pattern = FirePattern::parse(pattern)
+                    .ok_or_else(|| format!("invalid firing pattern {pattern:?}"))?;
+                let period = match fields.next() {
+                    Some(period) => Some(
+                        period
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|period| period.is_finite() && *period > 0.0)
+                            .ok_or_else(|| format!("invalid group period {period:?}"))?,
+                    ),
+                    None => None,
+                };
+                if fields.next().is_some() {
+                    return Err(format!("too many fields for group {id:?}"));
+                }
+                self.groups
+                    .insert(id.to_string(), SpawnerGroup { pattern, period });
+            }
+            "pattern" => {
+                let (name, steps) = value
+                    .split_once('\t')
+                    .filter(|(name, _)| !name.is_empty())
+                    .ok_or_else(|| format!("invalid pattern {value:?}"))?;
+                self.patterns
+                    .insert(name.to_string(), Pattern::parse(steps)?);
+            }
+            "intro" if !value.is_empty() => self.intro = Some(value.to_string()),
+            "stamina" if value.is_empty() => self.stamina = true,
+            "centered" if value.is_empty() => self.centered = true,
+            // @spawner_art\t<kind>\t<option>...
+            "spawner_art" => {
+                let (kind, options) = value.split_once('\t').unwrap_or((value, ""));
+                match kind {
+                    "laser" => {}
+                    "missile" => return Err("missile spawners don't aim".to_string()),
+                    _ => return Err(format!("unknown spawner kind {kind:?}")),
+                }
+                self.spawner_art
+                    .insert(kind.to_string(), SpawnerArt::parse(options)?);
+            }
+            _ => return Err(format!("unknown setting {key:?}")),
+        }
+        Ok(())
+    }
+
+    fn serialize(&self) -> String {
+        let mut output = String::new();
+        if let Some(radius) = self.fog {
+            output.push_str(&format!("@fog\t{radius}\n"));
+        }
+        if self.boundary {
+            output.push_str("@boundary\n");
+        }
+        match self.out_of_bounds {
+            Some(OutOfBounds::Respawn) => output.push_str("@out_of_bounds\trespawn\n"),
+            Some(OutOfBounds::Death) => output.push_str("@out_of_bounds\tdeath\n"),
+            None => {}
+        }
+        for (id, group) in &self.groups {
+            output.push_str(&format!("@group\t{id}\t{}", group.pattern.code()));
+            if let Some(period) = group.period {
+                output.push_str(&format!("\t{period}"));
+            }
+            output.push('\n');
+        }
+        for (name, pattern) in &self.patterns {
+            output.push_str(&format!("@pattern\t{name}\t{pattern}\n"));
+        }
+        if let Some(intro) = &self.intro {
+            output.push_str(&format!("@intro\t{intro}\n"));
+        }
+        if self.stamina {
+            output.push_str("@stamina\n");
+        }
+        if self.centered {
+            output.push_str("@centered\n");
+        }
+        for (kind, art) in &self.spawner_art {
+            output.push_str(&format!("@spawner_art\t{kind}\t{}\n", art.serialize()));
+        }
+        output
+    }
+}
+
 pub struct GameWorld {
     pub world_type: WorldType,
     // Coordinates of the player's spawn location: (x, y)
     player_start_coordinates: (usize, usize),
+    pub metadata: LevelMetadata,
     layout: Vec<Vec<Option<Tile>>>,
+    // Sprite overrides from a level pack
+    pub sprites: HashMap<String, PathBuf>,
+    // Source of the level's trigger script, if it has one
+    pub script: Option<String>,
+    pub variant: Variant,
+    // How the layout has been transformed from the level as written
+    orientation: Orientation,
+}
+
+// Alternate versions of a level made by transforming its layout
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq, Hash)]
+pub enum Variant {
+    Original,
+    Rotated90,
+    Rotated180,
+    Rotated270,
+    Flipped,
+}
+
+impl Variant {
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Original => "Original",
+            Variant::Rotated90 => "Rotated 90°",
+            Variant::Rotated180 => "Rotated 180°",
+            Variant::Rotated270 => "Rotated 270°",
+            Variant::Flipped => "Upside down",
+        }
+    }
+
+    // Name of the level's variant, recorded as a level of its own
+    pub fn level_name(self, level: &str) -> String {
+        match self {
+            Variant::Original => level.to_string(),
+            variant => format!("{level} ({})", variant.name()),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        Variant::iter()
+            .cycle()
+            .skip_while(|variant| *variant != self)
+            .nth(1)
+            .unwrap()
+    }
+
+    pub fn apply(self, world: &mut GameWorld) {
+        let turns = match self {
+            Variant::Original => 0,
+            Variant::Rotated90 => 1,
+            Variant::Rotated180 | Variant::Flipped => 2,
+            Variant::Rotated270 => 3,
+        };
+        // A vertical flip is a horizontal one turned upside down
+        if self == Variant::Flipped {
+            world.mirror();
+        }
+        for _ in 0..turns {
+            world.rotate();
+        }
+        world.variant = self;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Orientation {
+    // Size of the layout as written: (width, height)
+    source_size: (usize, usize),
+    mirrored: bool,
+    // Clockwise quarter turns, applied after mirroring
+    turns: u8,
+}
+
+// Tiles from a missile spawner within which there's some danger of being caught
+const MISSILE_DANGER_RADIUS: f32 = 8.0;
+// Danger of a laser line that only fires some of the time
+const SCHEDULED_LASER_DANGER: f32 = 0.6;
+
+pub enum Diagnostic {
+    Warning(String),
+    Error(String),
 }
 
-pub const LEVELS: [(&'static str, &'static str); 2] = [
-    ("Level 0", include_str!("../assets/levels/level0.tsv")),
-    ("Level 1", include_str!("../assets/levels/level1.tsv"))
+// Built-in levels: name, asset path and the source the game ships with
+pub const LEVELS: [(&str, &str, &str); 3] = [
+    (
+        "Level 0",
+        "levels/level0.tsv",
+        include_str!("../assets/levels/level0.tsv"),
+    ),
+    (
+        "Level 1",
+        "levels/level1.tsv",
+        include_str!("../assets/levels/level1.tsv"),
+    ),
+    // Introduces the power-up pickups
+    (
+        "Level 2",
+        "levels/level2.tsv",
+        include_str!("../assets/levels/level2.tsv"),
+    ),
 ];
 
+// Drop the byte order mark and carriage returns some editors write, leaving plain lines
+pub fn normalize_line_endings(source: &str) -> String {
+    source
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+// Tiles in a layout row, separated by tabs or spaces. Nothing between two tabs is kept as an
+// empty tile to be reported, since it's more likely a mistake than lining up columns.
+pub fn row_tiles(line: &str) -> Vec<&str> {
+    line.split('\t')
+        .flat_map(|cell| {
+            let mut tiles: Vec<_> = cell.split_whitespace().collect();
+            if tiles.is_empty() {
+                tiles.push("");
+            }
+            tiles
+        })
+        .collect()
+}
+
 impl GameWorld {
-    pub fn load_level(level: usize) -> io::Result<Self> {
-        // Open file and collect rows
-        let lines = LEVELS[level].1.lines();
+    // Parse a level layout. Tiles are separated by tabs or spaces, and short rows are
+    // padded with empty tiles, so a level reads the same whichever editor it was written in.
+    pub fn parse(source: &str, world_type: WorldType) -> io::Result<Self> {
+        let source = normalize_line_endings(source);
+        let lines = source.trim_end().lines().map(str::trim_end);
 
         let mut start = None;
+        let mut metadata = LevelMetadata::default();
         let mut layout = Vec::new();
-        for (i, line) in lines.enumerate() {
+        // Members seen so far in each spawner group
+        let mut group_sizes: HashMap<String, usize> = HashMap::new();
+        let mut patterns_used = Vec::new();
+        for (line_number, line) in lines.enumerate() {
+            // Lines starting with @ are level settings rather than layout rows
+            if let Some(entry) = line.strip_prefix('@') {
+                metadata.parse_entry(entry).map_err(|reason| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: {reason}", line_number + 1),
+                    )
+                })?;
+                continue;
+            }
+
+            let i = layout.len();
+            if line.trim().is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("row {}: empty row", i + 1),
+                ));
+            }
             let mut row = Vec::new();
-            for (j, value) in line.split('\t').enumerate() {
-                let tile = match value.chars().next().unwrap() {
-                    '.' => None,
-                    '#' => Some(Tile::Wall),
-                    'L' => Some(Tile::Spawner(Spawner::new(Projectile::Laser {
-                        angle: (&value[2..]).parse::<f32>().unwrap(),
-                    }))),
-                    'M' => Some(Tile::Spawner(Spawner::new(Projectile::Missile))),
-                    'T' => Some(Tile::Trap),
-                    'G' => Some(Tile::Goal),
-                    '*' => {
+            for (j, value) in row_tiles(line).into_iter().enumerate() {
+                let invalid = |reason: &str| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("row {}, column {}: {reason}", i + 1, j + 1),
+                    )
+                };
+                // Spawners can join a group with a /<id> suffix, or play a pattern with ~<name>
+                let (value, control) = match value.split_once(['/', '~']) {
+                    Some((spawner, name))
+                        if spawner.starts_with(['L', 'M']) && !name.is_empty() =>
+                    {
+                        let control = if value.as_bytes()[spawner.len()] == b'/' {
+                            let size = group_sizes.entry(name.to_string()).or_default();
+                            *size += 1;
+                            FireControl::Group(GroupSlot {
+                                group: name.to_string(),
+                                index: *size - 1,
+                            })
+                        } else {
+                            patterns_used.push(name.to_string());
+                            FireControl::Pattern(name.to_string())
+                        };
+                        (spawner, Some(control))
+                    }
+                    _ => (value, None),
+                };
+                let tile = match value.chars().next() {
+                    None => return Err(invalid("empty tile")),
+                    Some('.') => None,
+                    Some('#') => Some(Tile::Wall),
+                    Some('L') => {
+                        // A trailing ! makes the lasers stop at walls
+                        let (angle, blocked) = match value.strip_suffix('!') {
+                            Some(angle) => (angle, true),
+                            None => (value, false),
+                        };
+                        let angle = angle
+                            .strip_prefix("L:")
+                            .and_then(|angle| angle.parse::<f32>().ok())
+                            .filter(|angle| angle.is_finite())
+                            .ok_or_else(|| invalid(&format!("invalid laser angle {value:?}")))?;
+                        Some(Tile::Spawner(Projectile::Laser { angle, blocked }, control))
+                    }
+                    Some('M') => Some(Tile::Spawner(Projectile::Missile, control)),
+                    Some('T') => Some(Tile::Trap),
+                    Some('G') => Some(Tile::Goal),
+                    Some('C') => Some(Tile::Checkpoint),
+                    Some('F') => Some(Tile::Flower),
+                    Some('P') => {
+                        let power_up = value
+                            .strip_prefix("P:")
+                            .and_then(PowerUp::from_code)
+                            .ok_or_else(|| invalid(&format!("invalid power-up {value:?}")))?;
+                        Some(Tile::PowerUp(power_up))
+                    }
+                    Some('*') => {
                         // The * character indicates player's spawn location
                         start = Some((j, i));
                         None
                     }
-                    _ => panic!("Invalid value: {value}"),
+                    _ => return Err(invalid(&format!("invalid value {value:?}"))),
                 };
                 row.push(tile);
             }
             layout.push(row);
         }
 
-        Ok(Self {
-            world_type: WorldType::Level {
-                index: level,
-            },
+        if layout.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "level has no layout",
+            ));
+        }
+        if let Some(id) = group_sizes
+            .keys()
+            .find(|id| !metadata.groups.contains_key(*id))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("spawner group {id:?} has no @group setting"),
+            ));
+        }
+        if let Some(name) = patterns_used
+            .iter()
+            .find(|name| !metadata.patterns.contains_key(*name))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("pattern {name:?} has no @pattern setting"),
+            ));
+        }
+
+        let mut world = Self {
+            world_type,
             player_start_coordinates: start.unwrap_or((0, 0)),
+            metadata,
+            sprites: HashMap::new(),
+            script: None,
+            variant: Variant::Original,
+            orientation: Orientation {
+                source_size: (layout.iter().map(Vec::len).max().unwrap_or(0), layout.len()),
+                ..Orientation::default()
+            },
             layout,
+        };
+        world.pad_rows();
+        Ok(world)
+    }
+
+    // Check a level for problems that parse fine but break the game
+    pub fn validate(source: &str) -> Vec<Diagnostic> {
+        let world = match Self::parse(source, WorldType::Endless) {
+            Ok(world) => world,
+            Err(err) => return vec![Diagnostic::Error(err.to_string())],
+        };
+
+        let mut diagnostics = Vec::new();
+        let starts = normalize_line_endings(source)
+            .lines()
+            .filter(|line| !line.starts_with('@'))
+            .flat_map(|line| row_tiles(line.trim_end()))
+            .filter(|value| *value == "*")
+            .count();
+        match starts {
+            0 => diagnostics.push(Diagnostic::Warning(
+                "no player start (*), defaulting to the top-left tile".into(),
+            )),
+            1 => {}
+            n => diagnostics.push(Diagnostic::Warning(format!(
+                "{n} player starts (*), only the last one is used"
+            ))),
+        }
+
+        let goals = world
+            .layout
+            .iter()
+            .flatten()
+            .filter(|tile| matches!(tile, Some(Tile::Goal)))
+            .count();
+        match goals {
+            0 => diagnostics.push(Diagnostic::Error("no goal (G), level can't be won".into())),
+            1 => {}
+            // Any goal wins, but the camera and pathfinding go by the first
+            n => diagnostics.push(Diagnostic::Warning(format!(
+                "{n} goals (G), only the first is used for paths and the camera"
+            ))),
+        }
+
+        let start = world.safe_start();
+        if start != world.player_start_coordinates {
+            diagnostics.push(Diagnostic::Warning(format!(
+                "player start {:?} is blocked, the player will spawn at {start:?} instead",
+                world.player_start_coordinates
+            )));
+        }
+        if goals > 0 && world.path_to_goal().is_none() {
+            diagnostics.push(Diagnostic::Warning(
+                "the goal can't be reached from the player start".into(),
+            ));
+        }
+
+        diagnostics
+    }
+
+    // Tile nearest the player start where the player fits without touching a wall or
+    // spawner. Checked at full size, which also covers the shrink upgrade.
+    pub fn safe_start(&self) -> (usize, usize) {
+        // Tiles around the center the player reaches into
+        let reach = ((Player::SIZE / Tile::SIZE - 1.0) / 2.0).ceil().max(0.0) as isize;
+        let fits = |(x, y): (usize, usize)| {
+            (-reach..=reach).all(|dy| {
+                (-reach..=reach).all(|dx| {
+                    let tile = self
+                        .layout
+                        .get((y as isize + dy) as usize)
+                        .and_then(|row| row.get((x as isize + dx) as usize));
+                    !matches!(tile, Some(Some(Tile::Wall | Tile::Spawner(..))))
+                })
+            })
+        };
+
+        let start = self.player_start_coordinates;
+        if fits(start) {
+            return start;
+        }
+        let distance = |(x, y): (usize, usize)| {
+            let (dx, dy) = (x.abs_diff(start.0), y.abs_diff(start.1));
+            dx * dx + dy * dy
+        };
+        self.layout
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| (x, y)))
+            .filter(|tile| fits(*tile))
+            .min_by_key(|tile| distance(*tile))
+            .unwrap_or(start)
+    }
+
+    // Flip the layout left to right. Scripts keep using the original layout's
+    // coordinates, which are translated with `map_tile`.
+    pub fn mirror(&mut self) {
+        let width = self.pad_rows();
+        for row in &mut self.layout {
+            row.reverse();
+        }
+        self.map_lasers(|angle| PI - angle);
+
+        let (x, y) = self.player_start_coordinates;
+        self.player_start_coordinates = (width.saturating_sub(x + 1), y);
+        // Mirroring reverses the direction of any earlier turns
+        self.orientation.mirrored = !self.orientation.mirrored;
+        self.orientation.turns = (4 - self.orientation.turns) % 4;
+    }
+
+    // Turn the layout a quarter turn clockwise
+    pub fn rotate(&mut self) {
+        let width = self.pad_rows();
+        let height = self.layout.len();
+        let mut layout: Vec<Vec<Option<Tile>>> = (0..width)
+            .map(|_| (0..height).map(|_| None).collect())
+            .collect();
+        for (y, row) in self.layout.drain(..).enumerate() {
+            for (x, tile) in row.into_iter().enumerate() {
+                layout[x][height - 1 - y] = tile;
+            }
+        }
+        self.layout = layout;
+        self.map_lasers(|angle| angle - PI / 2.0);
+
+        let (x, y) = self.player_start_coordinates;
+        self.player_start_coordinates = (height.saturating_sub(y + 1), x);
+        self.orientation.turns = (self.orientation.turns + 1) % 4;
+    }
+
+    // Convert coordinates in the level as written into the current layout
+    #[cfg(any(feature = "scripting", test))]
+    pub fn map_tile(&self, (mut x, mut y): (usize, usize)) -> (usize, usize) {
+        let (mut width, mut height) = self.orientation.source_size;
+        if self.orientation.mirrored {
+            x = width.saturating_sub(x + 1);
+        }
+        for _ in 0..self.orientation.turns {
+            (x, y) = (height.saturating_sub(y + 1), x);
+            (width, height) = (height, width);
+        }
+        (x, y)
+    }
+
+    // Fill out ragged rows so the layout is rectangular, returning its width
+    fn pad_rows(&mut self) -> usize {
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0);
+        for row in &mut self.layout {
+            row.resize_with(width, || None);
+        }
+        width
+    }
+
+    fn map_lasers(&mut self, map: impl Fn(f32) -> f32) {
+        for tile in self.layout.iter_mut().flatten().flatten() {
+            if let Tile::Spawner(Projectile::Laser { angle, .. }, _) = tile {
+                *angle = map(*angle);
+            }
+        }
+    }
+
+    // Write the layout back out in the same format accepted by `parse`
+    pub fn serialize(&self) -> String {
+        let mut output = self.metadata.serialize();
+        for (i, row) in self.layout.iter().enumerate() {
+            let values: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(j, tile)| match tile {
+                    None if (j, i) == self.player_start_coordinates => "*".into(),
+                    None => ".".into(),
+                    Some(Tile::Wall) => "#".into(),
+                    Some(Tile::Spawner(projectile, control)) => {
+                        let value = match projectile {
+                            Projectile::Laser { angle, blocked } => {
+                                format!("L:{angle}{}", if *blocked { "!" } else { "" })
+                            }
+                            Projectile::Missile => "M".into(),
+                        };
+                        match control {
+                            Some(FireControl::Group(slot)) => format!("{value}/{}", slot.group),
+                            Some(FireControl::Pattern(name)) => format!("{value}~{name}"),
+                            None => value,
+                        }
+                    }
+                    Some(Tile::Trap) => "T".into(),
+                    Some(Tile::Goal) => "G".into(),
+                    Some(Tile::Checkpoint) => "C".into(),
+                    Some(Tile::Flower) => "F".into(),
+                    Some(Tile::PowerUp(power_up)) => format!("P:{}", power_up.code()),
+                })
+                .collect();
+            output.push_str(&values.join("\t"));
+            output.push('\n');
+        }
+        output
+    }
+
+    // Fingerprint of the level as played, so recordings can tell when it's been changed.
+    // FNV-1a rather than std's hasher, whose output isn't guaranteed to stay the same.
+    pub fn hash(&self) -> u64 {
+        self.serialize()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    // Draw the layout as a color-coded image, with each tile as a square of pixels
+    pub fn thumbnail(&self, tile_pixels: u32) -> RgbImage {
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0) as u32;
+        let height = self.layout.len() as u32;
+        let mut image = RgbImage::from_pixel(
+            width * tile_pixels,
+            height * tile_pixels,
+            Rgb([51, 51, 102]),
+        );
+
+        for (i, row) in self.layout.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                let color = match tile {
+                    None if (j, i) == self.player_start_coordinates => Rgb([250, 209, 122]),
+                    None => continue,
+                    Some(Tile::Wall) => Rgb([40, 40, 40]),
+                    Some(Tile::Spawner(projectile, _)) => match projectile {
+                        Projectile::Laser { .. } => Rgb([220, 50, 50]),
+                        Projectile::Missile => Rgb([240, 140, 30]),
+                    },
+                    Some(Tile::Trap) => Rgb([150, 60, 170]),
+                    Some(Tile::Goal) => Rgb([166, 204, 112]),
+                    Some(Tile::Checkpoint) => Rgb([120, 200, 200]),
+                    Some(Tile::Flower) => Rgb([240, 120, 170]),
+                    Some(Tile::PowerUp(_)) => Rgb([90, 170, 230]),
+                };
+
+                for y in 0..tile_pixels {
+                    for x in 0..tile_pixels {
+                        image.put_pixel(
+                            j as u32 * tile_pixels + x,
+                            i as u32 * tile_pixels + y,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    // Resolve a sprite's asset path, preferring the level pack's replacement
+    fn sprite(&self, path: &str) -> PathBuf {
+        self.sprites
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| path.into())
+    }
+
+    // Where the layout is placed in the world
+    pub fn origin(&self) -> WorldOrigin {
+        if !self.metadata.centered {
+            return WorldOrigin::default();
+        }
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0);
+        let height = self.layout.len();
+        // Puts the middle of the layout on (0, 0)
+        WorldOrigin(
+            Vec2::new(
+                -(width.saturating_sub(1) as f32),
+                height.saturating_sub(1) as f32,
+            ) * Tile::SIZE
+                / 2.0,
+        )
+    }
+
+    // Whether the tile at (x, y) is a wall, with anything outside the layout open
+    pub fn is_wall(&self, (x, y): (isize, isize)) -> bool {
+        let tile = usize::try_from(y)
+            .ok()
+            .zip(usize::try_from(x).ok())
+            .and_then(|(y, x)| self.layout.get(y)?.get(x));
+        matches!(tile, Some(Some(Tile::Wall)))
+    }
+
+    // Coordinates of the tile the world `position` is on, which can be outside the layout
+    fn tile_at(&self, position: Vec2, origin: &WorldOrigin) -> (isize, isize) {
+        // Tile (x, y) is centered on (x, -y) * Tile::SIZE from the origin
+        let center = position - origin.0;
+        (
+            (center.x / Tile::SIZE).round() as isize,
+            (-center.y / Tile::SIZE).round() as isize,
+        )
+    }
+
+    // How far a line from the world `position` in `direction` goes before it enters a wall,
+    // if it does within `max`
+    pub fn ray_to_wall(&self, position: Vec2, direction: Vec2, max: f32) -> Option<f32> {
+        // Small enough steps that the line can't skip over the corner of a wall
+        const STEP: f32 = Tile::SIZE / 8.0;
+        let origin = self.origin();
+        let direction = direction.normalize_or_zero();
+        (1..=(max / STEP).ceil() as usize)
+            .map(|step| (step as f32 * STEP).min(max))
+            .find(|distance| self.is_wall(self.tile_at(position + direction * *distance, &origin)))
+    }
+
+    // Gap between a box centered on the world `position` and the nearest wall, if there's
+    // one within `max`. A box touching or overlapping a wall has a gap of 0.
+    pub fn distance_to_wall(&self, position: Vec2, half_size: Vec2, max: f32) -> Option<f32> {
+        let center = position - self.origin().0;
+        let (x, y) = self.tile_at(position, &self.origin());
+        let reach = ((half_size.max_element() + max) / Tile::SIZE).ceil() as isize + 1;
+        (y - reach..=y + reach)
+            .flat_map(|y| (x - reach..=x + reach).map(move |x| (x, y)))
+            .filter(|coordinates| self.is_wall(*coordinates))
+            .map(|(x, y)| {
+                let wall = Vec2::new(x as f32, -(y as f32)) * Tile::SIZE;
+                let gap = (center - wall).abs() - half_size - Vec2::splat(Tile::SIZE / 2.0);
+                gap.max(Vec2::ZERO).length()
+            })
+            .filter(|gap| *gap <= max)
+            .min_by(f32::total_cmp)
+    }
+
+    // Rough danger of each tile from 0 to 1, indexed [y][x] like the layout. Lasers on their
+    // own cooldown fire fast enough that their lines are fully dangerous, while groups and
+    // patterns leave gaps. Missiles home in, so tiles are more dangerous the less room there
+    // is to dodge one. Patterns that turn spawners aren't followed, only the angle each
+    // starts at. Tiles that can't be flown over are left at 0.
+    pub fn danger_map(&self) -> Vec<Vec<f32>> {
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0);
+        let mut danger = vec![vec![0.0; width]; self.layout.len()];
+        let origin = self.origin();
+        for (y, row) in self.layout.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                match tile {
+                    Some(Tile::Spawner(Projectile::Laser { angle, blocked }, control)) => {
+                        let line_danger = match control {
+                            None => 1.0,
+                            Some(_) => SCHEDULED_LASER_DANGER,
+                        };
+                        for (x, y) in self.laser_line((x, y), *angle, *blocked, &origin) {
+                            danger[y][x] = f32::max(danger[y][x], line_danger);
+                        }
+                    }
+                    Some(Tile::Spawner(Projectile::Missile, _)) => {
+                        for (ty, row) in danger.iter_mut().enumerate() {
+                            for (tx, tile_danger) in row.iter_mut().enumerate() {
+                                let (dx, dy) = (tx.abs_diff(x) as f32, ty.abs_diff(y) as f32);
+                                let distance = (dx * dx + dy * dy).sqrt();
+                                let added = (1.0 - distance / MISSILE_DANGER_RADIUS).max(0.0);
+                                *tile_danger = (*tile_danger + added).min(1.0);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let passable = self.passable();
+        for (y, row) in danger.iter_mut().enumerate() {
+            for (x, tile_danger) in row.iter_mut().enumerate() {
+                if !passable((x, y)) {
+                    *tile_danger = 0.0;
+                }
+            }
+        }
+        danger
+    }
+
+    // Tiles inside the layout that a spawner's lasers fly through, not including its own
+    fn laser_line(
+        &self,
+        spawner: (usize, usize),
+        angle: f32,
+        blocked: bool,
+        origin: &WorldOrigin,
+    ) -> Vec<(usize, usize)> {
+        // Small enough steps that the line can't skip over a tile's corner
+        const STEP: f32 = Tile::SIZE / 8.0;
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0);
+        let position = origin.tile_to_world(spawner);
+        let direction = polar_to_cartesian(angle, 1.0);
+        let mut line = Vec::new();
+        for step in 1..=(PROJECTILE_RANGE / STEP) as usize {
+            let (x, y) = self.tile_at(position + direction * step as f32 * STEP, origin);
+            if blocked && self.is_wall((x, y)) {
+                break;
+            }
+            let tile = usize::try_from(x)
+                .ok()
+                .zip(usize::try_from(y).ok())
+                .filter(|(x, y)| *x < width && *y < self.layout.len());
+            match tile {
+                Some(tile) if tile != spawner && line.last() != Some(&tile) => line.push(tile),
+                Some(_) => {}
+                // A straight line that's left the layout doesn't come back
+                None => break,
+            }
+        }
+        line
+    }
+
+    // World space rectangle (min, max) covered by the layout's tiles
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        let height = self.layout.len().max(1);
+        let half_tile = Vec2::splat(Tile::SIZE / 2.0);
+        let origin = self.origin();
+        let (top_left, bottom_right) = (
+            origin.tile_to_world((0, 0)),
+            origin.tile_to_world((width - 1, height - 1)),
+        );
+        (
+            Vec2::new(top_left.x, bottom_right.y) - half_tile,
+            Vec2::new(bottom_right.x, top_left.y) + half_tile,
+        )
+    }
+
+    // How many checkpoints come before the given one, reading the layout row by row
+    fn checkpoint_index(&self, (x, y): (usize, usize)) -> usize {
+        self.layout[..y]
+            .iter()
+            .flatten()
+            .chain(&self.layout[y][..x])
+            .filter(|tile| matches!(tile, Some(Tile::Checkpoint)))
+            .count()
+    }
+
+    // Coordinates of the checkpoint with the given number
+    pub fn checkpoint_coordinates(&self, index: usize) -> Option<(usize, usize)> {
+        self.layout
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, tile)| ((x, y), tile)))
+            .filter(|(_, tile)| matches!(tile, Some(Tile::Checkpoint)))
+            .nth(index)
+            .map(|(coordinates, _)| coordinates)
+    }
+
+    pub fn checkpoint_count(&self) -> usize {
+        self.layout
+            .iter()
+            .flatten()
+            .filter(|tile| matches!(tile, Some(Tile::Checkpoint)))
+            .count()
+    }
+
+    fn goal_position(&self) -> Option<Vec2> {
+        let origin = self.origin();
+        self.goal_coordinates()
+            .map(|coordinates| origin.tile_to_world(coordinates))
+    }
+
+    fn goal_coordinates(&self) -> Option<(usize, usize)> {
+        self.layout.iter().enumerate().find_map(|(y, row)| {
+            let x = row.iter().position(|tile| *tile == Some(Tile::Goal))?;
+            Some((x, y))
         })
     }
 
+    // Tiles from the player's start to the goal, avoiding anything solid or deadly
+    pub fn path_to_goal(&self) -> Option<Vec<(usize, usize)>> {
+        pathfinding::find_path(self.safe_start(), self.goal_coordinates()?, self.passable())
+    }
+
+    // Like path_to_goal, but also keeping more than `margin` tiles from every spawner
+    // where there's a way round. Falls back to the plain path where there isn't.
+    pub fn path_avoiding_spawners(&self, margin: usize) -> Option<Vec<(usize, usize)>> {
+        let spawners: Vec<_> = self
+            .layout
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, tile)| matches!(tile, Some(Tile::Spawner(..))))
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect();
+        let covered = |(x, y): (usize, usize)| {
+            spawners
+                .iter()
+                .any(|spawner| spawner.0.abs_diff(x).max(spawner.1.abs_diff(y)) <= margin)
+        };
+        let (start, goal) = (self.safe_start(), self.goal_coordinates()?);
+        let passable = self.passable();
+        // The start and goal can't be moved away from, wherever they are
+        let is_open = |tile| passable(tile) && (tile == start || tile == goal || !covered(tile));
+        pathfinding::find_path(start, goal, is_open).or_else(|| self.path_to_goal())
+    }
+
+    // Whether a path can go through a tile without touching anything solid or deadly
+    fn passable(&self) -> impl Fn((usize, usize)) -> bool + '_ {
+        let width = self.layout.iter().map(Vec::len).max().unwrap_or(0);
+        move |(x, y)| {
+            // Short rows are open past their end, as nothing is spawned there
+            let tile = match self.layout.get(y) {
+                Some(row) if x < width => row.get(x).and_then(Option::as_ref),
+                _ => return false,
+            };
+            matches!(
+                tile,
+                None | Some(Tile::Goal | Tile::PowerUp(_) | Tile::Checkpoint | Tile::Flower)
+            )
+        }
+    }
+
     fn get_wall_neighbors(&self, x: usize, y: usize) -> [bool; 4] {
         let mut neighbors = [false; 4];
         let height = self.layout.len();
@@ -136,174 +1165,1407 @@ pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(spawn_world))
-            .add_system_set(SystemSet::on_update(AppState::Game).with_system(spawn_projectiles));
+        app.init_resource::<WorldOrigin>()
+            .init_resource::<OpenWalls>()
+            .init_resource::<SpawnerGrid>()
+            .init_resource::<SpawnerClock>()
+            .init_resource::<SpawnerGroups>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Game)
+                    .with_system(reset_spawners)
+                    .with_system(spawn_world),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(playing)
+                    .with_system(index_spawners)
+                    .with_system(spawn_queued_tiles)
+                    .with_system(tick_spawners.before(SpawnEnemies))
+                    .with_system(warn_before_launch),
+            );
+    }
+}
+
+// Spawners further than this outside the camera's view are asleep
+const SPAWNER_ACTIVATION_MARGIN: f32 = 400.0;
+// Missile spawners flash and play a rising tone for this long before they fire
+const WARNING_SECONDS: f32 = 0.5;
+const WARNING_COLOR: Color = Color::rgb(1.0, 0.35, 0.1);
+
+// Time all spawners fire against, advanced once per frame instead of ticking a timer per spawner
+#[derive(Default)]
+struct SpawnerClock(f64);
+
+// Spatial index of spawners, so only the ones near the camera are looked at each frame
+#[derive(Default)]
+struct SpawnerGrid {
+    cells: HashMap<(i32, i32), Vec<Entity>>,
+}
+
+impl SpawnerGrid {
+    const CELL_SIZE: f32 = Tile::SIZE * 16.0;
+
+    fn cell(position: Vec2) -> (i32, i32) {
+        let cell = (position / Self::CELL_SIZE).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+
+    fn insert(&mut self, entity: Entity, position: Vec2) {
+        self.cells
+            .entry(Self::cell(position))
+            .or_default()
+            .push(entity);
+    }
+
+    // Spawners in the cells overlapping the given rectangle
+    fn nearby(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = Entity> + '_ {
+        let (min, max) = (Self::cell(min), Self::cell(max));
+        (min.1..=max.1)
+            .flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+// Schedules for each spawner group, replacing the members' own cooldowns
+#[derive(Default)]
+struct SpawnerGroups(HashMap<String, GroupSchedule>);
+
+impl SpawnerGroups {
+    fn new(world: &GameWorld) -> Self {
+        let mut groups = HashMap::new();
+        for tile in world.layout.iter().flatten().flatten() {
+            if let Tile::Spawner(projectile, Some(FireControl::Group(slot))) = tile {
+                let group = world.metadata.groups[&slot.group];
+                let schedule = groups
+                    .entry(slot.group.clone())
+                    .or_insert_with(|| GroupSchedule::new(group.pattern, 0.0));
+                if schedule.members.len() <= slot.index {
+                    schedule.members.resize(slot.index + 1, None);
+                }
+                let period = group
+                    .period
+                    .unwrap_or_else(|| Spawner::new(projectile.clone(), None).cooldown);
+                schedule.period = schedule.period.max(period as f64);
+                schedule.next_fire = schedule.period;
+            }
+        }
+        Self(groups)
+    }
+}
+
+struct GroupSchedule {
+    pattern: FirePattern,
+    // Seconds between volleys
+    period: f64,
+    // SpawnerClock time the current volley starts
+    next_fire: f64,
+    // Next member to fire, for patterns that go through them in order
+    turn: usize,
+    // Spawned members by slot index
+    members: Vec<Option<Entity>>,
+}
+
+impl GroupSchedule {
+    fn new(pattern: FirePattern, period: f64) -> Self {
+        Self {
+            pattern,
+            period,
+            next_fire: period,
+            turn: 0,
+            members: Vec::new(),
+        }
+    }
+
+    // Slot indices of the members that fire at the given time.
+    // Like single spawners, missed volleys are skipped rather than caught up on.
+    fn fire(&mut self, now: f64) -> Vec<usize> {
+        let count = self.members.len();
+        let mut due = Vec::new();
+        if count == 0 {
+            return due;
+        }
+        match self.pattern {
+            FirePattern::Simultaneous if now >= self.next_fire => {
+                due.extend(0..count);
+                self.advance(now);
+            }
+            FirePattern::Alternate if now >= self.next_fire => {
+                due.push(self.turn);
+                self.turn = (self.turn + 1) % count;
+                self.advance(now);
+            }
+            FirePattern::Sequential { offset } => {
+                while self.turn < count && now >= self.next_fire + self.turn as f64 * offset as f64
+                {
+                    due.push(self.turn);
+                    self.turn += 1;
+                }
+                if self.turn == count {
+                    self.turn = 0;
+                    self.advance(now);
+                }
+            }
+            _ => {}
+        }
+        due
+    }
+
+    fn advance(&mut self, now: f64) {
+        self.next_fire += self.period;
+        if self.next_fire <= now {
+            self.next_fire = now + self.period;
+        }
+    }
+
+    // SpawnerClock time the given member fires next
+    fn next_fire_for(&self, index: usize) -> f64 {
+        let count = self.members.len();
+        match self.pattern {
+            FirePattern::Simultaneous => self.next_fire,
+            FirePattern::Alternate => {
+                self.next_fire + ((index + count - self.turn) % count) as f64 * self.period
+            }
+            FirePattern::Sequential { offset } => {
+                let volley = if index < self.turn { self.period } else { 0.0 };
+                self.next_fire + volley + index as f64 * offset as f64
+            }
+        }
     }
 }
 
 fn spawn_world(
     mut commands: Commands,
-    world: Res<GameWorld>,
+    world: Res<Arc<GameWorld>>,
     mut animations: ResMut<Assets<SpriteSheetAnimation>>,
     mut textures: ResMut<Assets<TextureAtlas>>,
     asset_server: Res<AssetServer>,
     upgrades: Res<UpgradeTracker>,
+    skins: Res<SkinRegistry>,
+    accessories: Res<AccessoryRegistry>,
+    save: Res<SaveData>,
+    font: Res<GameFont>,
 ) {
-    let tile_size = Vec2::splat(Tile::SIZE);
-    let mut goal_position = None;
+    // A start inside a wall or spawner would soft-lock the level
+    let start = world.safe_start();
+    if start != world.player_start_coordinates {
+        warn!(
+            "Player start {:?} is blocked, moved to {start:?}",
+            world.player_start_coordinates
+        );
+    }
+
+    // Tiles nearest the player are spawned first, and anything that doesn't fit in
+    // this frame is left for spawn_queued_tiles
+    let distance = |(x, y): (usize, usize)| x.abs_diff(start.0).max(y.abs_diff(start.1));
+    let mut queue: Vec<(usize, usize)> = world
+        .layout
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, tile)| tile.is_some())
+                .map(move |(x, _)| (x, y))
+        })
+        .collect();
+    queue.sort_by_key(|coordinates| std::cmp::Reverse(distance(*coordinates)));
+    let total = queue.len();
+    let seed = world.hash();
+    for coordinates in queue.drain(queue.len().saturating_sub(TILES_PER_FRAME)..) {
+        spawn_tile(
+            &mut commands,
+            &world,
+            seed,
+            coordinates,
+            &mut animations,
+            &mut textures,
+            &asset_server,
+        );
+    }
+    if !queue.is_empty() {
+        spawn_loading_text(&mut commands, &font);
+    }
+    commands.insert_resource(TileQueue {
+        remaining: queue,
+        total,
+        seed,
+    });
+    commands.insert_resource(OpenWalls::default());
+
+    // Convert player start coordinates into world position
+    let origin = world.origin();
+    commands.insert_resource(origin);
+    let player_start_location = origin.tile_to_world(start);
+
+    // Spawn the player
+    let player = player::spawn_player(
+        &mut commands,
+        animations,
+        textures,
+        &asset_server,
+        upgrades,
+        &skins.selected(&save).path,
+        player_start_location,
+    );
+    if let Some(accessory) = accessories.selected(&save) {
+        cosmetics::attach_accessory(&mut commands, player, accessory, &asset_server);
+    }
+
+    camera::spawn_camera(&mut commands, world.goal_position().unwrap_or(Vec2::ZERO));
+}
+
+// Most tiles spawned in a single frame, so huge levels don't stall the game
+const TILES_PER_FRAME: usize = 2000;
+
+// Tiles of the level still to be spawned, nearest to the player last
+struct TileQueue {
+    remaining: Vec<(usize, usize)>,
+    total: usize,
+    seed: u64,
+}
+
+#[derive(Component)]
+pub struct LoadingText;
+
+fn spawn_loading_text(commands: &mut Commands, font: &GameFont) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(2.0),
+                    left: Val::Percent(2.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.get_handle(),
+                    font_size: 35.0,
+                    ..TextStyle::default()
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(LoadingText);
+}
+
+fn spawn_queued_tiles(
+    mut commands: Commands,
+    world: Res<Arc<GameWorld>>,
+    mut queue: ResMut<TileQueue>,
+    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
+    mut textures: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+    mut loading_text: Query<(Entity, &mut Text), With<LoadingText>>,
+) {
+    if queue.remaining.is_empty() {
+        return;
+    }
+
+    let count = queue.remaining.len().min(TILES_PER_FRAME);
+    let remaining = queue.remaining.len() - count;
+    let seed = queue.seed;
+    for coordinates in queue.remaining.drain(remaining..) {
+        spawn_tile(
+            &mut commands,
+            &world,
+            seed,
+            coordinates,
+            &mut animations,
+            &mut textures,
+            &asset_server,
+        );
+    }
+
+    if let Ok((entity, mut text)) = loading_text.get_single_mut() {
+        if queue.remaining.is_empty() {
+            commands.entity(entity).despawn();
+        } else {
+            let progress = 1.0 - queue.remaining.len() as f32 / queue.total as f32;
+            text.sections[0].value = format!("Loading level {:.0}%", progress * 100.0);
+        }
+    }
+}
 
-    // Iterate through the world layout and spawn tiles accordingly
+// Spawn the sprites for every tile in the layout
+pub fn spawn_tiles(
+    commands: &mut Commands,
+    world: &GameWorld,
+    animations: &mut ResMut<Assets<SpriteSheetAnimation>>,
+    textures: &mut ResMut<Assets<TextureAtlas>>,
+    asset_server: &Res<AssetServer>,
+) {
+    let seed = world.hash();
     for (i, row) in world.layout.iter().enumerate() {
-        for (j, tile) in row.iter().enumerate() {
-            let transform =
-                Transform::from_xyz(j as f32 * Tile::SIZE, -(i as f32 * Tile::SIZE), 0.0);
-            match tile {
-                Some(Tile::Wall) => {
-                    let neighbors = world.get_wall_neighbors(j, i);
-                    let name = match neighbors {
-                        [true, false, true, false] => "wewe",
-                        [false, false, true, false] => "eewe",
-                        [true, false, true, true] => "weww",
-                        [true, true, true, false] => "wwwe",
-                        [false, true, false, true] => "ewew",
-                        [false, false, false, true] => "eeew",
-                        [false, true, false, false] => "ewee",
-                        [true, true, false, true] => "wwew",
-                        [true, true, true, true] => "wwww",
-                        [true, false, false, false] => "weee",
-                        [true, false, false, true] => "weew",
-                        [true, true, false, false] => "wwee",
-                        [false, true, true, true] => "ewww",
-                        [false, true, true, false] => "ewwe",
-                        [false, false, true, true] => "eeww",
-                        [false, false, false, false] => "eeee",
-                    };
+        for j in 0..row.len() {
+            spawn_tile(
+                commands,
+                world,
+                seed,
+                (j, i),
+                animations,
+                textures,
+                asset_server,
+            );
+        }
+    }
+}
 
-                    let path = format!("walls/{name}.png");
-                    commands.spawn_bundle(SpriteBundle {
-                        sprite: Sprite {
-                            custom_size: Some(tile_size),
-                            ..Sprite::default()
-                        },
-                        texture: asset_server.load(&path),
-                        transform,
-                        ..SpriteBundle::default()
-                    });
-                }
-                Some(Tile::Spawner(spawner)) => match spawner.projectile {
-                    Projectile::Missile => {
-                        commands
-                            .spawn_bundle(SpriteBundle {
-                                sprite: Sprite {
-                                    custom_size: Some(tile_size),
-                                    ..Sprite::default()
-                                },
-                                texture: asset_server.load("missile-spawner.png"),
-                                transform,
-                                ..SpriteBundle::default()
-                            })
-                            .insert(spawner.clone());
-                    }
-                    Projectile::Laser { angle, .. } => {
-                        commands
-                            .spawn_bundle(AnimatedSprite::new(
-                                &mut animations,
-                                &mut textures,
-                                &asset_server,
+// Walls are shaded up to this much darker than the sprite
+const WALL_SHADE_RANGE: f32 = 0.08;
+
+// Cosmetic differences between tiles of the same kind, so big areas of wall don't look
+// like a grid. Picked from the level's hash and the tile's coordinates, so a level looks
+// the same every time it's played.
+#[derive(Debug, PartialEq)]
+struct TileVariation {
+    shade: f32,
+    // Only for tiles that look the same from every side
+    quarter_turns: u32,
+    // Flipped, or out of step with its neighbors if animated
+    alternate: bool,
+}
+
+impl TileVariation {
+    fn new(seed: u64, (x, y): (usize, usize)) -> Self {
+        // SplitMix64 finalizer over the seed and coordinates
+        let mut hash = seed ^ ((x as u64) << 32 | y as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+        Self {
+            shade: 1.0 - WALL_SHADE_RANGE * (hash & 0xff) as f32 / 255.0,
+            quarter_turns: (hash >> 8) as u32 % 4,
+            alternate: hash >> 10 & 1 == 1,
+        }
+    }
+}
+
+// Spawn the sprite for the tile at layout coordinates (x, y)
+#[allow(clippy::too_many_arguments)]
+fn spawn_tile(
+    commands: &mut Commands,
+    world: &GameWorld,
+    seed: u64,
+    (j, i): (usize, usize),
+    animations: &mut ResMut<Assets<SpriteSheetAnimation>>,
+    textures: &mut ResMut<Assets<TextureAtlas>>,
+    asset_server: &Res<AssetServer>,
+) {
+    let tile_size = Vec2::splat(Tile::SIZE);
+    let transform = Transform::from_translation(world.origin().tile_to_world((j, i)).extend(0.0));
+    let variation = TileVariation::new(seed, (j, i));
+    match &world.layout[i][j] {
+        Some(Tile::Wall) => {
+            let neighbors = world.get_wall_neighbors(j, i);
+            let name = match neighbors {
+                [true, false, true, false] => "wewe",
+                [false, false, true, false] => "eewe",
+                [true, false, true, true] => "weww",
+                [true, true, true, false] => "wwwe",
+                [false, true, false, true] => "ewew",
+                [false, false, false, true] => "eeew",
+                [false, true, false, false] => "ewee",
+                [true, true, false, true] => "wwew",
+                [true, true, true, true] => "wwww",
+                [true, false, false, false] => "weee",
+                [true, false, false, true] => "weew",
+                [true, true, false, false] => "wwee",
+                [false, true, true, true] => "ewww",
+                [false, true, true, false] => "ewwe",
+                [false, false, true, true] => "eeww",
+                [false, false, false, false] => "eeee",
+            };
+
+            // Only sprites the same all the way round can be turned without showing it.
+            // Collision goes by the layout, so none of this changes it.
+            let rotation = match name {
+                "wwww" | "eeee" => Quat::from_rotation_z(variation.quarter_turns as f32 * PI / 2.0),
+                _ => Quat::IDENTITY,
+            };
+            let path = format!("walls/{name}.png");
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(variation.shade, variation.shade, variation.shade),
+                        custom_size: Some(tile_size),
+                        ..Sprite::default()
+                    },
+                    texture: asset_server.load(world.sprite(&path)),
+                    transform: transform.with_rotation(rotation),
+                    ..SpriteBundle::default()
+                })
+                .insert(Wall)
+                .insert(WorldTile)
+                .insert(RenderLayer::Tiles);
+        }
+        Some(Tile::Spawner(projectile, control)) => {
+            let mut spawner = match *projectile {
+                Projectile::Missile => commands.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(tile_size),
+                        flip_x: variation.alternate,
+                        ..Sprite::default()
+                    },
+                    texture: asset_server.load(world.sprite("missile-spawner.png")),
+                    transform,
+                    ..SpriteBundle::default()
+                }),
+                Projectile::Laser { angle, .. } => {
+                    let art = world.metadata.spawner_art.get("laser");
+                    let mut spawner = match art.and_then(|art| art.directions.as_deref()) {
+                        Some(sheet) => commands.spawn_bundle(SpriteSheetBundle {
+                            sprite: TextureAtlasSprite {
+                                index: spawner_art::direction_frame(angle),
+                                custom_size: Some(tile_size),
+                                ..TextureAtlasSprite::default()
+                            },
+                            texture_atlas: textures.add(TextureAtlas::from_grid(
+                                asset_server.load(world.sprite(sheet)),
+                                tile_size,
+                                spawner_art::DIRECTIONS,
+                                1,
+                            )),
+                            transform,
+                            ..SpriteSheetBundle::default()
+                        }),
+                        None => {
+                            // A barrel does the aiming when there is one
+                            let rotation = if art.is_none_or(SpawnerArt::turns_base) {
+                                Quat::from_rotation_z(angle - PI / 2.0)
+                            } else {
+                                Quat::IDENTITY
+                            };
+                            commands.spawn_bundle(AnimatedSprite::new(
+                                animations,
+                                textures,
+                                asset_server,
                                 AnimatedSpriteData {
-                                    path: "laser-spawner.png".into(),
+                                    path: world.sprite("laser-spawner.png"),
                                     frames: 2,
+                                    first_frame: variation.alternate as usize,
                                     size: tile_size,
                                     transform: Transform {
                                         translation: transform.translation,
-                                        rotation: Quat::from_rotation_z(angle - PI / 2.0),
+                                        rotation,
                                         ..Transform::default()
                                     },
                                     ..AnimatedSpriteData::default()
                                 },
                             ))
-                            .insert(spawner.clone());
+                        }
+                    };
+                    if let Some(barrel) = art.and_then(|art| art.barrel.as_deref()) {
+                        let texture = asset_server.load(world.sprite(barrel));
+                        spawner.insert(Aim(angle)).with_children(|spawner| {
+                            spawner_art::attach_barrel(spawner, texture, tile_size, angle)
+                        });
                     }
-                },
-                Some(Tile::Trap) => {
-                    commands
-                        .spawn_bundle(AnimatedSprite::new(
-                            &mut animations,
-                            &mut textures,
-                            &asset_server,
-                            AnimatedSpriteData {
-                                path: "trap.png".into(),
-                                frames: 6,
-                                size: tile_size,
-                                transform,
-                                ..AnimatedSpriteData::default()
-                            },
-                        ))
-                        .insert(CollisionShape::new_rectangle(tile_size.x, tile_size.y))
-                        .insert(Enemy);
-                }
-                Some(Tile::Goal) => {
-                    commands
-                        .spawn_bundle(AnimatedSprite::new(
-                            &mut animations,
-                            &mut textures,
-                            &asset_server,
-                            AnimatedSpriteData {
-                                path: "goal.png".into(),
-                                frames: 6,
-                                size: tile_size,
-                                transform,
-                                ..AnimatedSpriteData::default()
-                            },
-                        ))
-                        .insert(CollisionShape::new_rectangle(tile_size.x, tile_size.y))
-                        .insert(Goal);
-                    goal_position = Some(transform.translation.truncate());
+                    spawner
                 }
-                None => {}
+            };
+            spawner
+                .insert(Spawner::new(projectile.clone(), control.clone()))
+                .insert(WorldTile)
+                .insert(RenderLayer::Tiles);
+            if let Some(FireControl::Pattern(name)) = control {
+                spawner.insert(PatternRunner::new(world.metadata.patterns[name].clone()));
             }
         }
+        Some(Tile::Trap) => {
+            commands
+                .spawn_bundle(AnimatedSprite::new(
+                    animations,
+                    textures,
+                    asset_server,
+                    AnimatedSpriteData {
+                        path: world.sprite("trap.png"),
+                        frames: 6,
+                        size: tile_size,
+                        transform,
+                        ..AnimatedSpriteData::default()
+                    },
+                ))
+                .insert(CollisionShape::new_rectangle(tile_size.x, tile_size.y))
+                .insert(Enemy)
+                .insert(WorldTile)
+                .insert(RenderLayer::Tiles);
+        }
+        Some(Tile::Goal) => {
+            commands
+                .spawn_bundle(AnimatedSprite::new(
+                    animations,
+                    textures,
+                    asset_server,
+                    AnimatedSpriteData {
+                        path: world.sprite("goal.png"),
+                        frames: 6,
+                        size: tile_size,
+                        transform,
+                        ..AnimatedSpriteData::default()
+                    },
+                ))
+                .insert(CollisionShape::new_rectangle(tile_size.x, tile_size.y))
+                .insert(Goal)
+                .insert(WorldTile)
+                .insert(RenderLayer::Tiles);
+        }
+        Some(Tile::PowerUp(power_up)) => {
+            let size = Vec2::splat(Pickup::SIZE);
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        ..Sprite::default()
+                    },
+                    texture: asset_server.load(world.sprite(&power_up.sprite())),
+                    transform,
+                    ..SpriteBundle::default()
+                })
+                .insert(CollisionShape::new_rectangle(size.x, size.y))
+                .insert(Pickup(*power_up))
+                .insert(Shadow::new(size * 0.8, Pickup::HEIGHT))
+                .insert(WorldTile)
+                .insert(RenderLayer::Pickups);
+        }
+        Some(Tile::Checkpoint) => {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Checkpoint::COLOR,
+                        custom_size: Some(tile_size),
+                        ..Sprite::default()
+                    },
+                    transform,
+                    ..SpriteBundle::default()
+                })
+                .insert(CollisionShape::new_rectangle(tile_size.x, tile_size.y))
+                .insert(Checkpoint(world.checkpoint_index((j, i))))
+                .insert(WorldTile)
+                .insert(RenderLayer::Tiles);
+        }
+        Some(Tile::Flower) => {
+            let size = Vec2::splat(Flower::SIZE);
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        ..Sprite::default()
+                    },
+                    texture: asset_server.load(world.sprite("flower.png")),
+                    transform,
+                    ..SpriteBundle::default()
+                })
+                .insert(CollisionShape::new_rectangle(tile_size.x, tile_size.y))
+                .insert(Flower)
+                .insert(WorldTile)
+                .insert(RenderLayer::Tiles);
+        }
+        None => {}
     }
+}
 
-    // Convert player start coordinates into world position
-    let player_start_location = Vec2::new(
-        world.player_start_coordinates.0 as f32,
-        -(world.player_start_coordinates.1 as f32),
-    ) * Tile::SIZE;
+fn reset_spawners(
+    world: Res<Arc<GameWorld>>,
+    mut grid: ResMut<SpawnerGrid>,
+    mut clock: ResMut<SpawnerClock>,
+    mut groups: ResMut<SpawnerGroups>,
+) {
+    *grid = SpawnerGrid::default();
+    *clock = SpawnerClock::default();
+    *groups = SpawnerGroups::new(&world);
+}
 
-    // Spawn the player
-    player::spawn_player(
-        &mut commands,
-        animations,
-        textures,
-        &asset_server,
-        upgrades,
-        player_start_location,
-    );
+// Spawners never move, so they only need indexing once
+fn index_spawners(
+    mut grid: ResMut<SpawnerGrid>,
+    mut groups: ResMut<SpawnerGroups>,
+    spawners: Query<(Entity, &Transform, &Spawner), Added<Spawner>>,
+) {
+    for (entity, transform, spawner) in spawners.iter() {
+        grid.insert(entity, transform.translation.truncate());
+        if let Some(FireControl::Group(slot)) = &spawner.control {
+            if let Some(schedule) = groups.0.get_mut(&slot.group) {
+                schedule.members[slot.index] = Some(entity);
+            }
+        }
+    }
+}
 
-    camera::spawn_camera(&mut commands, goal_position.unwrap_or(Vec2::ZERO));
+fn tick_spawners(
+    game_clock: Res<GameClock>,
+    mut clock: ResMut<SpawnerClock>,
+    grid: Res<SpawnerGrid>,
+    mut groups: ResMut<SpawnerGroups>,
+    mut launches: EventWriter<SpawnEnemy>,
+    camera: Query<&Transform, With<MainCamera>>,
+    mut spawners: Query<(
+        &Transform,
+        &mut Spawner,
+        Option<&mut PatternRunner>,
+        Option<&mut Aim>,
+    )>,
+) {
+    clock.0 += game_clock.delta_seconds_f64();
+
+    let (min, max) = match camera.get_single() {
+        Ok(camera) => active_area(camera),
+        Err(_) => return,
+    };
+
+    let mut awake = HashSet::new();
+    for entity in grid.nearby(min, max) {
+        if let Ok((transform, mut spawner, runner, aim)) = spawners.get_mut(entity) {
+            let position = transform.translation.truncate();
+            if let Some(mut runner) = runner {
+                let shots = runner.run(clock.0);
+                if !shots.is_empty() {
+                    spawner.warned = false;
+                }
+                spawner.next_fire = runner.next;
+                // Barrels follow the pattern round as it turns
+                if let (Projectile::Laser { angle, .. }, Some(mut aim), Some(turn)) =
+                    (&spawner.projectile, aim, shots.last())
+                {
+                    aim.0 = angle + turn;
+                }
+                for turn in shots {
+                    let projectile = match spawner.projectile {
+                        Projectile::Laser { angle, blocked } => Projectile::Laser {
+                            angle: angle + turn,
+                            blocked,
+                        },
+                        Projectile::Missile => Projectile::Missile,
+                    };
+                    launches.send(SpawnEnemy {
+                        projectile,
+                        position,
+                    });
+                }
+            } else if spawner.control.is_some() {
+                awake.insert(entity);
+            } else if spawner.fire(clock.0) {
+                launches.send(SpawnEnemy {
+                    projectile: spawner.projectile.clone(),
+                    position,
+                });
+            }
+        }
+    }
+
+    // Groups keep time even while asleep so their patterns stay in step,
+    // but only members near the camera actually fire
+    for schedule in groups.0.values_mut() {
+        let due = schedule.fire(clock.0);
+        for (index, entity) in schedule.members.iter().enumerate() {
+            let entity = match entity {
+                Some(entity) => *entity,
+                None => continue,
+            };
+            let (transform, mut spawner, ..) = match spawners.get_mut(entity) {
+                Ok(spawner) => spawner,
+                Err(_) => continue,
+            };
+            if due.contains(&index) {
+                spawner.warned = false;
+                if awake.contains(&entity) {
+                    launches.send(SpawnEnemy {
+                        projectile: spawner.projectile.clone(),
+                        position: transform.translation.truncate(),
+                    });
+                }
+            }
+            // Keeps the launch warning in sync with the group
+            spawner.next_fire = schedule.next_fire_for(index);
+        }
+    }
 }
 
-fn spawn_projectiles(
-    mut commands: Commands,
-    mut animations: ResMut<Assets<SpriteSheetAnimation>>,
-    mut textures: ResMut<Assets<TextureAtlas>>,
-    asset_server: Res<AssetServer>,
-    time: Res<Time>,
-    mut spawners: Query<(&Transform, &mut Spawner)>,
+// Rectangle around the camera's view where spawners are awake. Based on the logical view
+// rather than the window, so the window size and zoom don't change what fires.
+fn active_area(camera: &Transform) -> (Vec2, Vec2) {
+    let center = camera.translation.truncate();
+    let extent = camera::widest_view() / 2.0 + Vec2::splat(SPAWNER_ACTIVATION_MARGIN);
+    (center - extent, center + extent)
+}
+
+// Flash missile spawners that are about to fire, so launches can be seen coming
+fn warn_before_launch(
+    clock: Res<SpawnerClock>,
+    grid: Res<SpawnerGrid>,
+    audio: Res<Audio<Tone>>,
+    sounds: Res<Sounds>,
+    camera: Query<&Transform, With<MainCamera>>,
+    mut spawners: Query<(&mut Spawner, &mut Sprite)>,
 ) {
-    for (spawner_transform, mut spawner) in spawners.iter_mut() {
-        let spawn_position = spawner_transform.translation.truncate();
-
-        if spawner.timer.tick(time.delta()).just_finished() {
-            // Spawn projectile if timer has just finished
-            spawner.projectile.spawn(
-                &mut commands,
-                &mut animations,
-                &mut textures,
-                &asset_server,
-                spawn_position,
-            );
+    let (min, max) = match camera.get_single() {
+        Ok(camera) => active_area(camera),
+        Err(_) => return,
+    };
+
+    for entity in grid.nearby(min, max) {
+        if let Ok((mut spawner, mut sprite)) = spawners.get_mut(entity) {
+            sprite.color = match spawner.warning(clock.0) {
+                Some(progress) => {
+                    if !spawner.warned {
+                        spawner.warned = true;
+                        audio.play(sounds.missile_warning.clone());
+                    }
+                    // Pulses faster as the launch gets closer
+                    let pulse = (progress * progress * 6.0 * PI).sin().abs();
+                    lerp_color(Color::WHITE, WARNING_COLOR, pulse * (0.5 + progress / 2.0))
+                }
+                None => Color::WHITE,
+            };
+        }
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let (from, to) = (Vec4::from(from), Vec4::from(to));
+    from.lerp(to, t).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::Events;
+
+    fn round_trip(source: &str) {
+        let world = GameWorld::parse(source, WorldType::Endless).unwrap();
+        let reparsed = GameWorld::parse(&world.serialize(), WorldType::Endless).unwrap();
+        assert_eq!(world.layout, reparsed.layout);
+        assert_eq!(world.metadata, reparsed.metadata);
+        assert_eq!(
+            world.player_start_coordinates,
+            reparsed.player_start_coordinates
+        );
+    }
+
+    #[test]
+    fn builtin_levels_round_trip() {
+        for (_, _, source) in LEVELS {
+            round_trip(source);
+        }
+    }
+
+    #[test]
+    fn all_tile_types_round_trip() {
+        round_trip("#\tM\tT\tG\n.\t*\tL:0\t.\nP:speed\tP:shield\tP:slow\tF\n");
+    }
+
+    #[test]
+    fn metadata_round_trips() {
+        round_trip("@fog\n*\tG\n");
+        round_trip("@fog\t64.5\n*\tG\n");
+        round_trip("@fog\t64.5\n@boundary\n*\tG\n");
+        round_trip("@out_of_bounds\trespawn\n*\tG\n");
+        round_trip("@out_of_bounds\tdeath\n*\tG\n");
+        round_trip("@intro\tcutscenes/hive.cutscene\n*\tG\n");
+        round_trip("@stamina\n*\tF\tG\n");
+        round_trip("@spawner_art\tlaser\tdirections:turret.png\tbarrel:barrel.png\n*\tL:45\n");
+
+        let world = GameWorld::parse("@fog\t80\n*\tG\n", WorldType::Endless).unwrap();
+        assert_eq!(world.metadata.fog, Some(80.0));
+        assert_eq!(world.player_start_coordinates, (0, 0));
+        assert!(GameWorld::parse("@fog\t-1\n*\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@weather\train\n*\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@boundary\tyes\n*\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@out_of_bounds\n*\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@intro\n*\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@stamina\tlots\n*\n", WorldType::Endless).is_err());
+        let art = "@spawner_art\tmissile\tbarrel:barrel.png\n*\n";
+        assert!(GameWorld::parse(art, WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@spawner_art\tlaser\n*\n", WorldType::Endless).is_err());
+    }
+
+    #[test]
+    fn bounds_cover_every_tile() {
+        let world = GameWorld::parse("*\t.\t.\n.\n.\tG\n", WorldType::Endless).unwrap();
+        let (min, max) = world.bounds();
+        assert_eq!(min, Vec2::new(-12.0, -60.0));
+        assert_eq!(max, Vec2::new(60.0, 12.0));
+    }
+
+    #[test]
+    fn laser_angles_round_trip() {
+        for angle in ["0", "3.14", "-1.5707964", "0.1", "1e-7", "6.2831855"] {
+            round_trip(&format!(".\tL:{angle}\t*\n"));
+            round_trip(&format!(".\tL:{angle}!\t*\n"));
+        }
+        let world = GameWorld::parse("L:0!\t*\n", WorldType::Endless).unwrap();
+        assert_eq!(
+            world.layout[0][0],
+            Some(Tile::Spawner(
+                Projectile::Laser {
+                    angle: 0.0,
+                    blocked: true
+                },
+                None
+            ))
+        );
+        assert!(GameWorld::parse("L:!\t*\n", WorldType::Endless).is_err());
+    }
+
+    #[test]
+    fn ragged_rows_round_trip() {
+        round_trip("#\t#\t#\t#\n#\t*\n#\t.\t.\tG\t.\t.\n");
+    }
+
+    #[test]
+    fn mirroring_twice_restores_the_layout() {
+        let source = "#\tL:0\t*\n.\tG\n";
+        let original = GameWorld::parse(source, WorldType::Endless).unwrap();
+        let mut world = GameWorld::parse(source, WorldType::Endless).unwrap();
+
+        world.mirror();
+        assert_eq!(world.player_start_coordinates, (0, 0));
+        assert_eq!(world.map_tile((1, 1)), (1, 1));
+        assert_eq!(world.map_tile((0, 1)), (2, 1));
+        assert_eq!(
+            world.layout[0][1],
+            Some(Tile::Spawner(
+                Projectile::Laser {
+                    angle: PI,
+                    blocked: false
+                },
+                None
+            ))
+        );
+
+        world.mirror();
+        assert_eq!(world.layout[0][..2], original.layout[0][..2]);
+        assert_eq!(
+            world.player_start_coordinates,
+            original.player_start_coordinates
+        );
+        assert_eq!(world.map_tile((0, 1)), (0, 1));
+    }
+
+    #[test]
+    fn transformed_coordinates_follow_the_layout() {
+        // Move the start around a ragged level, checking that map_tile agrees
+        // with where the start ends up after each variant
+        let rows = ["#\t.\t.", "."];
+        for variant in Variant::iter() {
+            for mirror in [false, true] {
+                for (y, row) in rows.iter().enumerate() {
+                    for x in 0..row.split('\t').count() {
+                        let mut cells: Vec<Vec<&str>> =
+                            rows.iter().map(|row| row.split('\t').collect()).collect();
+                        cells[y][x] = "*";
+                        let source: Vec<String> = cells.iter().map(|row| row.join("\t")).collect();
+
+                        let mut world =
+                            GameWorld::parse(&source.join("\n"), WorldType::Endless).unwrap();
+                        variant.apply(&mut world);
+                        if mirror {
+                            world.mirror();
+                        }
+                        assert_eq!(world.map_tile((x, y)), world.player_start_coordinates);
+                    }
+                }
+            }
         }
     }
+
+    #[test]
+    fn validation_reports_problems() {
+        assert!(GameWorld::validate("*\tG\n").is_empty());
+        assert!(matches!(
+            GameWorld::validate("*\t\tG\n").as_slice(),
+            [Diagnostic::Error(_)]
+        ));
+        assert!(matches!(
+            GameWorld::validate("*\tL:north\tG\n").as_slice(),
+            [Diagnostic::Error(_)]
+        ));
+        assert!(matches!(
+            GameWorld::validate("*\t*\n").as_slice(),
+            [Diagnostic::Warning(_), Diagnostic::Error(_)]
+        ));
+        assert!(matches!(
+            GameWorld::validate("*\tG\tG\n").as_slice(),
+            [Diagnostic::Warning(_)]
+        ));
+    }
+
+    #[test]
+    fn danger_is_mapped_from_spawners() {
+        let row = ".\t.\t.\t.\t.\n";
+        let level =
+            |laser_row: &str| format!("*\t.\t.\t.\t.\n{row}{laser_row}{row}.\t.\t.\t.\tG\n");
+
+        let open = level("L:0\t.\t.\t.\t.\n");
+        let danger = GameWorld::parse(&open, WorldType::Endless)
+            .unwrap()
+            .danger_map();
+        assert_eq!(danger[2], [0.0, 1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(danger[1], [0.0; 5]);
+
+        // Stopped by a wall
+        let blocked = level("L:0!\t.\t#\t.\t.\n");
+        let danger = GameWorld::parse(&blocked, WorldType::Endless)
+            .unwrap()
+            .danger_map();
+        assert_eq!(danger[2], [0.0, 1.0, 0.0, 0.0, 0.0]);
+
+        let missile = GameWorld::parse("M\t.\t.\t.\n", WorldType::Endless).unwrap();
+        let danger = &missile.danger_map()[0];
+        assert_eq!(danger[0], 0.0);
+        assert!(danger[1] > danger[2] && danger[2] > danger[3] && danger[3] > 0.0);
+    }
+
+    #[test]
+    fn blocked_starts_move_to_the_nearest_free_tile() {
+        let world = GameWorld::parse("*\t#\nM\t.\n", WorldType::Endless).unwrap();
+        assert_eq!(world.safe_start(), (0, 0));
+        // Without a *, the start defaults to the top-left tile
+        let world = GameWorld::parse("#\t#\tM\n#\tL:0\t.\n", WorldType::Endless).unwrap();
+        assert_eq!(world.safe_start(), (2, 1));
+        let world = GameWorld::parse("#\n", WorldType::Endless).unwrap();
+        assert_eq!(world.safe_start(), (0, 0));
+        assert!(matches!(
+            GameWorld::validate("#\t.\tG\n").as_slice(),
+            [Diagnostic::Warning(_), Diagnostic::Warning(_)]
+        ));
+    }
+
+    #[test]
+    fn distances_to_walls_are_measured_between_edges() {
+        let world = GameWorld::parse("*\t.\t.\t#\n", WorldType::Endless).unwrap();
+        let half_size = Vec2::splat(12.0);
+        let wall_edge = 2.5 * Tile::SIZE;
+        let gap = |x| world.distance_to_wall(Vec2::new(x, 0.0), half_size, 20.0);
+        assert_eq!(gap(wall_edge - 22.0), Some(10.0));
+        assert_eq!(gap(wall_edge - 12.0), Some(0.0));
+        assert_eq!(gap(wall_edge - 40.0), None);
+        // Diagonally off the corner
+        let corner = world.distance_to_wall(Vec2::new(wall_edge - 15.0, 28.0), half_size, 20.0);
+        assert_eq!(corner, Some(5.0));
+
+        let centered = GameWorld::parse("@centered\n*\t.\t#\n", WorldType::Endless).unwrap();
+        assert_eq!(
+            centered.distance_to_wall(Vec2::new(-2.0, 0.0), half_size, 20.0),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn rays_stop_at_the_first_wall() {
+        let world = GameWorld::parse("*\t.\t.\t#\t#\n.\t.\t.\t.\t.\n", WorldType::Endless).unwrap();
+        let wall_edge = 2.5 * Tile::SIZE;
+        assert_eq!(
+            world.ray_to_wall(Vec2::ZERO, Vec2::X, 200.0),
+            Some(wall_edge)
+        );
+        assert_eq!(world.ray_to_wall(Vec2::ZERO, Vec2::X, 50.0), None);
+        // Along the open row, and out of the layout
+        assert_eq!(
+            world.ray_to_wall(Vec2::new(0.0, -Tile::SIZE), Vec2::X, 200.0),
+            None
+        );
+    }
+
+    #[test]
+    fn centered_levels_are_laid_out_around_the_origin() {
+        let world = GameWorld::parse("@centered\n*\t.\t.\n.\t.\tG\n", WorldType::Endless).unwrap();
+        let origin = world.origin();
+        assert_eq!(origin, WorldOrigin(Vec2::new(-24.0, 12.0)));
+        let (min, max) = world.bounds();
+        assert_eq!(min + max, Vec2::ZERO);
+        assert_eq!(world.goal_position(), Some(Vec2::new(24.0, -12.0)));
+        assert_eq!(origin.world_to_tile(Vec2::new(20.0, -10.0)), Some((2, 1)));
+        assert_eq!(origin.world_to_tile(Vec2::new(-40.0, 0.0)), None);
+        round_trip("@centered\n*\tG\n");
+
+        let uncentered = GameWorld::parse("*\t.\t.\n.\t.\tG\n", WorldType::Endless).unwrap();
+        assert_eq!(uncentered.origin(), WorldOrigin::default());
+        assert_eq!(uncentered.bounds().0, min - origin.0);
+    }
+
+    #[test]
+    fn goal_is_found_in_layout() {
+        let world = GameWorld::parse("*\t.\n.\tG\n", WorldType::Endless).unwrap();
+        assert_eq!(
+            world.goal_position(),
+            Some(world.origin().tile_to_world((1, 1)))
+        );
+        let world = GameWorld::parse("*\t.\n", WorldType::Endless).unwrap();
+        assert_eq!(world.goal_position(), None);
+    }
+
+    #[test]
+    fn sleeping_spawners_skip_missed_shots() {
+        let mut spawner = Spawner::new(Projectile::Missile, None);
+        assert!(!spawner.fire(0.5));
+        assert!(spawner.fire(1.0));
+        assert!(!spawner.fire(1.5));
+        // Out of range for a while, then fires once on waking
+        assert!(spawner.fire(10.2));
+        assert!(!spawner.fire(10.5));
+        assert!(spawner.fire(11.2));
+    }
+
+    #[test]
+    fn missile_spawners_warn_before_firing() {
+        let mut spawner = Spawner::new(Projectile::Missile, None);
+        assert_eq!(spawner.warning(0.4), None);
+        assert_eq!(spawner.warning(0.75), Some(0.5));
+        assert!(spawner.fire(1.0));
+        assert_eq!(spawner.warning(1.0), None);
+        assert_eq!(spawner.warning(1.5), Some(0.0));
+
+        let laser = Spawner::new(
+            Projectile::Laser {
+                angle: 0.0,
+                blocked: false,
+            },
+            None,
+        );
+        assert_eq!(laser.warning(laser.next_fire - 0.1), None);
+    }
+
+    #[test]
+    fn hashes_change_with_the_layout() {
+        let parse = |source| GameWorld::parse(source, WorldType::Endless).unwrap();
+        assert_eq!(parse("*\tG\n").hash(), parse("*\tG\n").hash());
+        assert_ne!(parse("*\tG\n").hash(), parse("*\t#\tG\n").hash());
+        assert_ne!(parse("*\tG\n").hash(), parse("@boundary\n*\tG\n").hash());
+    }
+
+    #[test]
+    fn tile_variation_is_stable_and_varied() {
+        assert_eq!(TileVariation::new(7, (3, 4)), TileVariation::new(7, (3, 4)));
+        let variations: Vec<_> = (0..64).map(|x| TileVariation::new(7, (x, 0))).collect();
+        assert!(variations
+            .iter()
+            .all(|variation| (1.0 - WALL_SHADE_RANGE..=1.0).contains(&variation.shade)));
+        assert!((0..4).all(|turns| variations.iter().any(|v| v.quarter_turns == turns)));
+        assert!(variations.iter().any(|variation| variation.alternate));
+        assert!(variations.iter().any(|variation| !variation.alternate));
+        assert_ne!(TileVariation::new(7, (3, 4)), TileVariation::new(8, (3, 4)));
+    }
+
+    #[test]
+    fn checkpoints_are_numbered_in_reading_order() {
+        let source = ".\tC\n*\n.\t.\tC\nC\n";
+        round_trip(source);
+        let world = GameWorld::parse(source, WorldType::Endless).unwrap();
+        assert_eq!(world.checkpoint_count(), 3);
+        assert_eq!(world.checkpoint_index((1, 0)), 0);
+        assert_eq!(world.checkpoint_index((2, 2)), 1);
+        assert_eq!(world.checkpoint_index((0, 3)), 2);
+        assert_eq!(world.checkpoint_coordinates(1), Some((2, 2)));
+        assert_eq!(world.checkpoint_coordinates(3), None);
+    }
+
+    #[test]
+    fn spawner_groups_are_parsed() {
+        let source = "@group\ta\talternate\t0.5\n@group\tb\tsequential:0.2\nM/a\tL:0/b\tM/a\n";
+        round_trip(source);
+        let world = GameWorld::parse(source, WorldType::Endless).unwrap();
+        assert_eq!(
+            world.layout[0][2],
+            Some(Tile::Spawner(
+                Projectile::Missile,
+                Some(FireControl::Group(GroupSlot {
+                    group: "a".into(),
+                    index: 1
+                }))
+            ))
+        );
+        assert_eq!(
+            world.metadata.groups["b"],
+            SpawnerGroup {
+                pattern: FirePattern::Sequential { offset: 0.2 },
+                period: None
+            }
+        );
+
+        assert!(GameWorld::parse("M/a\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@group\ta\tspiral\nM/a\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@group\ta\talternate\t0\nM/a\n", WorldType::Endless).is_err());
+    }
+
+    #[test]
+    fn spawners_can_play_patterns() {
+        let source = "@pattern\tring\tfan 8 5.5; wait 1\nL:0~ring\tM~ring\n";
+        round_trip(source);
+        let world = GameWorld::parse(source, WorldType::Endless).unwrap();
+        assert_eq!(
+            world.layout[0][1],
+            Some(Tile::Spawner(
+                Projectile::Missile,
+                Some(FireControl::Pattern("ring".into()))
+            ))
+        );
+
+        assert!(GameWorld::parse("M~ring\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@pattern\tring\tfan 8\nM~ring\n", WorldType::Endless).is_err());
+    }
+
+    #[test]
+    fn group_patterns_schedule_members() {
+        let schedule = |pattern| {
+            let mut schedule = GroupSchedule::new(pattern, 1.0);
+            schedule.members = vec![None; 3];
+            schedule
+        };
+
+        let mut simultaneous = schedule(FirePattern::Simultaneous);
+        assert!(simultaneous.fire(0.5).is_empty());
+        assert_eq!(simultaneous.fire(1.0), vec![0, 1, 2]);
+        assert_eq!(simultaneous.next_fire_for(2), 2.0);
+
+        let mut alternate = schedule(FirePattern::Alternate);
+        assert_eq!(alternate.fire(1.0), vec![0]);
+        assert_eq!(alternate.next_fire_for(0), 4.0);
+        assert_eq!(alternate.fire(2.0), vec![1]);
+        assert_eq!(alternate.fire(3.0), vec![2]);
+        assert_eq!(alternate.fire(4.0), vec![0]);
+
+        let mut sequential = schedule(FirePattern::Sequential { offset: 0.25 });
+        assert_eq!(sequential.fire(1.0), vec![0]);
+        assert_eq!(sequential.next_fire_for(0), 2.0);
+        assert_eq!(sequential.next_fire_for(2), 1.5);
+        assert_eq!(sequential.fire(1.6), vec![1, 2]);
+        assert_eq!(sequential.fire(2.0), vec![0]);
+    }
+
+    #[test]
+    fn grid_finds_spawners_near_a_rectangle() {
+        let mut world = World::new();
+        let mut grid = SpawnerGrid::default();
+        let near = world.spawn().id();
+        let far = world.spawn().id();
+        grid.insert(near, Vec2::new(10.0, -10.0));
+        grid.insert(far, Vec2::new(5000.0, 0.0));
+
+        let found: Vec<Entity> = grid
+            .nearby(Vec2::splat(-100.0), Vec2::splat(100.0))
+            .collect();
+        assert_eq!(found, vec![near]);
+    }
+
+    // Average time to tick every spawner of a level, with the camera on the player
+    fn spawner_frame_cost(level: &GameWorld, keep: impl Fn(Vec2) -> bool) -> std::time::Duration {
+        const FRAMES: u32 = 200;
+
+        let mut world = World::new();
+        world.insert_resource(Time::default());
+        world.insert_resource(SpawnerGrid::default());
+        world.insert_resource(SpawnerClock::default());
+        world.insert_resource(SpawnerGroups::new(level));
+        world.insert_resource(Events::<SpawnEnemy>::default());
+
+        let start = level.origin().tile_to_world(level.player_start_coordinates);
+        world
+            .spawn()
+            .insert(Transform::from_translation(start.extend(0.0)))
+            .insert(MainCamera);
+        for (y, row) in level.layout.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let position = level.origin().tile_to_world((x, y));
+                if let (Some(Tile::Spawner(projectile, control)), true) = (tile, keep(position)) {
+                    world
+                        .spawn()
+                        .insert(Transform::from_translation(position.extend(0.0)))
+                        .insert(Spawner::new(projectile.clone(), control.clone()));
+                }
+            }
+        }
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(index_spawners).add_system(tick_spawners);
+        stage.run(&mut world);
+
+        let started = std::time::Instant::now();
+        for _ in 0..FRAMES {
+            world.get_resource_mut::<Time>().unwrap().update();
+            stage.run(&mut world);
+            world
+                .get_resource_mut::<Events<SpawnEnemy>>()
+                .unwrap()
+                .update();
+        }
+        started.elapsed() / FRAMES
+    }
+
+    // Run with `cargo test --release -- --ignored spawner_cost`
+    #[test]
+    #[ignore]
+    fn spawner_cost_stays_flat() {
+        let level = GameWorld::parse(
+            include_str!("../assets/levels/stress.tsv"),
+            WorldType::Endless,
+        )
+        .unwrap();
+        let start = level.origin().tile_to_world(level.player_start_coordinates);
+
+        // Only the spawners the camera can wake up, compared to all 10k of them
+        let nearby = spawner_frame_cost(&level, |position| {
+            (position - start).abs().max_element() < 1000.0
+        });
+        let all = spawner_frame_cost(&level, |_| true);
+        println!("nearby spawners: {nearby:?} per frame, all spawners: {all:?} per frame");
+        assert!(all < nearby * 2);
+    }
+
+    // Parse a level from tests/fixtures/levels
+    macro_rules! fixture {
+        ($name:literal) => {
+            GameWorld::parse(
+                include_str!(concat!("../tests/fixtures/levels/", $name, ".tsv")),
+                WorldType::Endless,
+            )
+        };
+    }
+
+    #[test]
+    fn whitespace_around_tiles_is_ignored() {
+        let world = fixture!("trailing_whitespace").unwrap();
+        assert_eq!(world.metadata.fog, Some(80.0));
+        assert_eq!(world.layout.len(), 2);
+        assert_eq!(world.layout[0], [None, None, None]);
+        assert_eq!(world.layout[1], [Some(Tile::Wall), None, Some(Tile::Goal)]);
+
+        // Only a blank line after the layout, not one inside it
+        assert!(GameWorld::parse("*\t.\n\n.\tG\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("*\t.\n \t\n.\tG\n", WorldType::Endless).is_err());
+    }
+
+    #[test]
+    fn windows_line_endings_are_read() {
+        let source = include_str!("../tests/fixtures/levels/crlf.tsv");
+        assert!(source.contains("\r\n"));
+        let world = fixture!("crlf").unwrap();
+        assert!(world.metadata.boundary);
+        assert_eq!(world.layout[0][2], Some(Tile::Wall));
+        assert_eq!(
+            world.layout[1][0],
+            Some(Tile::Spawner(
+                Projectile::Laser {
+                    angle: 1.5,
+                    blocked: false
+                },
+                None
+            ))
+        );
+        assert_eq!(world.layout[1][2], Some(Tile::Goal));
+        assert!(GameWorld::validate(source).is_empty());
+    }
+
+    #[test]
+    fn paths_keep_away_from_spawners_where_they_can() {
+        let source = "*\t.\t.\t.\t.\n.\t.\tM\t.\t.\n.\t.\t.\t.\t.\n.\t.\t.\t.\tG\n";
+        let world = GameWorld::parse(source, WorldType::Endless).unwrap();
+        let path = world.path_avoiding_spawners(1).unwrap();
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(4, 3)));
+        assert!(path
+            .iter()
+            .all(|(x, y)| x.abs_diff(2).max(y.abs_diff(1)) > 1 || (*x, *y) == (0, 0)));
+
+        // Nowhere is far enough away, so any way through will do
+        assert_eq!(world.path_avoiding_spawners(5), world.path_to_goal());
+    }
+
+    #[test]
+    fn ragged_rows_are_open_past_their_end() {
+        let world = fixture!("ragged").unwrap();
+        assert_eq!(
+            world.layout.iter().map(Vec::len).collect::<Vec<_>>(),
+            [4, 4, 4]
+        );
+        assert!(world.path_to_goal().is_some());
+        assert_eq!(world.bounds().1, Vec2::new(84.0, 12.0));
+
+        let mut mirrored = fixture!("ragged").unwrap();
+        mirrored.mirror();
+        assert_eq!(mirrored.layout[1], [None, None, None, Some(Tile::Wall)]);
+        assert_eq!(mirrored.player_start_coordinates, (3, 0));
+    }
+
+    #[test]
+    fn layouts_read_the_same_from_any_editor() {
+        let hash = |source: &str| GameWorld::parse(source, WorldType::Endless).unwrap().hash();
+        let tabs = hash("@fog\t80\n*\t.\tL:1.5\n#\t.\tG\n");
+        assert_eq!(hash("@fog\t80\n*  .  L:1.5\n#  .  G\n"), tabs);
+        assert_eq!(hash("@fog\t80\n* .\tL:1.5\n#\t. G\n"), tabs);
+        assert_eq!(hash("\u{feff}@fog\t80\r\n*\t.\tL:1.5\r\n#\t.\tG\r\n"), tabs);
+        assert_eq!(hash("@fog\t80\r*\t.\tL:1.5\r#\t.\tG\r"), tabs);
+        // Short rows are filled out with empty tiles
+        assert_eq!(
+            hash("@fog\t80\n*\t.\tL:1.5\n#\t.\tG\n#\n"),
+            hash("@fog\t80\n*\t.\tL:1.5\n#\t.\tG\n#\t.\t.\n")
+        );
+    }
+
+    #[test]
+    fn levels_without_a_start_use_the_nearest_open_tile() {
+        let world = fixture!("no_start").unwrap();
+        assert_eq!(world.player_start_coordinates, (0, 0));
+        assert_ne!(world.safe_start(), (0, 0));
+        assert!(matches!(
+            GameWorld::validate(include_str!("../tests/fixtures/levels/no_start.tsv"))[..],
+            [Diagnostic::Warning(_), Diagnostic::Warning(_)]
+        ));
+    }
+
+    #[test]
+    fn invalid_laser_angles_are_errors() {
+        let err = match fixture!("invalid_laser") {
+            Ok(_) => panic!("invalid laser angle was accepted"),
+            Err(err) => err,
+        };
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("row 2, column 2"));
+        for value in ["L", "L:", "L:inf", "L:NaN", "L: ", "L:1.5.2"] {
+            assert!(GameWorld::parse(&format!("*\t{value}\n"), WorldType::Endless).is_err());
+        }
+    }
+
+    #[test]
+    fn empty_levels_are_errors() {
+        assert!(fixture!("empty").is_err());
+        assert!(GameWorld::parse("\n\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("@boundary\n", WorldType::Endless).is_err());
+        assert!(matches!(
+            GameWorld::validate("")[..],
+            [Diagnostic::Error(_)]
+        ));
+    }
+
+    #[test]
+    fn names_can_be_unicode() {
+        let world = fixture!("unicode").unwrap();
+        assert!(world.metadata.groups.contains_key("蜂の巣"));
+        assert!(world.metadata.patterns.contains_key("étoile"));
+        assert_eq!(
+            world.metadata.intro.as_deref(),
+            Some("cutscenes/ruché.cutscene")
+        );
+        assert_eq!(
+            world.layout[0][2],
+            Some(Tile::Spawner(
+                Projectile::Missile,
+                Some(FireControl::Group(GroupSlot {
+                    group: "蜂の巣".into(),
+                    index: 1
+                }))
+            ))
+        );
+        round_trip(include_str!("../tests/fixtures/levels/unicode.tsv"));
+
+        // Tiles themselves are ASCII
+        assert!(GameWorld::parse("*\t蜂\n", WorldType::Endless).is_err());
+        assert!(GameWorld::parse("*\tM/\u{301}\n", WorldType::Endless).is_err());
+    }
 }